@@ -0,0 +1,12 @@
+fn main() {
+    // Node bindings need extra linker arguments on some platforms
+    #[cfg(feature = "node")]
+    {
+        napi_build::setup();
+        // The N-API symbols come from the Node process which loads the addon, so the unit tests and the
+        // binaries (such as with `cargo test --all-features`) leave them unresolved rather than fail to link
+        if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux") {
+            println!("cargo:rustc-link-arg=-Wl,--unresolved-symbols=ignore-all");
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! A minimal Fountain language server over stdio, built with the `lsp` feature:
+//!
+//! ```text
+//! cargo run --features lsp --bin fountain-lsp
+//! ```
+//!
+//! Documents are synced in full, and re-parsed with `parse_document_full` on every change.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, FoldingRangeRequest, Request as LspRequest,
+    SemanticTokensFullRequest,
+};
+use lsp_types::{
+    CompletionOptions, CompletionParams, DocumentSymbolParams, DocumentSymbolResponse,
+    FoldingRangeParams, FoldingRangeProviderCapability, OneOf, PublishDiagnosticsParams,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensServerCapabilities, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+
+use fountain_parser_rs::fountain_full_parse::{
+    parse_document_full, FNFullParse, FNFullParseConfig,
+};
+use fountain_parser_rs::fountain_lsp::{
+    get_lsp_completions, get_lsp_diagnostics, get_lsp_document_symbols, get_lsp_folding_ranges,
+    get_lsp_semantic_tokens, get_semantic_tokens_legend,
+};
+
+type ServerResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
+
+fn main() -> ServerResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: get_semantic_tokens_legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        completion_provider: Some(CompletionOptions::default()),
+        ..Default::default()
+    };
+    if let Err(error) = connection.initialize(serde_json::to_value(capabilities)?) {
+        if error.channel_is_disconnected() {
+            io_threads.join()?;
+        }
+        return Err(error.into());
+    }
+
+    // The connection is moved in, so its channels are closed before the IO threads are joined
+    main_loop(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+fn main_loop(connection: Connection) -> ServerResult<()> {
+    let mut documents: HashMap<String, FNFullParse> = HashMap::new();
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = handle_request(&documents, request)?;
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                handle_notification(&connection, &mut documents, notification)?;
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn handle_request(
+    documents: &HashMap<String, FNFullParse>,
+    request: Request,
+) -> ServerResult<Response> {
+    let get_lines = |uri: &Uri| {
+        documents
+            .get(uri.as_str())
+            .map(|full| full.lines.as_slice())
+            .unwrap_or_default()
+    };
+
+    let result = match request.method.as_str() {
+        DocumentSymbolRequest::METHOD => {
+            let params: DocumentSymbolParams = serde_json::from_value(request.params)?;
+            let symbols = get_lsp_document_symbols(get_lines(&params.text_document.uri));
+            serde_json::to_value(DocumentSymbolResponse::Nested(symbols))?
+        }
+        FoldingRangeRequest::METHOD => {
+            let params: FoldingRangeParams = serde_json::from_value(request.params)?;
+            serde_json::to_value(get_lsp_folding_ranges(get_lines(&params.text_document.uri)))?
+        }
+        SemanticTokensFullRequest::METHOD => {
+            let params: SemanticTokensParams = serde_json::from_value(request.params)?;
+            serde_json::to_value(SemanticTokens {
+                result_id: None,
+                data: get_lsp_semantic_tokens(get_lines(&params.text_document.uri)),
+            })?
+        }
+        Completion::METHOD => {
+            let params: CompletionParams = serde_json::from_value(request.params)?;
+            let position = params.text_document_position;
+            serde_json::to_value(get_lsp_completions(
+                get_lines(&position.text_document.uri),
+                position.position,
+            ))?
+        }
+        _ => {
+            return Ok(Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("unhandled method: {}", request.method),
+            ))
+        }
+    };
+    Ok(Response::new_ok(request.id, result))
+}
+
+fn handle_notification(
+    connection: &Connection,
+    documents: &mut HashMap<String, FNFullParse>,
+    notification: Notification,
+) -> ServerResult<()> {
+    let (uri, text) = match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: lsp_types::DidOpenTextDocumentParams =
+                serde_json::from_value(notification.params)?;
+            (params.text_document.uri, params.text_document.text)
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: lsp_types::DidChangeTextDocumentParams =
+                serde_json::from_value(notification.params)?;
+            // Full sync: the last change holds the whole document
+            let Some(change) = params.content_changes.into_iter().last() else {
+                return Ok(());
+            };
+            (params.text_document.uri, change.text)
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: lsp_types::DidCloseTextDocumentParams =
+                serde_json::from_value(notification.params)?;
+            documents.remove(params.text_document.uri.as_str());
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    let full = parse_document_full(&text, &FNFullParseConfig::default());
+    let diagnostics = PublishDiagnosticsParams::new(uri.clone(), get_lsp_diagnostics(&full), None);
+    documents.insert(uri.as_str().to_string(), full);
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            diagnostics,
+        )))?;
+    Ok(())
+}
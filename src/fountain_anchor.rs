@@ -0,0 +1,24 @@
+//! Anchors are persistent references to a position within a line, for bookmarks, comments, and review threads.
+//!
+//! An `FNAnchor` refers to a line by its `uuid` instead of its index, so it still points to the same
+//! line after other lines are inserted or removed. Use `FNDocument::resolve_anchor` to find where it is now.
+
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FNAnchor {
+    pub line_uuid: Uuid,
+    /// Byte offset into the line's `raw_string`
+    pub local_offset: usize,
+}
+
+/// The current position of an `FNAnchor` within a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FNAnchorPosition {
+    pub line_index: usize,
+    /// Byte offset into the line's `raw_string`. If the line got shorter since the anchor was created,
+    /// this is clamped to the end of the line.
+    pub local_offset: usize,
+    /// The position of the anchor within the whole document, in the same units as `FNLine::position`
+    pub document_position: i32,
+}
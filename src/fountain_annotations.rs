@@ -0,0 +1,191 @@
+//! An annotation layer for external comments (such as review threads), which is kept separate from the Fountain text.
+//!
+//! Each `FNAnnotation` is attached to an `FNAnchor`, so it follows its line through edits.
+//! Annotations can optionally be written into the document as notes, and read back out of it:
+//!
+//! ```text
+//! The door creaks open.[[@Jane (1712345678): Too quiet?]]
+//! ```
+//!
+//! Regular notes (which don't start with `@`) are never touched.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::fountain_anchor::{FNAnchor, FNAnchorPosition};
+use crate::fountain_document::FNDocument;
+use crate::fountain_line::FNLine;
+use crate::static_fountain_preparser::get_text_from_lines;
+
+const ANNOTATION_NOTE_PATTERN: &str = r"\[\[@([^\(\]]*?)\s*\((\d+)\):\s?(.*?)\]\]";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNAnnotation {
+    pub id: Uuid,
+    pub anchor: FNAnchor,
+    pub author: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub text: String,
+}
+
+impl FNAnnotation {
+    /// Creates a new annotation, timestamped with the current system time.
+    pub fn new(anchor: FNAnchor, author: &str, text: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        FNAnnotation {
+            id: Uuid::new_v4(),
+            anchor,
+            author: author.to_string(),
+            timestamp,
+            text: text.to_string(),
+        }
+    }
+
+    /// Returns this annotation written as a Fountain note, such as `[[@Jane (1712345678): Too quiet?]]`
+    pub fn get_note_string(&self) -> String {
+        // Notes can't contain a closing pattern or a line break
+        let author = self.author.replace(['(', ')', '[', ']'], "");
+        let text = self.text.replace(['\r', '\n'], " ").replace("]]", "] ]");
+        format!("[[@{} ({}): {}]]", author.trim(), self.timestamp, text)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FNAnnotationLayer {
+    annotations: Vec<FNAnnotation>,
+}
+
+impl FNAnnotationLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_annotation(&mut self, annotation: FNAnnotation) {
+        self.annotations.push(annotation);
+    }
+
+    /// Removes the annotation with the given id, and returns it.
+    pub fn remove_annotation(&mut self, id: &Uuid) -> Option<FNAnnotation> {
+        let idx = self.annotations.iter().position(|a| a.id == *id)?;
+        Some(self.annotations.remove(idx))
+    }
+
+    pub fn get_annotations(&self) -> &[FNAnnotation] {
+        &self.annotations
+    }
+
+    /// Returns every annotation whose line still exists in the document, with its current position,
+    /// ordered by position in the document.
+    pub fn get_resolved_annotations<'a>(
+        &'a self,
+        document: &FNDocument,
+    ) -> Vec<(&'a FNAnnotation, FNAnchorPosition)> {
+        let mut resolved: Vec<(&FNAnnotation, FNAnchorPosition)> = self
+            .annotations
+            .iter()
+            .filter_map(|a| document.resolve_anchor(&a.anchor).map(|pos| (a, pos)))
+            .collect();
+        resolved.sort_by_key(|(a, pos)| (pos.line_index, pos.local_offset, a.timestamp));
+        resolved
+    }
+
+    /// Returns every annotation attached to the line at `line_index`.
+    pub fn get_annotations_for_line(
+        &self,
+        document: &FNDocument,
+        line_index: usize,
+    ) -> Vec<&FNAnnotation> {
+        self.get_resolved_annotations(document)
+            .into_iter()
+            .filter(|(_, pos)| pos.line_index == line_index)
+            .map(|(a, _)| a)
+            .collect()
+    }
+
+    /// Returns every annotation whose line has been removed from the document.
+    pub fn get_orphaned_annotations(&self, document: &FNDocument) -> Vec<&FNAnnotation> {
+        self.annotations
+            .iter()
+            .filter(|a| document.resolve_anchor(&a.anchor).is_none())
+            .collect()
+    }
+}
+
+/// Returns the raw text of the document, with every annotation written into its line as a note.
+pub fn get_document_text_with_annotations_as_notes(
+    document: &FNDocument,
+    layer: &FNAnnotationLayer,
+) -> String {
+    let mut lines: Vec<FNLine> = document.get_lines();
+
+    // Insert from the end of each line backwards, so that earlier offsets stay valid
+    let mut resolved = layer.get_resolved_annotations(document);
+    resolved.reverse();
+    for (annotation, position) in resolved {
+        lines[position.line_index]
+            .raw_string
+            .insert_str(position.local_offset, &annotation.get_note_string());
+    }
+
+    get_text_from_lines(&lines)
+}
+
+/// Removes every annotation note (`[[@Author (timestamp): text]]`) from the document's lines,
+/// and returns them as an `FNAnnotationLayer` anchored where each note used to be.
+///
+/// The stripped lines keep their `uuid`, so the returned anchors are valid for the updated document.
+/// The ids of the annotations come from the document's `FNUuidGenerator`.
+pub fn extract_annotations_from_document(document: &mut FNDocument) -> FNAnnotationLayer {
+    let re = Regex::new(ANNOTATION_NOTE_PATTERN).unwrap();
+    let mut layer = FNAnnotationLayer::new();
+
+    for line_index in 0..document.len() {
+        let Some(line) = document.get_line(line_index) else {
+            continue;
+        };
+        if !re.is_match(&line.raw_string) {
+            continue;
+        }
+
+        let mut stripped_raw = String::with_capacity(line.raw_string.len());
+        let mut pending: Vec<(usize, String, u64, String)> = Vec::new();
+        let mut last_end: usize = 0;
+        for caps in re.captures_iter(&line.raw_string) {
+            let whole = caps.get(0).unwrap();
+            stripped_raw.push_str(&line.raw_string[last_end..whole.start()]);
+            pending.push((
+                stripped_raw.len(),
+                caps[1].trim().to_string(),
+                caps[2].parse::<u64>().unwrap_or(0),
+                caps[3].to_string(),
+            ));
+            last_end = whole.end();
+        }
+        stripped_raw.push_str(&line.raw_string[last_end..]);
+
+        let mut new_line = line.clone();
+        new_line.string = re.replace_all(&new_line.string, "").to_string();
+        new_line.raw_string = stripped_raw;
+        document.replace_line(line_index, new_line);
+
+        for (offset, author, timestamp, text) in pending {
+            if let Some(anchor) = document.create_anchor(line_index, offset) {
+                layer.add_annotation(FNAnnotation {
+                    id: document.get_new_uuid(),
+                    anchor,
+                    author,
+                    timestamp,
+                    text,
+                });
+            }
+        }
+    }
+
+    layer
+}
@@ -0,0 +1,235 @@
+//! Exports dialogue-centric scripts in the layouts of audio/visual and radio scripts.
+//!
+//! Each dialogue block becomes a single speech, with its parentheticals inline, and each paragraph of action becomes
+//! a direction. The two-column layout puts the speaker next to the line, and the radio layout numbers every speech:
+//!
+//! ```text
+//! Two-column                                  Radio
+//!
+//! INT. STUDIO - NIGHT                         SCENE 1: INT. STUDIO - NIGHT
+//!
+//!                 A door slams.                   [A door slams.]
+//!
+//! JOE             (quietly) Are you still     1.  JOE: (quietly) Are you still
+//!                 there?                          there?
+//! ```
+//!
+//! Text is taken as it would be printed, so notes, boneyards, and emphasis markers are removed.
+
+use std::ops::Range;
+
+use crate::fountain_editing::get_dialogue_block_for_line_index;
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::fountain_text_width::get_display_width;
+use crate::fountain_wrap::wrap_words;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FNAvRowKind {
+    Scene,
+    Direction,
+    Speech,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNAvRow {
+    pub kind: FNAvRowKind,
+    /// The cue, with any extension such as `(V.O.)`. Only set for speeches.
+    pub speaker: Option<String>,
+    pub text: String,
+    /// Global indices of the lines the row was made from. The end is exclusive.
+    pub line_indices: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNAvOptions {
+    /// The width of the speaker column of the two-column layout, in columns, including the gap after it
+    pub speaker_width: usize,
+    /// The width of the line column of the two-column layout, and of the text of the radio layout
+    pub line_width: usize,
+}
+
+impl Default for FNAvOptions {
+    fn default() -> Self {
+        FNAvOptions {
+            speaker_width: 16,
+            line_width: 44,
+        }
+    }
+}
+
+/// Returns the rows of the parsed lines: scene headings, directions, and speeches, in document order.
+/// Both sides of a dual dialogue are speeches, left side first. Sections, synopses, and the title page are skipped.
+pub fn get_av_rows(lines: &[FNLine]) -> Vec<FNAvRow> {
+    let printable = get_printable_strings_for_lines(lines);
+    let mut rows: Vec<FNAvRow> = Vec::new();
+    let mut idx: usize = 0;
+
+    while idx < lines.len() {
+        let ln = &lines[idx];
+        if ln.is_any_character() {
+            let block = get_dialogue_block_for_line_index(lines, idx).unwrap_or(idx..idx + 1);
+            let text: Vec<&str> = block
+                .clone()
+                .skip(1)
+                .filter(|line_idx| {
+                    !matches!(
+                        lines[*line_idx].fn_type,
+                        FNLineType::More | FNLineType::DualDialogueMore
+                    )
+                })
+                .map(|line_idx| printable[line_idx].as_str())
+                .filter(|text| !text.is_empty())
+                .collect();
+            rows.push(FNAvRow {
+                kind: FNAvRowKind::Speech,
+                speaker: Some(printable[idx].clone()),
+                text: text.join(" "),
+                line_indices: block.clone(),
+            });
+            idx = block.end;
+            continue;
+        }
+        if ln.fn_type == FNLineType::Heading && !printable[idx].is_empty() {
+            rows.push(FNAvRow {
+                kind: FNAvRowKind::Scene,
+                speaker: None,
+                text: printable[idx].clone(),
+                line_indices: idx..idx + 1,
+            });
+        } else if is_direction(ln) && !printable[idx].is_empty() {
+            // A paragraph is a single direction
+            match rows.last_mut() {
+                Some(row)
+                    if row.kind == FNAvRowKind::Direction
+                        && row.line_indices.end == idx
+                        && ln.fn_type == lines[idx - 1].fn_type =>
+                {
+                    row.text = format!("{} {}", row.text, printable[idx]);
+                    row.line_indices.end = idx + 1;
+                }
+                _ => rows.push(FNAvRow {
+                    kind: FNAvRowKind::Direction,
+                    speaker: None,
+                    text: printable[idx].clone(),
+                    line_indices: idx..idx + 1,
+                }),
+            }
+        }
+        idx += 1;
+    }
+
+    rows
+}
+
+/// Returns the rows in two columns: the speaker on the left, and the line (or the direction) on the right.
+/// Scene headings use both columns. Rows are separated by empty lines.
+pub fn get_two_column_text(lines: &[FNLine], options: &FNAvOptions) -> String {
+    let speaker_width = options.speaker_width.max(1);
+    let mut text_rows: Vec<String> = Vec::new();
+    for row in get_av_rows(lines) {
+        if !text_rows.is_empty() {
+            text_rows.push(String::new());
+        }
+        if row.kind == FNAvRowKind::Scene {
+            text_rows.extend(wrap_words(&row.text, speaker_width + options.line_width));
+            continue;
+        }
+        // The speaker can take more than one row of its column
+        let speaker_rows = wrap_words(
+            row.speaker.as_deref().unwrap_or_default(),
+            speaker_width.saturating_sub(1).max(1),
+        );
+        let line_rows = wrap_words(&row.text, options.line_width);
+        for row_idx in 0..speaker_rows.len().max(line_rows.len()) {
+            let speaker = speaker_rows.get(row_idx).map_or("", |speaker| speaker);
+            let line = line_rows.get(row_idx).map_or("", |line| line);
+            let text_row = format!("{}{}", get_padded(speaker, speaker_width), line);
+            text_rows.push(text_row.trim_end().to_string());
+        }
+    }
+    get_joined_rows(text_rows, lines)
+}
+
+/// Returns the rows in the layout of a radio or podcast script: numbered speeches as `SPEAKER: line`, directions
+/// in brackets, and scenes numbered from 1. Speeches are numbered from 1 through the whole script.
+pub fn get_radio_script_text(lines: &[FNLine], options: &FNAvOptions) -> String {
+    let rows = get_av_rows(lines);
+    // Wide enough for the last speech number, its `.`, and a space
+    let speech_count = rows
+        .iter()
+        .filter(|row| row.kind == FNAvRowKind::Speech)
+        .count();
+    let number_width = (speech_count.to_string().len() + 2).max(4);
+    let mut text_rows: Vec<String> = Vec::new();
+    let mut scene_number: usize = 0;
+    let mut speech_number: usize = 0;
+
+    for row in rows {
+        if !text_rows.is_empty() {
+            text_rows.push(String::new());
+        }
+        let (first_prefix, text) = match row.kind {
+            FNAvRowKind::Scene => {
+                scene_number += 1;
+                text_rows.extend(wrap_words(
+                    &format!("SCENE {}: {}", scene_number, row.text),
+                    number_width + options.line_width,
+                ));
+                continue;
+            }
+            FNAvRowKind::Direction => (String::new(), format!("[{}]", row.text)),
+            FNAvRowKind::Speech => {
+                speech_number += 1;
+                (
+                    format!("{}.", speech_number),
+                    format!(
+                        "{}: {}",
+                        row.speaker.as_deref().unwrap_or_default(),
+                        row.text
+                    ),
+                )
+            }
+        };
+        for (row_idx, line) in wrap_words(&text, options.line_width).iter().enumerate() {
+            let prefix = if row_idx == 0 {
+                first_prefix.as_str()
+            } else {
+                ""
+            };
+            text_rows.push(format!("{}{}", get_padded(prefix, number_width), line));
+        }
+    }
+    get_joined_rows(text_rows, lines)
+}
+
+fn is_direction(ln: &FNLine) -> bool {
+    matches!(
+        ln.fn_type,
+        FNLineType::Action
+            | FNLineType::Centered
+            | FNLineType::Shot
+            | FNLineType::Lyrics
+            | FNLineType::TransitionLine
+    )
+}
+
+// Pads by display width, so that a wide speaker name doesn't push its line out of the column
+fn get_padded(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(get_display_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+// Rows end with the line break of the document, `\r\n` if its lines do
+fn get_joined_rows(text_rows: Vec<String>, lines: &[FNLine]) -> String {
+    let line_break = match lines.first() {
+        Some(first) if first.terminator_length == 2 => "\r\n",
+        _ => "\n",
+    };
+    let mut text = text_rows.join(line_break);
+    if !text.is_empty() {
+        text.push_str(line_break);
+    }
+    text
+}
@@ -0,0 +1,88 @@
+//! The writing direction of lines, for exporters of scripts with right-to-left (Hebrew, Arabic, ...) text.
+//!
+//! A line's direction is the direction of its first strong character (letter), as in rules P2 and P3 of the
+//! Unicode Bidirectional Algorithm (UAX #9), such as this Hebrew line of dialogue with an English name in it:
+//!
+//! ```text
+//! שלום, JOE, מה שלומך?   ->   RightToLeft
+//! ```
+//!
+//! HTML and PDF writers should set the direction of each paragraph from this; without it,
+//! right-to-left lines are laid out left-to-right, and their punctuation ends up on the wrong side.
+
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FNTextDirection {
+    LeftToRight,
+    RightToLeft,
+    /// No letters at all, such as an empty line or `...`. The direction should come from the surrounding text.
+    #[default]
+    Neutral,
+}
+
+impl FNTextDirection {
+    /// The value of the HTML `dir` attribute
+    pub fn get_html_dir(&self) -> &'static str {
+        match self {
+            FNTextDirection::LeftToRight => "ltr",
+            FNTextDirection::RightToLeft => "rtl",
+            FNTextDirection::Neutral => "auto",
+        }
+    }
+}
+
+/// Returns the direction of the first strong character of the text (rules P2 and P3 of UAX #9).
+/// Characters between an isolate initiator (`U+2066` to `U+2068`) and its `U+2069` are skipped.
+pub fn get_text_direction(text: &str) -> FNTextDirection {
+    let mut isolate_depth: usize = 0;
+    for c in text.chars() {
+        match c {
+            '\u{2066}'..='\u{2068}' => isolate_depth += 1,
+            '\u{2069}' => isolate_depth = isolate_depth.saturating_sub(1),
+            _ if isolate_depth > 0 => {}
+            _ if is_strong_rtl_char(c) => return FNTextDirection::RightToLeft,
+            _ if c.is_alphabetic() && !is_combining_mark(c) => return FNTextDirection::LeftToRight,
+            _ => {}
+        }
+    }
+    FNTextDirection::Neutral
+}
+
+/// Returns the direction of each line, from its text as it would be printed (without notes or boneyards).
+pub fn get_text_directions_for_lines(lines: &[FNLine]) -> Vec<FNTextDirection> {
+    get_printable_strings_for_lines(lines)
+        .iter()
+        .map(|text| get_text_direction(text))
+        .collect()
+}
+
+// Letters of the right-to-left scripts. Digits and punctuation in the same blocks aren't letters, so they're weak.
+fn is_strong_rtl_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF
+            | 0xFB1D..=0xFDFF
+            | 0xFE70..=0xFEFF
+            | 0x10800..=0x10FFF
+            | 0x1E800..=0x1EFFF
+    ) && c.is_alphabetic()
+        && !is_combining_mark(c)
+}
+
+// Hebrew points and Arabic harakat take the direction of the letter they're on
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0591..=0x05BD
+            | 0x05BF
+            | 0x05C1..=0x05C2
+            | 0x05C4..=0x05C5
+            | 0x05C7
+            | 0x0610..=0x061A
+            | 0x064B..=0x065F
+            | 0x0670
+            | 0x06D6..=0x06ED
+    )
+}
@@ -0,0 +1,209 @@
+//! Builds documents line by line, for tests and for generating documents programmatically.
+//!
+//! The `FNDocumentBuilder` keeps track of each line's `position`, so lines can be appended without any position math.
+//! Lines are taken as they are: their types aren't re-parsed.
+//!
+//! The `FNScriptBuilder` works at a higher level, with scenes, action, and dialogue, and emits valid Fountain text.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::fountain_document::FNDocument;
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_title_page::get_title_page_entry_lines;
+use crate::static_fountain_parser;
+
+#[derive(Debug, Clone, Default)]
+pub struct FNDocumentBuilder {
+    lines: Vec<FNLine>,
+    next_position: i32,
+}
+
+impl FNDocumentBuilder {
+    pub fn new() -> Self {
+        FNDocumentBuilder::default()
+    }
+
+    /// Appends a line, setting its `position` to the end of the document so far.
+    pub fn push_line(&mut self, line: FNLine) -> &mut Self {
+        let grapheme_len = line.raw_string.graphemes(true).count() as i32;
+        self.lines.push(line.with_position(self.next_position));
+        self.next_position += grapheme_len + 1; // +1 is to account for newline character
+        self
+    }
+
+    /// Appends a new line with the given text and type.
+    pub fn push(&mut self, raw: &str, fn_type: FNLineType) -> &mut Self {
+        self.push_line(FNLine::new(raw, fn_type))
+    }
+
+    /// Appends an `Empty` line.
+    pub fn push_empty(&mut self) -> &mut Self {
+        self.push("", FNLineType::Empty)
+    }
+
+    pub fn get_lines(&self) -> &[FNLine] {
+        &self.lines
+    }
+
+    /// Returns the raw text of the document, with a newline after every line.
+    pub fn get_text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|ln| format!("{}\n", ln.raw_string))
+            .collect()
+    }
+
+    pub fn build(self) -> Vec<FNLine> {
+        self.lines
+    }
+
+    /// Returns an `FNDocument` holding the built lines.
+    pub fn build_document(self) -> FNDocument {
+        FNDocument::from_parsed_lines(self.lines)
+    }
+}
+
+/// Builds a screenplay out of high-level elements, and emits it as Fountain text.
+///
+/// Elements are separated by empty lines. Whenever an element wouldn't be parsed as intended
+/// (such as a heading without `INT.` or `EXT.`, or a lowercase character name), its forcing marker is added,
+/// so that parsing `get_text()` always gives back the same elements.
+#[derive(Debug, Clone, Default)]
+pub struct FNScriptBuilder {
+    raw_lines: Vec<String>,
+}
+
+impl FNScriptBuilder {
+    pub fn new() -> Self {
+        FNScriptBuilder::default()
+    }
+
+    /// Appends a title page made of `(key, value)` entries, such as `("Title", "Big Fish")`.
+    /// Values with several lines are written on indented lines after the key.
+    pub fn title_page(&mut self, entries: &[(&str, &str)]) -> &mut Self {
+        let block: Vec<String> = entries
+            .iter()
+            .flat_map(|(key, value)| get_title_page_entry_lines(key, value))
+            .collect();
+        self.push_block(block)
+    }
+
+    /// Appends a section, such as `## Act Two` for a `depth` of 2.
+    pub fn section(&mut self, title: &str, depth: usize) -> &mut Self {
+        let line = format!("{} {}", "#".repeat(depth.max(1)), title.trim());
+        self.push_block(vec![line])
+    }
+
+    /// Appends a scene heading, forcing it with `.` if needed.
+    pub fn scene(&mut self, heading: &str) -> &mut Self {
+        let heading = heading.trim();
+        let line = if self.parses_as(&[heading], &[FNLineType::Heading]) {
+            heading.to_string()
+        } else {
+            format!(".{}", heading)
+        };
+        self.push_block(vec![line])
+    }
+
+    /// Appends a paragraph of action. Lines which would be parsed as something else are forced with `!`.
+    pub fn action(&mut self, text: &str) -> &mut Self {
+        let lines: Vec<String> = text.lines().map(|ln| ln.to_string()).collect();
+        let types = self.get_block_types(&lines);
+        let block = lines
+            .into_iter()
+            .zip(types)
+            .map(|(ln, fn_type)| match fn_type {
+                FNLineType::Action | FNLineType::Empty => ln,
+                _ => format!("!{}", ln),
+            })
+            .collect();
+        self.push_block(block)
+    }
+
+    /// Appends a dialogue block. Lines of `text` which start with `(` become parentheticals,
+    /// and the character name is forced with `@` if needed.
+    pub fn dialogue(&mut self, character: &str, text: &str) -> &mut Self {
+        let character = character.trim();
+        // Empty lines would end the dialogue block, so they need two spaces
+        let mut block: Vec<String> = text
+            .lines()
+            .map(|ln| {
+                if ln.trim().is_empty() {
+                    String::from("  ")
+                } else {
+                    ln.trim().to_string()
+                }
+            })
+            .collect();
+        block.insert(0, character.to_string());
+
+        let types = self.get_block_types(&block);
+        if types.first() != Some(&FNLineType::Character) {
+            block[0] = format!("@{}", character);
+        }
+        self.push_block(block)
+    }
+
+    /// Appends a transition, forcing it with `>` if needed.
+    pub fn transition(&mut self, text: &str) -> &mut Self {
+        let text = text.trim();
+        let line = if self.parses_as(&[text], &[FNLineType::TransitionLine]) {
+            text.to_string()
+        } else {
+            format!("> {}", text)
+        };
+        self.push_block(vec![line])
+    }
+
+    /// Returns the Fountain text of the script so far.
+    pub fn get_text(&self) -> String {
+        self.raw_lines
+            .iter()
+            .map(|ln| format!("{}\n", ln))
+            .collect()
+    }
+
+    /// Returns the parsed lines of the script.
+    pub fn build(&self) -> Vec<FNLine> {
+        static_fountain_parser::get_parsed_lines_from_raw_string(self.get_text())
+    }
+
+    fn push_block(&mut self, block: Vec<String>) -> &mut Self {
+        if !self.raw_lines.is_empty() {
+            self.raw_lines.push(String::new());
+        }
+        self.raw_lines.extend(block);
+        self
+    }
+
+    // Parses the block as if it were appended right now, and returns the types of its lines.
+    // Only the last element before it is parsed along with it (such as the dialogue before a dual dialogue cue),
+    // from the empty line before that element: the lines above an empty line don't change how the block is parsed.
+    fn get_block_types(&self, block: &[String]) -> Vec<FNLineType> {
+        let element_start = self
+            .raw_lines
+            .iter()
+            .rposition(|ln| ln.is_empty())
+            .unwrap_or(0);
+        let mut candidate = FNScriptBuilder {
+            raw_lines: self.raw_lines[element_start..].to_vec(),
+        };
+        candidate.push_block(block.to_vec());
+        let block_start = candidate.raw_lines.len() - block.len();
+        // A trailing empty line, so that the block is parsed as a complete element
+        candidate.raw_lines.push(String::new());
+        candidate
+            .build()
+            .into_iter()
+            .skip(block_start)
+            .take(block.len())
+            .map(|ln| ln.fn_type)
+            .collect()
+    }
+
+    fn parses_as(&self, block: &[&str], expected: &[FNLineType]) -> bool {
+        let block: Vec<String> = block.iter().map(|ln| ln.to_string()).collect();
+        self.get_block_types(&block) == expected
+    }
+}
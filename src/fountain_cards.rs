@@ -0,0 +1,110 @@
+//! Index cards for every scene, for outline and corkboard UIs.
+//!
+//! A card holds what's written on the front of a physical index card: the heading, the synopsis (the first `=` line
+//! of the scene), the first line of action, the scene's color, and its length in eighths of a page.
+//! Text is taken as it would be printed, so notes, boneyards, and emphasis markers are removed.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::helper_funcs::get_json_string;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCardOptions {
+    /// Used to measure the length of each scene
+    pub pagination: FNPaginationOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCard {
+    pub scene_index: usize,
+    pub heading_index: Option<usize>,
+    /// Empty for text before the first heading
+    pub heading: String,
+    pub synopsis: Option<String>,
+    /// The `color` of the heading line, if it has one
+    pub color: Option<String>,
+    /// The printed length of the scene, in eighths of a page. Every scene is at least `1`.
+    pub page_eighths: usize,
+    pub first_action: Option<String>,
+}
+
+impl FNCard {
+    pub fn to_json(&self) -> String {
+        let get_json_option = |value: &Option<String>| -> String {
+            match value {
+                Some(value) => get_json_string(value),
+                None => String::from("null"),
+            }
+        };
+        let heading_index = match self.heading_index {
+            Some(idx) => idx.to_string(),
+            None => String::from("null"),
+        };
+        format!(
+            "{{\"scene_index\":{},\"heading_index\":{},\"heading\":{},\"synopsis\":{},\"color\":{},\"page_eighths\":{},\"first_action\":{}}}",
+            self.scene_index,
+            heading_index,
+            get_json_string(&self.heading),
+            get_json_option(&self.synopsis),
+            get_json_option(&self.color),
+            self.page_eighths,
+            get_json_option(&self.first_action)
+        )
+    }
+}
+
+/// Returns a card for every scene, in document order.
+pub fn get_cards(lines: &[FNLine], options: &FNCardOptions) -> Vec<FNCard> {
+    let printable = get_printable_strings_for_lines(lines);
+
+    let mut line_heights: Vec<f64> = vec![0.0; lines.len()];
+    for page in get_pages(lines, &options.pagination, &FNMonospaceMeasurer) {
+        for row in page.rows {
+            if let Some(height) = line_heights.get_mut(row.line_index) {
+                *height += row.height;
+            }
+        }
+    }
+
+    get_scenes_from_parsed_lines(lines)
+        .iter()
+        .enumerate()
+        .map(|(scene_index, scene)| {
+            let range = scene.start..scene.end.min(lines.len());
+            let get_first_printable = |fn_type: FNLineType| -> Option<String> {
+                range
+                    .clone()
+                    .find(|idx| lines[*idx].fn_type == fn_type && !printable[*idx].is_empty())
+                    .map(|idx| printable[idx].clone())
+            };
+
+            let height: f64 = line_heights[range.clone()].iter().sum();
+            let page_eighths = (height / options.pagination.page_height * 8.0).round() as usize;
+
+            FNCard {
+                scene_index,
+                heading_index: scene.heading_index,
+                heading: scene
+                    .heading_index
+                    .map(|idx| printable[idx].clone())
+                    .unwrap_or_default(),
+                synopsis: get_first_printable(FNLineType::Synopse),
+                color: scene
+                    .get_heading_line(lines)
+                    .map(|ln| ln.color.trim().to_string())
+                    .filter(|color| !color.is_empty()),
+                page_eighths: page_eighths.max(1),
+                first_action: get_first_printable(FNLineType::Action),
+            }
+        })
+        .collect()
+}
+
+/// Returns the cards as a JSON array.
+pub fn get_cards_json(cards: &[FNCard]) -> String {
+    let cards: Vec<String> = cards.iter().map(|card| card.to_json()).collect();
+    format!("[{}]", cards.join(","))
+}
@@ -0,0 +1,520 @@
+//! Character analysis for parsed `FNLine` objects.
+//!
+//! Script readers commonly flag characters who speak before they've been introduced.
+//! By convention, a character is introduced the first time their name appears in ALL CAPS in an `Action` line.
+//!
+//! Roles without any lines are found the same way: `get_non_speaking_characters` lists the ALL CAPS names in
+//! `Action` lines which never get a cue, for casting breakdowns.
+//!
+//! `get_character_presence` tells which characters are in each scene, either speaking or only mentioned,
+//! for day-out-of-days style reports.
+//!
+//! Cue variations (such as "DET. SMITH" and "SMITH") can be merged with an `FNCharacterAliasMap`,
+//! so that reports aren't fragmented across several names for the same character.
+//!
+//! Callers can attach their own metadata (gender, actor, color, ...) to characters with an `FNCharacterMetadata`
+//! table. The crate doesn't interpret any of it; `get_character_list` and the draft report just carry it along.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_scene::{get_scene_index_for_line_index, get_scenes_from_parsed_lines};
+use crate::helper_funcs::{contains_whole_word, get_json_string, get_json_string_map};
+
+/// A caller-provided map of alternate character names to a single canonical name.
+///
+/// Names are compared case-insensitively. Any name which isn't in the map is its own canonical name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FNCharacterAliasMap {
+    aliases: HashMap<String, String>,
+}
+
+impl FNCharacterAliasMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `alias` as another name for `canonical`.
+    ///
+    /// If `canonical` is itself an alias, `alias` is merged into the name it resolves to, and the names which
+    /// resolved to `alias` follow it, so the same set of calls merges the same names in any order.
+    pub fn add_alias(&mut self, alias: &str, canonical: &str) {
+        let canonical = self.get_canonical_name(canonical);
+        let alias = alias.trim().to_uppercase();
+        self.aliases
+            .entry(canonical.clone())
+            .or_insert_with(|| canonical.clone());
+        if alias == canonical {
+            return;
+        }
+        for target in self.aliases.values_mut() {
+            if *target == alias {
+                *target = canonical.clone();
+            }
+        }
+        self.aliases.insert(alias, canonical);
+    }
+
+    /// Returns the canonical name for the given name, in uppercase.
+    pub fn get_canonical_name(&self, name: &str) -> String {
+        let key = name.trim().to_uppercase();
+        match self.aliases.get(&key) {
+            Some(canonical) => canonical.clone(),
+            None => key,
+        }
+    }
+
+    /// Returns every name (including the canonical name itself) which resolves to the given name's canonical name.
+    pub fn get_all_names_for(&self, name: &str) -> Vec<String> {
+        let canonical = self.get_canonical_name(name);
+        let mut names: Vec<String> = self
+            .aliases
+            .iter()
+            .filter(|(_, c)| **c == canonical)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        if !names.contains(&canonical) {
+            names.push(canonical);
+        }
+        names.sort();
+        names
+    }
+}
+
+/// A caller-provided side table of metadata for characters, such as `gender`, `actor`, or `color`.
+///
+/// Characters are keyed by their name in uppercase, like the `FNCharacterAliasMap`. Keys and values are free-form.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FNCharacterMetadata {
+    entries: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl FNCharacterMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &str, key: &str, value: &str) {
+        self.entries
+            .entry(name.trim().to_uppercase())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Removes a single value, and returns it.
+    pub fn remove(&mut self, name: &str, key: &str) -> Option<String> {
+        let name = name.trim().to_uppercase();
+        let values = self.entries.get_mut(&name)?;
+        let value = values.remove(key);
+        if values.is_empty() {
+            self.entries.remove(&name);
+        }
+        value
+    }
+
+    pub fn get(&self, name: &str, key: &str) -> Option<&str> {
+        self.get_all(name)?.get(key).map(|value| value.as_str())
+    }
+
+    /// Returns every value for the character, ordered by key.
+    pub fn get_all(&self, name: &str) -> Option<&BTreeMap<String, String>> {
+        self.entries.get(&name.trim().to_uppercase())
+    }
+
+    /// Same as `get_all`, but looks up the canonical name of the character first, then the name itself.
+    pub fn get_all_with_aliases(
+        &self,
+        name: &str,
+        aliases: &FNCharacterAliasMap,
+    ) -> Option<&BTreeMap<String, String>> {
+        self.get_all(&aliases.get_canonical_name(name))
+            .or_else(|| self.get_all(name))
+    }
+}
+
+/// The first appearance of a single character in the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNCharacterIntroduction {
+    pub name: String,
+    /// Global index of the first character cue for this character
+    pub first_cue_index: usize,
+    /// Index of the scene (from `fountain_scene::get_scenes_from_parsed_lines`) containing the first cue
+    pub first_cue_scene_index: Option<usize>,
+    /// Global index of the first `Action` line before the first cue which mentions the name in ALL CAPS
+    pub introduction_index: Option<usize>,
+}
+
+impl FNCharacterIntroduction {
+    pub fn is_introduced_before_speaking(&self) -> bool {
+        self.introduction_index.is_some()
+    }
+}
+
+/// Returns an `FNCharacterIntroduction` for every character who has at least one cue,
+/// ordered by the position of their first cue.
+pub fn get_character_introductions(lines: &[FNLine]) -> Vec<FNCharacterIntroduction> {
+    get_character_introductions_with_aliases(lines, &FNCharacterAliasMap::default())
+}
+
+/// Same as `get_character_introductions`, but merges characters using the given `FNCharacterAliasMap`.
+///
+/// An introduction using any of the character's aliases counts as an introduction.
+pub fn get_character_introductions_with_aliases(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+) -> Vec<FNCharacterIntroduction> {
+    let scenes = get_scenes_from_parsed_lines(lines);
+
+    let mut first_cues: HashMap<String, usize> = HashMap::new();
+    for (idx, ln) in lines.iter().enumerate() {
+        if let Some(name) = get_canonical_character_name(ln, aliases) {
+            first_cues.entry(name).or_insert(idx);
+        }
+    }
+
+    let mut introductions: Vec<FNCharacterIntroduction> = first_cues
+        .into_iter()
+        .map(|(name, first_cue_index)| {
+            let all_names = aliases.get_all_names_for(&name);
+            let introduction_index = lines[..first_cue_index].iter().position(|ln| {
+                ln.fn_type == FNLineType::Action
+                    && all_names
+                        .iter()
+                        .any(|alias| contains_whole_word(&ln.string, alias))
+            });
+            FNCharacterIntroduction {
+                first_cue_scene_index: get_scene_index_for_line_index(&scenes, first_cue_index),
+                name,
+                first_cue_index,
+                introduction_index,
+            }
+        })
+        .collect();
+
+    introductions.sort_by_key(|intro| intro.first_cue_index);
+    introductions
+}
+
+/// Returns only the characters who speak before an ALL CAPS introduction in an earlier `Action` line,
+/// merging characters with the given `FNCharacterAliasMap`.
+pub fn get_characters_speaking_before_introduction(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+) -> Vec<FNCharacterIntroduction> {
+    get_character_introductions_with_aliases(lines, aliases)
+        .into_iter()
+        .filter(|intro| !intro.is_introduced_before_speaking())
+        .collect()
+}
+
+/// Returns the number of character cues for each (canonical) character, ordered from most to fewest cues.
+pub fn get_cue_counts_for_characters(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for ln in lines {
+        if let Some(name) = get_canonical_character_name(ln, aliases) {
+            *counts.entry(name).or_insert(0) += 1;
+        }
+    }
+    let mut counts_vec: Vec<(String, usize)> = counts.into_iter().collect();
+    counts_vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts_vec
+}
+
+/// Which characters are present in each scene, indexed like `fountain_scene::get_scenes_from_parsed_lines`.
+///
+/// A character is present in a scene if they have a cue in it, or if their name (or an alias) is mentioned
+/// in ALL CAPS in one of its `Action` lines. Only characters with at least one cue anywhere are counted,
+/// so that sound effects and other capitalized words aren't mistaken for characters.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCharacterPresence {
+    /// Every character with a cue, ordered by the position of their first cue
+    pub characters: Vec<String>,
+    /// For each scene, the characters with a cue in it
+    pub speaking: Vec<BTreeSet<String>>,
+    /// For each scene, the characters mentioned in ALL CAPS in its action, whether they speak in it or not
+    pub mentioned: Vec<BTreeSet<String>>,
+}
+
+impl FNCharacterPresence {
+    /// Returns every character who speaks or is mentioned in the scene.
+    pub fn get_characters_in_scene(&self, scene_index: usize) -> BTreeSet<String> {
+        let speaking = self.speaking.get(scene_index).into_iter().flatten();
+        let mentioned = self.mentioned.get(scene_index).into_iter().flatten();
+        speaking.chain(mentioned).cloned().collect()
+    }
+
+    /// Returns `true` if the character speaks or is mentioned in the scene. The name is matched without case.
+    pub fn is_present(&self, scene_index: usize, name: &str) -> bool {
+        let name = name.trim().to_uppercase();
+        self.speaking
+            .get(scene_index)
+            .is_some_and(|names| names.contains(&name))
+            || self
+                .mentioned
+                .get(scene_index)
+                .is_some_and(|names| names.contains(&name))
+    }
+
+    /// Returns the index of every scene the character is present in, in document order.
+    ///
+    /// The name must be canonical, as the names are in `characters`.
+    pub fn get_scenes_for_character(&self, name: &str) -> Vec<usize> {
+        (0..self.speaking.len())
+            .filter(|scene_index| self.is_present(*scene_index, name))
+            .collect()
+    }
+}
+
+/// Returns the characters present in every scene, merging characters with the given `FNCharacterAliasMap`.
+pub fn get_character_presence(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+) -> FNCharacterPresence {
+    let scenes = get_scenes_from_parsed_lines(lines);
+    let characters: Vec<String> = get_character_introductions_with_aliases(lines, aliases)
+        .into_iter()
+        .map(|intro| intro.name)
+        .collect();
+    let all_names: Vec<Vec<String>> = characters
+        .iter()
+        .map(|name| aliases.get_all_names_for(name))
+        .collect();
+
+    let mut presence = FNCharacterPresence {
+        speaking: vec![BTreeSet::new(); scenes.len()],
+        mentioned: vec![BTreeSet::new(); scenes.len()],
+        ..Default::default()
+    };
+    for (idx, ln) in lines.iter().enumerate() {
+        let Some(scene_index) = get_scene_index_for_line_index(&scenes, idx) else {
+            continue;
+        };
+        if let Some(name) = get_canonical_character_name(ln, aliases) {
+            presence.speaking[scene_index].insert(name);
+        } else if ln.fn_type == FNLineType::Action {
+            for (name, names) in characters.iter().zip(&all_names) {
+                if names
+                    .iter()
+                    .any(|alias| contains_whole_word(&ln.string, alias))
+                {
+                    presence.mentioned[scene_index].insert(name.clone());
+                }
+            }
+        }
+    }
+    presence.characters = characters;
+    presence
+}
+
+/// A name introduced in ALL CAPS in an `Action` line, which never gets a cue.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNNonSpeakingCharacter {
+    /// The canonical name, in uppercase
+    pub name: String,
+    /// Global index of the first `Action` line with the name
+    pub introduction_index: usize,
+    /// Global indices of every `Action` line with the name in ALL CAPS, including the introduction
+    pub mention_indices: Vec<usize>,
+    /// Indices of every scene with a mention, in document order
+    pub scene_indices: Vec<usize>,
+}
+
+/// Returns every name mentioned in ALL CAPS in an `Action` line which isn't the name (or an alias) of
+/// a character with a cue, ordered by their introduction.
+///
+/// A name is a run of ALL CAPS words, such as `DET. SMITH` or `GUARD #2`. Runs ending in `!` are
+/// taken as sound effects (`BANG!`), and single letters are skipped. Capitalized sounds without a `!`
+/// (the door SLAMS) can't be told apart from names; merge or drop them with the alias map or in the caller.
+pub fn get_non_speaking_characters(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+) -> Vec<FNNonSpeakingCharacter> {
+    let scenes = get_scenes_from_parsed_lines(lines);
+    let speaking: BTreeSet<String> = lines
+        .iter()
+        .filter_map(|ln| get_canonical_character_name(ln, aliases))
+        .collect();
+
+    let mut characters: Vec<FNNonSpeakingCharacter> = Vec::new();
+    for (idx, ln) in lines.iter().enumerate() {
+        if ln.fn_type != FNLineType::Action {
+            continue;
+        }
+        for name in get_all_caps_names(&ln.string) {
+            let name = aliases.get_canonical_name(&name);
+            if speaking.contains(&name) {
+                continue;
+            }
+            let character = match characters.iter().position(|c| c.name == name) {
+                Some(position) => &mut characters[position],
+                None => {
+                    characters.push(FNNonSpeakingCharacter {
+                        name,
+                        introduction_index: idx,
+                        ..Default::default()
+                    });
+                    characters.last_mut().unwrap()
+                }
+            };
+            if character.mention_indices.last() != Some(&idx) {
+                character.mention_indices.push(idx);
+            }
+            if let Some(scene_index) = get_scene_index_for_line_index(&scenes, idx) {
+                if character.scene_indices.last() != Some(&scene_index) {
+                    character.scene_indices.push(scene_index);
+                }
+            }
+        }
+    }
+    characters
+}
+
+// Runs of ALL CAPS words in a line of action, without trailing punctuation or a possessive `'s`
+fn get_all_caps_names(text: &str) -> Vec<String> {
+    const RUN_ENDING_PUNCTUATION: [char; 8] = [',', ';', ':', '?', '!', ')', '"', '\u{2019}'];
+
+    let mut names: Vec<String> = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut flush = |run: &mut Vec<&str>, is_shouted: bool| {
+        // A leading `A` or `I` is an article or a pronoun, not part of the name
+        let first_word = run.iter().position(|word| word.chars().count() > 1);
+        let name = first_word.map_or(String::new(), |first| run[first..].join(" "));
+        let name = name.trim_end_matches('.');
+        if !is_shouted && name.chars().filter(|c| c.is_alphabetic()).count() > 1 {
+            names.push(name.to_string());
+        }
+        run.clear();
+    };
+
+    for token in text.split_whitespace() {
+        let word = token.trim_start_matches(['(', '"', '\'', '\u{201C}']);
+        let core = word.trim_end_matches(RUN_ENDING_PUNCTUATION);
+        let possessive = ["'S", "'s", "\u{2019}S", "\u{2019}s"]
+            .into_iter()
+            .find_map(|suffix| core.strip_suffix(suffix));
+        let (core, is_possessive) = match possessive {
+            Some(core) => (core, true),
+            None => (core, false),
+        };
+        // A number like the `#2` in `GUARD #2` continues a name, but doesn't start one
+        let is_name_part =
+            core.chars().any(char::is_alphabetic) || (!run.is_empty() && core.starts_with('#'));
+        let is_caps = is_name_part && !core.chars().any(char::is_lowercase);
+        if !is_caps {
+            flush(&mut run, false);
+            continue;
+        }
+        run.push(core);
+        if is_possessive || core.len() < word.len() {
+            flush(&mut run, word.ends_with('!'));
+        }
+    }
+    flush(&mut run, false);
+    names
+}
+
+/// A single character in a character list, with their caller-provided metadata.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCharacterListEntry {
+    pub name: String,
+    pub cue_count: usize,
+    /// Indices of every scene the character speaks or is mentioned in
+    pub scene_indices: Vec<usize>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl FNCharacterListEntry {
+    pub fn is_speaking(&self) -> bool {
+        self.cue_count > 0
+    }
+
+    pub fn to_json(&self) -> String {
+        let scene_indices: Vec<String> = self
+            .scene_indices
+            .iter()
+            .map(|idx| idx.to_string())
+            .collect();
+        format!(
+            "{{\"name\":{},\"cue_count\":{},\"scene_indices\":[{}],\"metadata\":{}}}",
+            get_json_string(&self.name),
+            self.cue_count,
+            scene_indices.join(","),
+            get_json_string_map(&self.metadata)
+        )
+    }
+}
+
+/// Returns every character, speaking ones first (from most to fewest cues), then non-speaking ones
+/// in order of their introduction, each with their metadata.
+pub fn get_character_list(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+    metadata: &FNCharacterMetadata,
+) -> Vec<FNCharacterListEntry> {
+    let presence = get_character_presence(lines, aliases);
+    let get_metadata = |name: &str| -> BTreeMap<String, String> {
+        metadata
+            .get_all_with_aliases(name, aliases)
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let speaking = get_cue_counts_for_characters(lines, aliases)
+        .into_iter()
+        .map(|(name, cue_count)| FNCharacterListEntry {
+            scene_indices: presence.get_scenes_for_character(&name),
+            metadata: get_metadata(&name),
+            name,
+            cue_count,
+        });
+    let non_speaking = get_non_speaking_characters(lines, aliases)
+        .into_iter()
+        .map(|character| FNCharacterListEntry {
+            metadata: get_metadata(&character.name),
+            name: character.name,
+            cue_count: 0,
+            scene_indices: character.scene_indices,
+        });
+    speaking.chain(non_speaking).collect()
+}
+
+/// Returns the character list as a JSON array.
+pub fn get_character_list_json(entries: &[FNCharacterListEntry]) -> String {
+    let entries: Vec<String> = entries.iter().map(|entry| entry.to_json()).collect();
+    format!("[{}]", entries.join(","))
+}
+
+/// Returns the canonical character name for a character cue, or `None` if the line isn't a cue.
+pub fn get_canonical_character_name(
+    line: &FNLine,
+    aliases: &FNCharacterAliasMap,
+) -> Option<String> {
+    line.get_character_name()
+        .map(|name| aliases.get_canonical_name(&name))
+}
+
+/// Returns the name of the character speaking a dialogue or parenthetical line (single or dual),
+/// or the name on the cue itself. Returns `None` for any other line.
+pub fn get_speaking_character_for_line_index(
+    lines: &[FNLine],
+    global_index: usize,
+) -> Option<String> {
+    let mut idx = global_index;
+    loop {
+        let ln = lines.get(idx)?;
+        if ln.is_any_character() {
+            return ln.get_character_name();
+        }
+        let is_in_block = ln.is_dialogue_element()
+            || ln.is_dual_dialogue_element()
+            || ln.fn_type == FNLineType::More;
+        if !is_in_block || idx == 0 {
+            return None;
+        }
+        idx -= 1;
+    }
+}
@@ -0,0 +1,117 @@
+//! Scene colors and markers, written as notes the way Beat writes them:
+//!
+//! ```text
+//! INT. BAR - NIGHT [[COLOR RED]]       ->  the heading's `color` is `red`
+//! # Act One [[blue]]                   ->  the section's `color` is `blue`
+//! Joe leaves. [[marker green: cut?]]   ->  a green marker on the line, described as `cut?`
+//! ```
+//!
+//! A color is one of the names of `get_color_names`, or a hex color such as `#ff8800`. Colors are only set on
+//! headings and sections, while markers can be on any line. The full parse fills in the `color` of each line,
+//! so that the outline, the cards, and the exports keep a writer's color coding.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_notes::get_notes;
+
+/// A marker left on a line, with an optional color and description.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNMarker {
+    /// Global index of the line the marker's note starts on
+    pub line_index: usize,
+    pub color: Option<String>,
+    pub description: String,
+}
+
+/// Returns the names of the colors, with the hex color each one is exported as.
+pub fn get_color_names() -> &'static [(&'static str, &'static str)] {
+    &[
+        ("red", "#e0404a"),
+        ("blue", "#2f7fe0"),
+        ("green", "#36b04a"),
+        ("pink", "#f29bd0"),
+        ("magenta", "#d83cc0"),
+        ("gray", "#a0a0a0"),
+        ("grey", "#a0a0a0"),
+        ("purple", "#9450d0"),
+        ("prussian", "#1c3f66"),
+        ("yellow", "#f0c832"),
+        ("cyan", "#3cc8e0"),
+        ("teal", "#20a0a0"),
+        ("orange", "#f08c28"),
+        ("brown", "#9a6434"),
+    ]
+}
+
+/// Returns the color as a lowercase `#rrggbb` hex color, or `None` if it isn't a color name or a hex color.
+pub fn get_hex_color(color: &str) -> Option<String> {
+    let color = color.trim().to_lowercase();
+    if let Some(digits) = color.strip_prefix('#') {
+        if digits.len() == 6 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Some(color);
+        }
+        return None;
+    }
+    get_color_names()
+        .iter()
+        .find(|(name, _)| *name == color)
+        .map(|(_, hex)| hex.to_string())
+}
+
+/// Returns the color set by the text of a note, such as `COLOR RED`, `red`, or `#ff8800`, in lowercase.
+/// Returns `None` if the note isn't only a color.
+pub fn get_color_from_note(note: &str) -> Option<String> {
+    let mut color = note.trim();
+    for prefix in ["color ", "colour "] {
+        if color
+            .get(..prefix.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+        {
+            color = color[prefix.len()..].trim_start();
+        }
+    }
+    get_hex_color(color)?;
+    Some(color.to_lowercase())
+}
+
+/// Returns every marker (`[[marker]]`, `[[marker red]]`, or `[[marker red: description]]`) in document order.
+pub fn get_markers(lines: &[FNLine]) -> Vec<FNMarker> {
+    get_notes(lines)
+        .iter()
+        .filter_map(|note| {
+            let text = note.text.trim();
+            let rest = text
+                .get(..6)
+                .filter(|head| head.eq_ignore_ascii_case("marker"))
+                .map(|_| &text[6..])?;
+            if !rest.is_empty() && !rest.starts_with(|c: char| c.is_whitespace() || c == ':') {
+                return None;
+            }
+            let (color, description) = rest.split_once(':').unwrap_or((rest, ""));
+            let color = match color.trim() {
+                "" => None,
+                color => Some(get_color_from_note(color)?),
+            };
+            Some(FNMarker {
+                line_index: note.start_index,
+                color,
+                description: description.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Returns a copy of the lines with the `color` of each heading and section set from its first color note.
+pub fn get_lines_with_colors(lines: &[FNLine]) -> Vec<FNLine> {
+    let mut colored: Vec<FNLine> = lines.to_vec();
+    for note in get_notes(lines).iter().rev() {
+        let ln = &mut colored[note.start_index];
+        if !matches!(ln.fn_type, FNLineType::Heading | FNLineType::Section) {
+            continue;
+        }
+        if let Some(color) = get_color_from_note(&note.text) {
+            ln.color = color;
+        }
+    }
+    colored
+}
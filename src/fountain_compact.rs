@@ -0,0 +1,277 @@
+//! A compact, read-only representation of parsed lines, for embedding in memory-constrained apps.
+//!
+//! Every `FNLine` owns its `string` and `raw_string`, and carries nine range sets and a few more strings,
+//! most of which are empty. An `FNCompactDocument` stores the text of all lines in one buffer, and each line
+//! only holds its type and offsets into that buffer. Range sets, notes, scene numbers and colors are only
+//! stored for the lines which have them.
+//!
+//! Lines can be turned back into `FNLine`s one at a time with `get_line`, when a full line is needed.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use uuid::Uuid;
+
+use crate::fountain_enums::{FNForcedBy, FNLineType, FNPartialLineType};
+use crate::fountain_line::FNLine;
+use crate::fountain_partial_line_range::FNRangedElementMarkers;
+use crate::intervals::RangeSet;
+
+#[derive(Debug, Clone, PartialEq)]
+struct FNCompactLine {
+    fn_type: FNLineType,
+    forced_by: FNForcedBy,
+    is_forced: bool,
+    forced_character_cue: bool,
+    section_depth: i32,
+    position: i32,
+    length: i32,
+    terminator_length: i32,
+    /// Byte range of `raw_string` in the text buffer
+    raw_range: Range<usize>,
+    /// Byte range of `string` in the text buffer
+    string_range: Range<usize>,
+    uuid: Option<Uuid>,
+}
+
+// Everything which is empty for most lines
+#[derive(Debug, Clone, PartialEq, Default)]
+struct FNCompactLineExtras {
+    scene_number: String,
+    color: String,
+    bold_ranges: RangeSet,
+    italic_ranges: RangeSet,
+    underlined_ranges: RangeSet,
+    bold_italic_ranges: RangeSet,
+    strikeout_ranges: RangeSet,
+    note_ranges: RangeSet,
+    omitted_ranges: RangeSet,
+    additional_note_ranges: RangeSet,
+    additional_omitted_ranges: RangeSet,
+    escape_ranges: RangeSet,
+    removal_suggestion_ranges: RangeSet,
+    note_type: Option<FNPartialLineType>,
+    boneyard_type: Option<FNPartialLineType>,
+    note_markers: Option<FNRangedElementMarkers>,
+    boneyard_markers: Option<FNRangedElementMarkers>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCompactDocument {
+    text: String,
+    lines: Vec<FNCompactLine>,
+    extras: HashMap<usize, Box<FNCompactLineExtras>>,
+}
+
+impl FNCompactDocument {
+    pub fn from_lines(lines: &[FNLine]) -> Self {
+        let mut document = FNCompactDocument {
+            text: String::with_capacity(lines.iter().map(|ln| ln.raw_string.len() + 1).sum()),
+            lines: Vec::with_capacity(lines.len()),
+            extras: HashMap::new(),
+        };
+
+        for (idx, ln) in lines.iter().enumerate() {
+            let raw_start = document.text.len();
+            document.text.push_str(&ln.raw_string);
+            let raw_range = raw_start..document.text.len();
+            // `string` is usually the end of `raw_string`, after any stripped marker;
+            // if it was changed some other way, it gets its own place in the buffer
+            let string_range = if ln.raw_string.ends_with(&ln.string) {
+                raw_range.end - ln.string.len()..raw_range.end
+            } else {
+                let string_start = document.text.len();
+                document.text.push_str(&ln.string);
+                string_start..document.text.len()
+            };
+
+            document.lines.push(FNCompactLine {
+                fn_type: ln.fn_type.clone(),
+                forced_by: ln.forced_by.clone(),
+                is_forced: ln.is_forced,
+                forced_character_cue: ln.forced_character_cue,
+                section_depth: ln.section_depth,
+                position: ln.position,
+                length: ln.length,
+                terminator_length: ln.terminator_length,
+                raw_range,
+                string_range,
+                uuid: ln.uuid,
+            });
+
+            let extras = FNCompactLineExtras {
+                scene_number: ln.scene_number.clone(),
+                color: ln.color.clone(),
+                bold_ranges: ln.bold_ranges.clone(),
+                italic_ranges: ln.italic_ranges.clone(),
+                underlined_ranges: ln.underlined_ranges.clone(),
+                bold_italic_ranges: ln.bold_italic_ranges.clone(),
+                strikeout_ranges: ln.strikeout_ranges.clone(),
+                note_ranges: ln.note_ranges.clone(),
+                omitted_ranges: ln.omitted_ranges.clone(),
+                additional_note_ranges: ln.additional_note_ranges.clone(),
+                additional_omitted_ranges: ln.additional_omitted_ranges.clone(),
+                escape_ranges: ln.escape_ranges.clone(),
+                removal_suggestion_ranges: ln.removal_suggestion_ranges.clone(),
+                note_type: ln.note_type.clone(),
+                boneyard_type: ln.boneyard_type.clone(),
+                note_markers: ln.note_markers.clone(),
+                boneyard_markers: ln.boneyard_markers.clone(),
+            };
+            if extras != FNCompactLineExtras::default() {
+                document.extras.insert(idx, Box::new(extras));
+            }
+        }
+        document.text.shrink_to_fit();
+        document
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn get_line_type(&self, index: usize) -> Option<&FNLineType> {
+        self.lines.get(index).map(|ln| &ln.fn_type)
+    }
+
+    pub fn get_string(&self, index: usize) -> Option<&str> {
+        self.lines
+            .get(index)
+            .map(|ln| &self.text[ln.string_range.clone()])
+    }
+
+    pub fn get_raw_string(&self, index: usize) -> Option<&str> {
+        self.lines
+            .get(index)
+            .map(|ln| &self.text[ln.raw_range.clone()])
+    }
+
+    /// Returns a full copy of a line.
+    pub fn get_line(&self, index: usize) -> Option<FNLine> {
+        let compact = self.lines.get(index)?;
+        let extras = self
+            .extras
+            .get(&index)
+            .map(|extras| extras.as_ref().clone())
+            .unwrap_or_default();
+        Some(FNLine {
+            fn_type: compact.fn_type.clone(),
+            string: self.text[compact.string_range.clone()].to_string(),
+            raw_string: self.text[compact.raw_range.clone()].to_string(),
+            position: compact.position,
+            length: compact.length,
+            terminator_length: compact.terminator_length,
+            section_depth: compact.section_depth,
+            scene_number: extras.scene_number,
+            color: extras.color,
+            is_forced: compact.is_forced,
+            forced_character_cue: compact.forced_character_cue,
+            forced_by: compact.forced_by.clone(),
+            bold_ranges: extras.bold_ranges,
+            italic_ranges: extras.italic_ranges,
+            underlined_ranges: extras.underlined_ranges,
+            bold_italic_ranges: extras.bold_italic_ranges,
+            strikeout_ranges: extras.strikeout_ranges,
+            note_ranges: extras.note_ranges,
+            omitted_ranges: extras.omitted_ranges,
+            additional_note_ranges: extras.additional_note_ranges,
+            additional_omitted_ranges: extras.additional_omitted_ranges,
+            escape_ranges: extras.escape_ranges,
+            removal_suggestion_ranges: extras.removal_suggestion_ranges,
+            note_type: extras.note_type,
+            boneyard_type: extras.boneyard_type,
+            note_markers: extras.note_markers,
+            boneyard_markers: extras.boneyard_markers,
+            uuid: compact.uuid,
+        })
+    }
+
+    /// Returns full copies of all lines.
+    pub fn get_lines(&self) -> Vec<FNLine> {
+        (0..self.len())
+            .filter_map(|idx| self.get_line(idx))
+            .collect()
+    }
+
+    /// An estimate of the memory used by the document, in bytes, for comparing with `get_memory_size_of_lines`.
+    pub fn get_memory_size(&self) -> usize {
+        let extras_size: usize = self
+            .extras
+            .values()
+            .map(|extras| {
+                std::mem::size_of::<FNCompactLineExtras>()
+                    + extras.scene_number.capacity()
+                    + extras.color.capacity()
+                    + get_range_sets_memory_size(&[
+                        &extras.bold_ranges,
+                        &extras.italic_ranges,
+                        &extras.underlined_ranges,
+                        &extras.bold_italic_ranges,
+                        &extras.strikeout_ranges,
+                        &extras.note_ranges,
+                        &extras.omitted_ranges,
+                        &extras.additional_note_ranges,
+                        &extras.additional_omitted_ranges,
+                        &extras.escape_ranges,
+                        &extras.removal_suggestion_ranges,
+                    ])
+                    + get_markers_memory_size(&[&extras.note_markers, &extras.boneyard_markers])
+            })
+            .sum();
+        std::mem::size_of::<Self>()
+            + self.text.capacity()
+            + self.lines.capacity() * std::mem::size_of::<FNCompactLine>()
+            + self.extras.capacity()
+                * (std::mem::size_of::<usize>() + std::mem::size_of::<Box<FNCompactLineExtras>>())
+            + extras_size
+    }
+}
+
+/// An estimate of the memory used by a `Vec` of lines, in bytes.
+pub fn get_memory_size_of_lines(lines: &[FNLine]) -> usize {
+    let lines_size: usize = lines
+        .iter()
+        .map(|ln| {
+            ln.string.capacity()
+                + ln.raw_string.capacity()
+                + ln.scene_number.capacity()
+                + ln.color.capacity()
+                + get_range_sets_memory_size(&[
+                    &ln.bold_ranges,
+                    &ln.italic_ranges,
+                    &ln.underlined_ranges,
+                    &ln.bold_italic_ranges,
+                    &ln.strikeout_ranges,
+                    &ln.note_ranges,
+                    &ln.omitted_ranges,
+                    &ln.additional_note_ranges,
+                    &ln.additional_omitted_ranges,
+                    &ln.escape_ranges,
+                    &ln.removal_suggestion_ranges,
+                ])
+                + get_markers_memory_size(&[&ln.note_markers, &ln.boneyard_markers])
+        })
+        .sum();
+    std::mem::size_of::<Vec<FNLine>>() + std::mem::size_of_val(lines) + lines_size
+}
+
+fn get_range_sets_memory_size(range_sets: &[&RangeSet]) -> usize {
+    range_sets
+        .iter()
+        .map(|set| std::mem::size_of_val(set.ranges()))
+        .sum()
+}
+
+fn get_markers_memory_size(markers: &[&Option<FNRangedElementMarkers>]) -> usize {
+    markers
+        .iter()
+        .filter_map(|markers| markers.as_ref())
+        .map(|markers| {
+            std::mem::size_of_val(&markers.opens[..]) + std::mem::size_of_val(&markers.closes[..])
+        })
+        .sum()
+}
@@ -0,0 +1,247 @@
+//! Completions for scene headings, ranked from most to least likely.
+//!
+//! After a prefix, the locations of the document are suggested. After the ` - ` which ends the location,
+//! times of day are suggested: first the ones already used at that location, then the ones used anywhere
+//! in the document, and then common ones:
+//!
+//! ```text
+//! INT. KI              ->  INT. KITCHEN, INT. KIDS' ROOM, ...
+//! INT. KITCHEN - N     ->  INT. KITCHEN - NIGHT, INT. KITCHEN - NOON, ...
+//! ```
+//!
+//! Each completion has the byte ranges of its label which match the typed text, for highlighting.
+
+use std::ops::Range;
+
+use crate::fountain_heading::{get_scene_heading_from_line, get_scene_heading_from_string};
+use crate::fountain_line::FNLine;
+use crate::fountain_locations::{
+    get_location_clusters, get_normalized_location, FNLocationCluster,
+    FNLocationNormalizationOptions,
+};
+use crate::helper_funcs::get_edit_distance;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNHeadingCompletionOptions {
+    /// Times of day suggested even if the document never uses them, in order of preference
+    pub common_times_of_day: Vec<String>,
+    /// Decides which headings are at the same location as the one being typed
+    pub location_normalization: FNLocationNormalizationOptions,
+}
+
+impl Default for FNHeadingCompletionOptions {
+    fn default() -> Self {
+        FNHeadingCompletionOptions {
+            common_times_of_day: [
+                "DAY",
+                "NIGHT",
+                "CONTINUOUS",
+                "LATER",
+                "MOMENTS LATER",
+                "MORNING",
+                "AFTERNOON",
+                "EVENING",
+                "DAWN",
+                "DUSK",
+                "SAME",
+            ]
+            .iter()
+            .map(|time| time.to_string())
+            .collect(),
+            location_normalization: FNLocationNormalizationOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FNHeadingCompletionKind {
+    Location,
+    TimeOfDay,
+}
+
+/// Where a completion was found. Completions are ranked in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FNHeadingCompletionSource {
+    /// A time of day already used at the location being typed
+    SameLocation,
+    /// A location, or a time of day used somewhere else in the document
+    Document,
+    /// One of the `common_times_of_day`
+    Common,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNHeadingCompletion {
+    pub kind: FNHeadingCompletionKind,
+    pub source: FNHeadingCompletionSource,
+    /// The suggested location or time of day alone, such as `NIGHT`
+    pub label: String,
+    /// The whole heading, to replace the typed text with
+    pub text: String,
+    /// Byte ranges of `label` which match the typed text
+    pub match_ranges: Vec<Range<usize>>,
+    /// How many headings use this location, or this time of day (at the same location, for `SameLocation`)
+    pub count: usize,
+}
+
+/// Returns the completions for `typed`, the text of a heading line up to the cursor, from best to worst.
+/// Text without a heading prefix (or a forcing `.`) has no completions.
+pub fn get_heading_completions(
+    lines: &[FNLine],
+    typed: &str,
+    options: &FNHeadingCompletionOptions,
+) -> Vec<FNHeadingCompletion> {
+    let heading = get_scene_heading_from_string(typed);
+    if heading.prefix.is_empty() && !typed.trim_start().starts_with('.') {
+        return Vec::new();
+    }
+    let clusters = get_location_clusters(lines, &options.location_normalization);
+
+    match typed.rfind(" - ") {
+        Some(dash) => get_time_of_day_completions(
+            lines,
+            &clusters,
+            &typed[..dash + 3],
+            &typed[dash + 3..],
+            options,
+        ),
+        None => {
+            let typed_location = &heading.location;
+            let mut completions: Vec<FNHeadingCompletion> = clusters
+                .iter()
+                .filter_map(|cluster| {
+                    let match_ranges = get_match_ranges(&cluster.canonical_name, typed_location)?;
+                    Some(FNHeadingCompletion {
+                        kind: FNHeadingCompletionKind::Location,
+                        source: FNHeadingCompletionSource::Document,
+                        label: cluster.canonical_name.clone(),
+                        text: get_heading_text(
+                            typed,
+                            typed_location,
+                            heading.prefix.is_empty(),
+                            &cluster.canonical_name,
+                        ),
+                        match_ranges,
+                        count: cluster.heading_indices.len(),
+                    })
+                })
+                .collect();
+            // Stable, so locations with as many headings stay in order of first appearance
+            completions.sort_by_key(|completion| {
+                (
+                    completion.match_ranges.first().map(|range| range.start) != Some(0),
+                    std::cmp::Reverse(completion.count),
+                )
+            });
+            completions
+        }
+    }
+}
+
+fn get_time_of_day_completions(
+    lines: &[FNLine],
+    clusters: &[FNLocationCluster],
+    typed_before_time: &str,
+    typed_time: &str,
+    options: &FNHeadingCompletionOptions,
+) -> Vec<FNHeadingCompletion> {
+    let location =
+        get_scene_heading_from_string(typed_before_time.trim_end_matches(" - ")).location;
+    let normalized = get_normalized_location(&location);
+    let cluster = clusters.iter().find(|cluster| {
+        cluster.normalized_name == normalized
+            || options
+                .location_normalization
+                .fuzzy_max_distance
+                .is_some_and(|max| get_edit_distance(&cluster.normalized_name, &normalized) <= max)
+    });
+
+    // (source, time, count), in order of first appearance
+    let mut candidates: Vec<(FNHeadingCompletionSource, String, usize)> = Vec::new();
+    let mut add_candidate = |source: FNHeadingCompletionSource, time: &str| {
+        let time = time.trim().to_uppercase();
+        if time.is_empty() {
+            return;
+        }
+        match candidates
+            .iter_mut()
+            .find(|(_, existing, _)| *existing == time)
+        {
+            Some(candidate) if candidate.0 == source => candidate.2 += 1,
+            Some(_) => {}
+            None => candidates.push((source, time, 1)),
+        }
+    };
+    let time_at = |idx: usize| get_scene_heading_from_line(&lines[idx])?.time_of_day;
+    for idx in cluster.map_or(&[][..], |cluster| &cluster.heading_indices[..]) {
+        if let Some(time) = time_at(*idx) {
+            add_candidate(FNHeadingCompletionSource::SameLocation, &time);
+        }
+    }
+    for idx in 0..lines.len() {
+        if let Some(time) = time_at(idx) {
+            add_candidate(FNHeadingCompletionSource::Document, &time);
+        }
+    }
+    for time in &options.common_times_of_day {
+        add_candidate(FNHeadingCompletionSource::Common, time);
+    }
+
+    let mut completions: Vec<FNHeadingCompletion> = candidates
+        .into_iter()
+        .filter_map(|(source, time, count)| {
+            let match_ranges = get_match_ranges(&time, typed_time.trim())?;
+            Some(FNHeadingCompletion {
+                kind: FNHeadingCompletionKind::TimeOfDay,
+                source,
+                text: format!("{}{}", typed_before_time, time),
+                label: time,
+                match_ranges,
+                count: match source {
+                    FNHeadingCompletionSource::Common => 0,
+                    _ => count,
+                },
+            })
+        })
+        .collect();
+    completions.sort_by_key(|completion| {
+        (
+            completion.source,
+            completion.match_ranges.first().map(|range| range.start) != Some(0),
+            std::cmp::Reverse(completion.count),
+        )
+    });
+    completions
+}
+
+// The typed text up to the location, followed by the completed location
+fn get_heading_text(typed: &str, typed_location: &str, is_forced: bool, location: &str) -> String {
+    let before = typed.trim_end();
+    let before = before
+        .strip_suffix(typed_location)
+        .unwrap_or(before)
+        .trim_end();
+    if is_forced {
+        return format!("{}{}", before, location);
+    }
+    format!("{} {}", before, location)
+}
+
+// `None` if the label doesn't match. Each typed word matches the start of a word of the label, in order and
+// ignoring case: `LA` matches `LATER` and `MOMENTS LATER`, and `MOM LA` matches `MOMENTS LATER` with two ranges.
+fn get_match_ranges(label: &str, typed: &str) -> Option<Vec<Range<usize>>> {
+    let mut word_starts = label
+        .char_indices()
+        .filter(|(idx, _)| *idx == 0 || label[..*idx].ends_with(char::is_whitespace))
+        .map(|(idx, _)| idx);
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for typed_word in typed.split_whitespace() {
+        let start = word_starts.find(|start| {
+            label
+                .get(*start..start + typed_word.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(typed_word))
+        })?;
+        ranges.push(start..start + typed_word.len());
+    }
+    Some(ranges)
+}
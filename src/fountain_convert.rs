@@ -0,0 +1,640 @@
+//! Converts parsed documents to JSON, Final Draft (FDX), or HTML, and converts whole directories of
+//! Fountain files at once, for migrating an archive.
+//!
+//! `convert_dir` keeps the directory structure of the input, and writes each output next to its input
+//! unless an `output_dir` is given:
+//!
+//! ```text
+//! scripts/pilot.fountain        ->  converted/pilot.fdx
+//! scripts/s01/e02.fountain      ->  converted/s01/e02.fdx
+//! ```
+//!
+//! A file which can't be read, isn't UTF-8, or can't be written doesn't stop the conversion: it is listed in the
+//! `FNConvertReport`, next to every file that was converted.
+//!
+//! Every format has the printable text of each line, without emphasis markers, notes, or boneyards.
+//! HTML places each element with the `FNElementLayouts`, and lays out both sides of dual dialogue one after the
+//! other. FDX has no sections or synopses.
+//!
+//! Scene colors (see `fountain_colors`) are kept: JSON has the color of each line and every marker, HTML colors
+//! the scene headings, and FDX puts the color in the `SceneProperties` of each scene heading.
+//!
+//! `ReviewHtml` is HTML for sharing an annotated draft: every note becomes a numbered comment in the margin,
+//! and its number is left in the text where the note was:
+//!
+//! ```text
+//! Joe waits. [[Too slow?]] He leaves.    ->    Joe waits. ¹ He leaves.        1  Too slow?
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::fountain_bidi::{get_text_direction, FNTextDirection};
+use crate::fountain_colors::{get_hex_color, get_markers};
+use crate::fountain_dual_dialogue::get_dual_dialogue_blocks;
+use crate::fountain_enums::FNLineType;
+use crate::fountain_full_parse::FNFullParseConfig;
+use crate::fountain_layout::FNElementLayouts;
+use crate::fountain_line::FNLine;
+use crate::fountain_notes::{get_notes, FNNote};
+use crate::fountain_parsed_document::FNParsedDocument;
+use crate::fountain_text_extraction::{
+    get_invisible_byte_ranges_for_lines, get_printable_strings_for_lines,
+};
+use crate::helper_funcs::get_json_string;
+use crate::intervals::RangeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FNOutputFormat {
+    Json,
+    Fdx,
+    Html,
+    /// HTML with the notes as numbered margin comments
+    ReviewHtml,
+}
+
+impl FNOutputFormat {
+    /// The file extension of the format, without the dot
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            FNOutputFormat::Json => "json",
+            FNOutputFormat::Fdx => "fdx",
+            FNOutputFormat::Html | FNOutputFormat::ReviewHtml => "html",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNConvertOptions {
+    /// Where the outputs are written, in the same directory structure as the input.
+    /// `None` writes each output next to its input.
+    pub output_dir: Option<PathBuf>,
+    /// If `true`, the files in subdirectories are converted too. Symlinks to directories aren't followed.
+    pub recursive: bool,
+    /// The extensions of the files to convert, without the dot. Matched without case.
+    pub extensions: Vec<String>,
+    /// If `false`, a file whose output already exists is listed as a failure, and the output is left as it is
+    pub overwrite: bool,
+    pub parse_config: FNFullParseConfig,
+    /// Where each element goes in HTML output
+    pub layouts: FNElementLayouts,
+}
+
+impl Default for FNConvertOptions {
+    fn default() -> Self {
+        FNConvertOptions {
+            output_dir: None,
+            recursive: true,
+            extensions: vec![String::from("fountain")],
+            overwrite: false,
+            parse_config: FNFullParseConfig::default(),
+            layouts: FNElementLayouts::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNConvertFailure {
+    /// The input file, or a directory which couldn't be read
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNConvertReport {
+    /// `(input, output)` paths of every converted file, in the order they were converted
+    pub converted: Vec<(PathBuf, PathBuf)>,
+    pub failures: Vec<FNConvertFailure>,
+}
+
+impl FNConvertReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A summary for the end of a batch run: the number of converted files, and every failure on its own line.
+impl fmt::Display for FNConvertReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Converted {} file{}, {} failed",
+            self.converted.len(),
+            if self.converted.len() == 1 { "" } else { "s" },
+            self.failures.len()
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {}: {}", failure.path.display(), failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts every Fountain file in `input_dir` to `output_format`. Files are converted in order of their paths.
+///
+/// Returns an error only if `input_dir` itself can't be read. Every other error is a failure in the report.
+pub fn convert_dir(
+    input_dir: &Path,
+    output_format: FNOutputFormat,
+    options: &FNConvertOptions,
+) -> io::Result<FNConvertReport> {
+    let mut report = FNConvertReport::default();
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    collect_input_paths(input_dir, options, &mut inputs, &mut report, true)?;
+    inputs.sort();
+
+    for input in inputs {
+        let relative = input.strip_prefix(input_dir).unwrap_or(&input);
+        let output = match &options.output_dir {
+            Some(output_dir) => output_dir.join(relative),
+            None => input.clone(),
+        }
+        .with_extension(output_format.get_extension());
+
+        match convert_file(&input, &output, output_format, options) {
+            Ok(()) => report.converted.push((input, output)),
+            Err(error) => report
+                .failures
+                .push(FNConvertFailure { path: input, error }),
+        }
+    }
+    Ok(report)
+}
+
+/// Returns the document in `format`.
+pub fn get_converted_text(
+    document: &FNParsedDocument,
+    format: FNOutputFormat,
+    layouts: &FNElementLayouts,
+) -> String {
+    match format {
+        FNOutputFormat::Json => get_json_text(document),
+        FNOutputFormat::Fdx => get_fdx_text(document),
+        FNOutputFormat::Html => get_html_text(document, layouts),
+        FNOutputFormat::ReviewHtml => get_review_html_text(document, layouts),
+    }
+}
+
+/// Returns the title page entries, every line of the document, and every marker as a JSON object:
+/// `{"title_page":[{"key":"title","values":["Big Fish"]}],"lines":[{"type":"Heading","text":"...","raw":"...","color":"red"}],"markers":[{"line_index":4,"color":null,"description":"cut?"}]}`
+///
+/// `color` is `null` for lines without a color.
+pub fn get_json_text(document: &FNParsedDocument) -> String {
+    let printable = get_printable_strings_for_lines(&document.full.lines);
+    let title_page: Vec<String> = document
+        .full
+        .title_page
+        .iter()
+        .map(|entry| {
+            let values: Vec<String> = entry.values.iter().map(|v| get_json_string(v)).collect();
+            format!(
+                "{{\"key\":{},\"values\":[{}]}}",
+                get_json_string(&entry.key),
+                values.join(",")
+            )
+        })
+        .collect();
+    let lines: Vec<String> = document
+        .full
+        .lines
+        .iter()
+        .zip(&printable)
+        .map(|(ln, text)| {
+            format!(
+                "{{\"type\":{},\"text\":{},\"raw\":{},\"color\":{}}}",
+                get_json_string(&ln.fn_type.to_string()),
+                get_json_string(text),
+                get_json_string(&ln.raw_string),
+                get_json_color(&ln.color)
+            )
+        })
+        .collect();
+    let markers: Vec<String> = get_markers(&document.full.lines)
+        .iter()
+        .map(|marker| {
+            format!(
+                "{{\"line_index\":{},\"color\":{},\"description\":{}}}",
+                marker.line_index,
+                get_json_color(marker.color.as_deref().unwrap_or_default()),
+                get_json_string(&marker.description)
+            )
+        })
+        .collect();
+    format!(
+        "{{\"title_page\":[{}],\"lines\":[{}],\"markers\":[{}]}}",
+        title_page.join(","),
+        lines.join(","),
+        markers.join(",")
+    )
+}
+
+// `null` for no color
+fn get_json_color(color: &str) -> String {
+    match color.trim() {
+        "" => String::from("null"),
+        color => get_json_string(color),
+    }
+}
+
+/// Returns the document as a Final Draft XML file. Both sides of a dual dialogue go in one `DualDialogue`.
+pub fn get_fdx_text(document: &FNParsedDocument) -> String {
+    let lines = &document.full.lines;
+    let printable = get_printable_strings_for_lines(lines);
+    let dual_blocks = get_dual_dialogue_blocks(lines);
+    let mut fdx = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\" ?>\n<FinalDraft DocumentType=\"Script\" Template=\"No\" Version=\"4\">\n  <Content>\n",
+    );
+    let mut starts_new_page = false;
+    let mut idx: usize = 0;
+
+    while idx < lines.len() {
+        if let Some(block) = dual_blocks.iter().find(|block| block.left_start == idx) {
+            fdx.push_str("    <Paragraph>\n      <DualDialogue>\n");
+            for dual_idx in block.left_start..block.right_end {
+                if let Some(fdx_type) = get_fdx_paragraph_type(&lines[dual_idx].fn_type) {
+                    push_fdx_paragraph(
+                        &mut fdx,
+                        "        ",
+                        fdx_type,
+                        None,
+                        false,
+                        None,
+                        &printable[dual_idx],
+                    );
+                }
+            }
+            fdx.push_str("      </DualDialogue>\n    </Paragraph>\n");
+            starts_new_page = false;
+            idx = block.right_end;
+            continue;
+        }
+
+        let ln = &lines[idx];
+        if ln.fn_type == FNLineType::PageBreak {
+            starts_new_page = true;
+        } else if let Some(fdx_type) = get_fdx_paragraph_type(&ln.fn_type) {
+            let alignment = match ln.fn_type {
+                FNLineType::Centered => Some("Center"),
+                _ => None,
+            };
+            let scene_color = match ln.fn_type {
+                FNLineType::Heading => get_hex_color(&ln.color),
+                _ => None,
+            };
+            push_fdx_paragraph(
+                &mut fdx,
+                "    ",
+                fdx_type,
+                alignment,
+                starts_new_page,
+                scene_color.as_deref(),
+                &printable[idx],
+            );
+            starts_new_page = false;
+        }
+        idx += 1;
+    }
+
+    fdx.push_str("  </Content>\n");
+    if !document.full.title_page.is_empty() {
+        fdx.push_str("  <TitlePage>\n    <Content>\n");
+        for entry in &document.full.title_page {
+            let alignment = match entry.key.as_str() {
+                "title" | "credit" | "author" | "authors" | "source" => "Center",
+                _ => "Left",
+            };
+            for value in &entry.values {
+                push_fdx_paragraph(
+                    &mut fdx,
+                    "      ",
+                    "General",
+                    Some(alignment),
+                    false,
+                    None,
+                    value,
+                );
+            }
+        }
+        fdx.push_str("    </Content>\n  </TitlePage>\n");
+    }
+    fdx.push_str("</FinalDraft>\n");
+    fdx
+}
+
+/// Returns the document as a standalone HTML page, with a class for each line type and a style sheet made from
+/// the layouts (ten columns to the inch). Right-to-left lines get `dir="rtl"`.
+pub fn get_html_text(document: &FNParsedDocument, layouts: &FNElementLayouts) -> String {
+    let printable = get_printable_strings_for_lines(&document.full.lines);
+    get_html_page(document, &printable, layouts, &[])
+}
+
+/// Same as `get_html_text`, but every note is a numbered comment in the right margin, next to the line where
+/// the note starts. The number is a link, placed in the text where the note was, and the comment links back to it.
+/// Notes are numbered from 1, in document order.
+pub fn get_review_html_text(document: &FNParsedDocument, layouts: &FNElementLayouts) -> String {
+    let notes = get_notes(&document.full.lines);
+    let printable =
+        get_printable_strings_for_lines(&get_lines_with_note_anchors(&document.full.lines, &notes));
+    get_html_page(document, &printable, layouts, &notes)
+}
+
+// The lines with every note and boneyard removed, and a `NOTE_ANCHOR` where each note starts
+fn get_lines_with_note_anchors(lines: &[FNLine], notes: &[FNNote]) -> Vec<FNLine> {
+    get_invisible_byte_ranges_for_lines(lines)
+        .iter()
+        .zip(lines)
+        .enumerate()
+        .map(|(idx, (invisible, ln))| {
+            let mut raw = String::with_capacity(ln.raw_string.len());
+            let mut last_end: usize = 0;
+            // Boneyards go too, since the anchors would move the byte ranges the rest of the text is hidden by
+            for range in invisible.notes.union(&invisible.boneyards).iter() {
+                raw.push_str(&ln.raw_string[last_end..range.start]);
+                for _ in notes.iter().filter(|note| {
+                    note.start_index == idx
+                        && range.contains(&note.local_start)
+                        && invisible.notes.contains(note.local_start)
+                }) {
+                    raw.push(NOTE_ANCHOR);
+                }
+                last_end = range.end;
+            }
+            raw.push_str(&ln.raw_string[last_end..]);
+            FNLine {
+                string: raw.clone(),
+                raw_string: raw,
+                additional_note_ranges: RangeSet::default(),
+                additional_omitted_ranges: RangeSet::default(),
+                ..ln.clone()
+            }
+        })
+        .collect()
+}
+
+// Stands for a note in the printable text, until it's replaced with the note's number.
+// From the private use area, so it survives the removal of markers.
+const NOTE_ANCHOR: char = '\u{E000}';
+
+// `notes` are the notes of the document, whose numbers replace the `NOTE_ANCHOR`s in the printable text
+fn get_html_page(
+    document: &FNParsedDocument,
+    printable: &[String],
+    layouts: &FNElementLayouts,
+    notes: &[FNNote],
+) -> String {
+    let lines = &document.full.lines;
+    let title = document
+        .get_title_page_values("title")
+        .map(|values| values.join(" "))
+        .unwrap_or_default();
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n",
+        get_xml_escaped(&title)
+    );
+    html.push_str(
+        "body { font-family: \"Courier Prime\", Courier, monospace; font-size: 12pt; }\n",
+    );
+    html.push_str("p { margin: 0; white-space: pre-wrap; }\n");
+    for (fn_type, layout) in layouts.get_layout_table() {
+        html.push_str(&format!(
+            ".{} {{ margin-left: {}in; width: {}in; text-align: {}; }}\n",
+            get_html_class(&fn_type),
+            layout.left_margin as f64 / 10.0,
+            layout.width as f64 / 10.0,
+            layout.alignment.get_css_text_align()
+        ));
+    }
+    html.push_str(".page-break { break-after: page; border: none; }\n");
+    if !notes.is_empty() {
+        html.push_str(".note-ref { font-size: 8pt; vertical-align: super; }\n");
+        html.push_str(
+            ".margin-note { float: right; clear: right; width: 2in; font-size: 9pt; white-space: pre-wrap; }\n",
+        );
+    }
+    html.push_str("</style>\n</head>\n<body>\n");
+
+    // Never two empty rows in a row, since skipped lines can leave empty lines next to each other
+    let mut last_was_empty = true;
+    for (idx, (ln, text)) in lines.iter().zip(printable).enumerate() {
+        // Numbered from 1
+        let line_notes: Vec<(usize, &FNNote)> = notes
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| note.start_index == idx)
+            .map(|(note_idx, note)| (note_idx + 1, note))
+            .collect();
+        for (number, note) in &line_notes {
+            html.push_str(&format!(
+                "<aside class=\"margin-note\" id=\"note-{0}\"><a href=\"#note-ref-{0}\">{0}</a> {1}</aside>\n",
+                number,
+                get_xml_escaped(note.text.trim())
+            ));
+        }
+        match ln.fn_type {
+            FNLineType::Empty => {
+                if !last_was_empty {
+                    html.push_str("<br>\n");
+                }
+                last_was_empty = true;
+            }
+            FNLineType::PageBreak => {
+                html.push_str("<hr class=\"page-break\">\n");
+                last_was_empty = true;
+            }
+            _ if layouts.get_layout_for_line_type(&ln.fn_type).is_some() && !text.is_empty() => {
+                let dir = match get_text_direction(text) {
+                    FNTextDirection::RightToLeft => " dir=\"rtl\"",
+                    _ => "",
+                };
+                let mut numbers = line_notes.iter().map(|(number, _)| number);
+                let mut escaped = String::new();
+                for c in get_xml_escaped(text).chars() {
+                    if c != NOTE_ANCHOR {
+                        escaped.push(c);
+                    } else if let Some(number) = numbers.next() {
+                        escaped.push_str(&format!(
+                            "<a class=\"note-ref\" id=\"note-ref-{0}\" href=\"#note-{0}\">{0}</a>",
+                            number
+                        ));
+                    }
+                }
+                let style = match get_hex_color(&ln.color) {
+                    Some(hex) if ln.fn_type == FNLineType::Heading => {
+                        format!(" style=\"color: {}\"", hex)
+                    }
+                    _ => String::new(),
+                };
+                html.push_str(&format!(
+                    "<p class=\"{}\"{}{}>{}</p>\n",
+                    get_html_class(&ln.fn_type),
+                    dir,
+                    style,
+                    escaped
+                ));
+                last_was_empty = false;
+            }
+            _ => {}
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn collect_input_paths(
+    dir: &Path,
+    options: &FNConvertOptions,
+    inputs: &mut Vec<PathBuf>,
+    report: &mut FNConvertReport,
+    is_root: bool,
+) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if is_root => return Err(error),
+        Err(error) => {
+            report.failures.push(FNConvertFailure {
+                path: dir.to_path_buf(),
+                error: error.to_string(),
+            });
+            return Ok(());
+        }
+    };
+    for entry in entries {
+        // The type of the entry itself, so that a symlink to a directory isn't taken for a directory
+        let (path, file_type) = match entry.and_then(|entry| Ok((entry.path(), entry.file_type()?)))
+        {
+            Ok(entry) => entry,
+            Err(error) => {
+                report.failures.push(FNConvertFailure {
+                    path: dir.to_path_buf(),
+                    error: error.to_string(),
+                });
+                continue;
+            }
+        };
+        // The outputs are never converted again, when they're written inside the input directory
+        if options.output_dir.as_deref() == Some(path.as_path()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            if options.recursive {
+                collect_input_paths(&path, options, inputs, report, false)?;
+            }
+        } else if file_type.is_symlink() && path.is_dir() {
+            // Symlinked directories aren't followed, so a link to a parent directory can't loop
+            continue;
+        } else if path.extension().is_some_and(|extension| {
+            options
+                .extensions
+                .iter()
+                .any(|wanted| extension.eq_ignore_ascii_case(wanted.as_str()))
+        }) {
+            inputs.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn convert_file(
+    input: &Path,
+    output: &Path,
+    output_format: FNOutputFormat,
+    options: &FNConvertOptions,
+) -> Result<(), String> {
+    if !options.overwrite && output.exists() {
+        return Err(format!("{} already exists", output.display()));
+    }
+    let text = fs::read_to_string(input).map_err(|error| error.to_string())?;
+    let document = FNParsedDocument::with_config(&text, &options.parse_config);
+    let converted = get_converted_text(&document, output_format, &options.layouts);
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    fs::write(output, converted).map_err(|error| error.to_string())
+}
+
+fn get_fdx_paragraph_type(fn_type: &FNLineType) -> Option<&'static str> {
+    match fn_type {
+        FNLineType::Heading => Some("Scene Heading"),
+        FNLineType::Action | FNLineType::Centered => Some("Action"),
+        FNLineType::Character | FNLineType::DualDialogueCharacter => Some("Character"),
+        FNLineType::Parenthetical | FNLineType::DualDialogueParenthetical => Some("Parenthetical"),
+        FNLineType::Dialogue | FNLineType::DualDialogue => Some("Dialogue"),
+        FNLineType::TransitionLine => Some("Transition"),
+        FNLineType::Shot => Some("Shot"),
+        FNLineType::Lyrics => Some("General"),
+        _ => None,
+    }
+}
+
+fn push_fdx_paragraph(
+    fdx: &mut String,
+    indent: &str,
+    fdx_type: &str,
+    alignment: Option<&str>,
+    starts_new_page: bool,
+    scene_color: Option<&str>,
+    text: &str,
+) {
+    fdx.push_str(&format!("{}<Paragraph Type=\"{}\"", indent, fdx_type));
+    if let Some(alignment) = alignment {
+        fdx.push_str(&format!(" Alignment=\"{}\"", alignment));
+    }
+    if starts_new_page {
+        fdx.push_str(" StartsNewPage=\"Yes\"");
+    }
+    fdx.push_str(">\n");
+    if let Some(hex) = scene_color {
+        fdx.push_str(&format!(
+            "{}  <SceneProperties Color=\"{}\"/>\n",
+            indent,
+            get_fdx_color(hex)
+        ));
+    }
+    fdx.push_str(&format!(
+        "{}  <Text>{}</Text>\n{}</Paragraph>\n",
+        indent,
+        get_xml_escaped(text),
+        indent
+    ));
+}
+
+// Final Draft colors have 16 bits for each channel: `#ff8800` becomes `#FFFF88880000`
+fn get_fdx_color(hex: &str) -> String {
+    let mut color = String::from("#");
+    for channel in hex.trim_start_matches('#').as_bytes().chunks(2) {
+        let channel = String::from_utf8_lossy(channel).to_uppercase();
+        color.push_str(&channel);
+        color.push_str(&channel);
+    }
+    color
+}
+
+// `TransitionLine` becomes `transition-line`
+fn get_html_class(fn_type: &FNLineType) -> String {
+    let mut class = String::new();
+    for c in fn_type.to_string().chars() {
+        if c.is_ascii_uppercase() && !class.is_empty() {
+            class.push('-');
+        }
+        class.push(c.to_ascii_lowercase());
+    }
+    class
+}
+
+fn get_xml_escaped(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
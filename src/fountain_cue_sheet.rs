@@ -0,0 +1,223 @@
+//! A cue sheet for audio drama producers: for every scene, who speaks and how much, and the sounds called for
+//! in the action.
+//!
+//! Scripts written for audio put sounds in capitals, so a sound cue is an ALL-CAPS word of an action line which
+//! starts with one of the `sound_keywords`. Names of speaking characters are never sound cues, since characters
+//! are also introduced in capitals:
+//!
+//! ```text
+//! JOE KNOCKS twice. A dog BARKS.      ->  sound cues: KNOCKS, BARKS
+//! ```
+
+use std::collections::BTreeSet;
+
+use crate::fountain_characters::{get_canonical_character_name, FNCharacterAliasMap};
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::helper_funcs::get_json_string;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNCueSheetOptions {
+    /// A capitalized word is a sound cue if it starts with one of these, ignoring case.
+    /// If empty, every capitalized word which isn't a character name is a sound cue.
+    pub sound_keywords: Vec<String>,
+    pub aliases: FNCharacterAliasMap,
+}
+
+impl Default for FNCueSheetOptions {
+    fn default() -> Self {
+        FNCueSheetOptions {
+            sound_keywords: [
+                "BANG", "BARK", "BEEP", "BELL", "BOOM", "BUZZ", "CLANG", "CLATTER", "CLICK",
+                "CRACK", "CRASH", "CREAK", "HONK", "HUM", "KNOCK", "RING", "RUMBLE", "RUSTLE",
+                "SCREAM", "SHOT", "SIREN", "SLAM", "SMASH", "SNAP", "SPLASH", "THUD", "THUNDER",
+                "WHISTLE",
+            ]
+            .iter()
+            .map(|keyword| keyword.to_string())
+            .collect(),
+            aliases: FNCharacterAliasMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCueSheetCharacter {
+    /// The canonical name
+    pub name: String,
+    /// The number of dialogue blocks the character speaks
+    pub speeches: usize,
+    /// The number of words of dialogue, without parentheticals
+    pub words: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNSoundCue {
+    pub line_index: usize,
+    /// The word as written, such as `SLAMS`
+    pub word: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCueSheetScene {
+    pub scene_index: usize,
+    /// Empty for text before the first heading
+    pub heading: String,
+    /// In order of their first speech in the scene
+    pub characters: Vec<FNCueSheetCharacter>,
+    pub sound_cues: Vec<FNSoundCue>,
+}
+
+impl FNCueSheetScene {
+    pub fn to_json(&self) -> String {
+        let characters: Vec<String> = self
+            .characters
+            .iter()
+            .map(|character| {
+                format!(
+                    "{{\"name\":{},\"speeches\":{},\"words\":{}}}",
+                    get_json_string(&character.name),
+                    character.speeches,
+                    character.words
+                )
+            })
+            .collect();
+        let sound_cues: Vec<String> = self
+            .sound_cues
+            .iter()
+            .map(|cue| {
+                format!(
+                    "{{\"line_index\":{},\"word\":{}}}",
+                    cue.line_index,
+                    get_json_string(&cue.word)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"scene_index\":{},\"heading\":{},\"characters\":[{}],\"sound_cues\":[{}]}}",
+            self.scene_index,
+            get_json_string(&self.heading),
+            characters.join(","),
+            sound_cues.join(",")
+        )
+    }
+}
+
+/// Returns the cue sheet of every scene, in document order. Every scene is listed, even without speeches or sounds.
+pub fn get_cue_sheet(lines: &[FNLine], options: &FNCueSheetOptions) -> Vec<FNCueSheetScene> {
+    let printable = get_printable_strings_for_lines(lines);
+    let speaking_names: BTreeSet<String> = lines
+        .iter()
+        .filter_map(|ln| get_canonical_character_name(ln, &options.aliases))
+        .collect();
+    let keywords: Vec<String> = options
+        .sound_keywords
+        .iter()
+        .map(|keyword| keyword.trim().to_uppercase())
+        .filter(|keyword| !keyword.is_empty())
+        .collect();
+    let is_sound_cue = |word: &str| {
+        !speaking_names.contains(&options.aliases.get_canonical_name(word))
+            && (keywords.is_empty() || keywords.iter().any(|keyword| word.starts_with(keyword)))
+    };
+
+    get_scenes_from_parsed_lines(lines)
+        .iter()
+        .enumerate()
+        .map(|(scene_index, scene)| {
+            let mut sheet = FNCueSheetScene {
+                scene_index,
+                heading: scene
+                    .heading_index
+                    .map(|idx| printable[idx].clone())
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+            // The character speaking the current dialogue block
+            let mut speaker: Option<usize> = None;
+            for idx in scene.start..scene.end.min(lines.len()) {
+                let ln = &lines[idx];
+                if let Some(name) = get_canonical_character_name(ln, &options.aliases) {
+                    let position = match sheet.characters.iter().position(|c| c.name == name) {
+                        Some(position) => position,
+                        None => {
+                            sheet.characters.push(FNCueSheetCharacter {
+                                name,
+                                ..Default::default()
+                            });
+                            sheet.characters.len() - 1
+                        }
+                    };
+                    sheet.characters[position].speeches += 1;
+                    speaker = Some(position);
+                } else if ln.is_any_dialogue() {
+                    if let Some(position) = speaker {
+                        sheet.characters[position].words +=
+                            printable[idx].split_whitespace().count();
+                    }
+                } else if ln.fn_type == FNLineType::Action {
+                    sheet.sound_cues.extend(
+                        get_capitalized_words(&printable[idx])
+                            .filter(|word| is_sound_cue(word))
+                            .map(|word| FNSoundCue {
+                                line_index: idx,
+                                word: word.to_string(),
+                            }),
+                    );
+                }
+                if !ln.is_any_sort_of_dialogue() {
+                    speaker = None;
+                }
+            }
+            sheet
+        })
+        .collect()
+}
+
+/// Returns the cue sheet as a JSON array, one object per scene.
+pub fn get_cue_sheet_json(scenes: &[FNCueSheetScene]) -> String {
+    let scenes: Vec<String> = scenes.iter().map(|scene| scene.to_json()).collect();
+    format!("[{}]", scenes.join(","))
+}
+
+/// Returns the cue sheet as Markdown, with a table of the characters of each scene and a list of its sound cues.
+pub fn get_cue_sheet_markdown(scenes: &[FNCueSheetScene]) -> String {
+    let mut markdown = String::from("# Cue sheet\n");
+    for scene in scenes {
+        let heading = match scene.heading.as_str() {
+            "" => "(before the first scene)",
+            heading => heading,
+        };
+        markdown.push_str(&format!("\n## {}. {}\n", scene.scene_index + 1, heading));
+        if !scene.characters.is_empty() {
+            markdown.push_str("\n| Character | Speeches | Words |\n| --- | --- | --- |\n");
+            for character in &scene.characters {
+                markdown.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    character.name, character.speeches, character.words
+                ));
+            }
+        }
+        if !scene.sound_cues.is_empty() {
+            let words: Vec<&str> = scene
+                .sound_cues
+                .iter()
+                .map(|cue| cue.word.as_str())
+                .collect();
+            markdown.push_str(&format!("\nSound cues: {}\n", words.join(", ")));
+        }
+    }
+    markdown
+}
+
+// Words of at least two letters, with no lowercase letters
+fn get_capitalized_words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+        .map(|word| word.trim_matches(|c: char| c == '\'' || c == '-'))
+        .filter(|word| {
+            word.chars().filter(|c| c.is_alphabetic()).count() >= 2
+                && !word.chars().any(|c| c.is_lowercase())
+        })
+}
@@ -0,0 +1,178 @@
+//! How dialogue is shared between characters over the course of the script.
+//!
+//! The script is cut into buckets (pages or scenes), and each bucket counts the words of dialogue spoken by
+//! every character. Charting a character's share bucket by bucket shows where they carry the story,
+//! and where they drop out of it:
+//!
+//! ```text
+//! page    1     2     3     4
+//! JANE   0.80  0.55  0.10  0.00
+//! JOE    0.20  0.45  0.90  1.00
+//! ```
+//!
+//! Only `Dialogue` and `DualDialogue` lines are counted; cues and parentheticals are not spoken.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::fountain_characters::{get_speaking_character_for_line_index, FNCharacterAliasMap};
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::helper_funcs::get_json_string;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FNDialogueBalanceBucketing {
+    /// One bucket per page, from `get_pages`. Dialogue split across pages counts on both.
+    #[default]
+    Page,
+    /// One bucket per scene, from `get_scenes_from_parsed_lines`
+    Scene,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDialogueBalanceOptions {
+    pub bucketing: FNDialogueBalanceBucketing,
+    /// Used to cut the script into pages
+    pub pagination: FNPaginationOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDialogueBalanceBucket {
+    /// Words of dialogue for each (canonical) character
+    pub words_by_character: BTreeMap<String, usize>,
+}
+
+impl FNDialogueBalanceBucket {
+    pub fn get_total_words(&self) -> usize {
+        self.words_by_character.values().sum()
+    }
+
+    /// The character's share of the dialogue in this bucket, from `0.0` to `1.0`.
+    /// A bucket without any dialogue has a share of `0.0` for everyone.
+    pub fn get_share(&self, name: &str) -> f64 {
+        let total = self.get_total_words();
+        if total == 0 {
+            return 0.0;
+        }
+        let words = self.words_by_character.get(name).copied().unwrap_or(0);
+        words as f64 / total as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDialogueBalance {
+    /// Every character with dialogue, from most to fewest words
+    pub characters: Vec<String>,
+    /// One bucket per page or scene, in document order
+    pub buckets: Vec<FNDialogueBalanceBucket>,
+}
+
+impl FNDialogueBalance {
+    /// Returns the character's share of the dialogue in every bucket.
+    pub fn get_shares_for_character(&self, name: &str) -> Vec<f64> {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.get_share(name))
+            .collect()
+    }
+
+    /// Returns the shares of every character, as `{"characters":[...],"shares":{"NAME":[...]}}`.
+    pub fn to_json(&self) -> String {
+        let characters: Vec<String> = self
+            .characters
+            .iter()
+            .map(|name| get_json_string(name))
+            .collect();
+        let shares: Vec<String> = self
+            .characters
+            .iter()
+            .map(|name| {
+                let values: Vec<String> = self
+                    .get_shares_for_character(name)
+                    .iter()
+                    .map(|share| format!("{:.4}", share))
+                    .collect();
+                format!("{}:[{}]", get_json_string(name), values.join(","))
+            })
+            .collect();
+        format!(
+            "{{\"characters\":[{}],\"shares\":{{{}}}}}",
+            characters.join(","),
+            shares.join(",")
+        )
+    }
+}
+
+/// Returns the words of dialogue of every character, bucketed by page or by scene.
+pub fn get_dialogue_balance(
+    lines: &[FNLine],
+    aliases: &FNCharacterAliasMap,
+    options: &FNDialogueBalanceOptions,
+) -> FNDialogueBalance {
+    let speakers: Vec<Option<String>> = (0..lines.len())
+        .map(|idx| {
+            let is_spoken = matches!(
+                lines[idx].fn_type,
+                FNLineType::Dialogue | FNLineType::DualDialogue
+            );
+            if !is_spoken {
+                return None;
+            }
+            get_speaking_character_for_line_index(lines, idx)
+                .map(|name| aliases.get_canonical_name(&name))
+        })
+        .collect();
+
+    // The text of each bucket, as (line index, text) pairs
+    let bucket_texts: Vec<Vec<(usize, String)>> = match options.bucketing {
+        FNDialogueBalanceBucketing::Page => {
+            get_pages(lines, &options.pagination, &FNMonospaceMeasurer)
+                .into_iter()
+                .map(|page| {
+                    page.rows
+                        .into_iter()
+                        .map(|row| (row.line_index, row.text))
+                        .collect()
+                })
+                .collect()
+        }
+        FNDialogueBalanceBucketing::Scene => {
+            let printable = get_printable_strings_for_lines(lines);
+            get_scenes_from_parsed_lines(lines)
+                .iter()
+                .map(|scene| {
+                    (scene.start..scene.end.min(lines.len()))
+                        .map(|idx| (idx, printable[idx].clone()))
+                        .collect()
+                })
+                .collect()
+        }
+    };
+
+    let mut totals: HashMap<String, usize> = HashMap::new();
+    let mut buckets: Vec<FNDialogueBalanceBucket> = Vec::with_capacity(bucket_texts.len());
+    for texts in bucket_texts {
+        let mut bucket = FNDialogueBalanceBucket::default();
+        for (line_index, text) in texts {
+            let Some(Some(name)) = speakers.get(line_index) else {
+                continue;
+            };
+            let words = text.split_whitespace().count();
+            if words == 0 {
+                continue;
+            }
+            *bucket.words_by_character.entry(name.clone()).or_insert(0) += words;
+            *totals.entry(name.clone()).or_insert(0) += words;
+        }
+        buckets.push(bucket);
+    }
+
+    let mut characters: Vec<(String, usize)> = totals.into_iter().collect();
+    characters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    FNDialogueBalance {
+        characters: characters.into_iter().map(|(name, _)| name).collect(),
+        buckets,
+    }
+}
@@ -0,0 +1,257 @@
+//! A semantic diff between two versions of a parsed document.
+//!
+//! Rather than comparing raw text, this compares parsed lines, so it can tell the difference between
+//! a line whose text changed and a line which only changed type (such as a `Character` cue becoming `Action`).
+
+use std::collections::HashMap;
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNLineChange {
+    Inserted {
+        new_index: usize,
+    },
+    Removed {
+        old_index: usize,
+    },
+    /// The text of the line changed (and possibly its type too)
+    TextChanged {
+        old_index: usize,
+        new_index: usize,
+    },
+    /// Only the type of the line changed, such as when an edit on another line changes how this one is parsed
+    Retyped {
+        old_index: usize,
+        new_index: usize,
+        old_type: FNLineType,
+        new_type: FNLineType,
+    },
+}
+
+/// Returns the changes needed to go from the `old` lines to the `new` lines, in document order.
+///
+/// Lines are matched by their `raw_string` with a longest-common-subsequence search (Myers' diff, in linear space).
+/// Unmatched lines that sit between the same matched neighbours are paired up as `TextChanged`; the rest are
+/// `Inserted` or `Removed`. Matched lines whose type changed are `Retyped`.
+pub fn get_semantic_diff(old: &[FNLine], new: &[FNLine]) -> Vec<FNLineChange> {
+    let matches = get_matched_lines(old, new);
+
+    let mut changes: Vec<FNLineChange> = Vec::new();
+    let mut old_cursor: usize = 0;
+    let mut new_cursor: usize = 0;
+
+    for (old_match, new_match) in matches
+        .into_iter()
+        .chain(std::iter::once((old.len(), new.len())))
+    {
+        // Pair up unmatched lines between the previous match and this one
+        let old_gap = old_match - old_cursor;
+        let new_gap = new_match - new_cursor;
+        let paired = old_gap.min(new_gap);
+        for i in 0..paired {
+            changes.push(FNLineChange::TextChanged {
+                old_index: old_cursor + i,
+                new_index: new_cursor + i,
+            });
+        }
+        for i in paired..old_gap {
+            changes.push(FNLineChange::Removed {
+                old_index: old_cursor + i,
+            });
+        }
+        for i in paired..new_gap {
+            changes.push(FNLineChange::Inserted {
+                new_index: new_cursor + i,
+            });
+        }
+
+        if old_match < old.len() && new_match < new.len() {
+            let (old_type, new_type) = (&old[old_match].fn_type, &new[new_match].fn_type);
+            if old_type != new_type {
+                changes.push(FNLineChange::Retyped {
+                    old_index: old_match,
+                    new_index: new_match,
+                    old_type: old_type.clone(),
+                    new_type: new_type.clone(),
+                });
+            }
+        }
+        old_cursor = old_match + 1;
+        new_cursor = new_match + 1;
+    }
+
+    changes
+}
+
+/// Returns the index pairs `(old, new)` of the lines which are unchanged between the `old` and `new` lines, in
+/// document order: the lines of a longest common subsequence of their `raw_string`s.
+pub fn get_matched_lines(old: &[FNLine], new: &[FNLine]) -> Vec<(usize, usize)> {
+    // Skip the common prefix and suffix, so the LCS search only covers the edited region
+    let prefix_len = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a.raw_string == b.raw_string)
+        .count();
+    let max_suffix_len = old.len().min(new.len()) - prefix_len;
+    let suffix_len = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix_len)
+        .take_while(|(a, b)| a.raw_string == b.raw_string)
+        .count();
+
+    let old_mid = &old[prefix_len..old.len() - suffix_len];
+    let new_mid = &new[prefix_len..new.len() - suffix_len];
+
+    let mut matches: Vec<(usize, usize)> = (0..prefix_len).map(|i| (i, i)).collect();
+    matches.extend(
+        get_lcs_matches(old_mid, new_mid)
+            .into_iter()
+            .map(|(o, n)| (o + prefix_len, n + prefix_len)),
+    );
+    matches
+        .extend((0..suffix_len).map(|i| (old.len() - suffix_len + i, new.len() - suffix_len + i)));
+    matches
+}
+
+// Myers' linear-space diff: the matches of a shortest edit script are a longest common subsequence.
+// Takes O((n + m) * d) time and O(n + m) space, where d is the number of removed and inserted lines.
+fn get_lcs_matches(old: &[FNLine], new: &[FNLine]) -> Vec<(usize, usize)> {
+    // Compare lines by an id for their text, rather than comparing strings over and over
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let old_ids = get_line_ids(old, &mut ids);
+    let new_ids = get_line_ids(new, &mut ids);
+
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    push_lcs_matches(&old_ids, &new_ids, (0, 0), &mut matches);
+    matches
+}
+
+fn get_line_ids<'a>(lines: &'a [FNLine], ids: &mut HashMap<&'a str, usize>) -> Vec<usize> {
+    lines
+        .iter()
+        .map(|ln| {
+            let next_id = ids.len();
+            *ids.entry(ln.raw_string.as_str()).or_insert(next_id)
+        })
+        .collect()
+}
+
+fn push_lcs_matches(
+    old: &[usize],
+    new: &[usize],
+    offset: (usize, usize),
+    matches: &mut Vec<(usize, usize)>,
+) {
+    let prefix_len = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    matches.extend((0..prefix_len).map(|i| (offset.0 + i, offset.1 + i)));
+    let (old, new) = (&old[prefix_len..], &new[prefix_len..]);
+    let offset = (offset.0 + prefix_len, offset.1 + prefix_len);
+
+    let suffix_len = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (
+        &old[..old.len() - suffix_len],
+        &new[..new.len() - suffix_len],
+    );
+
+    // Without a common prefix or suffix, at least two edits are left, so both halves are smaller than the whole
+    if !old.is_empty() && !new.is_empty() {
+        let (start, end) = get_middle_snake(old, new);
+        push_lcs_matches(&old[..start.0], &new[..start.1], offset, matches);
+        matches
+            .extend((0..end.0 - start.0).map(|i| (offset.0 + start.0 + i, offset.1 + start.1 + i)));
+        push_lcs_matches(
+            &old[end.0..],
+            &new[end.1..],
+            (offset.0 + end.0, offset.1 + end.1),
+            matches,
+        );
+    }
+
+    matches.extend((0..suffix_len).map(|i| (offset.0 + old.len() + i, offset.1 + new.len() + i)));
+}
+
+// Returns the start and end of the middle snake: the run of matching lines in the middle of a shortest edit script,
+// found by searching forward from the start and backward from the end until the two searches overlap.
+fn get_middle_snake(old: &[usize], new: &[usize]) -> ((usize, usize), (usize, usize)) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let delta = n - m;
+    let is_odd = delta % 2 != 0;
+    // The furthest `x` reached on each diagonal `k = x - y`, stored at `k + center`.
+    // The backward search works on the reversed lines, where the diagonal `k` is `delta - k` going forward.
+    let center = max_d + 1;
+    let mut forward: Vec<isize> = vec![0; (2 * center + 1) as usize];
+    let mut backward: Vec<isize> = vec![0; (2 * center + 1) as usize];
+
+    for d in 0..=max_d {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d
+                || (k != d
+                    && forward[(k - 1 + center) as usize] < forward[(k + 1 + center) as usize])
+            {
+                forward[(k + 1 + center) as usize]
+            } else {
+                forward[(k - 1 + center) as usize] + 1
+            };
+            let mut y = x - k;
+            let start = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[(k + center) as usize] = x;
+
+            let reverse_k = delta - k;
+            if is_odd
+                && (-(d - 1)..=d - 1).contains(&reverse_k)
+                && x + backward[(reverse_k + center) as usize] >= n
+            {
+                return (
+                    (start.0 as usize, start.1 as usize),
+                    (x as usize, y as usize),
+                );
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d
+                || (k != d
+                    && backward[(k - 1 + center) as usize] < backward[(k + 1 + center) as usize])
+            {
+                backward[(k + 1 + center) as usize]
+            } else {
+                backward[(k - 1 + center) as usize] + 1
+            };
+            let mut y = x - k;
+            let start = (x, y);
+            while x < n && y < m && old[(n - 1 - x) as usize] == new[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[(k + center) as usize] = x;
+
+            let forward_k = delta - k;
+            if !is_odd
+                && (-d..=d).contains(&forward_k)
+                && forward[(forward_k + center) as usize] + x >= n
+            {
+                return (
+                    ((n - x) as usize, (m - y) as usize),
+                    ((n - start.0) as usize, (m - start.1) as usize),
+                );
+            }
+        }
+    }
+
+    // There is always an edit script of at most `n + m` edits, so the searches overlap before this
+    unreachable!("the forward and backward searches didn't meet")
+}
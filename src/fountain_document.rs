@@ -1,5 +1,46 @@
+//! The `FNDocument` holds the lines of a whole document, and tracks a version number for every change.
+//!
+//! Lines are stored behind `Arc`s, so taking an `FNDocumentSnapshot` is cheap: it only copies a pointer.
+//! When the document is changed while a snapshot is alive, only the list of line pointers is copied
+//! (copy-on-write), and every unchanged line is still shared between the document and the snapshot.
+//!
+//! This lets async consumers (such as diagnostics which are published later) refer to the exact
+//! version of the document that their results were computed against.
+//!
+//! Every line in an `FNDocument` gets a `uuid`. Line identities are preserved across edits
+//! (and across re-parses with `replace_all_lines`, for any unchanged lines), so that `FNAnchor`s stay valid.
+//! The uuids come from the document's `FNUuidGenerator`, which can be seeded for reproducible ids.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
+
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use crate::fountain_anchor::{FNAnchor, FNAnchorPosition};
+use crate::fountain_colors::get_lines_with_colors;
+use crate::fountain_diff::{get_matched_lines, get_semantic_diff, FNLineChange};
+use crate::fountain_emphasis::{get_lines_with_emphasis, FNEmphasisOptions};
+use crate::fountain_enums::{FNForcedBy, FNLineType, FNRangedElementType};
+use crate::fountain_line::FNLine;
+use crate::fountain_partial_line_range::{
+    FNPartialMultilineRange, FNRangedElementDiagnostic, FNRangedElementDiagnosticKind,
+    FNRangedElementResolutionStrategy,
+};
+use crate::fountain_stripped::parse_stripped;
+use crate::fountain_text_extraction::get_invisible_byte_ranges_for_lines;
+use crate::fountain_title_page::{get_title_page_entry_lines, get_title_page_from_lines};
+use crate::fountain_transitions::get_lines_with_standalone_transitions;
+use crate::fountain_uuid::FNUuidGenerator;
+use crate::partial_line_resolver;
+use crate::static_fountain_parser::{self, get_title_page_region_end};
+
+#[derive(Debug, Clone, Default)]
 pub struct FNDocument {
-    raw_fnlines: Vec<FNLine>,
+    version: u64,
+    raw_fnlines: Arc<Vec<Arc<FNLine>>>,
+    #[allow(dead_code)]
     stripped_fnlines: Vec<FNLine>,
 
     // The key in this Hashmap is the index of a stripped line
@@ -7,17 +48,1016 @@ pub struct FNDocument {
     // Any FNLine in the stripped_fnlines has
     // a corresponding line or set of lines in the raw_fnlines vec
     // this is why only the second part of the tuple is Optional.
+    #[allow(dead_code)]
     stripped_fnlines_map: HashMap<usize, (usize, Option<usize>)>,
+    uuid_generator: FNUuidGenerator,
+    // The notes and boneyards of the lines, updated with every edit. `None` until they're first needed.
+    ranged_elements: Option<Vec<FNRangedElementState>>,
+}
+
+// The partial lines, multiline ranges, and diagnostics of one ranged element type, kept between edits so that
+// an edit only resolves the ranges around it again
+#[derive(Debug, Clone)]
+struct FNRangedElementState {
+    ranged_element_type: FNRangedElementType,
+    partials_map: BTreeMap<usize, FNLine>,
+    multiline_ranges: Vec<FNPartialMultilineRange>,
+    diagnostics: Vec<FNRangedElementDiagnostic>,
+}
+
+/// An immutable view of an `FNDocument` at a specific version.
+#[derive(Debug, Clone)]
+pub struct FNDocumentSnapshot {
+    version: u64,
+    lines: Arc<Vec<Arc<FNLine>>>,
 }
 
 impl FNDocument {
+    /// Parses the raw text of a document with the `static_fountain_parser`, starting at version 0.
+    pub fn new(text: String) -> Self {
+        Self::with_uuid_generator(text, FNUuidGenerator::random())
+    }
+
+    /// Same as `new`, but the uuids of the lines (and of every line added later) come from `uuid_generator`.
+    pub fn with_uuid_generator(text: String, uuid_generator: FNUuidGenerator) -> Self {
+        Self::from_parsed_lines_with_uuid_generator(
+            static_fountain_parser::get_parsed_lines_from_raw_string(text),
+            uuid_generator,
+        )
+    }
+
+    pub fn from_parsed_lines(lines: Vec<FNLine>) -> Self {
+        Self::from_parsed_lines_with_uuid_generator(lines, FNUuidGenerator::random())
+    }
+
+    /// Lines which already have a `uuid` keep it.
+    pub fn from_parsed_lines_with_uuid_generator(
+        lines: Vec<FNLine>,
+        uuid_generator: FNUuidGenerator,
+    ) -> Self {
+        let mut document = FNDocument {
+            uuid_generator,
+            ranged_elements: Some(get_ranged_element_states(&lines)),
+            ..Default::default()
+        };
+        document.raw_fnlines = Arc::new(
+            lines
+                .into_iter()
+                .map(|ln| Arc::new(document.get_line_with_uuid(ln)))
+                .collect(),
+        );
+        document
+    }
+
+    /// The current version of the document. Every change increments the version by 1.
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw_fnlines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw_fnlines.is_empty()
+    }
+
+    pub fn get_line(&self, index: usize) -> Option<&FNLine> {
+        self.raw_fnlines.get(index).map(|ln| ln.as_ref())
+    }
+
+    pub fn get_lines(&self) -> Vec<FNLine> {
+        self.raw_fnlines.iter().map(|ln| (**ln).clone()).collect()
+    }
+
+    /// Returns a snapshot of the current version. This does not copy any lines.
+    pub fn snapshot(&self) -> FNDocumentSnapshot {
+        FNDocumentSnapshot {
+            version: self.version,
+            lines: Arc::clone(&self.raw_fnlines),
+        }
+    }
+
+    /// Returns the current index of the line with the given `uuid`.
+    pub fn get_line_index_for_uuid(&self, uuid: &Uuid) -> Option<usize> {
+        self.raw_fnlines
+            .iter()
+            .position(|ln| ln.uuid.as_ref() == Some(uuid))
+    }
+
+    /// Replaces the line at `index`, and returns the new version, or `None` if the index is out of bounds.
+    ///
+    /// If the new line doesn't have a `uuid`, it keeps the `uuid` of the line it replaces.
+    pub fn replace_line(&mut self, index: usize, mut line: FNLine) -> Option<u64> {
+        let old_line = self.raw_fnlines.get(index)?;
+        if line.uuid.is_none() {
+            line.uuid = old_line.uuid;
+        }
+        Arc::make_mut(&mut self.raw_fnlines)[index] = Arc::new(line);
+        self.update_ranged_elements(index..index + 1, 0);
+        Some(self.increment_version())
+    }
+
+    /// Inserts a line at `index`, and returns the new version, or `None` if the index is out of bounds.
+    pub fn insert_line(&mut self, index: usize, line: FNLine) -> Option<u64> {
+        if index > self.raw_fnlines.len() {
+            return None;
+        }
+        let line = self.get_line_with_uuid(line);
+        Arc::make_mut(&mut self.raw_fnlines).insert(index, Arc::new(line));
+        self.update_ranged_elements(index..index + 1, 1);
+        Some(self.increment_version())
+    }
+
+    /// Removes the line at `index`, and returns the new version, or `None` if the index is out of bounds.
+    pub fn remove_line(&mut self, index: usize) -> Option<u64> {
+        if index >= self.raw_fnlines.len() {
+            return None;
+        }
+        Arc::make_mut(&mut self.raw_fnlines).remove(index);
+        self.update_ranged_elements(index..index, -1);
+        Some(self.increment_version())
+    }
+
+    /// Replaces every line in the document (such as after a re-parse), and returns the new version.
+    ///
+    /// Lines whose `raw_string` didn't change keep their `uuid`, so that anchors on unchanged lines survive the edit.
+    /// The unchanged lines are found the same way as `get_semantic_diff` matches them, so this includes lines between
+    /// edits, and lines which were moved along by inserted or removed lines.
+    pub fn replace_all_lines(&mut self, lines: Vec<FNLine>) -> u64 {
+        let mut new_lines: Vec<FNLine> = lines;
+        for (old_index, new_index) in get_matched_lines(&self.get_lines(), &new_lines) {
+            new_lines[new_index].uuid = self.raw_fnlines[old_index].uuid;
+        }
+
+        self.raw_fnlines = Arc::new(
+            new_lines
+                .into_iter()
+                .map(|ln| Arc::new(self.get_line_with_uuid(ln)))
+                .collect(),
+        );
+        self.ranged_elements = Some(get_ranged_element_states(&self.get_lines()));
+        self.increment_version()
+    }
+
+    /// Splits an `Action`, `Lyrics`, or `Centered` line in two at a byte offset of its `raw_string`, like pressing
+    /// return in the middle of a paragraph, and returns the changes. Returns `None` if the line can't be split
+    /// there (see `FNLine::can_be_split_paragraph`), or if the offset isn't a char boundary.
+    ///
+    /// The second half of `Lyrics` gets a `~`, and both halves of `Centered` get their `>` and `<`, so that they
+    /// keep the type of the line. As with `join_with_previous`, the lines between the empty lines around the split
+    /// are parsed again (through the next paragraph, if the split leaves an empty line), so the changes include any
+    /// line whose type changed (such as an ALL CAPS action line split into a cue and its dialogue), and the emphasis,
+    /// note, and boneyard ranges of both halves are found again. The first half keeps the line's `uuid`, and the
+    /// positions of every later line are moved (which isn't listed in the changes).
+    ///
+    /// Splitting the last line at its end only gives it a line break, since that's how the text is parsed.
+    pub fn split_line_at(&mut self, line_index: usize, offset: usize) -> Option<Vec<FNLineChange>> {
+        let line = self.raw_fnlines.get(line_index)?;
+        if !line.can_be_split_paragraph() || !line.raw_string.is_char_boundary(offset) {
+            return None;
+        }
+        let (head_marker, tail_marker) = match line.fn_type {
+            FNLineType::Lyrics => ("", "~"),
+            FNLineType::Centered => (" <", "> "),
+            _ => ("", ""),
+        };
+        // Centered text is trimmed at the split, so the markers aren't padded twice
+        let head_end = match line.fn_type {
+            FNLineType::Centered => line.raw_string[..offset].trim_end().len(),
+            _ => offset,
+        };
+        let tail_start = match line.fn_type {
+            FNLineType::Centered => {
+                line.raw_string.len() - line.raw_string[offset..].trim_start().len()
+            }
+            _ => offset,
+        };
+
+        let mut head = get_line_slice(line, 0..head_end, "", head_marker);
+        let mut tail = get_line_slice(line, tail_start..line.raw_string.len(), tail_marker, "");
+        head.terminator_length = if line.terminator_length == 2 { 2 } else { 1 };
+        tail.position = head.position + head.raw_string.graphemes(true).count() as i32 + 1;
+        let delta = tail.position + tail.raw_string.graphemes(true).count() as i32
+            - line.position
+            - line.raw_string.graphemes(true).count() as i32;
+        // A line break at the very end of the document is the terminator of the last line, not an empty line
+        let is_last_line_break = line_index + 1 == self.raw_fnlines.len()
+            && line.terminator_length == 0
+            && tail.raw_string.is_empty();
+        tail.uuid = Some(self.get_new_uuid());
+
+        let mut lines: Vec<Arc<FNLine>> = (*self.raw_fnlines).clone();
+        move_positions(&mut lines[line_index + 1..], delta);
+        let edited = match is_last_line_break {
+            true => line_index..line_index + 1,
+            false => line_index..line_index + 2,
+        };
+        lines[line_index] = Arc::new(head);
+        if !is_last_line_break {
+            lines.insert(line_index + 1, Arc::new(tail));
+        }
+        let (region, new_region) = get_reparsed_region(&lines, edited.clone());
+
+        let old_region_end = region.end + 1 - edited.len();
+        Some(self.replace_region(lines, region, new_region, old_region_end))
+    }
+
+    /// Joins the line at `line_index` onto the end of the line before it, like pressing backspace at the start
+    /// of a line, and returns the changes. Returns `None` for the first line, or an index out of bounds.
+    ///
+    /// The markers of the joined line are dropped where the lines meet (the `~` of `Lyrics`, and the `<` and `>`
+    /// of `Centered`), and a space is added if neither side has one. The lines between the empty lines around
+    /// the join are parsed again, so the changes include any line whose type changed (such as a cue which
+    /// lost its dialogue). The joined line keeps the `uuid` of the line before it.
+    pub fn join_with_previous(&mut self, line_index: usize) -> Option<Vec<FNLineChange>> {
+        if line_index == 0 {
+            return None;
+        }
+        let line = self.raw_fnlines.get(line_index)?;
+        let previous = &self.raw_fnlines[line_index - 1];
+
+        let mut head = previous.raw_string.as_str();
+        let mut tail = line.raw_string.as_str();
+        if line.fn_type == FNLineType::Lyrics {
+            tail = tail.strip_prefix('~').unwrap_or(tail);
+        }
+        if previous.fn_type == FNLineType::Centered && line.fn_type == FNLineType::Centered {
+            head = head.trim_end().strip_suffix('<').unwrap_or(head).trim_end();
+            tail = tail
+                .trim_start()
+                .strip_prefix('>')
+                .unwrap_or(tail)
+                .trim_start();
+        }
+        let separator = if head.is_empty()
+            || tail.is_empty()
+            || head.ends_with(char::is_whitespace)
+            || tail.starts_with(char::is_whitespace)
+        {
+            ""
+        } else {
+            " "
+        };
+        let joined_text = format!("{}{}{}", head, separator, tail);
+        let delta = joined_text.graphemes(true).count() as i32
+            - previous.raw_string.graphemes(true).count() as i32
+            - 1
+            - line.raw_string.graphemes(true).count() as i32;
+        let joined = FNLine {
+            string: joined_text.clone(),
+            length: joined_text.len() as i32,
+            raw_string: joined_text,
+            terminator_length: line.terminator_length,
+            ..(**previous).clone()
+        };
+
+        let joined_index = line_index - 1;
+        let mut lines: Vec<Arc<FNLine>> = (*self.raw_fnlines).clone();
+        move_positions(&mut lines[line_index + 1..], delta);
+        lines[joined_index] = Arc::new(joined);
+        lines.remove(line_index);
+        let (region, new_region) = get_reparsed_region(&lines, joined_index..joined_index + 1);
+
+        let old_region_end = region.end + 1;
+        Some(self.replace_region(lines, region, new_region, old_region_end))
+    }
+
+    /// Changes the type of the line at `line_index` by adding or removing forcing characters, like an editor's
+    /// "make this a Character" command, and returns the changes. The old forcing characters are removed first,
+    /// and new ones are only added if the line isn't already `fn_type` without them.
+    ///
+    /// Supported types are `Heading`, `Action`, `Shot`, `Character`, `TransitionLine`, `Centered`, `Lyrics`,
+    /// `Synopse`, and `Section`. Returns an empty `Vec` if the line already has the type, and `None` for an index
+    /// out of bounds, another type, or if the line still isn't `fn_type` after forcing (the document is then
+    /// unchanged). As with `join_with_previous`, the changes include lines around it whose type changed.
+    pub fn force_line_type(
+        &mut self,
+        line_index: usize,
+        fn_type: FNLineType,
+    ) -> Option<Vec<FNLineChange>> {
+        let line = (**self.raw_fnlines.get(line_index)?).clone();
+        if line.fn_type == fn_type {
+            return Some(Vec::new());
+        }
+        let (prefix, suffix) = get_forcing_markers(&fn_type)?;
+        let unforced = get_unforced_text(&line);
+
+        for text in [
+            unforced.to_string(),
+            format!("{}{}{}", prefix, unforced, suffix),
+        ] {
+            let delta = text.graphemes(true).count() as i32
+                - line.raw_string.graphemes(true).count() as i32;
+            let mut lines: Vec<Arc<FNLine>> = (*self.raw_fnlines).clone();
+            move_positions(&mut lines[line_index + 1..], delta);
+            lines[line_index] = Arc::new(FNLine {
+                string: text.clone(),
+                length: text.len() as i32,
+                raw_string: text,
+                ..line.clone()
+            });
+            let (region, new_region) = get_reparsed_region(&lines, line_index..line_index + 1);
+            if new_region[line_index - region.start].fn_type == fn_type {
+                let old_region_end = region.end;
+                return Some(self.replace_region(lines, region, new_region, old_region_end));
+            }
+        }
+        None
+    }
+
+    /// Sets the `Title` of the title page, and returns the changes. See `set_title_page_value`.
+    pub fn set_title(&mut self, title: &str) -> Vec<FNLineChange> {
+        self.set_title_page_entry(&["title"], "Title", Some(title))
+            .unwrap_or_default()
+    }
+
+    /// Sets the `Author` of the title page (or `Authors`, if the document already has that key), and returns
+    /// the changes. See `set_title_page_value`.
+    pub fn set_author(&mut self, author: &str) -> Vec<FNLineChange> {
+        self.set_title_page_entry(&["author", "authors"], "Author", Some(author))
+            .unwrap_or_default()
+    }
+
+    /// Sets the `Draft date` of the title page (or `Draft`, if the document already has that key), and returns
+    /// the changes. See `set_title_page_value`.
+    pub fn set_draft_date(&mut self, draft_date: &str) -> Vec<FNLineChange> {
+        self.set_title_page_entry(&["draft date", "draft"], "Draft date", Some(draft_date))
+            .unwrap_or_default()
+    }
+
+    /// Sets the value of a title page entry, and returns the changes. Keys are matched ignoring case.
+    ///
+    /// The lines of an existing entry (its key, and the indented lines continuing its value) are replaced,
+    /// keeping its key as written. A new entry is added at the end
+    /// of the title page, and a document without a title page gets one, with an empty line after it. A value
+    /// with several lines is written on indented lines after the key.
+    pub fn set_title_page_value(&mut self, key: &str, value: &str) -> Vec<FNLineChange> {
+        let lowercase_key = key.trim().to_lowercase();
+        self.set_title_page_entry(&[lowercase_key.as_str()], key.trim(), Some(value))
+            .unwrap_or_default()
+    }
+
+    /// Removes a title page entry, and returns the changes. Removing the last entry also removes the empty
+    /// line after the title page. Returns `None` if there's no entry with the key.
+    pub fn remove_title_page_value(&mut self, key: &str) -> Option<Vec<FNLineChange>> {
+        let lowercase_key = key.trim().to_lowercase();
+        self.set_title_page_entry(&[lowercase_key.as_str()], key.trim(), None)
+    }
+
+    /// Creates an `FNAnchor` at a byte offset within the line at `line_index`.
+    pub fn create_anchor(&self, line_index: usize, local_offset: usize) -> Option<FNAnchor> {
+        let line = self.raw_fnlines.get(line_index)?;
+        Some(FNAnchor {
+            line_uuid: line.uuid?,
+            local_offset: local_offset.min(line.raw_string.len()),
+        })
+    }
+
+    /// Returns the current position of an `FNAnchor`, or `None` if its line has been removed.
+    pub fn resolve_anchor(&self, anchor: &FNAnchor) -> Option<FNAnchorPosition> {
+        let line_index = self.get_line_index_for_uuid(&anchor.line_uuid)?;
+        let line = &self.raw_fnlines[line_index];
+
+        let mut local_offset = anchor.local_offset.min(line.raw_string.len());
+        while !line.raw_string.is_char_boundary(local_offset) {
+            local_offset -= 1;
+        }
+
+        let document_position =
+            line.position + line.raw_string[..local_offset].graphemes(true).count() as i32;
+
+        Some(FNAnchorPosition {
+            line_index,
+            local_offset,
+            document_position,
+        })
+    }
+
     /// When the editor makes some change, it may change a range of text from a local "stripped view,"
     /// But those changes need to be made as part of the "raw lines", so that the data can be saved in proper foutnain formatting.
-    /// So, if the editor wants to delete characters 7 through 26 on stripped line 54,
+    /// So, if the editor wants to delete bytes 7 through 26 on stripped line 54,
     /// that might actually correspond to a non-consequtive
-    /// set of characters 12 through 31 on raw line 56,
+    /// set of bytes 12 through 31 on raw line 56,
     /// because of potential inline Notes or Boneyards.
-    pub fn get_raw_index_from_stripped_index(&self) {
-        todo!()
+    ///
+    /// Returns the raw line index and the byte offset in its `raw_string` for a byte offset of a line of
+    /// `parse_stripped`, or `None` if there is no such stripped line or offset.
+    pub fn get_raw_index_from_stripped_index(
+        &self,
+        stripped_index: usize,
+        stripped_offset: usize,
+    ) -> Option<(usize, usize)> {
+        let (_, map) = parse_stripped(&self.get_lines());
+        map.get_raw_position(stripped_index, stripped_offset)
+    }
+
+    // Replaces the entry with any of the `keys` by `value`, or removes it if `value` is `None`
+    fn set_title_page_entry(
+        &mut self,
+        keys: &[&str],
+        key: &str,
+        value: Option<&str>,
+    ) -> Option<Vec<FNLineChange>> {
+        let lines = self.get_lines();
+        let title_page_end = get_title_page_region_end(&lines);
+        let entries = get_title_page_from_lines(&lines);
+        let entry = entries
+            .iter()
+            .find(|entry| keys.contains(&entry.key.as_str()));
+
+        let (mut replaced, mut texts) = match entry {
+            Some(entry) => {
+                let first = entry.line_indices[0];
+                let raw = &lines[first].raw_string;
+                let written_key = raw[..raw.find(':').unwrap_or(raw.len())].trim();
+                // Only the indented lines after the key continue its value, so no other line is replaced
+                let continued = entry.line_indices[1..]
+                    .iter()
+                    .take_while(|idx| lines[**idx].raw_string.starts_with([' ', '\t']))
+                    .count();
+                (
+                    first..first + continued + 1,
+                    value.map_or(Vec::new(), |value| {
+                        get_title_page_entry_lines(written_key, value)
+                    }),
+                )
+            }
+            None => (
+                title_page_end..title_page_end,
+                get_title_page_entry_lines(key, value?),
+            ),
+        };
+        if title_page_end == 0 && lines.first().is_some_and(|ln| !ln.raw_string.is_empty()) {
+            texts.push(String::new());
+        }
+        if texts.is_empty()
+            && replaced == (0..title_page_end)
+            && lines
+                .get(title_page_end)
+                .is_some_and(|ln| ln.raw_string.is_empty())
+        {
+            replaced.end += 1;
+        }
+
+        let terminator_length = match lines.first() {
+            Some(first) if first.terminator_length == 2 => 2,
+            _ => 1,
+        };
+        let mut position = match lines.get(replaced.start) {
+            Some(ln) => ln.position,
+            None => lines.last().map_or(0, |ln| {
+                ln.position + ln.raw_string.graphemes(true).count() as i32 + 1
+            }),
+        };
+        let old_length: i32 = lines[replaced.clone()]
+            .iter()
+            .map(|ln| ln.raw_string.graphemes(true).count() as i32 + 1)
+            .sum();
+        let new_count = texts.len();
+        let mut new_lines: Vec<Arc<FNLine>> = Vec::with_capacity(new_count);
+        for (idx, text) in texts.into_iter().enumerate() {
+            let length = text.graphemes(true).count() as i32;
+            // The first line of the entry keeps its identity
+            let uuid = match idx {
+                0 => lines.get(replaced.start).and_then(|ln| ln.uuid),
+                _ => None,
+            };
+            new_lines.push(Arc::new(self.get_line_with_uuid(FNLine {
+                string: text.clone(),
+                length: text.len() as i32,
+                raw_string: text,
+                position,
+                terminator_length,
+                uuid,
+                ..Default::default()
+            })));
+            position += length + 1;
+        }
+        let new_length = position - lines.get(replaced.start).map_or(0, |ln| ln.position);
+
+        let mut document_lines: Vec<Arc<FNLine>> = (*self.raw_fnlines).clone();
+        // The last line of the document keeps the terminator of the old last line
+        if replaced.end == lines.len() {
+            let last_terminator_length = lines.last().map_or(0, |ln| ln.terminator_length);
+            if replaced.start > 0 {
+                Arc::make_mut(&mut document_lines[replaced.start - 1]).terminator_length =
+                    match new_lines.is_empty() {
+                        true => last_terminator_length,
+                        false => terminator_length,
+                    };
+            }
+            if let Some(last) = new_lines.last_mut() {
+                Arc::make_mut(last).terminator_length = last_terminator_length;
+            }
+        }
+        move_positions(&mut document_lines[replaced.end..], new_length - old_length);
+        document_lines.splice(replaced.clone(), new_lines);
+
+        // The title page is the first paragraph, so it's parsed again from the start
+        let (region, new_region) = match document_lines.is_empty() {
+            true => (0..0, Vec::new()),
+            false => get_reparsed_region(&document_lines, 0..replaced.start + new_count),
+        };
+        let old_region_end = region.end + replaced.len() - new_count;
+        Some(self.replace_region(document_lines, region, new_region, old_region_end))
+    }
+
+    // Makes `lines` the lines of the document, with `new_region` parsed in place of `region`, and returns the
+    // changes from the old lines, which ended at `old_region_end`.
+    //
+    // The notes and boneyards are resolved again around the region, and the lines they changed outside of it
+    // (such as the lines hidden by a `/*` added to the region) get their ranges found again too.
+    fn replace_region(
+        &mut self,
+        mut lines: Vec<Arc<FNLine>>,
+        region: Range<usize>,
+        new_region: Vec<FNLine>,
+        old_region_end: usize,
+    ) -> Vec<FNLineChange> {
+        for (offset, ln) in new_region.into_iter().enumerate() {
+            lines[region.start + offset] = Arc::new(ln);
+        }
+        let line_delta = region.end as isize - old_region_end as isize;
+        let plain_lines: Vec<FNLine> = lines.iter().map(|ln| (**ln).clone()).collect();
+        let previous = self.ranged_elements.take();
+        let states = match &previous {
+            Some(states) => {
+                get_updated_ranged_element_states(states, &plain_lines, region.clone(), line_delta)
+            }
+            None => get_ranged_element_states(&plain_lines),
+        };
+
+        let span = get_resolved_span(
+            previous.as_deref(),
+            &states,
+            &plain_lines,
+            region,
+            line_delta,
+        );
+        let span_lines = get_lines_with_resolved_ranges(&plain_lines, &states, span.clone());
+        for (offset, ln) in span_lines.into_iter().enumerate() {
+            lines[span.start + offset] = Arc::new(ln);
+        }
+        self.ranged_elements = Some(states);
+
+        let old_span_end = (span.end as isize - line_delta) as usize;
+        let old_span: Vec<FNLine> = self.raw_fnlines[span.start..old_span_end]
+            .iter()
+            .map(|ln| (**ln).clone())
+            .collect();
+        let new_span: Vec<FNLine> = lines[span.clone()]
+            .iter()
+            .map(|ln| (**ln).clone())
+            .collect();
+        let changes = get_semantic_diff(&old_span, &new_span)
+            .into_iter()
+            .map(|change| get_moved_change(change, span.start))
+            .collect();
+        self.raw_fnlines = Arc::new(lines);
+        self.increment_version();
+        changes
+    }
+
+    // Updates the notes and boneyards after the lines in `dirty_range` were edited, which added `line_delta` lines
+    fn update_ranged_elements(&mut self, dirty_range: Range<usize>, line_delta: isize) {
+        if let Some(states) = &self.ranged_elements {
+            let states = get_updated_ranged_element_states(
+                states,
+                &self.get_lines(),
+                dirty_range,
+                line_delta,
+            );
+            self.ranged_elements = Some(states);
+        }
+    }
+
+    /// Returns a new uuid from the document's `FNUuidGenerator`, for ids which should be as reproducible as the
+    /// ids of the lines.
+    pub fn get_new_uuid(&mut self) -> Uuid {
+        self.uuid_generator.get_uuid()
+    }
+
+    fn get_line_with_uuid(&mut self, mut line: FNLine) -> FNLine {
+        if line.uuid.is_none() {
+            line.uuid = Some(self.get_new_uuid());
+        }
+        line
+    }
+
+    fn increment_version(&mut self) -> u64 {
+        self.version += 1;
+        self.version
+    }
+}
+
+fn move_positions(lines: &mut [Arc<FNLine>], delta: i32) {
+    if delta == 0 {
+        return;
+    }
+    for ln in lines.iter_mut() {
+        let mut moved = (**ln).clone();
+        moved.position += delta;
+        *ln = Arc::new(moved);
+    }
+}
+
+// The lines between the empty lines around the `edited` lines (inclusive), parsed again.
+// They don't depend on any line outside of them. Their ranges are found again by `replace_region`.
+//
+// An empty line among the edited lines isn't a boundary, so that when an edit adds or removes an empty line,
+// the paragraphs on both sides of it are parsed again too. `edited` may be empty, for lines which were removed.
+fn get_reparsed_region(lines: &[Arc<FNLine>], edited: Range<usize>) -> (Range<usize>, Vec<FNLine>) {
+    let is_boundary = |ln: &Arc<FNLine>| ln.raw_string.is_empty();
+    let start = lines[..edited.start]
+        .iter()
+        .rposition(is_boundary)
+        .unwrap_or(0);
+    let end = lines[edited.end..]
+        .iter()
+        .position(is_boundary)
+        .map_or(lines.len(), |idx| edited.end + idx + 1);
+    let unparsed: Vec<FNLine> = lines[start..end]
+        .iter()
+        .map(|ln| FNLine {
+            string: ln.raw_string.clone(),
+            raw_string: ln.raw_string.clone(),
+            position: ln.position,
+            length: ln.raw_string.len() as i32,
+            terminator_length: ln.terminator_length,
+            uuid: ln.uuid,
+            ..Default::default()
+        })
+        .collect();
+    let (parsed, _) = get_lines_with_standalone_transitions(
+        &static_fountain_parser::get_parsed_lines_from_line_vec(unparsed),
+        &Default::default(),
+    );
+    (start..end, parsed)
+}
+
+// The notes and boneyards of the lines, resolved from scratch
+fn get_ranged_element_states(lines: &[FNLine]) -> Vec<FNRangedElementState> {
+    [FNRangedElementType::note(), FNRangedElementType::boneyard()]
+        .into_iter()
+        .map(|ranged_element_type| {
+            let partials_map =
+                partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+                    lines,
+                    &ranged_element_type,
+                )
+                .unwrap_or_default();
+            let (multiline_ranges, diagnostics) =
+                partial_line_resolver::get_partial_multiline_ranges_and_diagnostics_with_strategy(
+                    &partials_map,
+                    lines,
+                    &ranged_element_type,
+                    FNRangedElementResolutionStrategy::default(),
+                );
+            FNRangedElementState {
+                ranged_element_type,
+                partials_map,
+                multiline_ranges,
+                diagnostics,
+            }
+        })
+        .collect()
+}
+
+// The notes and boneyards of the lines after the lines in `dirty_range` were edited, from the `states` before
+fn get_updated_ranged_element_states(
+    states: &[FNRangedElementState],
+    lines: &[FNLine],
+    dirty_range: Range<usize>,
+    line_delta: isize,
+) -> Vec<FNRangedElementState> {
+    states
+        .iter()
+        .map(|state| {
+            let partials_map =
+                partial_line_resolver::get_updated_partial_fnline_map_for_dirty_range(
+                    &state.partials_map,
+                    lines,
+                    dirty_range.clone(),
+                    line_delta,
+                    &state.ranged_element_type,
+                );
+            let (multiline_ranges, diagnostics) =
+                partial_line_resolver::get_updated_partial_multiline_ranges_for_dirty_range(
+                    &state.multiline_ranges,
+                    &state.diagnostics,
+                    &partials_map,
+                    lines,
+                    dirty_range.clone(),
+                    &state.ranged_element_type,
+                    FNRangedElementResolutionStrategy::default(),
+                );
+            FNRangedElementState {
+                ranged_element_type: state.ranged_element_type.clone(),
+                partials_map,
+                multiline_ranges,
+                diagnostics,
+            }
+        })
+        .collect()
+}
+
+// The lines whose note and boneyard ranges have to be found again after `region` was edited: every line from the
+// first opener the edit affects onward. That's the start of the region, or of an earlier range which the edit
+// changed, moved back until no range crosses it and no note or boneyard is still open at the line before it
+// (which the ranges don't always show, such as the nested `/*` of `/* a /* b */`).
+// Without the `previous` states, that's every line.
+fn get_resolved_span(
+    previous: Option<&[FNRangedElementState]>,
+    states: &[FNRangedElementState],
+    lines: &[FNLine],
+    region: Range<usize>,
+    line_delta: isize,
+) -> Range<usize> {
+    let len = lines.len();
+    let Some(previous) = previous else {
+        return 0..len;
+    };
+    let old_region_end = (region.end as isize - line_delta) as usize;
+    // The new index of an old line, or the start of the region for a line which was in it
+    let get_moved_index = |idx: usize| match idx {
+        idx if idx < region.start => idx,
+        idx if idx >= old_region_end => (idx as isize + line_delta) as usize,
+        _ => region.start,
+    };
+
+    let mut start = region.start;
+    for (old_state, state) in previous.iter().zip(states) {
+        let old_bounds: Vec<(usize, usize, usize, usize)> =
+            get_ranged_element_bounds(old_state, len)
+                .into_iter()
+                .map(|(start, local_start, end, local_end)| {
+                    (
+                        get_moved_index(start),
+                        local_start,
+                        get_moved_index(end),
+                        local_end,
+                    )
+                })
+                .collect();
+        let new_bounds = get_ranged_element_bounds(state, len);
+        for (range_start, _, _, _) in old_bounds
+            .iter()
+            .filter(|bounds| !new_bounds.contains(bounds))
+            .chain(
+                new_bounds
+                    .iter()
+                    .filter(|bounds| !old_bounds.contains(bounds)),
+            )
+        {
+            start = start.min(*range_start);
+        }
+    }
+    loop {
+        for (range_start, _, end, _) in states
+            .iter()
+            .flat_map(|state| get_ranged_element_bounds(state, len))
+        {
+            if range_start < start && end >= start {
+                start = range_start;
+            }
+        }
+        // The lines before the start weren't edited, so they're scanned as they are
+        let scanned = get_invisible_byte_ranges_for_lines(&lines[..start]);
+        let open_start = (0..start)
+            .rev()
+            .find(|idx| !scanned[*idx].is_open_at_end)
+            .map_or(0, |idx| idx + 1);
+        if open_start == start {
+            return start..len;
+        }
+        start = open_start;
+    }
+}
+
+// The global and local starts and ends of every multiline range of the state. A boneyard which is never
+// closed hides every line after it, so it reaches the last line.
+fn get_ranged_element_bounds(
+    state: &FNRangedElementState,
+    len: usize,
+) -> Vec<(usize, usize, usize, usize)> {
+    let is_boneyard = matches!(
+        state.ranged_element_type,
+        FNRangedElementType::Boneyard { .. }
+    );
+    let ranges = state.multiline_ranges.iter().filter_map(|range| {
+        Some((
+            range.global_start?,
+            range.local_start?,
+            range.global_end?,
+            range.local_end?,
+        ))
+    });
+    let unclosed = state
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            is_boneyard && diagnostic.kind == FNRangedElementDiagnosticKind::UnclosedOpen
+        })
+        .map(|diagnostic| {
+            let end = len.saturating_sub(1).max(diagnostic.global_index);
+            (
+                diagnostic.global_index,
+                diagnostic.local_index,
+                end,
+                usize::MAX,
+            )
+        });
+    ranges.chain(unclosed).collect()
+}
+
+// The lines in `span`, with their note and boneyard ranges (and the colors and emphasis which depend on them)
+// found again from the `states`
+fn get_lines_with_resolved_ranges(
+    lines: &[FNLine],
+    states: &[FNRangedElementState],
+    span: Range<usize>,
+) -> Vec<FNLine> {
+    let mut span_lines: Vec<FNLine> = lines[span.clone()]
+        .iter()
+        .map(|ln| FNLine {
+            additional_note_ranges: Default::default(),
+            additional_omitted_ranges: Default::default(),
+            note_type: None,
+            boneyard_type: None,
+            note_markers: None,
+            boneyard_markers: None,
+            ..ln.clone()
+        })
+        .collect();
+    let mut resolved_ranges: Vec<(FNRangedElementType, Vec<FNPartialMultilineRange>)> = Vec::new();
+    for state in states {
+        let partials_map = partial_line_resolver::get_partial_fnline_map_with_multiline_ranges(
+            &state.partials_map,
+            lines,
+            &state.multiline_ranges,
+            &state.ranged_element_type,
+        );
+        for (global_idx, ln) in partials_map.range(span.clone()) {
+            let line = &mut span_lines[global_idx - span.start];
+            match state.ranged_element_type {
+                FNRangedElementType::Boneyard { .. } => {
+                    line.boneyard_type = ln.boneyard_type.clone();
+                    line.boneyard_markers = ln.boneyard_markers.clone();
+                }
+                _ => {
+                    line.note_type = ln.note_type.clone();
+                    line.note_markers = ln.note_markers.clone();
+                }
+            }
+        }
+        let span_ranges = state
+            .multiline_ranges
+            .iter()
+            .filter(|range| {
+                range.global_start.is_some_and(|start| start >= span.start)
+                    && range.global_end.is_some_and(|end| end < span.end)
+            })
+            .map(|range| FNPartialMultilineRange {
+                global_start: range.global_start.map(|start| start - span.start),
+                global_end: range.global_end.map(|end| end - span.start),
+                ..range.clone()
+            })
+            .collect();
+        resolved_ranges.push((state.ranged_element_type.clone(), span_ranges));
+    }
+
+    let span_lines = partial_line_resolver::get_lines_with_invisible_ranges_for_resolved_ranges(
+        &span_lines,
+        &[FNRangedElementType::note()],
+        &resolved_ranges,
+    );
+    get_lines_with_emphasis(
+        &get_lines_with_colors(&span_lines),
+        &FNEmphasisOptions::default(),
+    )
+}
+
+// The text added before and after a line to force `fn_type`, or `None` if it can't be forced
+fn get_forcing_markers(fn_type: &FNLineType) -> Option<(&'static str, &'static str)> {
+    match fn_type {
+        FNLineType::Heading => Some((".", "")),
+        FNLineType::Action => Some(("!", "")),
+        FNLineType::Shot => Some(("!!", "")),
+        FNLineType::Character => Some(("@", "")),
+        FNLineType::TransitionLine => Some(("> ", "")),
+        FNLineType::Centered => Some(("> ", " <")),
+        FNLineType::Lyrics => Some(("~", "")),
+        FNLineType::Synopse => Some(("= ", "")),
+        FNLineType::Section => Some(("# ", "")),
+        _ => None,
+    }
+}
+
+// The raw text without the characters which forced its type, the `<` of centered text, or the `^` of a dual cue
+fn get_unforced_text(line: &FNLine) -> &str {
+    let mut text = line.raw_string.as_str();
+    if line.fn_type == FNLineType::Shot {
+        text = text.trim_start().strip_prefix("!!").unwrap_or(text);
+    } else if line.forced_by == FNForcedBy::Hash {
+        text = text.trim_start().trim_start_matches('#').trim_start();
+    } else if let Some(marker) = line.forced_by.get_marker() {
+        text = text.trim_start().strip_prefix(marker).unwrap_or(text);
+        if matches!(line.forced_by, FNForcedBy::GreaterThan | FNForcedBy::Equals) {
+            text = text.trim_start();
+        }
+    }
+    match line.fn_type {
+        FNLineType::Centered => text.trim_end().strip_suffix('<').unwrap_or(text).trim_end(),
+        FNLineType::DualDialogueCharacter => {
+            text.trim_end().strip_suffix('^').unwrap_or(text).trim_end()
+        }
+        _ => text,
+    }
+}
+
+// The change with its indices moved from the start of a region to the start of the document
+fn get_moved_change(change: FNLineChange, region_start: usize) -> FNLineChange {
+    match change {
+        FNLineChange::Inserted { new_index } => FNLineChange::Inserted {
+            new_index: new_index + region_start,
+        },
+        FNLineChange::Removed { old_index } => FNLineChange::Removed {
+            old_index: old_index + region_start,
+        },
+        FNLineChange::TextChanged {
+            old_index,
+            new_index,
+        } => FNLineChange::TextChanged {
+            old_index: old_index + region_start,
+            new_index: new_index + region_start,
+        },
+        FNLineChange::Retyped {
+            old_index,
+            new_index,
+            old_type,
+            new_type,
+        } => FNLineChange::Retyped {
+            old_index: old_index + region_start,
+            new_index: new_index + region_start,
+            old_type,
+            new_type,
+        },
+    }
+}
+
+// A copy of the line with only the text in `bounds` (between the two markers). Its ranges are found again
+// when the lines around it are parsed again.
+fn get_line_slice(line: &FNLine, bounds: Range<usize>, prefix: &str, suffix: &str) -> FNLine {
+    let text = &line.raw_string[bounds];
+    if text.trim().is_empty() {
+        return FNLine {
+            fn_type: FNLineType::Empty,
+            position: line.position,
+            terminator_length: line.terminator_length,
+            uuid: line.uuid,
+            ..Default::default()
+        };
+    }
+    let raw_string = format!("{}{}{}", prefix, text, suffix);
+    FNLine {
+        string: raw_string.clone(),
+        length: raw_string.len() as i32,
+        raw_string,
+        ..line.clone()
+    }
+}
+
+impl FNDocumentSnapshot {
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn get_line(&self, index: usize) -> Option<&FNLine> {
+        self.lines.get(index).map(|ln| ln.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FNLine> {
+        self.lines.iter().map(|ln| ln.as_ref())
+    }
+
+    pub fn get_lines(&self) -> Vec<FNLine> {
+        self.iter().cloned().collect()
+    }
+
+    /// Returns `true` if the line at `index` is the exact same (shared) line in both snapshots.
+    pub fn shares_line_with(&self, other: &FNDocumentSnapshot, index: usize) -> bool {
+        match (self.lines.get(index), other.lines.get(index)) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
     }
 }
@@ -0,0 +1,303 @@
+//! A human-readable summary of the changes between two drafts, for producers tracking draft-to-draft changes.
+//!
+//! The report is built from the semantic diff of the two drafts: which scenes were added, cut, or changed,
+//! how many lines of dialogue changed for each character, and how the page count changed.
+//! Scenes are matched by their heading text, so a scene whose heading was rewritten counts as cut and added.
+//! Each draft is identified by the `Draft date` of its title page, and characters are counted under their
+//! canonical name, so that `JOE` and `JOSEPH` aren't reported as two characters.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::fountain_characters::{
+    get_speaking_character_for_line_index, FNCharacterAliasMap, FNCharacterMetadata,
+};
+use crate::fountain_diff::{get_semantic_diff, FNLineChange};
+use crate::fountain_line::FNLine;
+use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+use crate::fountain_scene::{get_scenes_from_parsed_lines, FNScene};
+use crate::fountain_title_page::get_title_page_from_lines;
+use crate::helper_funcs::{get_json_string, get_json_string_map};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDraftReportOptions {
+    /// Used to count the pages of both drafts
+    pub pagination: FNPaginationOptions,
+    /// Carried into the `FNCharacterDialogueChange` of each character
+    pub character_metadata: FNCharacterMetadata,
+    pub aliases: FNCharacterAliasMap,
+}
+
+/// The number of dialogue lines (including cues and parentheticals) of a character which changed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCharacterDialogueChange {
+    pub character: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub lines_changed: usize,
+    /// The character's metadata from the `FNDraftReportOptions`
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDraftReport {
+    /// The `Draft date` of the title page of each draft, such as `Blue Revision - 3/4/2025`
+    pub old_draft_date: Option<String>,
+    pub new_draft_date: Option<String>,
+    /// The headings of the scenes, in the order of the draft they appear in
+    pub scenes_added: Vec<String>,
+    pub scenes_cut: Vec<String>,
+    pub scenes_changed: Vec<String>,
+    /// Ordered by character name
+    pub dialogue_changes: Vec<FNCharacterDialogueChange>,
+    pub old_page_count: usize,
+    pub new_page_count: usize,
+}
+
+impl FNDraftReport {
+    pub fn get_page_count_delta(&self) -> i64 {
+        self.new_page_count as i64 - self.old_page_count as i64
+    }
+
+    pub fn to_json(&self) -> String {
+        let get_json_list = |items: &[String]| -> String {
+            let items: Vec<String> = items.iter().map(|item| get_json_string(item)).collect();
+            format!("[{}]", items.join(","))
+        };
+        let dialogue_changes: Vec<String> = self
+            .dialogue_changes
+            .iter()
+            .map(|change| {
+                format!(
+                    "{{\"character\":{},\"lines_added\":{},\"lines_removed\":{},\"lines_changed\":{},\"metadata\":{}}}",
+                    get_json_string(&change.character),
+                    change.lines_added,
+                    change.lines_removed,
+                    change.lines_changed,
+                    get_json_string_map(&change.metadata)
+                )
+            })
+            .collect();
+
+        let get_json_draft_date = |draft_date: &Option<String>| -> String {
+            match draft_date {
+                Some(draft_date) => get_json_string(draft_date),
+                None => String::from("null"),
+            }
+        };
+
+        format!(
+            "{{\"old_draft_date\":{},\"new_draft_date\":{},\"old_page_count\":{},\"new_page_count\":{},\"page_count_delta\":{},\"scenes_added\":{},\"scenes_cut\":{},\"scenes_changed\":{},\"dialogue_changes\":[{}]}}",
+            get_json_draft_date(&self.old_draft_date),
+            get_json_draft_date(&self.new_draft_date),
+            self.old_page_count,
+            self.new_page_count,
+            self.get_page_count_delta(),
+            get_json_list(&self.scenes_added),
+            get_json_list(&self.scenes_cut),
+            get_json_list(&self.scenes_changed),
+            dialogue_changes.join(",")
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::from("# Draft comparison\n\n");
+        if self.old_draft_date.is_some() || self.new_draft_date.is_some() {
+            markdown.push_str(&format!(
+                "Drafts: {} -> {}\n\n",
+                self.old_draft_date.as_deref().unwrap_or("(undated)"),
+                self.new_draft_date.as_deref().unwrap_or("(undated)")
+            ));
+        }
+        markdown.push_str(&format!(
+            "Pages: {} -> {} ({:+})\n",
+            self.old_page_count,
+            self.new_page_count,
+            self.get_page_count_delta()
+        ));
+
+        let sections = [
+            ("Scenes added", &self.scenes_added),
+            ("Scenes cut", &self.scenes_cut),
+            ("Scenes changed", &self.scenes_changed),
+        ];
+        for (title, scenes) in sections {
+            if scenes.is_empty() {
+                continue;
+            }
+            markdown.push_str(&format!("\n## {}\n\n", title));
+            for scene in scenes {
+                markdown.push_str(&format!("- {}\n", scene));
+            }
+        }
+
+        if !self.dialogue_changes.is_empty() {
+            markdown.push_str("\n## Dialogue changes\n\n");
+            markdown.push_str("| Character | Added | Removed | Changed |\n");
+            markdown.push_str("| --- | --- | --- | --- |\n");
+            for change in &self.dialogue_changes {
+                markdown.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    change.character,
+                    change.lines_added,
+                    change.lines_removed,
+                    change.lines_changed
+                ));
+            }
+        }
+        markdown
+    }
+}
+
+/// Compares two drafts.
+pub fn get_draft_report(
+    old: &[FNLine],
+    new: &[FNLine],
+    options: &FNDraftReportOptions,
+) -> FNDraftReport {
+    let changes = get_semantic_diff(old, new);
+    let get_character = |lines: &[FNLine], global_index: usize| {
+        get_speaking_character_for_line_index(lines, global_index)
+            .map(|name| options.aliases.get_canonical_name(&name))
+    };
+    let old_scenes = get_headed_scenes(old);
+    let new_scenes = get_headed_scenes(new);
+
+    // Pair up scenes with the same heading, in order
+    let mut matched_new: HashSet<usize> = HashSet::new();
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    let mut scenes_cut: Vec<String> = Vec::new();
+    for (old_idx, (old_heading, _)) in old_scenes.iter().enumerate() {
+        let new_idx = new_scenes
+            .iter()
+            .enumerate()
+            .position(|(new_idx, (new_heading, _))| {
+                new_heading == old_heading && !matched_new.contains(&new_idx)
+            });
+        match new_idx {
+            Some(new_idx) => {
+                matched_new.insert(new_idx);
+                pairs.push((old_idx, new_idx));
+            }
+            None => scenes_cut.push(old_heading.clone()),
+        }
+    }
+    let scenes_added: Vec<String> = new_scenes
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !matched_new.contains(idx))
+        .map(|(_, (heading, _))| heading.clone())
+        .collect();
+
+    let mut changed_old_lines: HashSet<usize> = HashSet::new();
+    let mut changed_new_lines: HashSet<usize> = HashSet::new();
+    let mut dialogue_changes: BTreeMap<String, FNCharacterDialogueChange> = BTreeMap::new();
+    for change in &changes {
+        match change {
+            FNLineChange::Inserted { new_index } => {
+                changed_new_lines.insert(*new_index);
+                if let Some(character) = get_character(new, *new_index) {
+                    get_dialogue_change(&mut dialogue_changes, character).lines_added += 1;
+                }
+            }
+            FNLineChange::Removed { old_index } => {
+                changed_old_lines.insert(*old_index);
+                if let Some(character) = get_character(old, *old_index) {
+                    get_dialogue_change(&mut dialogue_changes, character).lines_removed += 1;
+                }
+            }
+            FNLineChange::TextChanged {
+                old_index,
+                new_index,
+            } => {
+                changed_old_lines.insert(*old_index);
+                changed_new_lines.insert(*new_index);
+                // The diff pairs up any changed lines, which may not belong to the same character
+                let old_character = get_character(old, *old_index);
+                let new_character = get_character(new, *new_index);
+                if old_character == new_character {
+                    if let Some(character) = new_character {
+                        get_dialogue_change(&mut dialogue_changes, character).lines_changed += 1;
+                    }
+                    continue;
+                }
+                if let Some(character) = old_character {
+                    get_dialogue_change(&mut dialogue_changes, character).lines_removed += 1;
+                }
+                if let Some(character) = new_character {
+                    get_dialogue_change(&mut dialogue_changes, character).lines_added += 1;
+                }
+            }
+            FNLineChange::Retyped {
+                old_index,
+                new_index,
+                ..
+            } => {
+                changed_old_lines.insert(*old_index);
+                changed_new_lines.insert(*new_index);
+            }
+        }
+    }
+
+    let scenes_changed: Vec<String> = pairs
+        .iter()
+        .filter(|(old_idx, new_idx)| {
+            let old_scene = &old_scenes[*old_idx].1;
+            let new_scene = &new_scenes[*new_idx].1;
+            (old_scene.start..old_scene.end).any(|idx| changed_old_lines.contains(&idx))
+                || (new_scene.start..new_scene.end).any(|idx| changed_new_lines.contains(&idx))
+        })
+        .map(|(_, new_idx)| new_scenes[*new_idx].0.clone())
+        .collect();
+
+    FNDraftReport {
+        old_draft_date: get_draft_date(old),
+        new_draft_date: get_draft_date(new),
+        scenes_added,
+        scenes_cut,
+        scenes_changed,
+        dialogue_changes: dialogue_changes
+            .into_values()
+            .map(|change| FNCharacterDialogueChange {
+                metadata: options
+                    .character_metadata
+                    .get_all_with_aliases(&change.character, &options.aliases)
+                    .cloned()
+                    .unwrap_or_default(),
+                ..change
+            })
+            .collect(),
+        old_page_count: get_pages(old, &options.pagination, &FNMonospaceMeasurer).len(),
+        new_page_count: get_pages(new, &options.pagination, &FNMonospaceMeasurer).len(),
+    }
+}
+
+fn get_dialogue_change(
+    dialogue_changes: &mut BTreeMap<String, FNCharacterDialogueChange>,
+    character: String,
+) -> &mut FNCharacterDialogueChange {
+    dialogue_changes
+        .entry(character.clone())
+        .or_insert_with(|| FNCharacterDialogueChange {
+            character,
+            ..Default::default()
+        })
+}
+
+fn get_draft_date(lines: &[FNLine]) -> Option<String> {
+    get_title_page_from_lines(lines)
+        .into_iter()
+        .find(|entry| entry.key == "draft date" || entry.key == "draft")
+        .filter(|entry| !entry.values.is_empty())
+        .map(|entry| entry.values.join(" "))
+}
+
+// Scenes with a heading, and their heading text
+fn get_headed_scenes(lines: &[FNLine]) -> Vec<(String, FNScene)> {
+    get_scenes_from_parsed_lines(lines)
+        .into_iter()
+        .filter_map(|scene| {
+            let heading = scene.get_heading_line(lines)?.string.trim().to_string();
+            Some((heading, scene))
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+//! Pairs up the two sides of dual dialogue, and lays them out in two columns for fixed-width output.
+//!
+//! In Fountain, the right side of dual dialogue is the block whose character cue ends with `^`;
+//! the left side is the dialogue block right before it. The two sides rarely have the same length,
+//! so the layout fills the shorter column with empty rows.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::fountain_text_width::get_display_width;
+use crate::fountain_wrap::wrap_words;
+
+/// The line ranges of both sides of a dual dialogue. Ends are exclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDualDialogueBlock {
+    pub left_start: usize,
+    pub left_end: usize,
+    pub right_start: usize,
+    pub right_end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDualDialogueLayoutOptions {
+    /// The width of both columns together, plus the gutter, in graphemes
+    pub total_width: usize,
+    /// The number of spaces between the two columns
+    pub gutter: usize,
+}
+
+impl Default for FNDualDialogueLayoutOptions {
+    fn default() -> Self {
+        FNDualDialogueLayoutOptions {
+            total_width: 60,
+            gutter: 4,
+        }
+    }
+}
+
+/// A single row of the layout. A side is `None` when its block has already ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDualDialogueRow {
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDualDialogueLayout {
+    pub column_width: usize,
+    pub gutter: usize,
+    pub rows: Vec<FNDualDialogueRow>,
+}
+
+impl FNDualDialogueLayout {
+    /// Returns each row as a single line of text, with the right column starting at the same column on every row.
+    pub fn get_fixed_width_lines(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let left = row.left.clone().unwrap_or_default();
+                match &row.right {
+                    Some(right) => {
+                        let padding = self.column_width.saturating_sub(get_display_width(&left))
+                            + self.gutter;
+                        format!("{}{}{}", left, " ".repeat(padding), right)
+                    }
+                    None => left,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns every dual dialogue in the parsed lines, in document order.
+///
+/// A `DualDialogueCharacter` without a dialogue block before it is skipped.
+pub fn get_dual_dialogue_blocks(lines: &[FNLine]) -> Vec<FNDualDialogueBlock> {
+    let mut blocks: Vec<FNDualDialogueBlock> = Vec::new();
+
+    for (idx, ln) in lines.iter().enumerate() {
+        if ln.fn_type != FNLineType::DualDialogueCharacter {
+            continue;
+        }
+        let right_end = idx
+            + 1
+            + lines[idx + 1..]
+                .iter()
+                .take_while(|next| next.is_dual_dialogue_element())
+                .count();
+
+        // The left block ends right before the cue, or before a single empty line
+        let mut left_end = idx;
+        if left_end > 0 && lines[left_end - 1].fn_type == FNLineType::Empty {
+            left_end -= 1;
+        }
+        let left_start = lines[..left_end]
+            .iter()
+            .rposition(|prev| prev.fn_type == FNLineType::Character);
+        let Some(left_start) = left_start else {
+            continue;
+        };
+        if !lines[left_start + 1..left_end]
+            .iter()
+            .all(|between| between.is_dialogue_element() || between.fn_type == FNLineType::More)
+        {
+            continue;
+        }
+
+        blocks.push(FNDualDialogueBlock {
+            left_start,
+            left_end,
+            right_start: idx,
+            right_end,
+        });
+    }
+
+    blocks
+}
+
+/// Lays out the printable text of both sides of a dual dialogue side by side, without notes, boneyards,
+/// or emphasis markers. Lines longer than a column are word-wrapped.
+pub fn get_dual_dialogue_layout(
+    lines: &[FNLine],
+    block: &FNDualDialogueBlock,
+    options: &FNDualDialogueLayoutOptions,
+) -> FNDualDialogueLayout {
+    let column_width = (options.total_width.saturating_sub(options.gutter) / 2).max(1);
+    // The stored note and boneyard ranges of each line still hide what a multiline note opened before the block
+    let printable = get_printable_strings_for_lines(&lines[block.left_start..block.right_end]);
+    let (left, right) = printable.split_at(block.right_start - block.left_start);
+    let left = get_column_rows(&left[..block.left_end - block.left_start], column_width);
+    let right = get_column_rows(right, column_width);
+
+    let rows = (0..left.len().max(right.len()))
+        .map(|row| FNDualDialogueRow {
+            left: left.get(row).cloned(),
+            right: right.get(row).cloned(),
+        })
+        .collect();
+
+    FNDualDialogueLayout {
+        column_width,
+        gutter: options.gutter,
+        rows,
+    }
+}
+
+// The rows of one side, from the printable text of its lines
+fn get_column_rows(printable: &[String], column_width: usize) -> Vec<String> {
+    printable
+        .iter()
+        .flat_map(|text| wrap_words(text, column_width))
+        .collect()
+}
@@ -0,0 +1,127 @@
+//! Finds dialogue which is spoken more than once, to catch lines that were accidentally repeated while rewriting.
+//!
+//! Lines are compared loosely: case and punctuation are ignored, and with a `min_similarity` below `1.0`,
+//! lines which differ by a few characters are caught as well:
+//!
+//! ```text
+//! JOE: I told you, we're not going back there.
+//! JOE: I told you we're not going back in there!
+//! ```
+//!
+//! Short lines such as "Yes." or "What?" are repeated on purpose all the time, so they're skipped by default.
+
+use crate::fountain_characters::get_speaking_character_for_line_index;
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::helper_funcs::{get_edit_distance, get_normalized_text_for_matching};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDuplicateDialogueOptions {
+    /// How similar two lines need to be, from `0.0` to `1.0`, where `1.0` only matches identical lines.
+    /// Similarity is `1 - edit distance / length of the longer line`, after normalizing both lines.
+    pub min_similarity: f64,
+    /// Lines with fewer words than this are never flagged
+    pub min_words: usize,
+    /// If `true`, only lines spoken by the same character are compared
+    pub same_character_only: bool,
+}
+
+impl Default for FNDuplicateDialogueOptions {
+    fn default() -> Self {
+        FNDuplicateDialogueOptions {
+            min_similarity: 0.9,
+            min_words: 3,
+            same_character_only: false,
+        }
+    }
+}
+
+/// A group of dialogue lines which are all similar to the first one.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDuplicateDialogue {
+    /// The text of the first line, as it would be printed
+    pub text: String,
+    /// Global indices of every line in the group, in document order
+    pub line_indices: Vec<usize>,
+    /// The speaker of each line, in the same order as `line_indices`
+    pub characters: Vec<String>,
+    /// The lowest similarity of any line to the first one
+    pub min_similarity: f64,
+}
+
+impl FNDuplicateDialogue {
+    pub fn is_exact(&self) -> bool {
+        self.min_similarity >= 1.0
+    }
+}
+
+/// Returns every group of similar dialogue lines, in order of their first line.
+pub fn get_duplicate_dialogue(
+    lines: &[FNLine],
+    options: &FNDuplicateDialogueOptions,
+) -> Vec<FNDuplicateDialogue> {
+    let printable = get_printable_strings_for_lines(lines);
+    // The normalized text of the first line of each group, next to the group
+    let mut groups: Vec<(String, FNDuplicateDialogue)> = Vec::new();
+
+    for (idx, ln) in lines.iter().enumerate() {
+        if !matches!(ln.fn_type, FNLineType::Dialogue | FNLineType::DualDialogue) {
+            continue;
+        }
+        let normalized = get_normalized_text_for_matching(&printable[idx]);
+        if normalized.is_empty() || normalized.split(' ').count() < options.min_words {
+            continue;
+        }
+        let character = get_speaking_character_for_line_index(lines, idx).unwrap_or_default();
+
+        let mut best: Option<(usize, f64)> = None;
+        for (group_index, (first, group)) in groups.iter().enumerate() {
+            if options.same_character_only && group.characters[0] != character {
+                continue;
+            }
+            let similarity = get_similarity(first, &normalized, options.min_similarity);
+            if similarity >= options.min_similarity && best.is_none_or(|(_, s)| similarity > s) {
+                best = Some((group_index, similarity));
+            }
+        }
+
+        match best {
+            Some((group_index, similarity)) => {
+                let group = &mut groups[group_index].1;
+                group.line_indices.push(idx);
+                group.characters.push(character);
+                group.min_similarity = group.min_similarity.min(similarity);
+            }
+            None => groups.push((
+                normalized,
+                FNDuplicateDialogue {
+                    text: printable[idx].clone(),
+                    line_indices: vec![idx],
+                    characters: vec![character],
+                    min_similarity: 1.0,
+                },
+            )),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(_, group)| group)
+        .filter(|group| group.line_indices.len() > 1)
+        .collect()
+}
+
+// Skips the edit distance when the lengths alone are too far apart to reach `min_similarity`
+fn get_similarity(a: &str, b: &str, min_similarity: f64) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let a_len = a.chars().count();
+    let b_len = b.chars().count();
+    let max_len = a_len.max(b_len) as f64;
+    if 1.0 - a_len.abs_diff(b_len) as f64 / max_len < min_similarity {
+        return 0.0;
+    }
+    1.0 - get_edit_distance(a, b) as f64 / max_len
+}
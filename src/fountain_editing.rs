@@ -0,0 +1,170 @@
+//! Helpers for editors, which turn a cursor position into the text of a single edit.
+//!
+//! `get_dialogue_continuation` continues the dialogue block around the cursor. After an action line
+//! interrupts the block, continuing it means a new cue for the same character, with `(CONT'D)`:
+//!
+//! ```text
+//! JOE                          JOE
+//! Wait here.                   Wait here.
+//!
+//! He leaves.|          ->      He leaves.
+//!
+//!                              JOE (CONT'D)
+//!                              |
+//! ```
+
+use std::ops::Range;
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDialogueContinuationOptions {
+    /// The extension added to the cue after an interruption, or `None` to repeat the cue as it is
+    pub continued_extension: Option<String>,
+}
+
+impl Default for FNDialogueContinuationOptions {
+    fn default() -> Self {
+        FNDialogueContinuationOptions {
+            continued_extension: Some(String::from("(CONT'D)")),
+        }
+    }
+}
+
+/// The edit which continues a dialogue block: `text` is inserted at the end of the line at `insert_after_index`,
+/// and the cursor goes at the end of the inserted text, on an empty line ready for dialogue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNDialogueContinuation {
+    pub character: String,
+    /// Global indices of the dialogue block being continued, from its cue to its last line. The end is exclusive.
+    pub block: Range<usize>,
+    pub insert_after_index: usize,
+    pub text: String,
+    /// `true` if action lines come between the block and the cursor, so the text starts with a new cue
+    pub is_interrupted: bool,
+}
+
+/// Returns the edit which continues the dialogue at the cursor line: either the dialogue block the line is in,
+/// or the block just before the action lines the line is in.
+///
+/// Empty lines alone don't interrupt a block. Returns `None` if there's no block, or if anything other than action
+/// and empty lines (such as a heading, a transition, or another cue) comes between the block and the cursor line.
+pub fn get_dialogue_continuation(
+    lines: &[FNLine],
+    line_index: usize,
+    options: &FNDialogueContinuationOptions,
+) -> Option<FNDialogueContinuation> {
+    lines.get(line_index)?;
+
+    if let Some(block) = get_dialogue_block_for_line_index(lines, line_index) {
+        return Some(FNDialogueContinuation {
+            character: lines[block.start].get_character_name()?,
+            insert_after_index: block.end - 1,
+            text: String::from("\n"),
+            is_interrupted: false,
+            block,
+        });
+    }
+
+    // Back over the interruption, to the last line of the block before it
+    let mut block_end = line_index;
+    while block_end > 0 && is_interruption(&lines[block_end - 1]) {
+        block_end -= 1;
+    }
+    if block_end == 0 || !is_interruption(&lines[line_index]) {
+        return None;
+    }
+    let block = get_dialogue_block_for_line_index(lines, block_end - 1)?;
+    let cue = &lines[block.start];
+    // Only empty lines after the block, so nothing interrupts it
+    if lines[block_end..=line_index]
+        .iter()
+        .all(|ln| ln.fn_type == FNLineType::Empty)
+    {
+        return Some(FNDialogueContinuation {
+            character: cue.get_character_name()?,
+            insert_after_index: block.end - 1,
+            text: String::from("\n"),
+            is_interrupted: false,
+            block,
+        });
+    }
+
+    // Forward to the end of the action paragraph the cursor is in
+    let mut insert_after_index = line_index;
+    while lines[insert_after_index].fn_type != FNLineType::Empty
+        && lines
+            .get(insert_after_index + 1)
+            .is_some_and(|ln| ln.fn_type == FNLineType::Action)
+    {
+        insert_after_index += 1;
+    }
+    let separator = if lines[insert_after_index].fn_type == FNLineType::Empty {
+        "\n"
+    } else {
+        "\n\n"
+    };
+
+    Some(FNDialogueContinuation {
+        character: cue.get_character_name()?,
+        text: format!("{}{}\n", separator, get_continued_cue(cue, options)),
+        insert_after_index,
+        is_interrupted: true,
+        block,
+    })
+}
+
+/// Returns the global indices of the dialogue block which contains the line, from its cue to its last line.
+/// The end is exclusive.
+pub fn get_dialogue_block_for_line_index(
+    lines: &[FNLine],
+    line_index: usize,
+) -> Option<Range<usize>> {
+    let mut start = line_index;
+    loop {
+        let ln = lines.get(start)?;
+        if ln.is_any_character() {
+            break;
+        }
+        if !is_dialogue_block_element(ln) || start == 0 {
+            return None;
+        }
+        start -= 1;
+    }
+    let mut end = start + 1;
+    while lines.get(end).is_some_and(is_dialogue_block_element) {
+        end += 1;
+    }
+    Some(start..end)
+}
+
+// Parentheticals, dialogue, and (MORE), without the cue
+fn is_dialogue_block_element(ln: &FNLine) -> bool {
+    ln.is_dialogue_element() || ln.is_dual_dialogue_element() || ln.fn_type == FNLineType::More
+}
+
+fn is_interruption(ln: &FNLine) -> bool {
+    matches!(ln.fn_type, FNLineType::Action | FNLineType::Empty)
+}
+
+// The cue without the dual dialogue `^` or an earlier (CONT'D), followed by the extension
+fn get_continued_cue(cue: &FNLine, options: &FNDialogueContinuationOptions) -> String {
+    let mut text = cue
+        .string
+        .trim()
+        .trim_end_matches('^')
+        .trim_end()
+        .to_string();
+    let Some(extension) = &options.continued_extension else {
+        return text;
+    };
+    if let Some(existing) = text
+        .to_ascii_uppercase()
+        .find(&extension.to_ascii_uppercase())
+    {
+        text.replace_range(existing..existing + extension.len(), "");
+        text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    }
+    format!("{} {}", text, extension)
+}
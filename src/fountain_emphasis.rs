@@ -0,0 +1,212 @@
+//! Finds emphasis (`*italic*`, `**bold**`, `***bold italic***`, and `_underline_`) within each line.
+//!
+//! Writers often paste URLs and identifiers (`https://example.com/some_page`, `snake_case_name`) into action lines.
+//! With the default `FNEmphasisInterpretation::Strict`, emphasis markers have to sit on word boundaries,
+//! and markers inside URL-like tokens are ignored, so these aren't mangled into underlines or italics.
+//! `FNEmphasisInterpretation::Permissive` pairs up any matching markers, like most Fountain apps do.
+//!
+//! Emphasis never spans multiple lines. A marker can be escaped with a backslash: `\*`.
+
+use std::collections::HashMap;
+
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNEmphasisKind {
+    Italic,
+    Bold,
+    BoldItalic,
+    Underline,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FNEmphasisInterpretation {
+    /// Markers must be on word boundaries, and are ignored inside URL-like tokens
+    #[default]
+    Strict,
+    /// Any matching pair of markers on the same line is emphasis
+    Permissive,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNEmphasisOptions {
+    pub interpretation: FNEmphasisInterpretation,
+}
+
+/// A single emphasized span within a line. All indices are byte indices into the line's `string`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNEmphasisSpan {
+    pub kind: FNEmphasisKind,
+    /// Start of the opening marker
+    pub open_start: usize,
+    /// Start of the emphasized text, right after the opening marker
+    pub content_start: usize,
+    /// End of the emphasized text, right before the closing marker (exclusive)
+    pub content_end: usize,
+    /// End of the closing marker (exclusive)
+    pub close_end: usize,
+}
+
+/// Returns every emphasized span in a single string, ordered by `open_start`.
+pub fn get_emphasis_spans_from_string(
+    text: &str,
+    options: &FNEmphasisOptions,
+) -> Vec<FNEmphasisSpan> {
+    let markers = get_marker_runs(text);
+    let protected = match options.interpretation {
+        FNEmphasisInterpretation::Strict => get_url_like_byte_ranges(text),
+        FNEmphasisInterpretation::Permissive => Vec::new(),
+    };
+    let is_protected = |idx: usize| protected.iter().any(|(s, e)| idx >= *s && idx < *e);
+
+    let mut spans: Vec<FNEmphasisSpan> = Vec::new();
+    // The unpaired openers of each marker, as (start, end), innermost last. A closer pairs with the innermost
+    // opener of its marker, so every marker is only looked at once.
+    let mut openers: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+
+    for (start, marker) in markers {
+        if is_protected(start) {
+            continue;
+        }
+        let end = start + marker.len();
+        let stack = openers.entry(marker).or_default();
+
+        let opener = stack
+            .last()
+            .filter(|(_, open_end)| start > *open_end && can_close(text, start, end, options))
+            .copied();
+        if let Some((open_start, open_end)) = opener {
+            stack.pop();
+            spans.push(FNEmphasisSpan {
+                kind: get_emphasis_kind(marker),
+                open_start,
+                content_start: open_end,
+                content_end: start,
+                close_end: end,
+            });
+        } else if can_open(text, start, end, options) {
+            stack.push((start, end));
+        }
+    }
+
+    spans.sort_by_key(|span| span.open_start);
+    spans
+}
+
+/// Returns a copy of the lines with `bold_ranges`, `italic_ranges`, `bold_italic_ranges`,
+/// and `underlined_ranges` filled in with the byte ranges of the emphasized text in each line's `string`.
+pub fn get_lines_with_emphasis(lines: &[FNLine], options: &FNEmphasisOptions) -> Vec<FNLine> {
+    lines
+        .iter()
+        .map(|ln| {
+            let mut new_line = ln.clone();
+            new_line.bold_ranges.clear();
+            new_line.italic_ranges.clear();
+            new_line.bold_italic_ranges.clear();
+            new_line.underlined_ranges.clear();
+
+            for span in get_emphasis_spans_from_string(&ln.string, options) {
+                let set = match span.kind {
+                    FNEmphasisKind::Bold => &mut new_line.bold_ranges,
+                    FNEmphasisKind::Italic => &mut new_line.italic_ranges,
+                    FNEmphasisKind::BoldItalic => &mut new_line.bold_italic_ranges,
+                    FNEmphasisKind::Underline => &mut new_line.underlined_ranges,
+                };
+                set.insert(span.content_start..span.content_end);
+            }
+            new_line
+        })
+        .collect()
+}
+
+fn get_emphasis_kind(marker: &str) -> FNEmphasisKind {
+    match marker {
+        "***" => FNEmphasisKind::BoldItalic,
+        "**" => FNEmphasisKind::Bold,
+        "_" => FNEmphasisKind::Underline,
+        _ => FNEmphasisKind::Italic,
+    }
+}
+
+// Returns (byte index, marker) for every unescaped run of `*` (up to 3) and every unescaped `_`
+fn get_marker_runs(text: &str) -> Vec<(usize, &str)> {
+    let bytes = text.as_bytes();
+    let mut runs: Vec<(usize, &str)> = Vec::new();
+    let mut idx: usize = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            b'*' => {
+                let mut end = idx;
+                while end < bytes.len() && bytes[end] == b'*' {
+                    end += 1;
+                }
+                // Runs longer than 3 can't be emphasis; treat them as plain text
+                if end - idx <= 3 {
+                    runs.push((idx, &text[idx..end]));
+                }
+                idx = end;
+            }
+            b'_' => {
+                runs.push((idx, &text[idx..idx + 1]));
+                idx += 1;
+            }
+            _ => idx += 1,
+        }
+    }
+
+    runs
+}
+
+fn can_open(text: &str, start: usize, end: usize, options: &FNEmphasisOptions) -> bool {
+    let after = text[end..].chars().next();
+    if after.is_none_or(char::is_whitespace) {
+        return false;
+    }
+    match options.interpretation {
+        FNEmphasisInterpretation::Permissive => true,
+        FNEmphasisInterpretation::Strict => !text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric),
+    }
+}
+
+fn can_close(text: &str, start: usize, end: usize, options: &FNEmphasisOptions) -> bool {
+    let before = text[..start].chars().next_back();
+    if before.is_none_or(char::is_whitespace) {
+        return false;
+    }
+    match options.interpretation {
+        FNEmphasisInterpretation::Permissive => true,
+        FNEmphasisInterpretation::Strict => !text[end..]
+            .chars()
+            .next()
+            .is_some_and(char::is_alphanumeric),
+    }
+}
+
+// Byte ranges of whitespace-separated tokens which look like URLs
+fn get_url_like_byte_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut token_start: Option<usize> = None;
+
+    for (idx, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        if c.is_whitespace() {
+            if let Some(start) = token_start.take() {
+                let token = &text[start..idx];
+                if token.contains("://") || token.to_lowercase().starts_with("www.") {
+                    ranges.push((start, idx));
+                }
+            }
+        } else if token_start.is_none() {
+            token_start = Some(idx);
+        }
+    }
+
+    ranges
+}
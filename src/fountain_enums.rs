@@ -1,9 +1,14 @@
 use core::fmt;
-use std::{default, fmt::Formatter, rc::Rc};
+use std::str::FromStr;
 
 use enum_iterator::{all, Sequence};
 
+/// The type of a parsed line.
+///
+/// The `as u8` value of each variant is stable, so it's safe to store line types as numbers;
+/// use `TryFrom<u8>` to read them back. New variants may be added in minor versions, with new numbers.
 #[derive(Debug, PartialEq, Sequence, Clone, Default)]
+#[non_exhaustive]
 pub enum FNLineType {
     Empty = 0,
     Section = 1,
@@ -33,13 +38,63 @@ pub enum FNLineType {
     TypeCount = 25, // This is the the max number of line types, used in `for` loops and enumerations, can be ignored
     #[default]
     Unparsed = 99,
-    PartialLine,
+    PartialLine = 100,
 }
 
 impl FNLineType {
     pub fn vec_of_line_types() -> Vec<FNLineType> {
         all::<FNLineType>().collect::<Vec<_>>()
     }
+
+    /// Returns `true` for any part of a dialogue block, single or dual, including character cues and parentheticals
+    pub fn is_dialogue_kind(&self) -> bool {
+        matches!(
+            self,
+            FNLineType::Character
+                | FNLineType::Parenthetical
+                | FNLineType::Dialogue
+                | FNLineType::More
+                | FNLineType::DualDialogueCharacter
+                | FNLineType::DualDialogueParenthetical
+                | FNLineType::DualDialogue
+                | FNLineType::DualDialogueMore
+        )
+    }
+
+    /// Returns `true` for character cues, single or dual
+    pub fn is_character_kind(&self) -> bool {
+        matches!(
+            self,
+            FNLineType::Character | FNLineType::DualDialogueCharacter
+        )
+    }
+
+    /// Returns `true` for parentheticals, single or dual
+    pub fn is_parenthetical_kind(&self) -> bool {
+        matches!(
+            self,
+            FNLineType::Parenthetical | FNLineType::DualDialogueParenthetical
+        )
+    }
+
+    /// Returns `true` for every title page type, including `TitlePageUnknown`
+    pub fn is_title_page_kind(&self) -> bool {
+        matches!(
+            self,
+            FNLineType::TitlePageTitle
+                | FNLineType::TitlePageAuthor
+                | FNLineType::TitlePageCredit
+                | FNLineType::TitlePageSource
+                | FNLineType::TitlePageContact
+                | FNLineType::TitlePageDraftDate
+                | FNLineType::TitlePageUnknown
+        )
+    }
+
+    /// Returns `true` for sections and headings
+    pub fn is_outline_kind(&self) -> bool {
+        matches!(self, FNLineType::Section | FNLineType::Heading)
+    }
 }
 
 impl fmt::Display for FNLineType {
@@ -48,6 +103,88 @@ impl fmt::Display for FNLineType {
     }
 }
 
+/// The error for converting an unknown number or name into an `FNLineType`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNUnknownLineTypeError(pub String);
+
+impl fmt::Display for FNUnknownLineTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown line type: {}", self.0)
+    }
+}
+
+impl std::error::Error for FNUnknownLineTypeError {}
+
+impl From<&FNLineType> for u8 {
+    fn from(fn_type: &FNLineType) -> Self {
+        fn_type.clone() as u8
+    }
+}
+
+impl From<FNLineType> for u8 {
+    fn from(fn_type: FNLineType) -> Self {
+        fn_type as u8
+    }
+}
+
+impl TryFrom<u8> for FNLineType {
+    type Error = FNUnknownLineTypeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        all::<FNLineType>()
+            .find(|fn_type| u8::from(fn_type) == value)
+            .ok_or_else(|| FNUnknownLineTypeError(value.to_string()))
+    }
+}
+
+/// Parses the name of a line type as printed by `Display`, such as `DualDialogueCharacter`. Case is ignored.
+impl FromStr for FNLineType {
+    type Err = FNUnknownLineTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        all::<FNLineType>()
+            .find(|fn_type| fn_type.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| FNUnknownLineTypeError(s.to_string()))
+    }
+}
+
+/// The marker which forced a line's type, if any.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum FNForcedBy {
+    #[default]
+    None,
+    /// `!` forces Action, `!!` forces a Shot
+    Bang,
+    /// `.` forces a Heading
+    Dot,
+    /// `>` forces a Transition, or Centered text when closed with `<`
+    GreaterThan,
+    /// `@` forces a Character cue
+    At,
+    /// `~` forces Lyrics
+    Tilde,
+    /// `=` forces a Synopse
+    Equals,
+    /// `#` forces a Section
+    Hash,
+}
+
+impl FNForcedBy {
+    /// Returns the forcing character itself, or `None` for lines which weren't forced.
+    pub fn get_marker(&self) -> Option<char> {
+        match self {
+            FNForcedBy::None => None,
+            FNForcedBy::Bang => Some('!'),
+            FNForcedBy::Dot => Some('.'),
+            FNForcedBy::GreaterThan => Some('>'),
+            FNForcedBy::At => Some('@'),
+            FNForcedBy::Tilde => Some('~'),
+            FNForcedBy::Equals => Some('='),
+            FNForcedBy::Hash => Some('#'),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FNRangedElementType {
     Boneyard { open: String, close: String },
@@ -70,6 +207,24 @@ impl FNRangedElementType {
         }
     }
 
+    /// A note written as an HTML comment, `<!-- -->`, for toolchains which keep private comments that way.
+    /// Only used as one of the `additional_note_types` of a full parse.
+    pub fn html_comment_note() -> Self {
+        Self::Note {
+            open: String::from("<!--"),
+            close: String::from("-->"),
+        }
+    }
+
+    /// A note in single brackets, `[ ]`. A `[[` still opens a regular note.
+    /// Only used as one of the `additional_note_types` of a full parse.
+    pub fn single_bracket_note() -> Self {
+        Self::Note {
+            open: String::from("["),
+            close: String::from("]"),
+        }
+    }
+
     pub fn get_open_and_close_patterns(&self) -> (String, String) {
         match self {
             FNRangedElementType::Boneyard { open, close }
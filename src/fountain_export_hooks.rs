@@ -0,0 +1,225 @@
+//! Per-copy personalization of paginated output, for distributing review copies.
+//!
+//! An `FNExportHook` is asked for a header, footer, and watermark for every page, and for the values of
+//! `{{key}}` placeholders in the text. `FNCopyPersonalization` is a ready-made hook for the usual recipient name,
+//! watermark, and draft id:
+//!
+//! ```text
+//! Prepared for {{recipient}} ({{draft_id}})   ->   Prepared for Jane Doe (D-0042)
+//! ```
+//!
+//! Placeholders are filled in before the lines are paginated, so that a long value wraps and breaks pages like
+//! any other text. Anything that renders the personalized pages (such as a PDF or HTML writer in a host app)
+//! gets the personalized text without any changes of its own.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::fountain_line::FNLine;
+use crate::fountain_pagination::{get_pages, FNPageRow, FNPaginationOptions, FNTextMeasurer};
+use crate::intervals::RangeSet;
+
+pub trait FNExportHook {
+    /// The value of a `{{key}}` placeholder. Placeholders without a value are left as they are.
+    fn get_substitution(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    /// `page_index` starts at `0`
+    fn get_page_header(&self, _page_index: usize, _page_count: usize) -> Option<String> {
+        None
+    }
+
+    fn get_page_footer(&self, _page_index: usize, _page_count: usize) -> Option<String> {
+        None
+    }
+
+    /// Text to be drawn across the page, behind the script
+    fn get_page_watermark(&self, _page_index: usize, _page_count: usize) -> Option<String> {
+        None
+    }
+}
+
+/// A hook for the recipient, watermark, and draft id of a single copy.
+///
+/// Fills in the `{{recipient}}`, `{{watermark}}`, and `{{draft_id}}` placeholders (and any `extra` ones),
+/// puts the watermark on every page, and adds a footer naming the recipient and draft.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNCopyPersonalization {
+    pub recipient: String,
+    pub watermark: String,
+    pub draft_id: String,
+    pub extra: HashMap<String, String>,
+}
+
+impl FNExportHook for FNCopyPersonalization {
+    fn get_substitution(&self, key: &str) -> Option<String> {
+        match key {
+            "recipient" => Some(self.recipient.clone()),
+            "watermark" => Some(self.watermark.clone()),
+            "draft_id" => Some(self.draft_id.clone()),
+            _ => self.extra.get(key).cloned(),
+        }
+    }
+
+    fn get_page_footer(&self, _page_index: usize, _page_count: usize) -> Option<String> {
+        let parts: Vec<&str> = [self.recipient.as_str(), self.draft_id.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(parts.join(" - "))
+    }
+
+    fn get_page_watermark(&self, _page_index: usize, _page_count: usize) -> Option<String> {
+        if self.watermark.is_empty() {
+            return None;
+        }
+        Some(self.watermark.clone())
+    }
+}
+
+/// A page with the header, footer, and watermark of a single copy, and its placeholders filled in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNPersonalizedPage {
+    pub header: Option<String>,
+    pub footer: Option<String>,
+    pub watermark: Option<String>,
+    pub rows: Vec<FNPageRow>,
+}
+
+/// Fills in the placeholders of the lines, paginates them, and applies the hook to every page.
+pub fn get_personalized_pages(
+    lines: &[FNLine],
+    options: &FNPaginationOptions,
+    measurer: &dyn FNTextMeasurer,
+    hook: &dyn FNExportHook,
+) -> Vec<FNPersonalizedPage> {
+    let pages = get_pages(
+        &get_lines_with_substitutions(lines, hook),
+        options,
+        measurer,
+    );
+    let page_count = pages.len();
+    pages
+        .into_iter()
+        .enumerate()
+        .map(|(page_index, page)| FNPersonalizedPage {
+            header: hook
+                .get_page_header(page_index, page_count)
+                .map(|header| get_text_with_substitutions(&header, hook)),
+            footer: hook
+                .get_page_footer(page_index, page_count)
+                .map(|footer| get_text_with_substitutions(&footer, hook)),
+            watermark: hook.get_page_watermark(page_index, page_count),
+            rows: page.rows,
+        })
+        .collect()
+}
+
+/// Returns a copy of the lines with every `{{key}}` placeholder that the hook has a value for filled in.
+/// Placeholders inside notes and boneyards are left as they are, and the ranges of each line (emphasis, notes,
+/// and so on) are moved to match the new text.
+pub fn get_lines_with_substitutions(lines: &[FNLine], hook: &dyn FNExportHook) -> Vec<FNLine> {
+    lines
+        .iter()
+        .map(|ln| get_line_with_substitutions(ln, hook))
+        .collect()
+}
+
+/// Replaces every `{{key}}` placeholder that the hook has a value for.
+pub fn get_text_with_substitutions(text: &str, hook: &dyn FNExportHook) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut remaining = text;
+    while let Some(open) = remaining.find("{{") {
+        let after_open = &remaining[open + 2..];
+        let Some(close) = after_open.find("}}") else {
+            break;
+        };
+        result.push_str(&remaining[..open]);
+        match hook.get_substitution(after_open[..close].trim()) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&remaining[open..open + 2 + close + 2]),
+        }
+        remaining = &after_open[close + 2..];
+    }
+    result.push_str(remaining);
+    result
+}
+
+fn get_line_with_substitutions(line: &FNLine, hook: &dyn FNExportHook) -> FNLine {
+    let raw = line.raw_string.as_str();
+    // Each placeholder, and its value. Found front to back, and replaced back to front so the earlier ones don't move.
+    let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+    let mut search_start: usize = 0;
+    while let Some(open) = raw[search_start..].find("{{").map(|idx| search_start + idx) {
+        let Some(close) = raw[open + 2..].find("}}").map(|idx| open + 2 + idx) else {
+            break;
+        };
+        let placeholder = open..close + 2;
+        search_start = placeholder.end;
+        if line.note_ranges.intersects(placeholder.clone())
+            || line.omitted_ranges.intersects(placeholder.clone())
+        {
+            continue;
+        }
+        if let Some(value) = hook.get_substitution(raw[open + 2..close].trim()) {
+            replacements.push((placeholder, value));
+        }
+    }
+    if replacements.is_empty() {
+        return line.clone();
+    }
+
+    let mut new_line = line.clone();
+    for (placeholder, value) in replacements.into_iter().rev() {
+        new_line
+            .raw_string
+            .replace_range(placeholder.clone(), &value);
+        let new_end = placeholder.start + value.len();
+        for ranges in [
+            &mut new_line.bold_ranges,
+            &mut new_line.italic_ranges,
+            &mut new_line.underlined_ranges,
+            &mut new_line.bold_italic_ranges,
+            &mut new_line.strikeout_ranges,
+            &mut new_line.note_ranges,
+            &mut new_line.omitted_ranges,
+            &mut new_line.additional_note_ranges,
+            &mut new_line.additional_omitted_ranges,
+            &mut new_line.escape_ranges,
+            &mut new_line.removal_suggestion_ranges,
+        ] {
+            *ranges = get_ranges_with_replacement(ranges, &placeholder, new_end);
+        }
+    }
+    new_line.string = get_text_with_substitutions(&line.string, hook);
+    new_line.length = new_line.raw_string.len() as i32;
+    new_line
+}
+
+// Moves the ranges after a replaced placeholder, and stretches the ranges around it over its value
+fn get_ranges_with_replacement(
+    ranges: &RangeSet,
+    placeholder: &Range<usize>,
+    new_end: usize,
+) -> RangeSet {
+    let get_new_index = |idx: usize, is_start: bool| {
+        if idx <= placeholder.start {
+            idx
+        } else if idx >= placeholder.end {
+            idx - placeholder.end + new_end
+        } else if is_start {
+            placeholder.start
+        } else {
+            new_end
+        }
+    };
+    ranges
+        .iter()
+        .map(|range| get_new_index(range.start, true)..get_new_index(range.end, false))
+        .filter(|range| range.start < range.end)
+        .collect()
+}
@@ -0,0 +1,58 @@
+//! Content hashes of scenes, so that sync tools can tell which scenes changed between two versions of a file
+//! without diffing them.
+//!
+//! A fingerprint covers the visible text and the type of every line in the scene. Notes, boneyards, empty lines,
+//! and differences in whitespace don't change it. The hash is FNV-1a, which is stable across platforms
+//! and Rust versions, so fingerprints can be stored and compared later.
+
+use crate::fountain_line::FNLine;
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+use crate::fountain_text_extraction::get_visible_byte_ranges_for_lines;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Returns the fingerprint of each scene, in the same order as `get_scenes_from_parsed_lines`.
+pub fn get_scene_fingerprints(lines: &[FNLine]) -> Vec<u64> {
+    let normalized = get_normalized_visible_strings(lines);
+    get_scenes_from_parsed_lines(lines)
+        .iter()
+        .map(|scene| {
+            let mut hash = FNV_OFFSET_BASIS;
+            for idx in scene.start..scene.end.min(lines.len()) {
+                let text = &normalized[idx];
+                if text.is_empty() {
+                    continue;
+                }
+                hash = get_fnv_hash(hash, &[u8::from(&lines[idx].fn_type)]);
+                hash = get_fnv_hash(hash, text.as_bytes());
+                // Separates lines, so that moving a word to the next line changes the hash
+                hash = get_fnv_hash(hash, b"\n");
+            }
+            hash
+        })
+        .collect()
+}
+
+// The visible text of each line, with runs of whitespace collapsed to a single space
+fn get_normalized_visible_strings(lines: &[FNLine]) -> Vec<String> {
+    get_visible_byte_ranges_for_lines(lines)
+        .iter()
+        .zip(lines)
+        .map(|(ranges, ln)| {
+            let visible: String = ranges
+                .iter()
+                .map(|(start, end)| &ln.raw_string[*start..*end])
+                .collect();
+            visible.split_whitespace().collect::<Vec<&str>>().join(" ")
+        })
+        .collect()
+}
+
+fn get_fnv_hash(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
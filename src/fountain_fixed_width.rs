@@ -0,0 +1,157 @@
+//! Renders a parsed script as monospaced plain text, with each element at its typewriter column.
+//!
+//! This is essentially a print preview without pagination, useful for viewing a script in a terminal,
+//! or for diffing two drafts as they would look on the page. Title page lines, sections, synopses,
+//! notes, and boneyards are not printed.
+
+use crate::fountain_dual_dialogue::{
+    get_dual_dialogue_blocks, get_dual_dialogue_layout, FNDualDialogueLayoutOptions,
+};
+use crate::fountain_enums::FNLineType;
+use crate::fountain_layout::{FNAlignment, FNElementLayout, FNElementLayouts};
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::fountain_text_width::get_display_width;
+use crate::fountain_wrap::{wrap_text, FNElementWidths};
+
+/// Where an element starts, counted in columns from the edge of the page, and how many columns it may use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNFixedWidthElementLayout {
+    pub indent: usize,
+    pub width: usize,
+}
+
+impl FNFixedWidthElementLayout {
+    pub fn new(indent: usize, width: usize) -> Self {
+        FNFixedWidthElementLayout { indent, width }
+    }
+}
+
+/// The columns of each element. Headings, shots, and lyrics use the `action` layout,
+/// and transitions are right-aligned within theirs. To change the alignments too, use `FNElementLayouts`
+/// with `get_fixed_width_rows_for_layouts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNFixedWidthOptions {
+    pub action: FNFixedWidthElementLayout,
+    pub character: FNFixedWidthElementLayout,
+    pub dialogue: FNFixedWidthElementLayout,
+    pub parenthetical: FNFixedWidthElementLayout,
+    pub transition: FNFixedWidthElementLayout,
+}
+
+impl FNFixedWidthOptions {
+    pub fn get_element_widths(&self) -> FNElementWidths {
+        self.get_element_layouts().get_element_widths()
+    }
+
+    /// Returns the same columns, with the standard alignments.
+    pub fn get_element_layouts(&self) -> FNElementLayouts {
+        let layout = |columns: &FNFixedWidthElementLayout, alignment: FNAlignment| {
+            FNElementLayout::new(columns.indent, columns.width, alignment)
+        };
+        FNElementLayouts {
+            action: layout(&self.action, FNAlignment::Left),
+            character: layout(&self.character, FNAlignment::Left),
+            dialogue: layout(&self.dialogue, FNAlignment::Left),
+            parenthetical: layout(&self.parenthetical, FNAlignment::Left),
+            transition: layout(&self.transition, FNAlignment::Right),
+        }
+    }
+}
+
+impl From<&FNElementLayouts> for FNFixedWidthOptions {
+    fn from(layouts: &FNElementLayouts) -> Self {
+        let columns = |layout: &FNElementLayout| {
+            FNFixedWidthElementLayout::new(layout.left_margin, layout.width)
+        };
+        FNFixedWidthOptions {
+            action: columns(&layouts.action),
+            character: columns(&layouts.character),
+            dialogue: columns(&layouts.dialogue),
+            parenthetical: columns(&layouts.parenthetical),
+            transition: columns(&layouts.transition),
+        }
+    }
+}
+
+impl Default for FNFixedWidthOptions {
+    fn default() -> Self {
+        FNFixedWidthOptions::from(&FNElementLayouts::default())
+    }
+}
+
+/// Returns the whole script rendered as fixed-width text, with a newline after every row.
+pub fn get_fixed_width_text(lines: &[FNLine], options: &FNFixedWidthOptions) -> String {
+    get_fixed_width_rows(lines, options)
+        .iter()
+        .map(|row| format!("{}\n", row.trim_end()))
+        .collect()
+}
+
+/// Returns every row of the rendered script.
+pub fn get_fixed_width_rows(lines: &[FNLine], options: &FNFixedWidthOptions) -> Vec<String> {
+    get_fixed_width_rows_for_layouts(lines, &options.get_element_layouts())
+}
+
+/// Returns every row of the rendered script, with each element placed and aligned by its layout.
+pub fn get_fixed_width_rows_for_layouts(
+    lines: &[FNLine],
+    layouts: &FNElementLayouts,
+) -> Vec<String> {
+    let printable = get_printable_strings_for_lines(lines);
+    let dual_blocks = get_dual_dialogue_blocks(lines);
+    let widths = layouts.get_element_widths();
+    let mut rows: Vec<String> = Vec::new();
+    let mut idx: usize = 0;
+
+    while idx < lines.len() {
+        if let Some(block) = dual_blocks.iter().find(|block| block.left_start == idx) {
+            let layout = get_dual_dialogue_layout(
+                lines,
+                block,
+                &FNDualDialogueLayoutOptions {
+                    total_width: layouts.action.width,
+                    ..Default::default()
+                },
+            );
+            let indent = " ".repeat(layouts.action.left_margin);
+            rows.extend(
+                layout
+                    .get_fixed_width_lines()
+                    .into_iter()
+                    .map(|ln| format!("{}{}", indent, ln)),
+            );
+            idx = block.right_end;
+            continue;
+        }
+
+        let ln = &lines[idx];
+        let text = printable[idx].as_str();
+        idx += 1;
+
+        if ln.fn_type == FNLineType::Empty || ln.fn_type == FNLineType::PageBreak {
+            // Never print two empty rows in a row, since skipped lines can leave empty lines next to each other
+            if rows.last().is_some_and(|row| !row.is_empty()) {
+                rows.push(String::new());
+            }
+            continue;
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let Some(layout) = layouts.get_layout_for_line_type(&ln.fn_type) else {
+            continue;
+        };
+
+        for wrapped in wrap_text(text, &ln.fn_type, &widths) {
+            let indent = layout.get_row_start(get_display_width(&wrapped));
+            rows.push(format!("{}{}", " ".repeat(indent), wrapped));
+        }
+    }
+
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    rows
+}
@@ -0,0 +1,446 @@
+//! Runs the whole parsing pipeline in one call.
+//!
+//! Parsing a document fully means splitting it into lines, resolving the partial lines and multiline ranges
+//! of both notes and boneyards, parsing the line types (and re-typing standalone transitions like `THE END`),
+//! and then collecting emphasis, scene colors, the title page, and the outline.
+//! `parse_document_full` does all of this in the right order.
+//!
+//! Input can be corrupted or hostile, so the text is cut to the `FNParseLimits` before any of it is resolved.
+//! Every cut leaves an `FNParseLimitDiagnostic`, and only the text before the cut is parsed:
+//!
+//! ```text
+//! A single 500 MB line            ->  LineTooLong, the line is cut to `max_line_bytes`
+//! [[ [[ [[ [[ ... (millions)      ->  TooManyRangedElementMarkers, cut before the first marker over the limit
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::fountain_colors::get_lines_with_colors;
+use crate::fountain_emphasis::{get_lines_with_emphasis, FNEmphasisOptions};
+use crate::fountain_enums::FNRangedElementType;
+use crate::fountain_line::FNLine;
+use crate::fountain_outline::{get_outline_from_lines, FNOutlineElement};
+use crate::fountain_partial_line_range::{
+    FNMergedPartialLine, FNPartialLineRange, FNPartialMultilineRange, FNRangedElementDiagnostic,
+    FNRangedElementRanges, FNRangedElementResolutionStrategy,
+};
+use crate::fountain_text_extraction::get_invisible_byte_ranges_for_lines;
+use crate::fountain_title_page::{get_title_page_from_lines, FNTitlePageEntry};
+use crate::fountain_transitions::{
+    get_lines_with_standalone_transitions, FNStandaloneTransitionKind,
+    FNStandaloneTransitionOptions,
+};
+use crate::partial_line_resolver;
+use crate::static_fountain_parser::{self, FNParseOptions};
+use crate::static_fountain_preparser::{self, FNPreparseOptions};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNFullParseConfig {
+    /// Options for filling in the emphasis ranges of each line, or `None` to skip emphasis entirely.
+    pub emphasis: Option<FNEmphasisOptions>,
+    /// How orphaned note and boneyard opens are paired with their closes
+    pub ranged_element_strategy: FNRangedElementResolutionStrategy,
+    /// Options for the rules which decide line types
+    pub parse: FNParseOptions,
+    /// Options for recognizing standalone transitions like `FADE OUT.` and `THE END`, or `None` to skip them
+    pub standalone_transitions: Option<FNStandaloneTransitionOptions>,
+    /// Options for splitting the text into lines, such as the `FNNewlinePolicy`
+    pub preparse: FNPreparseOptions,
+    pub limits: FNParseLimits,
+    /// Other delimiters which hide text the way notes do, such as `FNRangedElementType::html_comment_note()`.
+    /// `[[ ]]` is always a note. Notes of these syntaxes are in the `notes` ranges, the diagnostics,
+    /// and the `note_ranges` of each line, so they're stripped from every export.
+    pub additional_note_types: Vec<FNRangedElementType>,
+}
+
+impl Default for FNFullParseConfig {
+    fn default() -> Self {
+        FNFullParseConfig {
+            emphasis: Some(FNEmphasisOptions::default()),
+            ranged_element_strategy: FNRangedElementResolutionStrategy::default(),
+            parse: FNParseOptions::default(),
+            standalone_transitions: Some(FNStandaloneTransitionOptions::default()),
+            preparse: FNPreparseOptions::default(),
+            limits: FNParseLimits::default(),
+            additional_note_types: Vec::new(),
+        }
+    }
+}
+
+/// Bounds on the size of the input, so that a huge or corrupted document can't use unbounded memory.
+/// `None` turns a limit off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNParseLimits {
+    /// Text past this many bytes is dropped before it's split into lines
+    pub max_document_bytes: Option<usize>,
+    /// Each line is cut to this many bytes
+    pub max_line_bytes: Option<usize>,
+    /// Each line is cut before the note or boneyard marker (`[[`, `]]`, `/*`, `*/`, or the markers of the
+    /// `additional_note_types`) which goes over this count
+    pub max_ranged_element_markers_per_line: Option<usize>,
+}
+
+impl FNParseLimits {
+    /// No limits at all, so the whole text is always parsed.
+    pub fn unlimited() -> Self {
+        FNParseLimits {
+            max_document_bytes: None,
+            max_line_bytes: None,
+            max_ranged_element_markers_per_line: None,
+        }
+    }
+}
+
+impl Default for FNParseLimits {
+    fn default() -> Self {
+        FNParseLimits {
+            max_document_bytes: Some(16 * 1024 * 1024),
+            max_line_bytes: Some(256 * 1024),
+            max_ranged_element_markers_per_line: Some(256),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FNParseLimitDiagnosticKind {
+    /// The document was longer than `max_document_bytes`, and was cut on this line
+    DocumentTooLarge,
+    LineTooLong,
+    TooManyRangedElementMarkers,
+}
+
+/// A place where the text was cut to fit the `FNParseLimits`.
+/// Lines keep the `position` they had in the original text, but a cut document doesn't round trip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNParseLimitDiagnostic {
+    pub kind: FNParseLimitDiagnosticKind,
+    pub global_index: usize,
+    /// The length of the line (or, for `DocumentTooLarge`, the document) before the cut, in bytes
+    pub original_bytes: usize,
+    /// The length of the line (or the document) after the cut, in bytes
+    pub kept_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNFullParse {
+    /// The parsed lines, with `note_type` and `boneyard_type` (and their markers) set on partial lines,
+    /// `note_ranges` and `omitted_ranges` filled in from the resolved `notes` and `boneyards` (with what only they hide in
+    /// `additional_note_ranges` and `additional_omitted_ranges`), and the `color` of headings and sections set from their color notes
+    pub lines: Vec<FNLine>,
+    pub notes: FNRangedElementRanges,
+    pub boneyards: FNRangedElementRanges,
+    pub title_page: Vec<FNTitlePageEntry>,
+    pub outline: Vec<FNOutlineElement>,
+    /// Global indices and kinds of every standalone transition, see `fountain_transitions`
+    pub standalone_transitions: Vec<(usize, FNStandaloneTransitionKind)>,
+    /// Diagnostics for both notes and boneyards, notes first
+    pub diagnostics: Vec<FNRangedElementDiagnostic>,
+    /// Global indices of parentheticals which open a `(` and never close it
+    pub unclosed_parentheticals: Vec<usize>,
+    /// Every place the text was cut to fit the `FNParseLimits`, in document order
+    pub limit_diagnostics: Vec<FNParseLimitDiagnostic>,
+}
+
+impl FNFullParse {
+    /// Returns the lines spanned by multiline notes and boneyards, each merged into the single line of visible
+    /// text it would be without them. See `partial_line_resolver::get_merged_partial_lines`.
+    pub fn get_merged_partial_lines(&self) -> Vec<FNMergedPartialLine> {
+        let multiline_ranges: Vec<FNPartialMultilineRange> = self
+            .notes
+            .multiline_ranges
+            .iter()
+            .chain(&self.boneyards.multiline_ranges)
+            .cloned()
+            .collect();
+        partial_line_resolver::get_merged_partial_lines(&self.lines, &multiline_ranges)
+    }
+}
+
+/// Parses a raw text document, and returns the parsed lines along with everything derived from them.
+pub fn parse_document_full(text: &str, config: &FNFullParseConfig) -> FNFullParse {
+    let mut marker_types = get_note_types(config);
+    marker_types.push(FNRangedElementType::boneyard());
+    let (mut unparsed_lines, limit_diagnostics) =
+        get_unparsed_lines_within_limits(text, &config.preparse, &config.limits, &marker_types);
+    let mut diagnostics: Vec<FNRangedElementDiagnostic> = Vec::new();
+
+    let (mut notes, note_map) = get_ranged_element_ranges(
+        &unparsed_lines,
+        &FNRangedElementType::note(),
+        config.ranged_element_strategy,
+        &mut diagnostics,
+    );
+    // The multiline ranges of each type, which the `note_ranges` and `omitted_ranges` of the lines are derived from
+    let mut resolved_ranges: Vec<(FNRangedElementType, Vec<FNPartialMultilineRange>)> =
+        vec![(FNRangedElementType::note(), notes.multiline_ranges.clone())];
+    // The other note syntaxes are resolved with the text of regular notes and boneyards blanked out,
+    // so that the `[` of a `[[` or a `<!--` inside a boneyard doesn't open a note
+    let mut additional_note_maps: Vec<BTreeMap<usize, FNLine>> = Vec::new();
+    if !config.additional_note_types.is_empty() {
+        let blanked_lines = get_lines_with_invisible_text_blanked(&unparsed_lines);
+        for note_type in &config.additional_note_types {
+            let (ranges, map) = get_ranged_element_ranges(
+                &blanked_lines,
+                note_type,
+                config.ranged_element_strategy,
+                &mut diagnostics,
+            );
+            resolved_ranges.push((note_type.clone(), ranges.multiline_ranges.clone()));
+            notes.multiline_ranges.extend(ranges.multiline_ranges);
+            additional_note_maps.push(map);
+        }
+    }
+    let (mut boneyards, boneyard_map) = get_ranged_element_ranges(
+        &unparsed_lines,
+        &FNRangedElementType::boneyard(),
+        config.ranged_element_strategy,
+        &mut diagnostics,
+    );
+    resolved_ranges.push((
+        FNRangedElementType::boneyard(),
+        boneyards.multiline_ranges.clone(),
+    ));
+
+    let note_keys: Vec<usize> = note_map
+        .keys()
+        .chain(additional_note_maps.iter().flat_map(|map| map.keys()))
+        .copied()
+        .collect();
+    let boneyard_keys: Vec<usize> = boneyard_map.keys().copied().collect();
+    for (global_idx, ln) in note_map
+        .into_iter()
+        .chain(additional_note_maps.into_iter().flatten())
+    {
+        // A line keeps the markers of the first note syntax found on it
+        let line = &mut unparsed_lines[global_idx];
+        if line.note_type.is_none() {
+            line.note_type = ln.note_type;
+            line.note_markers = ln.note_markers;
+        }
+    }
+    for (global_idx, ln) in boneyard_map {
+        unparsed_lines[global_idx].boneyard_type = ln.boneyard_type;
+        unparsed_lines[global_idx].boneyard_markers = ln.boneyard_markers;
+    }
+
+    let mut lines = static_fountain_parser::get_parsed_lines_from_line_vec_with_options(
+        unparsed_lines,
+        &config.parse,
+    );
+    let mut standalone_transitions: Vec<(usize, FNStandaloneTransitionKind)> = Vec::new();
+    if let Some(transition_options) = &config.standalone_transitions {
+        (lines, standalone_transitions) =
+            get_lines_with_standalone_transitions(&lines, transition_options);
+    }
+    lines = partial_line_resolver::get_lines_with_invisible_ranges_for_resolved_ranges(
+        &lines,
+        &get_note_types(config),
+        &resolved_ranges,
+    );
+    if !config.additional_note_types.is_empty() {
+        let regular = get_invisible_byte_ranges_for_lines(&lines);
+        for (ln, regular) in lines.iter_mut().zip(regular) {
+            ln.additional_note_ranges = ln
+                .additional_note_ranges
+                .union(&ln.note_ranges.difference(&regular.notes));
+        }
+    }
+    lines = get_lines_with_colors(&lines);
+    if let Some(emphasis_options) = &config.emphasis {
+        lines = get_lines_with_emphasis(&lines, emphasis_options);
+    }
+    if !config.additional_note_types.is_empty() {
+        // Again from the parsed lines, so that the visible text of each partial line is without every note syntax
+        notes.partial_lines = get_single_line_partial_line_ranges(&lines, &note_keys);
+        boneyards.partial_lines = get_single_line_partial_line_ranges(&lines, &boneyard_keys);
+    }
+
+    FNFullParse {
+        title_page: get_title_page_from_lines(&lines),
+        unclosed_parentheticals: static_fountain_parser::get_unclosed_parenthetical_indices(&lines),
+        outline: get_outline_from_lines(&lines),
+        standalone_transitions,
+        lines,
+        notes,
+        boneyards,
+        diagnostics,
+        limit_diagnostics,
+    }
+}
+
+// Regular notes first, so that a `[[` opens a regular note before a `[` opens a single bracket note
+fn get_note_types(config: &FNFullParseConfig) -> Vec<FNRangedElementType> {
+    std::iter::once(FNRangedElementType::note())
+        .chain(config.additional_note_types.iter().cloned())
+        .collect()
+}
+
+// Copies of the lines with the text of every regular note and boneyard replaced by a placeholder byte,
+// so that byte indices stay the same and no line becomes empty
+fn get_lines_with_invisible_text_blanked(lines: &[FNLine]) -> Vec<FNLine> {
+    get_invisible_byte_ranges_for_lines(lines)
+        .iter()
+        .zip(lines)
+        .map(|(invisible, ln)| {
+            let mut blanked = ln.clone();
+            for range in invisible.notes.union(&invisible.boneyards).iter() {
+                blanked
+                    .raw_string
+                    .replace_range(range.clone(), &"\u{1}".repeat(range.len()));
+            }
+            blanked.string.clone_from(&blanked.raw_string);
+            blanked
+        })
+        .collect()
+}
+
+fn get_single_line_partial_line_ranges(
+    lines: &[FNLine],
+    keys: &[usize],
+) -> Vec<FNPartialLineRange> {
+    let partials_map: BTreeMap<usize, FNLine> = keys
+        .iter()
+        .filter_map(|idx| Some((*idx, lines.get(*idx)?.clone())))
+        .collect();
+    partial_line_resolver::create_single_line_partial_line_ranges(&partials_map, lines)
+}
+
+/// Splits the text into Unparsed `FNLine` objects like `static_fountain_preparser::get_unparsed_lines_from_raw_string`,
+/// after cutting it to the `FNParseLimits`. Markers of the `ranged_element_types` count towards
+/// `max_ranged_element_markers_per_line`.
+pub fn get_unparsed_lines_within_limits(
+    text: &str,
+    preparse: &FNPreparseOptions,
+    limits: &FNParseLimits,
+    ranged_element_types: &[FNRangedElementType],
+) -> (Vec<FNLine>, Vec<FNParseLimitDiagnostic>) {
+    let document_bytes = get_bytes_within_limit(text, limits.max_document_bytes);
+    let mut lines = static_fountain_preparser::get_unparsed_lines_from_raw_string(
+        &text[..document_bytes],
+        preparse,
+    );
+    let mut limit_diagnostics: Vec<FNParseLimitDiagnostic> = Vec::new();
+    if document_bytes < text.len() {
+        limit_diagnostics.push(FNParseLimitDiagnostic {
+            kind: FNParseLimitDiagnosticKind::DocumentTooLarge,
+            global_index: lines.len().saturating_sub(1),
+            original_bytes: text.len(),
+            kept_bytes: document_bytes,
+        });
+    }
+    for (global_index, ln) in lines.iter_mut().enumerate() {
+        limit_diagnostics.extend(get_line_cut_to_limits(
+            ln,
+            global_index,
+            limits,
+            ranged_element_types,
+        ));
+    }
+    (lines, limit_diagnostics)
+}
+
+// The largest char boundary within the limit
+fn get_bytes_within_limit(text: &str, max_bytes: Option<usize>) -> usize {
+    let Some(max_bytes) = max_bytes else {
+        return text.len();
+    };
+    if max_bytes >= text.len() {
+        return text.len();
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    end
+}
+
+// Cuts the line at the smaller of its limits, if it goes over either of them
+fn get_line_cut_to_limits(
+    ln: &mut FNLine,
+    global_index: usize,
+    limits: &FNParseLimits,
+    ranged_element_types: &[FNRangedElementType],
+) -> Option<FNParseLimitDiagnostic> {
+    let original_bytes = ln.raw_string.len();
+    let mut kind = FNParseLimitDiagnosticKind::LineTooLong;
+    let mut kept_bytes = get_bytes_within_limit(&ln.raw_string, limits.max_line_bytes);
+
+    if let Some(max_markers) = limits.max_ranged_element_markers_per_line {
+        // The start and length of every marker
+        let mut markers: Vec<(usize, usize)> = Vec::new();
+        for ranged_element_type in ranged_element_types {
+            let (open, close) = ranged_element_type.get_open_and_close_patterns();
+            let line = &ln.raw_string[..kept_bytes];
+            for pattern in [open, close] {
+                markers.extend(
+                    line.match_indices(&pattern)
+                        .map(|(idx, _)| (idx, pattern.len())),
+                );
+            }
+        }
+        // A marker inside a longer one isn't counted, such as each `[` of a `[[` when `[` is a note too
+        markers.sort_unstable_by_key(|(idx, len)| (*idx, std::cmp::Reverse(*len)));
+        let mut marker_indices: Vec<usize> = Vec::new();
+        let mut last_end: usize = 0;
+        for (idx, len) in markers {
+            if idx < last_end {
+                continue;
+            }
+            marker_indices.push(idx);
+            last_end = idx + len;
+        }
+        if marker_indices.len() > max_markers {
+            kind = FNParseLimitDiagnosticKind::TooManyRangedElementMarkers;
+            kept_bytes = marker_indices[max_markers];
+        }
+    }
+
+    if kept_bytes == original_bytes {
+        return None;
+    }
+    ln.raw_string.truncate(kept_bytes);
+    ln.string.clone_from(&ln.raw_string);
+    Some(FNParseLimitDiagnostic {
+        kind,
+        global_index,
+        original_bytes,
+        kept_bytes,
+    })
+}
+
+fn get_ranged_element_ranges(
+    lines: &[FNLine],
+    ranged_element_type: &FNRangedElementType,
+    strategy: FNRangedElementResolutionStrategy,
+    diagnostics: &mut Vec<FNRangedElementDiagnostic>,
+) -> (FNRangedElementRanges, BTreeMap<usize, FNLine>) {
+    let partials_map = partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+        lines,
+        ranged_element_type,
+    )
+    .unwrap_or_default();
+
+    let (multiline_ranges, mut element_diagnostics) =
+        partial_line_resolver::get_partial_multiline_ranges_and_diagnostics_with_strategy(
+            &partials_map,
+            lines,
+            ranged_element_type,
+            strategy,
+        );
+    diagnostics.append(&mut element_diagnostics);
+    let partials_map = partial_line_resolver::get_partial_fnline_map_with_multiline_ranges(
+        &partials_map,
+        lines,
+        &multiline_ranges,
+        ranged_element_type,
+    );
+
+    let ranges = FNRangedElementRanges {
+        partial_lines: partial_line_resolver::create_single_line_partial_line_ranges(
+            &partials_map,
+            lines,
+        ),
+        multiline_ranges,
+    };
+    (ranges, partials_map)
+}
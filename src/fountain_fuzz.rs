@@ -0,0 +1,76 @@
+//! An entry point for fuzzers, enabled with the `fuzz` feature.
+//!
+//! `parse_arbitrary` takes any bytes and runs them through the whole pipeline, so that a fuzz target
+//! (with `cargo fuzz`, or oss-fuzz) only has to call one function:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     fountain_parser_rs::fountain_fuzz::parse_arbitrary(data);
+//! });
+//! ```
+//!
+//! Any panic in here is a bug in the parser.
+
+use crate::fountain_characters::get_character_introductions;
+use crate::fountain_diff::get_semantic_diff;
+use crate::fountain_emphasis::FNEmphasisOptions;
+use crate::fountain_enums::FNRangedElementType;
+use crate::fountain_fingerprint::get_scene_fingerprints;
+use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+use crate::fountain_partial_line_range::FNRangedElementResolutionStrategy;
+use crate::fountain_stripped::parse_stripped;
+use crate::fountain_text_extraction::{
+    get_printable_strings_for_lines, get_prose_chunks_for_spellcheck,
+    get_visible_rich_text_for_line,
+};
+use crate::fountain_wrap::{wrap_line, FNElementWidths};
+use crate::static_fountain_parser;
+
+// The diff takes time in proportion to the number of lines times the number of changed lines,
+// so it only gets the start of the document, to keep every fuzz run fast
+const MAX_DIFF_LINES: usize = 2000;
+
+/// Parses arbitrary bytes (as lossy UTF-8) with every part of the pipeline, and throws the results away.
+pub fn parse_arbitrary(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+
+    let full = parse_document_full(&text, &FNFullParseConfig::default());
+    let _ = parse_document_full(
+        &text,
+        &FNFullParseConfig {
+            emphasis: None,
+            ranged_element_strategy: FNRangedElementResolutionStrategy::LastClose,
+            additional_note_types: vec![
+                FNRangedElementType::html_comment_note(),
+                FNRangedElementType::single_bracket_note(),
+            ],
+            ..Default::default()
+        },
+    );
+    let lines = full.lines;
+
+    let unparsed =
+        static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(text.to_string()));
+    let (stripped, _) = parse_stripped(&unparsed);
+
+    let emphasis_options = FNEmphasisOptions::default();
+    let widths = FNElementWidths::default();
+    for ln in &lines {
+        let _ = get_visible_rich_text_for_line(ln, &emphasis_options);
+        let _ = wrap_line(ln, &widths);
+    }
+    let _ = get_printable_strings_for_lines(&lines);
+    let _ = get_prose_chunks_for_spellcheck(&lines);
+    let _ = get_scene_fingerprints(&lines);
+    let _ = get_character_introductions(&lines);
+    let _ = get_pages(
+        &lines,
+        &FNPaginationOptions::default(),
+        &FNMonospaceMeasurer,
+    );
+    let _ = get_semantic_diff(
+        &lines[..lines.len().min(MAX_DIFF_LINES)],
+        &stripped[..stripped.len().min(MAX_DIFF_LINES)],
+    );
+}
@@ -0,0 +1,183 @@
+//! Splits scene heading text into its parts: the INT./EXT. prefix, the location, and the time of day.
+//!
+//! ```text
+//! INT. JOE'S BAR - NIGHT #12#
+//! ^^^^ ^^^^^^^^^   ^^^^^  ^^
+//! prefix  location  time  scene number
+//! ```
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+
+// Longest prefixes first, so that `INT./EXT.` isn't matched as just `INT.`
+const HEADING_PREFIXES: [&str; 14] = [
+    "INT./EXT.",
+    "EXT./INT.",
+    "INT/EXT.",
+    "EXT/INT.",
+    "INT/EXT",
+    "EXT/INT",
+    "I/E.",
+    "I/E",
+    "INT.",
+    "EXT.",
+    "EST.",
+    "INT",
+    "EXT",
+    "EST",
+];
+
+/// Time of day modifiers which mean a scene directly follows the one before it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNTimeModifier {
+    Continuous,
+    Same,
+    Later,
+    MomentsLater,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNSceneHeading {
+    /// The prefix as written, such as `INT.` or `EXT`. Empty for forced headings without a prefix.
+    pub prefix: String,
+    pub location: String,
+    pub time_of_day: Option<String>,
+    pub scene_number: Option<String>,
+}
+
+impl FNSceneHeading {
+    pub fn is_interior(&self) -> bool {
+        let prefix = self.prefix.to_uppercase();
+        prefix.starts_with("INT")
+            || prefix.starts_with("EXT/INT")
+            || prefix.starts_with("EXT./INT")
+            || prefix.starts_with("I/E")
+    }
+
+    pub fn is_exterior(&self) -> bool {
+        let prefix = self.prefix.to_uppercase();
+        prefix.starts_with("EXT")
+            || prefix.starts_with("EST")
+            || prefix.contains("/EXT")
+            || prefix.starts_with("I/E")
+    }
+
+    /// Returns the `FNTimeModifier` for this heading's time of day, if it is one of:
+    /// `CONTINUOUS`, `CONT'D`, `SAME`, `SAME TIME`, `LATER`, or `MOMENTS LATER`.
+    pub fn get_time_modifier(&self) -> Option<FNTimeModifier> {
+        let time = self.time_of_day.as_ref()?;
+        let normalized: String = time
+            .to_uppercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+            .collect();
+        match normalized
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .as_str()
+        {
+            "CONTINUOUS" | "CONTD" | "CONTINUED" => Some(FNTimeModifier::Continuous),
+            "SAME" | "SAME TIME" => Some(FNTimeModifier::Same),
+            "LATER" | "A LITTLE LATER" => Some(FNTimeModifier::Later),
+            "MOMENTS LATER" | "MOMENT LATER" | "A MOMENT LATER" => {
+                Some(FNTimeModifier::MomentsLater)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns an `FNSceneHeading` for a parsed `Heading` line, or `None` for any other line type.
+pub fn get_scene_heading_from_line(line: &FNLine) -> Option<FNSceneHeading> {
+    if line.fn_type != FNLineType::Heading {
+        return None;
+    }
+    Some(get_scene_heading_from_string(&line.string))
+}
+
+/// Splits the text of a scene heading into an `FNSceneHeading`.
+///
+/// This does not check whether the text is actually a valid heading; that is the job of the `static_fountain_parser`.
+pub fn get_scene_heading_from_string(text: &str) -> FNSceneHeading {
+    let mut remaining: &str = text.trim();
+
+    // Forced headings start with a single `.`
+    if remaining.starts_with('.') && !remaining.starts_with("..") {
+        remaining = remaining[1..].trim_start();
+    }
+
+    let mut scene_number: Option<String> = None;
+    if remaining.ends_with('#') {
+        if let Some(open) = remaining[..remaining.len() - 1].rfind('#') {
+            let number = remaining[open + 1..remaining.len() - 1].trim();
+            if !number.is_empty() {
+                scene_number = Some(number.to_string());
+            }
+            remaining = remaining[..open].trim_end();
+        }
+    }
+
+    let prefix = get_heading_prefix(remaining);
+    remaining = remaining[prefix.len()..].trim_start();
+
+    let (location, time_of_day) = match remaining.rfind(" - ") {
+        Some(idx) => {
+            let time = remaining[idx + 3..].trim();
+            (
+                remaining[..idx].trim(),
+                if time.is_empty() {
+                    None
+                } else {
+                    Some(time.to_string())
+                },
+            )
+        }
+        None => (remaining.trim(), None),
+    };
+
+    FNSceneHeading {
+        prefix,
+        location: location.to_string(),
+        time_of_day,
+        scene_number,
+    }
+}
+
+/// Returns the byte length of the INT./EXT. prefix at the start of `text`, including its separator,
+/// or `None` if `text` doesn't start with a prefix.
+///
+/// Prefixes without a dot must be followed by a separator: a `.`, a space, or a `/` (so `EST` isn't `ESTATE`).
+/// Case is ignored.
+pub fn get_heading_prefix_len(text: &str) -> Option<usize> {
+    let upper = text.to_ascii_uppercase();
+    for prefix in HEADING_PREFIXES.iter() {
+        if !upper.starts_with(prefix) {
+            continue;
+        }
+        if prefix.ends_with('.') {
+            return Some(prefix.len());
+        }
+        return match upper[prefix.len()..].chars().next() {
+            Some('.') | Some(' ') | Some('/') => Some(prefix.len() + 1),
+            _ => None,
+        };
+    }
+    None
+}
+
+fn get_heading_prefix(text: &str) -> String {
+    // Only ASCII is uppercased, so that every index into `upper` is also an index into `text`
+    let upper = text.to_ascii_uppercase();
+    for prefix in HEADING_PREFIXES.iter() {
+        if !upper.starts_with(prefix) {
+            continue;
+        }
+        // Prefixes without a dot have to be followed by whitespace or nothing at all (`INTERNATIONAL` isn't `INT`)
+        let next_char = upper[prefix.len()..].chars().next();
+        if prefix.ends_with('.') || next_char.is_none_or(|c| c.is_whitespace()) {
+            return text[..prefix.len()].to_string();
+        }
+    }
+    String::new()
+}
@@ -0,0 +1,155 @@
+//! The standard layout of each screenplay element: the column where it starts, how many columns it may use,
+//! and how its rows are aligned within those columns.
+//!
+//! Columns are typewriter columns, ten to the inch with 12pt Courier, counted from the left edge of the page.
+//! The defaults are:
+//!
+//! ```text
+//! Element          Left margin   Width   Alignment
+//! Action                    15      60   Left
+//! Character                 43      32   Left
+//! Dialogue                  29      35   Left
+//! Parenthetical             36      22   Left
+//! Transition                15      60   Right
+//! ```
+//!
+//! Every renderer should take its layout from the same `FNElementLayouts`, so that the fixed-width output,
+//! the rows of `get_pages`, and any HTML or PDF renderer in a host app put each element in the same place.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_wrap::FNElementWidths;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FNAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl FNAlignment {
+    /// Returns the value of the CSS `text-align` property.
+    pub fn get_css_text_align(&self) -> &'static str {
+        match self {
+            FNAlignment::Left => "left",
+            FNAlignment::Center => "center",
+            FNAlignment::Right => "right",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNElementLayout {
+    /// The first column the element may use
+    pub left_margin: usize,
+    pub width: usize,
+    pub alignment: FNAlignment,
+}
+
+impl FNElementLayout {
+    pub fn new(left_margin: usize, width: usize, alignment: FNAlignment) -> Self {
+        FNElementLayout {
+            left_margin,
+            width,
+            alignment,
+        }
+    }
+
+    /// Returns the column where a row `text_width` columns wide starts.
+    pub fn get_row_start(&self, text_width: usize) -> usize {
+        let free = self.width.saturating_sub(text_width);
+        match self.alignment {
+            FNAlignment::Left => self.left_margin,
+            FNAlignment::Center => self.left_margin + free / 2,
+            FNAlignment::Right => self.left_margin + free,
+        }
+    }
+}
+
+/// The layout of each element. Headings, shots, and lyrics use the `action` layout, and centered text uses
+/// the `action` columns, centered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNElementLayouts {
+    pub action: FNElementLayout,
+    pub character: FNElementLayout,
+    pub dialogue: FNElementLayout,
+    pub parenthetical: FNElementLayout,
+    pub transition: FNElementLayout,
+}
+
+impl Default for FNElementLayouts {
+    fn default() -> Self {
+        FNElementLayouts {
+            action: FNElementLayout::new(15, 60, FNAlignment::Left),
+            character: FNElementLayout::new(43, 32, FNAlignment::Left),
+            dialogue: FNElementLayout::new(29, 35, FNAlignment::Left),
+            parenthetical: FNElementLayout::new(36, 22, FNAlignment::Left),
+            transition: FNElementLayout::new(15, 60, FNAlignment::Right),
+        }
+    }
+}
+
+impl FNElementLayouts {
+    /// Returns the layout for a line type, or `None` for line types which aren't printed.
+    pub fn get_layout_for_line_type(&self, fn_type: &FNLineType) -> Option<FNElementLayout> {
+        match fn_type {
+            FNLineType::Heading | FNLineType::Action | FNLineType::Shot | FNLineType::Lyrics => {
+                Some(self.action.clone())
+            }
+            FNLineType::Centered => Some(FNElementLayout {
+                alignment: FNAlignment::Center,
+                ..self.action.clone()
+            }),
+            FNLineType::Character | FNLineType::DualDialogueCharacter => {
+                Some(self.character.clone())
+            }
+            FNLineType::Dialogue | FNLineType::DualDialogue => Some(self.dialogue.clone()),
+            FNLineType::Parenthetical | FNLineType::DualDialogueParenthetical => {
+                Some(self.parenthetical.clone())
+            }
+            FNLineType::TransitionLine => Some(self.transition.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the layout for one side of a dual dialogue. Each side gets half of the `action` columns,
+    /// with its elements at half of their usual distance from the left margin of the action.
+    pub fn get_dual_dialogue_layout_for_line_type(
+        &self,
+        fn_type: &FNLineType,
+        is_right_side: bool,
+    ) -> Option<FNElementLayout> {
+        let layout = self.get_layout_for_line_type(fn_type)?;
+        let side_start = match is_right_side {
+            true => self.action.left_margin + self.action.width / 2,
+            false => self.action.left_margin,
+        };
+        Some(FNElementLayout {
+            left_margin: side_start
+                + layout.left_margin.saturating_sub(self.action.left_margin) / 2,
+            width: layout.width / 2,
+            ..layout
+        })
+    }
+
+    /// Returns the layout of every printed line type, in the order of `FNLineType`.
+    pub fn get_layout_table(&self) -> Vec<(FNLineType, FNElementLayout)> {
+        FNLineType::vec_of_line_types()
+            .into_iter()
+            .filter_map(|fn_type| {
+                let layout = self.get_layout_for_line_type(&fn_type)?;
+                Some((fn_type, layout))
+            })
+            .collect()
+    }
+
+    pub fn get_element_widths(&self) -> FNElementWidths {
+        FNElementWidths {
+            action: self.action.width,
+            character: self.character.width,
+            dialogue: self.dialogue.width,
+            parenthetical: self.parenthetical.width,
+            transition: self.transition.width,
+        }
+    }
+}
@@ -6,11 +6,15 @@
 //  Copyright © 2016 Hendrik Noeller. All rights reserved.
 //  (most) parts copyright © 2019-2021 Lauri-Matti Parppei / Lauri-Matti Parppei. All Rights reserved.
 
-use std::collections::HashSet;
+use std::fmt;
 
 use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
 
-use crate::fountain_enums::{FNLineType, FNPartialLineType};
+use crate::fountain_bidi::{get_text_direction, FNTextDirection};
+use crate::fountain_enums::{FNForcedBy, FNLineType, FNPartialLineType};
+use crate::fountain_partial_line_range::FNRangedElementMarkers;
+use crate::intervals::RangeSet;
 use crate::location_and_length::LocationAndLength;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -18,13 +22,15 @@ pub struct FNLine {
     pub fn_type: FNLineType,
     pub string: String,
     pub raw_string: String,
-    pub position: i32,        //  Position (starting index) )in document
+    pub position: i32,        //  Position (starting index) in document, in graphemes; every line break counts as one, see `get_line_positions`
     pub length: i32,          //  Length of string
+    pub terminator_length: i32, //  Byte length of the line break after this line (0 for none); depends on the `FNNewlinePolicy`
     pub section_depth: i32, //  If the line is an outline element (section/heading) this value contains the section depth
     pub scene_number: String, //  If the line is an outline element, this value contains the scene number, but only after the outline structure has been updated
     pub color: String,        //  Color for outline element (`nil` or empty if no color is set)
     pub is_forced: bool,        // Is this line "forced" by using special characters or not
     pub forced_character_cue: bool, //  This line was forced to be a character cue in editor
+    pub forced_by: FNForcedBy,      //  Which marker forced this line's type, if `is_forced`
 
     // @interface Line() // syntax hurty : these 3 properties are private properties I guess
     //oldHash: i32,
@@ -34,20 +40,24 @@ pub struct FNLine {
 
     //formattedAs: any
     //parser: any
-    pub bold_ranges: HashSet<i32>,
-    pub italic_ranges: HashSet<i32>,
-    pub underlined_ranges: HashSet<i32>,
-    pub bold_italic_ranges: HashSet<i32>,
-    pub strikeout_ranges: HashSet<i32>,
-    pub note_ranges: HashSet<i32>,
-    pub omitted_ranges: HashSet<i32>,
-    pub escape_ranges: HashSet<i32>,
-    pub removal_suggestion_ranges: HashSet<i32>,
+    // Emphasis ranges are byte ranges of `string`; note and omitted (boneyard) ranges are byte ranges of `raw_string`
+    pub bold_ranges: RangeSet,
+    pub italic_ranges: RangeSet,
+    pub underlined_ranges: RangeSet,
+    pub bold_italic_ranges: RangeSet,
+    pub strikeout_ranges: RangeSet,
+    pub note_ranges: RangeSet,
+    pub omitted_ranges: RangeSet,
+    pub additional_note_ranges: RangeSet, // Notes which only a full parse finds (written with its `additional_note_types`, or paired by its ranged element strategy); also in `note_ranges`
+    pub additional_omitted_ranges: RangeSet, // Boneyard text which only the ranged element strategy of a full parse hides; also in `omitted_ranges`. In both, a range past the end of `raw_string` goes on to the next line
+    pub escape_ranges: RangeSet,
+    pub removal_suggestion_ranges: RangeSet,
     pub note_type: Option<FNPartialLineType>,
     pub boneyard_type: Option<FNPartialLineType>,
+    pub note_markers: Option<FNRangedElementMarkers>,     // Where the note opens and closes are in `raw_string`; set along with `note_type`
+    pub boneyard_markers: Option<FNRangedElementMarkers>, // Where the boneyard opens and closes are in `raw_string`; set along with `boneyard_type`
 
-    
-    //_uuid: uuid
+    pub uuid: Option<Uuid>, // Stable identity for this line; assigned by the `FNDocument`
 }
 
 impl Default for FNLine {
@@ -58,27 +68,100 @@ impl Default for FNLine {
             raw_string: String::from(""),
             position: 0,
             length: 0,
+            terminator_length: 0,
             section_depth: 0,
             scene_number: String::from(""),
             color: String::from(""),
             forced_character_cue: false,
-            bold_ranges: HashSet::default(),
-            italic_ranges: HashSet::default(),
-            underlined_ranges: HashSet::default(),
-            bold_italic_ranges: HashSet::default(),
-            strikeout_ranges: HashSet::default(),
-            note_ranges: HashSet::default(),
-            omitted_ranges: HashSet::default(),
-            escape_ranges: HashSet::default(),
-            removal_suggestion_ranges: HashSet::default(),
+            forced_by: FNForcedBy::None,
+            bold_ranges: RangeSet::default(),
+            italic_ranges: RangeSet::default(),
+            underlined_ranges: RangeSet::default(),
+            bold_italic_ranges: RangeSet::default(),
+            strikeout_ranges: RangeSet::default(),
+            note_ranges: RangeSet::default(),
+            omitted_ranges: RangeSet::default(),
+            additional_note_ranges: RangeSet::default(),
+            additional_omitted_ranges: RangeSet::default(),
+            escape_ranges: RangeSet::default(),
+            removal_suggestion_ranges: RangeSet::default(),
             note_type: None,
             boneyard_type: None,
+            note_markers: None,
+            boneyard_markers: None,
             is_forced: false,
+            uuid: None,
         }
     }
 }
 
+// Wide enough for the longest type name, `DualDialogueParenthetical`
+pub(crate) const LINE_TYPE_COLUMN_WIDTH: usize = 25;
+
+/// Prints the line type, padded to a column, and the line's `string`.
+impl fmt::Display for FNLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<width$}  {}",
+            self.fn_type.to_string(),
+            self.string,
+            width = LINE_TYPE_COLUMN_WIDTH
+        )
+    }
+}
+
 impl FNLine {
+    //  Returns a new line with the given text and type; `string` and `raw_string` are both set to `raw`
+    pub fn new(raw: &str, fn_type: FNLineType) -> Self{
+        FNLine {
+            fn_type,
+            string: raw.to_string(),
+            raw_string: raw.to_string(),
+            length: raw.graphemes(true).count() as i32,
+            ..Default::default()
+        }
+    }
+
+    //  Returns the writing direction of `string`; notes and emphasis markers are counted as they are
+    //  (use `fountain_bidi::get_text_directions_for_lines` for the printed text)
+    pub fn get_text_direction(&self) -> FNTextDirection {
+        get_text_direction(&self.string)
+    }
+
+    pub fn with_position(mut self, position: i32) -> Self{
+        self.position = position;
+        self
+    }
+
+    pub fn with_scene_number(mut self, scene_number: &str) -> Self{
+        self.scene_number = scene_number.to_string();
+        self
+    }
+
+    pub fn with_section_depth(mut self, section_depth: i32) -> Self{
+        self.section_depth = section_depth;
+        self
+    }
+
+    pub fn with_color(mut self, color: &str) -> Self{
+        self.color = color.to_string();
+        self
+    }
+
+    //  Marks the line as forced; `FNForcedBy::None` marks it as not forced
+    pub fn with_forced_by(mut self, forced_by: FNForcedBy) -> Self{
+        self.is_forced = forced_by != FNForcedBy::None;
+        self.forced_character_cue = forced_by == FNForcedBy::At;
+        self.forced_by = forced_by;
+        self
+    }
+
+    pub fn with_uuid(mut self, uuid: Uuid) -> Self{
+        self.uuid = Some(uuid);
+        self
+    }
+
     pub fn get_loc_len(&self) -> LocationAndLength {
         LocationAndLength {
             location: self.position,
@@ -96,7 +179,7 @@ impl FNLine {
     }
     //  Returns TRUE for scene, section and synopsis elements
     pub fn is_outline_element(&self) -> bool{
-        self.fn_type == FNLineType::Heading || self.fn_type == FNLineType::Section
+        self.fn_type.is_outline_kind()
     }
 
     //  Returns TRUE for any title page element
@@ -115,11 +198,11 @@ impl FNLine {
     }
     
     pub fn is_partial_line(&self) -> bool{
-        if let Some(_) = self.note_type {
+        if self.note_type.is_some() {
             return true;
         }
 
-        if let Some(_)= self.boneyard_type {
+        if self.boneyard_type.is_some() {
             return true;
         }
         false
@@ -145,7 +228,7 @@ impl FNLine {
 
     //  Returns `true` for ANY SORT OF dialogue element, including dual dialogue
     pub fn is_any_sort_of_dialogue(&self) -> bool{
-        self.is_dialogue() || self.is_dual_dialogue()
+        self.fn_type.is_dialogue_kind()
     }
 
     //  Returns `true` for any dialogue element, including character cue
@@ -179,14 +262,12 @@ impl FNLine {
 
     //  Returns `true` for ANY character cue (single || dual)
     pub fn is_any_character(&self) -> bool{
-        self.fn_type == FNLineType::Character 
-        || self.fn_type == FNLineType::DualDialogueCharacter
+        self.fn_type.is_character_kind()
     }
 
     //  Returns `true` for ANY parenthetical line (single || dual)
     pub fn is_any_parenthetical(&self) -> bool{
-        self.fn_type == FNLineType::Parenthetical 
-        || self.fn_type == FNLineType::DualDialogueParenthetical
+        self.fn_type.is_parenthetical_kind()
     }
 
     //  Returns `true` for ANY dialogue line (single || dual)
@@ -196,20 +277,59 @@ impl FNLine {
         ||self.fn_type == FNLineType::DualDialogue
     }
 
+    //  Returns the character name for a character cue, without any `@` or `^` markers or extensions like (V.O.)
+    pub fn get_character_name(&self) -> Option<String>{
+        if !self.is_any_character(){
+            return None;
+        }
+        let mut name: &str = self.string.trim();
+        name = name.strip_prefix('@').unwrap_or(name);
+        name = name.strip_suffix('^').unwrap_or(name);
+        if let Some(i) = name.find('('){
+            name = &name[..i];
+        }
+        let name = name.trim();
+        if name.is_empty(){
+            return None;
+        }
+        Some(name.to_string())
+    }
+
+    //  Returns the number of leading `#` for Section lines, `0` for any other line
+    pub fn get_section_depth(&self) -> i32{
+        if self.fn_type != FNLineType::Section{
+            return 0;
+        }
+        self.string.trim_start().chars().take_while(|c| *c == '#').count() as i32
+    }
+
+    //  Returns the display title of a Section line, without the leading and trailing `#` runs
+    //  `### Sequence 3 ###` -> `Sequence 3`
+    pub fn get_section_title(&self) -> Option<String>{
+        if self.fn_type != FNLineType::Section{
+            return None;
+        }
+        let title = self.string.trim()
+            .trim_start_matches('#')
+            .trim_end_matches('#')
+            .trim();
+        Some(title.to_string())
+    }
+
     // pragma mark - Title Page Stuff
     pub fn get_title_page_key(&self) -> String{ // TODO: Make this return an Optional string instead of using empty strings as special values
-        if self.string.len() == 0{
+        if self.string.is_empty(){
             return String::from("");
         }
         if self.string.contains(":"){
             let i = self.string.find(":").unwrap();
             if i == 0 
-                || self.string.graphemes(true).nth(0) == Some(" ")
+                || self.string.starts_with([' ', '\t']) // Indented lines continue the previous value
                 || self.string[..i].to_lowercase().ends_with(" to") // NOTE: maybe shouldn't be the responsibility of the title page key func to gatekeep transition lines
                 {
                 return String::from("");
             }
-            return String::from(self.string[..i].to_lowercase());
+            return self.string[..i].to_lowercase();
         }
         String::from("")
     }
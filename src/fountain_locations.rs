@@ -0,0 +1,111 @@
+//! Normalizes scene heading locations and clusters them into canonical locations.
+//!
+//! Locations are often typed inconsistently, such as `INT. JOE'S BAR` and `INT. JOES BAR - NIGHT`.
+//! Normalizing folds case, strips punctuation, and ignores the INT./EXT. prefix and time of day.
+//! Optionally, locations within a small edit distance of each other are also merged.
+
+use std::collections::HashMap;
+
+use crate::fountain_heading::get_scene_heading_from_line;
+use crate::fountain_line::FNLine;
+use crate::helper_funcs::{get_edit_distance, get_normalized_text_for_matching};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNLocationNormalizationOptions {
+    /// If `Some(n)`, normalized locations within an edit distance of `n` are merged into the same cluster.
+    pub fuzzy_max_distance: Option<usize>,
+}
+
+/// A single canonical location, and every heading which refers to it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNLocationCluster {
+    /// The most common spelling of this location, as written in the document
+    pub canonical_name: String,
+    pub normalized_name: String,
+    /// Every distinct spelling of this location, in order of first appearance
+    pub variants: Vec<String>,
+    /// Global indices of every heading line in this cluster
+    pub heading_indices: Vec<usize>,
+}
+
+/// Returns a normalized copy of a location string: uppercase, without punctuation, and with single spaces.
+///
+/// Apostrophes are removed entirely, so `JOE'S` and `JOES` normalize to the same value.
+pub fn get_normalized_location(location: &str) -> String {
+    get_normalized_text_for_matching(location)
+}
+
+/// Returns every location in the document, clustered into `FNLocationCluster` objects
+/// in order of first appearance.
+pub fn get_location_clusters(
+    lines: &[FNLine],
+    options: &FNLocationNormalizationOptions,
+) -> Vec<FNLocationCluster> {
+    let mut clusters: Vec<FNLocationCluster> = Vec::new();
+    let mut variant_counts: Vec<HashMap<String, usize>> = Vec::new();
+
+    for (idx, ln) in lines.iter().enumerate() {
+        let Some(heading) = get_scene_heading_from_line(ln) else {
+            continue;
+        };
+        let normalized = get_normalized_location(&heading.location);
+        if normalized.is_empty() {
+            continue;
+        }
+
+        let existing = clusters.iter().position(|cluster| {
+            cluster.normalized_name == normalized
+                || options.fuzzy_max_distance.is_some_and(|max| {
+                    get_edit_distance(&cluster.normalized_name, &normalized) <= max
+                })
+        });
+
+        let cluster_idx = match existing {
+            Some(cluster_idx) => cluster_idx,
+            None => {
+                clusters.push(FNLocationCluster {
+                    normalized_name: normalized,
+                    ..Default::default()
+                });
+                variant_counts.push(HashMap::new());
+                clusters.len() - 1
+            }
+        };
+
+        let cluster = &mut clusters[cluster_idx];
+        cluster.heading_indices.push(idx);
+        if !cluster.variants.contains(&heading.location) {
+            cluster.variants.push(heading.location.clone());
+        }
+        *variant_counts[cluster_idx]
+            .entry(heading.location)
+            .or_insert(0) += 1;
+    }
+
+    for (cluster, counts) in clusters.iter_mut().zip(variant_counts.iter()) {
+        // Ties go to whichever spelling appeared first
+        let mut best: Option<(&String, usize)> = None;
+        for variant in &cluster.variants {
+            let count = counts.get(variant).copied().unwrap_or(0);
+            if best.is_none_or(|(_, best_count)| count > best_count) {
+                best = Some((variant, count));
+            }
+        }
+        if let Some((name, _)) = best {
+            cluster.canonical_name = name.clone();
+        }
+    }
+
+    clusters
+}
+
+/// Returns the canonical location name for a heading line, if the line is a heading with a location.
+pub fn get_canonical_location_for_line(
+    clusters: &[FNLocationCluster],
+    global_index: usize,
+) -> Option<&str> {
+    clusters
+        .iter()
+        .find(|cluster| cluster.heading_indices.contains(&global_index))
+        .map(|cluster| cluster.canonical_name.as_str())
+}
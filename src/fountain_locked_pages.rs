@@ -0,0 +1,194 @@
+//! Page numbers for locked production drafts.
+//!
+//! Once a draft is locked, its page numbers never change. When material is added later, the new pages
+//! get the number of the locked page before them with a letter suffix (`32A`, `32B`, and `32C` after a locked `32B`),
+//! and the locked pages after them keep their numbers. Pages between two locked lettered pages get another letter
+//! (`32AA` between `32A` and `32B`). Pages inserted before the first locked page get a letter prefix (`A1`).
+//!
+//! Locked pages are recognized by the `uuid` of their first line, so the lines should come from an `FNDocument`,
+//! which keeps line identities across edits. A locked page whose first line was deleted, or moved to the middle
+//! of another page, has its number skipped.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::fountain_line::FNLine;
+use crate::fountain_pagination::FNPage;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FNPageNumber {
+    pub prefix: String,
+    pub number: usize,
+    pub suffix: String,
+}
+
+impl FNPageNumber {
+    pub fn new(number: usize) -> Self {
+        FNPageNumber {
+            number,
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for FNPageNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.prefix, self.number, self.suffix)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNLockedPage {
+    pub number: FNPageNumber,
+    pub first_line_uuid: Uuid,
+}
+
+/// The page numbers of a locked draft, in page order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNLockedPagination {
+    pub pages: Vec<FNLockedPage>,
+}
+
+/// Returns `1`, `2`, `3`... for each page.
+pub fn get_page_numbers(pages: &[FNPage]) -> Vec<FNPageNumber> {
+    (1..=pages.len()).map(FNPageNumber::new).collect()
+}
+
+/// Locks the current page numbers. Pages whose first line has no `uuid` can't be recognized later, and are left out.
+pub fn get_locked_pagination(
+    lines: &[FNLine],
+    pages: &[FNPage],
+    numbers: &[FNPageNumber],
+) -> FNLockedPagination {
+    let pages = pages
+        .iter()
+        .zip(numbers)
+        .filter_map(|(page, number)| {
+            let first_line_uuid = lines[page.get_first_line_index()?].uuid?;
+            Some(FNLockedPage {
+                number: number.clone(),
+                first_line_uuid,
+            })
+        })
+        .collect();
+    FNLockedPagination { pages }
+}
+
+/// Returns the number of each page, keeping the numbers of the locked pages and lettering the new ones.
+///
+/// Locked pages have to stay in order: a locked page which now comes before an earlier locked page
+/// is numbered as a new page.
+pub fn get_page_numbers_with_locked_pagination(
+    lines: &[FNLine],
+    pages: &[FNPage],
+    locked: &FNLockedPagination,
+) -> Vec<FNPageNumber> {
+    let locked_indices: HashMap<Uuid, usize> = locked
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(idx, page)| (page.first_line_uuid, idx))
+        .collect();
+
+    // The locked page each page keeps, if any
+    let mut matches: Vec<Option<usize>> = Vec::with_capacity(pages.len());
+    let mut last_locked: Option<usize> = None;
+    for page in pages {
+        let locked_idx = page
+            .get_first_line_index()
+            .and_then(|idx| lines[idx].uuid)
+            .and_then(|uuid| locked_indices.get(&uuid).copied())
+            .filter(|idx| last_locked.is_none_or(|last| *idx > last));
+        if locked_idx.is_some() {
+            last_locked = locked_idx;
+        }
+        matches.push(locked_idx);
+    }
+
+    let mut numbers: Vec<FNPageNumber> = Vec::with_capacity(pages.len());
+    let mut previous_locked: Option<&FNPageNumber> = None;
+    let mut inserted: usize = 0;
+    for (idx, locked_idx) in matches.iter().enumerate() {
+        if let Some(locked_idx) = locked_idx {
+            let number = &locked.pages[*locked_idx].number;
+            numbers.push(number.clone());
+            previous_locked = Some(number);
+            inserted = 0;
+            continue;
+        }
+
+        let number = if let Some(previous) = previous_locked {
+            let next = matches[idx..]
+                .iter()
+                .flatten()
+                .next()
+                .map(|next| &locked.pages[*next].number);
+            get_number_after(previous, inserted, next)
+        } else if let Some(next) = matches[idx..].iter().flatten().next() {
+            // Before the first locked page
+            FNPageNumber {
+                prefix: get_letters(inserted),
+                ..locked.pages[*next].number.clone()
+            }
+        } else {
+            // Nothing is locked
+            FNPageNumber::new(idx + 1)
+        };
+        inserted += 1;
+        numbers.push(number);
+    }
+    numbers
+}
+
+// The number of the page `inserted` pages after the locked page `previous`: its suffix letters are counted on
+// (`32A` is followed by `32B`), unless that would reach the `next` locked page, and then letters are added
+// (`32AA`, between `32A` and a locked `32B`)
+fn get_number_after(
+    previous: &FNPageNumber,
+    inserted: usize,
+    next: Option<&FNPageNumber>,
+) -> FNPageNumber {
+    let counted = get_letters_count(&previous.suffix).map(|count| FNPageNumber {
+        suffix: get_letters(count + inserted),
+        ..previous.clone()
+    });
+    let reaches_next = |counted: &FNPageNumber| {
+        next.is_some_and(|next| {
+            next.prefix == counted.prefix
+                && next.number == counted.number
+                && get_letters_count(&next.suffix).is_some_and(|next_count| {
+                    Some(next_count) <= get_letters_count(&counted.suffix)
+                })
+        })
+    };
+    match counted {
+        Some(counted) if !reaches_next(&counted) => counted,
+        _ => FNPageNumber {
+            suffix: format!("{}{}", previous.suffix, get_letters(inserted)),
+            ..previous.clone()
+        },
+    }
+}
+
+// The position of `letters` in the order of `get_letters`, counting from 1 (`A` is 1, `AA` is 27), and 0 without
+// letters. `None` for anything other than uppercase letters.
+fn get_letters_count(letters: &str) -> Option<usize> {
+    letters.chars().try_fold(0, |count: usize, c| {
+        c.is_ascii_uppercase()
+            .then(|| count * 26 + (c as u8 - b'A') as usize + 1)
+    })
+}
+
+// A, B, ... Z, AA, AB, ...
+fn get_letters(index: usize) -> String {
+    let mut letters = String::new();
+    let mut remaining = index + 1;
+    while remaining > 0 {
+        remaining -= 1;
+        letters.insert(0, (b'A' + (remaining % 26) as u8) as char);
+        remaining /= 26;
+    }
+    letters
+}
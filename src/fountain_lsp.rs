@@ -0,0 +1,417 @@
+//! Language server features for Fountain, enabled with the `lsp` feature.
+//!
+//! These functions turn the results of `parse_document_full` into `lsp_types` values: diagnostics,
+//! document symbols, folding ranges, semantic tokens, and completions. The `fountain-lsp` binary
+//! serves them over stdio; editors which embed the crate can call them directly.
+//!
+//! Line numbers are the global line indices, and columns are counted in UTF-16 code units, as LSP requires.
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic, DiagnosticSeverity,
+    DocumentSymbol, FoldingRange, FoldingRangeKind, Position, Range, SemanticToken,
+    SemanticTokenType, SemanticTokensLegend, SymbolKind, TextEdit,
+};
+
+use crate::fountain_characters::{get_cue_counts_for_characters, FNCharacterAliasMap};
+use crate::fountain_completion::{
+    get_heading_completions, FNHeadingCompletionKind, FNHeadingCompletionOptions,
+};
+use crate::fountain_enums::{FNLineType, FNRangedElementType};
+use crate::fountain_full_parse::{FNFullParse, FNParseLimitDiagnosticKind};
+use crate::fountain_heading::get_scene_heading_from_string;
+use crate::fountain_line::FNLine;
+use crate::fountain_outline::{get_outline_from_lines, FNOutlineElementKind};
+use crate::fountain_partial_line_range::FNRangedElementDiagnosticKind;
+use crate::fountain_scene::{get_scenes_from_parsed_lines, FNScene};
+use crate::fountain_symbols::{get_document_symbols, FNDocumentSymbols};
+
+// The order of `get_semantic_tokens_legend`
+const TOKEN_HEADING: u32 = 0;
+const TOKEN_CHARACTER: u32 = 1;
+const TOKEN_DIALOGUE: u32 = 2;
+const TOKEN_PARENTHETICAL: u32 = 3;
+const TOKEN_TRANSITION: u32 = 4;
+const TOKEN_SECTION: u32 = 5;
+const TOKEN_COMMENT: u32 = 6;
+const TOKEN_TITLE_PAGE: u32 = 7;
+
+/// The semantic token types, using standard LSP types so that every theme colors them:
+/// headings are `namespace`, cues are `class`, dialogue and lyrics are `string`, parentheticals are `parameter`,
+/// transitions and page breaks are `keyword`, sections are `type`, synopses, notes, and boneyards are `comment`,
+/// and title page lines are `property`. Action has no token.
+pub fn get_semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::NAMESPACE,
+            SemanticTokenType::CLASS,
+            SemanticTokenType::STRING,
+            SemanticTokenType::PARAMETER,
+            SemanticTokenType::KEYWORD,
+            SemanticTokenType::TYPE,
+            SemanticTokenType::COMMENT,
+            SemanticTokenType::PROPERTY,
+        ],
+        token_modifiers: Vec::new(),
+    }
+}
+
+pub fn get_lsp_diagnostics(full: &FNFullParse) -> Vec<Diagnostic> {
+    let parenthetical_diagnostics = full.unclosed_parentheticals.iter().filter_map(|idx| {
+        let ln = full.lines.get(*idx)?;
+        let start = ln.raw_string.find('(')?;
+        Some(Diagnostic {
+            range: get_range_for_bytes(ln, *idx, start, start + 1),
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some(String::from("fountain")),
+            message: String::from("Unclosed parenthetical"),
+            ..Default::default()
+        })
+    });
+    let limit_diagnostics = full.limit_diagnostics.iter().filter_map(|diagnostic| {
+        let ln = full.lines.get(diagnostic.global_index)?;
+        let message = match diagnostic.kind {
+            FNParseLimitDiagnosticKind::DocumentTooLarge => format!(
+                "Document is too large: only the first {} of {} bytes were parsed",
+                diagnostic.kept_bytes, diagnostic.original_bytes
+            ),
+            FNParseLimitDiagnosticKind::LineTooLong => format!(
+                "Line is too long: only the first {} of {} bytes were parsed",
+                diagnostic.kept_bytes, diagnostic.original_bytes
+            ),
+            FNParseLimitDiagnosticKind::TooManyRangedElementMarkers => String::from(
+                "Too many note or boneyard markers: the rest of the line was not parsed",
+            ),
+        };
+        let end = ln.raw_string.len();
+        Some(Diagnostic {
+            range: get_range_for_bytes(ln, diagnostic.global_index, end, end),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some(String::from("fountain")),
+            message,
+            ..Default::default()
+        })
+    });
+    full.diagnostics
+        .iter()
+        .filter_map(|diagnostic| {
+            let ln = full.lines.get(diagnostic.global_index)?;
+            let (name, open) = match &diagnostic.ranged_element_type {
+                FNRangedElementType::Note { open, .. } => ("note", open.as_str()),
+                FNRangedElementType::Boneyard { open, .. } => ("boneyard", open.as_str()),
+                FNRangedElementType::Other { open, .. } => ("element", open.as_str()),
+            };
+            let message = match diagnostic.kind {
+                FNRangedElementDiagnosticKind::UnclosedOpen => format!("Unclosed {}", name),
+            };
+            let start = diagnostic.local_index.min(ln.raw_string.len());
+            let end = (start + open.len()).min(ln.raw_string.len());
+            Some(Diagnostic {
+                range: get_range_for_bytes(ln, diagnostic.global_index, start, end),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some(String::from("fountain")),
+                message,
+                ..Default::default()
+            })
+        })
+        .chain(parenthetical_diagnostics)
+        .chain(limit_diagnostics)
+        .collect()
+}
+
+/// Returns the sections and headings as a tree: headings are children of the section they're in,
+/// and sections are children of the section with fewer `#` before them. The characters who speak in a scene
+/// are children of its heading, at their first cue.
+pub fn get_lsp_document_symbols(lines: &[FNLine]) -> Vec<DocumentSymbol> {
+    let outline = get_outline_from_lines(lines);
+    let scenes = get_scenes_from_parsed_lines(lines);
+    let document_symbols = get_document_symbols(lines, &FNCharacterAliasMap::default());
+    let last_line = lines.len().saturating_sub(1);
+
+    // (section depth, symbol) for every open section
+    let mut stack: Vec<(usize, DocumentSymbol)> = Vec::new();
+    let mut roots: Vec<DocumentSymbol> = Vec::new();
+    for (idx, element) in outline.iter().enumerate() {
+        let scene = match element.kind {
+            FNOutlineElementKind::Heading => scenes
+                .iter()
+                .find(|scene| scene.heading_index == Some(element.line_index)),
+            FNOutlineElementKind::Section => None,
+        };
+        let end_line = match element.kind {
+            FNOutlineElementKind::Heading => {
+                scene.map_or(element.line_index, |scene| scene.end.saturating_sub(1))
+            }
+            FNOutlineElementKind::Section => outline[idx + 1..]
+                .iter()
+                .find(|later| {
+                    later.kind == FNOutlineElementKind::Section && later.depth <= element.depth
+                })
+                .map_or(last_line, |later| later.line_index.saturating_sub(1)),
+        };
+        let ln = &lines[element.line_index];
+        #[allow(deprecated)]
+        let symbol = DocumentSymbol {
+            name: element.title.clone(),
+            detail: element.scene_number.clone(),
+            kind: match element.kind {
+                FNOutlineElementKind::Section => SymbolKind::NAMESPACE,
+                FNOutlineElementKind::Heading => SymbolKind::CLASS,
+            },
+            tags: None,
+            deprecated: None,
+            range: Range::new(
+                Position::new(element.line_index as u32, 0),
+                Position::new(
+                    end_line as u32,
+                    get_utf16_len(lines.get(end_line).map_or("", |ln| &ln.raw_string)),
+                ),
+            ),
+            selection_range: get_range_for_bytes(ln, element.line_index, 0, ln.raw_string.len()),
+            children: scene
+                .map(|scene| get_character_document_symbols(lines, &document_symbols, scene))
+                .filter(|characters| !characters.is_empty()),
+        };
+
+        let depth = match element.kind {
+            FNOutlineElementKind::Section => element.depth,
+            // Deeper than any section, so that nothing nests inside a heading
+            FNOutlineElementKind::Heading => usize::MAX,
+        };
+        while stack
+            .last()
+            .is_some_and(|(open_depth, _)| *open_depth >= depth)
+        {
+            close_document_symbol(&mut stack, &mut roots);
+        }
+        stack.push((depth, symbol));
+    }
+    while !stack.is_empty() {
+        close_document_symbol(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Returns a folding range for every scene and section.
+pub fn get_lsp_folding_ranges(lines: &[FNLine]) -> Vec<FoldingRange> {
+    let last_line = lines.len().saturating_sub(1);
+    let mut ranges: Vec<FoldingRange> = get_scenes_from_parsed_lines(lines)
+        .iter()
+        .filter_map(|scene| Some((scene.heading_index?, scene.end.saturating_sub(1))))
+        .chain(get_section_line_ranges(lines, last_line))
+        .filter(|(start, end)| end > start)
+        .map(|(start, end)| FoldingRange {
+            start_line: start as u32,
+            end_line: end as u32,
+            kind: Some(FoldingRangeKind::Region),
+            ..Default::default()
+        })
+        .collect();
+    ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+    ranges
+}
+
+/// Returns the semantic tokens of the lines, with the token types of `get_semantic_tokens_legend`.
+///
+/// Notes and boneyards get `comment` tokens, and the rest of each line gets the token of its line type.
+pub fn get_lsp_semantic_tokens(lines: &[FNLine]) -> Vec<SemanticToken> {
+    let mut tokens: Vec<SemanticToken> = Vec::new();
+    let mut previous_line: u32 = 0;
+    let mut previous_start: u32 = 0;
+
+    for (line_index, ln) in lines.iter().enumerate() {
+        let hidden = ln.note_ranges.union(&ln.omitted_ranges);
+        let mut segments: Vec<(std::ops::Range<usize>, u32)> = hidden
+            .iter()
+            .map(|range| (range.clone(), TOKEN_COMMENT))
+            .collect();
+        if let Some(token_type) = get_token_type_for_line_type(&ln.fn_type) {
+            segments.extend(
+                hidden
+                    .get_complement(0..ln.raw_string.len())
+                    .iter()
+                    .filter(|range| !ln.raw_string[(*range).clone()].trim().is_empty())
+                    .map(|range| (range.clone(), token_type)),
+            );
+        }
+        segments.sort_by_key(|(range, _)| range.start);
+
+        for (range, token_type) in segments {
+            let line = line_index as u32;
+            let start = get_utf16_len(&ln.raw_string[..range.start]);
+            let length = get_utf16_len(&ln.raw_string[range]);
+            let delta_start = if line == previous_line {
+                start - previous_start
+            } else {
+                start
+            };
+            tokens.push(SemanticToken {
+                delta_line: line - previous_line,
+                delta_start,
+                length,
+                token_type,
+                token_modifiers_bitset: 0,
+            });
+            previous_line = line;
+            previous_start = start;
+        }
+    }
+    tokens
+}
+
+/// Returns completions for the text before `position`: locations after a scene heading prefix like `INT.`,
+/// times of day after the ` - ` of a heading, or character names on a line which could be a character cue.
+pub fn get_lsp_completions(lines: &[FNLine], position: Position) -> Vec<CompletionItem> {
+    let line_index = position.line as usize;
+    let Some(ln) = lines.get(line_index) else {
+        return Vec::new();
+    };
+    let typed = &ln.raw_string[..get_byte_index_for_utf16(&ln.raw_string, position.character)];
+    let replace_range = Range::new(Position::new(position.line, 0), position);
+    let get_item = |new_text: String, kind: CompletionItemKind, label: &str| CompletionItem {
+        label: label.to_string(),
+        kind: Some(kind),
+        filter_text: Some(new_text.clone()),
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+            replace_range,
+            new_text,
+        ))),
+        ..Default::default()
+    };
+
+    let heading = get_scene_heading_from_string(typed);
+    if !heading.prefix.is_empty() && !typed.trim_start().starts_with('.') {
+        // Ranked, so the client's own sorting is overridden with the rank
+        return get_heading_completions(lines, typed, &FNHeadingCompletionOptions::default())
+            .into_iter()
+            .enumerate()
+            .map(|(rank, completion)| {
+                let kind = match completion.kind {
+                    FNHeadingCompletionKind::Location => CompletionItemKind::MODULE,
+                    FNHeadingCompletionKind::TimeOfDay => CompletionItemKind::VALUE,
+                };
+                CompletionItem {
+                    sort_text: Some(format!("{:04}", rank)),
+                    ..get_item(completion.text, kind, &completion.label)
+                }
+            })
+            .collect();
+    }
+
+    let follows_empty_line = line_index == 0
+        || lines[line_index - 1].fn_type == FNLineType::Empty
+        || lines[line_index - 1].raw_string.trim().is_empty();
+    if !follows_empty_line || typed.to_uppercase() != typed {
+        return Vec::new();
+    }
+    // The line being typed may already be parsed as a cue itself
+    let typed_name = typed.trim();
+    get_cue_counts_for_characters(lines, &FNCharacterAliasMap::default())
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(typed_name) && name != typed_name)
+        .map(|(name, _)| get_item(name.clone(), CompletionItemKind::CLASS, &name))
+        .collect()
+}
+
+// A symbol for every character who speaks in the scene, placed at their first cue in it
+fn get_character_document_symbols(
+    lines: &[FNLine],
+    document_symbols: &FNDocumentSymbols,
+    scene: &FNScene,
+) -> Vec<DocumentSymbol> {
+    document_symbols
+        .get_character_symbols_for_scene(scene)
+        .into_iter()
+        .filter_map(|symbol| {
+            let name = document_symbols.table.get_name(symbol)?;
+            let line_index = (scene.start..scene.end)
+                .find(|idx| document_symbols.get_character_symbol_for_line(*idx) == Some(symbol))?;
+            let ln = &lines[line_index];
+            let range = get_range_for_bytes(ln, line_index, 0, ln.raw_string.len());
+            #[allow(deprecated)]
+            Some(DocumentSymbol {
+                name: name.to_string(),
+                detail: None,
+                kind: SymbolKind::VARIABLE,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: None,
+            })
+        })
+        .collect()
+}
+
+fn close_document_symbol(
+    stack: &mut Vec<(usize, DocumentSymbol)>,
+    roots: &mut Vec<DocumentSymbol>,
+) {
+    let Some((_, symbol)) = stack.pop() else {
+        return;
+    };
+    match stack.last_mut() {
+        Some((_, parent)) => parent.children.get_or_insert_with(Vec::new).push(symbol),
+        None => roots.push(symbol),
+    }
+}
+
+// The first and last line of every section
+fn get_section_line_ranges(lines: &[FNLine], last_line: usize) -> Vec<(usize, usize)> {
+    let sections: Vec<(usize, usize)> = get_outline_from_lines(lines)
+        .into_iter()
+        .filter(|element| element.kind == FNOutlineElementKind::Section)
+        .map(|element| (element.line_index, element.depth))
+        .collect();
+    sections
+        .iter()
+        .enumerate()
+        .map(|(idx, (start, depth))| {
+            let end = sections[idx + 1..]
+                .iter()
+                .find(|(_, later_depth)| later_depth <= depth)
+                .map_or(last_line, |(later_start, _)| later_start.saturating_sub(1));
+            (*start, end)
+        })
+        .collect()
+}
+
+fn get_token_type_for_line_type(fn_type: &FNLineType) -> Option<u32> {
+    match fn_type {
+        FNLineType::Heading => Some(TOKEN_HEADING),
+        FNLineType::Character | FNLineType::DualDialogueCharacter => Some(TOKEN_CHARACTER),
+        FNLineType::Dialogue | FNLineType::DualDialogue | FNLineType::Lyrics => {
+            Some(TOKEN_DIALOGUE)
+        }
+        FNLineType::Parenthetical | FNLineType::DualDialogueParenthetical => {
+            Some(TOKEN_PARENTHETICAL)
+        }
+        FNLineType::TransitionLine | FNLineType::PageBreak => Some(TOKEN_TRANSITION),
+        FNLineType::Section => Some(TOKEN_SECTION),
+        FNLineType::Synopse => Some(TOKEN_COMMENT),
+        fn_type if fn_type.is_title_page_kind() => Some(TOKEN_TITLE_PAGE),
+        _ => None,
+    }
+}
+
+fn get_range_for_bytes(ln: &FNLine, line_index: usize, start: usize, end: usize) -> Range {
+    Range::new(
+        Position::new(line_index as u32, get_utf16_len(&ln.raw_string[..start])),
+        Position::new(line_index as u32, get_utf16_len(&ln.raw_string[..end])),
+    )
+}
+
+fn get_utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+// Columns past the end of the line, or in the middle of a surrogate pair, are clamped
+fn get_byte_index_for_utf16(text: &str, column: u32) -> usize {
+    let mut utf16_count: u32 = 0;
+    for (byte_idx, c) in text.char_indices() {
+        if utf16_count >= column {
+            return byte_idx;
+        }
+        utf16_count += c.len_utf16() as u32;
+    }
+    text.len()
+}
@@ -0,0 +1,275 @@
+//! Metrics of a whole document, for scripts kept in version control: stats, lint findings, and page estimates
+//! in a single JSON object, so a pipeline can fail when a draft is too long or has unclosed notes.
+//!
+//! ```text
+//! {"schema_version":1,
+//!  "stats":{"scene_count":42,"character_count":9,"word_count":21050,...},
+//!  "pages":{"page_count":104,"page_length":103.625,"estimated_runtime_minutes":104},
+//!  "lint":{"error_count":0,"warning_count":2,"findings":[{"rule":"unclosed-note","severity":"warning",...}]}}
+//! ```
+//!
+//! The field names and the `rule` of each finding are a stable contract: fields may be added, but existing ones
+//! are only renamed or removed along with a new `schema_version`.
+
+use crate::fountain_characters::{
+    get_character_introductions_with_aliases, get_cue_counts_for_characters, FNCharacterAliasMap,
+};
+use crate::fountain_duplicate_dialogue::{get_duplicate_dialogue, FNDuplicateDialogueOptions};
+use crate::fountain_enums::{FNLineType, FNRangedElementType};
+use crate::fountain_full_parse::FNParseLimitDiagnosticKind;
+use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+use crate::fountain_parsed_document::FNParsedDocument;
+use crate::fountain_partial_line_range::FNRangedElementDiagnosticKind;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::helper_funcs::get_json_string;
+
+/// The version of the JSON of `FNMetrics::to_json`.
+pub const METRICS_SCHEMA_VERSION: usize = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNMetricsOptions {
+    /// Used to count the pages
+    pub pagination: FNPaginationOptions,
+    /// Used to find repeated dialogue. `None` skips the `duplicate-dialogue` rule.
+    pub duplicate_dialogue: Option<FNDuplicateDialogueOptions>,
+    pub aliases: FNCharacterAliasMap,
+}
+
+impl Default for FNMetricsOptions {
+    fn default() -> Self {
+        FNMetricsOptions {
+            pagination: FNPaginationOptions::default(),
+            duplicate_dialogue: Some(FNDuplicateDialogueOptions::default()),
+            aliases: FNCharacterAliasMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FNLintSeverity {
+    Warning,
+    Error,
+}
+
+impl FNLintSeverity {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            FNLintSeverity::Warning => "warning",
+            FNLintSeverity::Error => "error",
+        }
+    }
+}
+
+/// A single problem found in the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNLintFinding {
+    /// One of `unclosed-note`, `unclosed-boneyard`, `unclosed-parenthetical`, `parse-limit`,
+    /// `speaks-before-introduction`, or `duplicate-dialogue`
+    pub rule: &'static str,
+    pub severity: FNLintSeverity,
+    /// Global index of the line the finding is on
+    pub line_index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNMetrics {
+    pub scene_count: usize,
+    /// The number of cues of each character, from most to fewest cues
+    pub character_cues: Vec<(String, usize)>,
+    /// Words as they would be printed, without the title page, sections, and synopses
+    pub word_count: usize,
+    pub dialogue_word_count: usize,
+    pub action_word_count: usize,
+    pub page_count: usize,
+    /// The length of the script in pages, counting the last page by how full it is, rounded to eighths
+    pub page_length: f64,
+    /// A minute per page
+    pub estimated_runtime_minutes: usize,
+    /// Ordered by line, then by rule
+    pub findings: Vec<FNLintFinding>,
+}
+
+impl FNMetrics {
+    pub fn get_error_count(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == FNLintSeverity::Error)
+            .count()
+    }
+
+    pub fn get_warning_count(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == FNLintSeverity::Warning)
+            .count()
+    }
+
+    pub fn to_json(&self) -> String {
+        let characters: Vec<String> = self
+            .character_cues
+            .iter()
+            .map(|(name, cues)| format!("{{\"name\":{},\"cues\":{}}}", get_json_string(name), cues))
+            .collect();
+        let findings: Vec<String> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                format!(
+                    "{{\"rule\":{},\"severity\":{},\"line_index\":{},\"message\":{}}}",
+                    get_json_string(finding.rule),
+                    get_json_string(finding.severity.get_name()),
+                    finding.line_index,
+                    get_json_string(&finding.message)
+                )
+            })
+            .collect();
+        let stats = format!(
+            "{{\"scene_count\":{},\"character_count\":{},\"word_count\":{},\"dialogue_word_count\":{},\"action_word_count\":{},\"characters\":[{}]}}",
+            self.scene_count,
+            self.character_cues.len(),
+            self.word_count,
+            self.dialogue_word_count,
+            self.action_word_count,
+            characters.join(",")
+        );
+        let pages = format!(
+            "{{\"page_count\":{},\"page_length\":{},\"estimated_runtime_minutes\":{}}}",
+            self.page_count, self.page_length, self.estimated_runtime_minutes
+        );
+        let lint = format!(
+            "{{\"error_count\":{},\"warning_count\":{},\"findings\":[{}]}}",
+            self.get_error_count(),
+            self.get_warning_count(),
+            findings.join(",")
+        );
+        format!(
+            "{{\"schema_version\":{},\"stats\":{},\"pages\":{},\"lint\":{}}}",
+            METRICS_SCHEMA_VERSION, stats, pages, lint
+        )
+    }
+}
+
+/// Returns the metrics of the document.
+pub fn get_metrics(document: &FNParsedDocument, options: &FNMetricsOptions) -> FNMetrics {
+    let lines = &document.full.lines;
+    let printable = get_printable_strings_for_lines(lines);
+    let mut metrics = FNMetrics {
+        scene_count: document
+            .scenes
+            .iter()
+            .filter(|scene| scene.heading_index.is_some())
+            .count(),
+        character_cues: get_cue_counts_for_characters(lines, &options.aliases),
+        ..Default::default()
+    };
+
+    for (ln, text) in lines.iter().zip(&printable) {
+        if ln.is_title_page() || (ln.is_outline_element() && ln.fn_type != FNLineType::Heading) {
+            continue;
+        }
+        let words = text.split_whitespace().count();
+        metrics.word_count += words;
+        if ln.is_any_dialogue() {
+            metrics.dialogue_word_count += words;
+        } else if ln.fn_type == FNLineType::Action {
+            metrics.action_word_count += words;
+        }
+    }
+
+    let pages = get_pages(lines, &options.pagination, &FNMonospaceMeasurer);
+    metrics.page_count = pages.len();
+    if let Some(last_page) = pages.last() {
+        let last_page_length = (last_page.get_height() / options.pagination.page_height).min(1.0);
+        metrics.page_length = (((pages.len() - 1) as f64 + last_page_length) * 8.0).round() / 8.0;
+    }
+    metrics.estimated_runtime_minutes = metrics.page_length.round() as usize;
+
+    metrics.findings = get_lint_findings(document, options);
+    metrics
+}
+
+fn get_lint_findings(
+    document: &FNParsedDocument,
+    options: &FNMetricsOptions,
+) -> Vec<FNLintFinding> {
+    let lines = &document.full.lines;
+    let mut findings: Vec<FNLintFinding> = Vec::new();
+
+    for diagnostic in &document.full.diagnostics {
+        let (rule, name) = match diagnostic.ranged_element_type {
+            FNRangedElementType::Note { .. } => ("unclosed-note", "note"),
+            FNRangedElementType::Boneyard { .. } => ("unclosed-boneyard", "boneyard"),
+            FNRangedElementType::Other { .. } => continue,
+        };
+        let message = match diagnostic.kind {
+            FNRangedElementDiagnosticKind::UnclosedOpen => format!("Unclosed {}", name),
+        };
+        findings.push(FNLintFinding {
+            rule,
+            severity: FNLintSeverity::Warning,
+            line_index: diagnostic.global_index,
+            message,
+        });
+    }
+    findings.extend(
+        document
+            .full
+            .unclosed_parentheticals
+            .iter()
+            .map(|idx| FNLintFinding {
+                rule: "unclosed-parenthetical",
+                severity: FNLintSeverity::Warning,
+                line_index: *idx,
+                message: String::from("Unclosed parenthetical"),
+            }),
+    );
+    findings.extend(document.full.limit_diagnostics.iter().map(|diagnostic| {
+        let message = match diagnostic.kind {
+            FNParseLimitDiagnosticKind::DocumentTooLarge => "Document is too large, and was cut",
+            FNParseLimitDiagnosticKind::LineTooLong => "Line is too long, and was cut",
+            FNParseLimitDiagnosticKind::TooManyRangedElementMarkers => {
+                "Too many note or boneyard markers, and the line was cut"
+            }
+        };
+        FNLintFinding {
+            rule: "parse-limit",
+            severity: FNLintSeverity::Error,
+            line_index: diagnostic.global_index,
+            message: message.to_string(),
+        }
+    }));
+    findings.extend(
+        get_character_introductions_with_aliases(lines, &options.aliases)
+            .into_iter()
+            .filter(|intro| !intro.is_introduced_before_speaking())
+            .map(|intro| FNLintFinding {
+                rule: "speaks-before-introduction",
+                severity: FNLintSeverity::Warning,
+                line_index: intro.first_cue_index,
+                message: format!("{} speaks before being introduced in capitals", intro.name),
+            }),
+    );
+    if let Some(duplicate_options) = &options.duplicate_dialogue {
+        for duplicate in get_duplicate_dialogue(lines, duplicate_options) {
+            findings.extend(
+                duplicate
+                    .line_indices
+                    .iter()
+                    .skip(1)
+                    .map(|idx| FNLintFinding {
+                        rule: "duplicate-dialogue",
+                        severity: FNLintSeverity::Warning,
+                        line_index: *idx,
+                        message: format!(
+                            "Repeats the dialogue of line {}",
+                            duplicate.line_indices[0] + 1
+                        ),
+                    }),
+            );
+        }
+    }
+
+    findings.sort_by_key(|finding| (finding.line_index, finding.rule));
+    findings
+}
@@ -0,0 +1,85 @@
+//! Node bindings with napi-rs, enabled with the `node` feature.
+//!
+//! Build the native addon with the napi CLI (`napi build --features node`). Function names are camelCased for JS:
+//!
+//! ```js
+//! const { parse, strip, exportPlainText } = require('./fountain-parser-rs.node');
+//!
+//! const lines = parse(text);
+//! const withoutNotes = strip(text);
+//! const preview = exportPlainText(text);
+//! ```
+
+use napi_derive::napi;
+
+use crate::fountain_fixed_width::{get_fixed_width_text, FNFixedWidthOptions};
+use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+use crate::fountain_stripped::parse_stripped;
+use crate::static_fountain_parser;
+
+/// A parsed line, as a plain JS object.
+#[napi(object)]
+pub struct FNNodeLine {
+    #[napi(js_name = "type")]
+    pub fn_type: String,
+    pub string: String,
+    pub raw_string: String,
+    pub position: i32,
+    pub is_forced: bool,
+    /// The character name, for character cues
+    pub character: Option<String>,
+}
+
+#[napi(object)]
+pub struct FNNodeTitlePageEntry {
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+/// Parses a document, and returns an object for every line.
+#[napi]
+pub fn parse(text: String) -> Vec<FNNodeLine> {
+    parse_document_full(&text, &FNFullParseConfig::default())
+        .lines
+        .iter()
+        .map(|ln| FNNodeLine {
+            fn_type: ln.fn_type.to_string(),
+            string: ln.string.clone(),
+            raw_string: ln.raw_string.clone(),
+            position: ln.position,
+            is_forced: ln.is_forced,
+            character: ln.get_character_name(),
+        })
+        .collect()
+}
+
+#[napi]
+pub fn get_title_page(text: String) -> Vec<FNNodeTitlePageEntry> {
+    parse_document_full(&text, &FNFullParseConfig::default())
+        .title_page
+        .into_iter()
+        .map(|entry| FNNodeTitlePageEntry {
+            key: entry.key,
+            values: entry.values,
+        })
+        .collect()
+}
+
+/// Returns the document with its notes and boneyards removed. Lines which only held notes or boneyards are removed too.
+#[napi]
+pub fn strip(text: String) -> String {
+    let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(text));
+    let (stripped, _) = parse_stripped(&lines);
+    stripped
+        .iter()
+        .map(|ln| ln.string.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Returns the script as monospaced plain text, with the default layout.
+#[napi]
+pub fn export_plain_text(text: String) -> String {
+    let lines = parse_document_full(&text, &FNFullParseConfig::default()).lines;
+    get_fixed_width_text(&lines, &FNFixedWidthOptions::default())
+}
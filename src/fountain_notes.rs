@@ -0,0 +1,173 @@
+//! Notes (`[[ ]]`) as whole pieces of text, and the tasks writers leave in them:
+//!
+//! ```text
+//! Joe waits by the door. [[TODO: fix this scene]]
+//! ```
+//!
+//! A note can span several lines, so notes are collected from the ranges of every line, with the same rules as
+//! `get_invisible_byte_ranges_for_lines`: a note inside a boneyard isn't a note, and an unclosed note is text.
+
+use crate::fountain_line::FNLine;
+use crate::fountain_scene::{get_scene_index_for_line_index, get_scenes_from_parsed_lines};
+use crate::fountain_text_extraction::{
+    get_invisible_byte_ranges_for_lines, get_printable_strings_for_lines,
+};
+use crate::helper_funcs::get_json_string;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNNote {
+    /// Global index of the line with the `[[`
+    pub start_index: usize,
+    /// Byte index of the `[[` in the `raw_string` of that line
+    pub local_start: usize,
+    /// Global index of the line with the `]]`
+    pub end_index: usize,
+    /// The text between the markers. The lines of a multiline note are joined with `\n`.
+    pub text: String,
+}
+
+/// Returns every note in the document, in document order.
+pub fn get_notes(lines: &[FNLine]) -> Vec<FNNote> {
+    let mut notes: Vec<FNNote> = Vec::new();
+    let mut current: Option<FNNote> = None;
+
+    for (idx, (ln, invisible)) in lines
+        .iter()
+        .zip(get_invisible_byte_ranges_for_lines(lines))
+        .enumerate()
+    {
+        for range in invisible.notes.iter() {
+            // Adjacent notes can share a single range, so the range is split at the markers
+            let mut segment = &ln.raw_string[range.clone()];
+            let mut segment_start = range.start;
+            loop {
+                if let Some(note) = current.as_mut() {
+                    let Some(close) = segment.find("]]") else {
+                        note.text.push_str(segment);
+                        break;
+                    };
+                    note.text.push_str(&segment[..close]);
+                    note.end_index = idx;
+                    notes.extend(current.take());
+                    segment = &segment[close + 2..];
+                    segment_start += close + 2;
+                } else {
+                    let Some(open) = segment.find("[[") else {
+                        break;
+                    };
+                    current = Some(FNNote {
+                        start_index: idx,
+                        local_start: segment_start + open,
+                        end_index: idx,
+                        text: String::new(),
+                    });
+                    segment = &segment[open + 2..];
+                    segment_start += open + 2;
+                }
+            }
+        }
+        if let Some(note) = current.as_mut() {
+            note.text.push('\n');
+        }
+    }
+
+    notes
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNNoteTaskOptions {
+    /// Notes starting with one of these (matched without case, and followed by `:` or a space) are tasks
+    pub prefixes: Vec<String>,
+}
+
+impl Default for FNNoteTaskOptions {
+    fn default() -> Self {
+        FNNoteTaskOptions {
+            prefixes: vec![
+                String::from("TODO"),
+                String::from("FIXME"),
+                String::from("NOTE TO SELF"),
+            ],
+        }
+    }
+}
+
+/// A note which starts with a task prefix, such as `[[TODO: fix this scene]]`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNNoteTask {
+    /// The prefix as it's written in the options, so tasks can be grouped by prefix
+    pub prefix: String,
+    /// The rest of the note, after the prefix and the `:`
+    pub text: String,
+    pub note: FNNote,
+    pub scene_index: Option<usize>,
+    /// The heading of the scene, as it would be printed
+    pub scene_heading: Option<String>,
+}
+
+impl FNNoteTask {
+    pub fn to_json(&self) -> String {
+        let get_json_option = |value: Option<String>| -> String {
+            match value {
+                Some(value) => value,
+                None => String::from("null"),
+            }
+        };
+        format!(
+            "{{\"prefix\":{},\"text\":{},\"start_index\":{},\"end_index\":{},\"scene_index\":{},\"scene_heading\":{}}}",
+            get_json_string(&self.prefix),
+            get_json_string(&self.text),
+            self.note.start_index,
+            self.note.end_index,
+            get_json_option(self.scene_index.map(|idx| idx.to_string())),
+            get_json_option(self.scene_heading.as_deref().map(get_json_string))
+        )
+    }
+}
+
+/// Returns every note which starts with one of the task prefixes, in document order.
+pub fn get_note_tasks(lines: &[FNLine], options: &FNNoteTaskOptions) -> Vec<FNNoteTask> {
+    let scenes = get_scenes_from_parsed_lines(lines);
+    let printable = get_printable_strings_for_lines(lines);
+
+    get_notes(lines)
+        .into_iter()
+        .filter_map(|note| {
+            let (prefix, text) = options
+                .prefixes
+                .iter()
+                .find_map(|prefix| Some((prefix, get_text_after_prefix(&note.text, prefix)?)))?;
+            let scene_index = get_scene_index_for_line_index(&scenes, note.start_index);
+            Some(FNNoteTask {
+                prefix: prefix.clone(),
+                text,
+                scene_heading: scene_index
+                    .and_then(|idx| scenes[idx].heading_index)
+                    .map(|idx| printable[idx].clone()),
+                scene_index,
+                note,
+            })
+        })
+        .collect()
+}
+
+/// Returns the tasks as a JSON array.
+pub fn get_note_tasks_json(tasks: &[FNNoteTask]) -> String {
+    let tasks: Vec<String> = tasks.iter().map(|task| task.to_json()).collect();
+    format!("[{}]", tasks.join(","))
+}
+
+// `TODO: fix this` and `todo fix this` both give `fix this`, but `TODOS` isn't a `TODO`
+fn get_text_after_prefix(note_text: &str, prefix: &str) -> Option<String> {
+    let text = note_text.trim_start();
+    let prefix = prefix.trim();
+    let head = text.get(..prefix.len())?;
+    if prefix.is_empty() || !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    let rest = &text[prefix.len()..];
+    if !rest.is_empty() && !rest.starts_with(':') && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim_start_matches(':').trim().to_string())
+}
@@ -0,0 +1,186 @@
+//! Omitting scenes from a production draft, and restoring them.
+//!
+//! Once scenes are numbered, a cut scene keeps its number: its heading is replaced with an `OMITTED` placeholder,
+//! and the original scene is kept in a boneyard right after it, so that it can be restored later:
+//!
+//! ```text
+//! .OMITTED #12#
+//!
+//! /*
+//! INT. BAR - NIGHT #12#
+//!
+//! Joe waits.
+//! */
+//! ```
+//!
+//! `restore_omitted_scene` undoes this: the boneyard is taken out, and the restored heading gets the
+//! number of the placeholder, so that the scene is back under the number it was omitted under.
+
+use crate::fountain_heading::{get_scene_heading_from_line, get_scene_heading_from_string};
+use crate::fountain_line::FNLine;
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+use crate::static_fountain_preparser::get_text_from_lines;
+
+const OMITTED_HEADING: &str = "OMITTED";
+
+/// Replaces the scene with the given number with an `OMITTED` placeholder, and returns the updated Fountain text.
+///
+/// Returns `None` if no heading has that scene number, or if the scene already holds a boneyard,
+/// since boneyards can't be nested.
+pub fn omit_scene(lines: &[FNLine], scene_number: &str) -> Option<String> {
+    let scenes = get_scenes_from_parsed_lines(lines);
+    let scene = scenes.iter().find(|scene| {
+        scene
+            .get_heading_line(lines)
+            .and_then(get_scene_heading_from_line)
+            .and_then(|heading| heading.scene_number)
+            .is_some_and(|number| number == scene_number.trim())
+    })?;
+
+    let scene_lines = scene.get_lines(lines);
+    if scene_lines
+        .iter()
+        .any(|ln| ln.raw_string.contains("/*") || ln.raw_string.contains("*/"))
+    {
+        return None;
+    }
+    // Empty lines at the end of the scene stay outside of the boneyard, to separate it from the next scene
+    let content_len = scene_lines
+        .iter()
+        .rposition(|ln| !ln.raw_string.trim().is_empty())
+        .map_or(0, |idx| idx + 1);
+
+    let terminator_length = get_document_terminator_length(lines);
+    let mut new_lines: Vec<FNLine> = lines[..scene.start].to_vec();
+    let placeholder = format!(".{} #{}#", OMITTED_HEADING, scene_number.trim());
+    new_lines.extend(
+        [placeholder.as_str(), "", "/*"]
+            .into_iter()
+            .map(|raw| get_new_line(raw, terminator_length)),
+    );
+    new_lines.extend_from_slice(&scene_lines[..content_len]);
+    new_lines.push(get_new_line("*/", terminator_length));
+    new_lines.extend_from_slice(&lines[scene.start + content_len..]);
+
+    Some(get_text_from_new_lines(new_lines, lines))
+}
+
+// `2` if the document's lines end with `\r\n`, so that added lines do too
+fn get_document_terminator_length(lines: &[FNLine]) -> i32 {
+    match lines.first() {
+        Some(first) if first.terminator_length == 2 => 2,
+        _ => 1,
+    }
+}
+
+fn get_new_line(raw: &str, terminator_length: i32) -> FNLine {
+    FNLine {
+        string: raw.to_string(),
+        raw_string: raw.to_string(),
+        terminator_length,
+        ..Default::default()
+    }
+}
+
+// Every line but the last gets a line break, and the last one ends like the document's old last line
+fn get_text_from_new_lines(mut new_lines: Vec<FNLine>, old_lines: &[FNLine]) -> String {
+    let terminator_length = get_document_terminator_length(old_lines);
+    for ln in new_lines.iter_mut() {
+        if ln.terminator_length <= 0 {
+            ln.terminator_length = terminator_length;
+        }
+    }
+    if let Some(last) = new_lines.last_mut() {
+        last.terminator_length = old_lines.last().map_or(0, |ln| ln.terminator_length);
+    }
+    get_text_from_lines(&new_lines)
+}
+
+/// Returns `true` for the heading of an `OMITTED` placeholder, with or without a scene number.
+pub fn is_omitted_placeholder(line: &FNLine) -> bool {
+    get_scene_heading_from_line(line)
+        .is_some_and(|heading| heading.prefix.is_empty() && heading.location == OMITTED_HEADING)
+}
+
+/// An `OMITTED` placeholder, and the boneyard holding the original scene.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNOmittedScene {
+    pub scene_number: Option<String>,
+    pub placeholder_index: usize,
+    /// Global indices of the lines with the `/*` and the `*/`
+    pub boneyard_open_index: usize,
+    pub boneyard_close_index: usize,
+}
+
+/// Returns every `OMITTED` placeholder which is followed by a boneyard (after any empty lines), in document order.
+pub fn get_omitted_scenes(lines: &[FNLine]) -> Vec<FNOmittedScene> {
+    let mut omitted: Vec<FNOmittedScene> = Vec::new();
+    for (placeholder_index, ln) in lines.iter().enumerate() {
+        if !is_omitted_placeholder(ln) {
+            continue;
+        }
+        let Some(boneyard_open_index) = lines[placeholder_index + 1..]
+            .iter()
+            .position(|ln| !ln.raw_string.trim().is_empty())
+            .map(|idx| placeholder_index + 1 + idx)
+            .filter(|idx| lines[*idx].raw_string.trim() == "/*")
+        else {
+            continue;
+        };
+        let Some(boneyard_close_index) = lines[boneyard_open_index + 1..]
+            .iter()
+            .position(|ln| ln.raw_string.trim() == "*/")
+            .map(|idx| boneyard_open_index + 1 + idx)
+        else {
+            continue;
+        };
+        omitted.push(FNOmittedScene {
+            scene_number: get_scene_heading_from_line(ln).and_then(|heading| heading.scene_number),
+            placeholder_index,
+            boneyard_open_index,
+            boneyard_close_index,
+        });
+    }
+    omitted
+}
+
+/// Restores the omitted scene with the given number, and returns the updated Fountain text.
+///
+/// The placeholder and the boneyard markers are removed, and the scene's heading gets the placeholder's number.
+/// Returns `None` if there is no omitted scene with that number.
+pub fn restore_omitted_scene(lines: &[FNLine], scene_number: &str) -> Option<String> {
+    let omitted = get_omitted_scenes(lines)
+        .into_iter()
+        .find(|omitted| omitted.scene_number.as_deref() == Some(scene_number.trim()))?;
+
+    let mut new_lines: Vec<FNLine> = lines[..omitted.placeholder_index].to_vec();
+    let mut is_heading_renumbered = false;
+    for ln in &lines[omitted.boneyard_open_index + 1..omitted.boneyard_close_index] {
+        if !is_heading_renumbered && !ln.raw_string.trim().is_empty() {
+            new_lines.push(get_new_line(
+                &get_heading_with_scene_number(&ln.raw_string, scene_number.trim()),
+                ln.terminator_length,
+            ));
+            is_heading_renumbered = true;
+            continue;
+        }
+        new_lines.push(ln.clone());
+    }
+    new_lines.extend_from_slice(&lines[omitted.boneyard_close_index + 1..]);
+
+    Some(get_text_from_new_lines(new_lines, lines))
+}
+
+// Replaces the `#12#` scene number at the end of a heading, or adds one
+fn get_heading_with_scene_number(raw_heading: &str, scene_number: &str) -> String {
+    let mut heading = raw_heading.trim_end();
+    if get_scene_heading_from_string(heading)
+        .scene_number
+        .is_some()
+    {
+        if let Some(open) = heading[..heading.len() - 1].rfind('#') {
+            heading = heading[..open].trim_end();
+        }
+    }
+    format!("{} #{}#", heading, scene_number)
+}
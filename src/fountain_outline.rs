@@ -0,0 +1,81 @@
+//! Builds an outline of the document from its sections and scene headings, for navigation UIs.
+//!
+//! Titles are cleaned up for display: sections lose their `#` markers (`### Sequence 3 ###` becomes `Sequence 3`),
+//! and headings lose their forcing `.` and scene number.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_heading::get_scene_heading_from_string;
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNOutlineElementKind {
+    Section,
+    Heading,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNOutlineElement {
+    pub line_index: usize,
+    pub kind: FNOutlineElementKind,
+    pub title: String,
+    /// For sections, the number of `#`. For headings, the depth of the section they're in, or `0` outside of any section.
+    pub depth: usize,
+    /// Only set for headings with a `#12#` style scene number
+    pub scene_number: Option<String>,
+    /// The `color` of the line, if it has one (see `fountain_colors`)
+    pub color: Option<String>,
+}
+
+/// Returns every section and heading of the parsed lines, in document order.
+pub fn get_outline_from_lines(lines: &[FNLine]) -> Vec<FNOutlineElement> {
+    let mut elements: Vec<FNOutlineElement> = Vec::new();
+    let mut current_section_depth: usize = 0;
+
+    for (line_index, ln) in lines.iter().enumerate() {
+        match ln.fn_type {
+            FNLineType::Section => {
+                let depth = ln.get_section_depth() as usize;
+                current_section_depth = depth;
+                elements.push(FNOutlineElement {
+                    line_index,
+                    kind: FNOutlineElementKind::Section,
+                    title: ln.get_section_title().unwrap_or_default(),
+                    depth,
+                    scene_number: None,
+                    color: get_color(ln),
+                });
+            }
+            FNLineType::Heading => {
+                elements.push(FNOutlineElement {
+                    line_index,
+                    kind: FNOutlineElementKind::Heading,
+                    title: get_heading_title(&ln.string),
+                    depth: current_section_depth,
+                    scene_number: get_scene_heading_from_string(&ln.string).scene_number,
+                    color: get_color(ln),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    elements
+}
+
+fn get_color(ln: &FNLine) -> Option<String> {
+    Some(ln.color.trim().to_string()).filter(|color| !color.is_empty())
+}
+
+// The heading text without the forcing `.` or the scene number
+fn get_heading_title(text: &str) -> String {
+    let mut title: &str = text.trim();
+    if title.starts_with('.') && !title.starts_with("..") {
+        title = title[1..].trim_start();
+    }
+    if title.ends_with('#') {
+        if let Some(open) = title[..title.len() - 1].rfind('#') {
+            title = title[..open].trim_end();
+        }
+    }
+    title.to_string()
+}
@@ -0,0 +1,482 @@
+//! Splits a parsed script into pages.
+//!
+//! Every printed line is wrapped into rows at the width of its element type, and rows are stacked onto
+//! pages until the page height is reached. Text is measured by an `FNTextMeasurer`: the default
+//! `FNMonospaceMeasurer` counts columns, like a typewriter with Courier, and GUI apps can plug in
+//! real font metrics to get the same page breaks as their editor.
+//!
+//! Only the visible text of a line is measured: notes and boneyards take up no space, so a line which is only
+//! a note or inside a boneyard has no rows, and a line with a note in it is as wide as the text around the note.
+//! For an annotated draft, `include_notes` prints notes with the text around them instead. Title page lines,
+//! sections, and synopses take up no space either. Both sides of a dual dialogue are laid out next to each other,
+//! and are never split across pages.
+//!
+//! A page break (`===`) always starts a new page. A paragraph or dialogue block with a `[[keep together]]` note
+//! is never split across pages, unless it is taller than a page.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::fountain_bidi::{get_text_direction, FNTextDirection};
+use crate::fountain_dual_dialogue::get_dual_dialogue_blocks;
+use crate::fountain_enums::FNLineType;
+use crate::fountain_layout::{FNElementLayout, FNElementLayouts};
+use crate::fountain_line::FNLine;
+use crate::fountain_notes::get_notes;
+use crate::fountain_text_extraction::{
+    get_invisible_byte_ranges_for_lines, get_printable_strings_for_lines,
+    get_printable_strings_for_lines_with_notes,
+};
+use crate::fountain_text_width::get_display_width;
+use crate::fountain_wrap::{wrap_text_with_measure, FNElementWidths};
+use crate::static_fountain_preparser::{get_text_with_expanded_tabs, FNTabPolicy};
+
+/// Measures text for pagination. Widths are in the same unit as the `FNElementWidths`, and heights in the same unit
+/// as the page height.
+pub trait FNTextMeasurer {
+    fn get_text_width(&self, text: &str, fn_type: &FNLineType) -> f64;
+
+    /// The height of a single row of text. Empty lines between elements are one row of `Empty` text.
+    fn get_line_height(&self, _fn_type: &FNLineType) -> f64 {
+        1.0
+    }
+}
+
+/// Every grapheme is one column wide (two for wide characters such as emoji), and every row is one line high.
+#[derive(Debug, Clone, Default)]
+pub struct FNMonospaceMeasurer;
+
+impl FNTextMeasurer for FNMonospaceMeasurer {
+    fn get_text_width(&self, text: &str, _fn_type: &FNLineType) -> f64 {
+        get_display_width(text) as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNPaginationOptions {
+    /// The height available for text on each page, in the unit of the `FNTextMeasurer` heights
+    pub page_height: f64,
+    /// Where each element goes on the page, attached to its rows for renderers. Rows are wrapped at the width
+    /// of each layout, in the unit of the `FNTextMeasurer`.
+    pub layouts: FNElementLayouts,
+    /// When a paragraph is split across pages, at least this many of its rows stay on each page.
+    /// Otherwise, the whole paragraph moves to the next page.
+    pub min_rows_per_split: usize,
+    /// A character cue is only placed on a page if at least this many rows of its dialogue fit after it.
+    pub min_dialogue_rows_after_cue: usize,
+    /// If `true`, a scene heading is never the last thing on a page
+    pub keep_heading_with_next: bool,
+    /// The text of a note which keeps its block (the lines between two empty lines) on a single page.
+    /// Matched without case, and ignoring whitespace around it. `None` ignores these notes.
+    pub keep_together_note: Option<String>,
+    /// If `true`, notes are printed (and measured) with the text around them, as in an annotated review draft.
+    /// Boneyards never take up space.
+    pub include_notes: bool,
+    /// How tabs left in the text are measured. Use the `FNTabPolicy` of the `FNPreparseOptions`, so that lines
+    /// parsed without expanding their tabs are still measured the same way.
+    pub tab_policy: FNTabPolicy,
+}
+
+impl Default for FNPaginationOptions {
+    fn default() -> Self {
+        FNPaginationOptions {
+            page_height: 55.0,
+            layouts: FNElementLayouts::default(),
+            min_rows_per_split: 2,
+            min_dialogue_rows_after_cue: 2,
+            keep_heading_with_next: true,
+            keep_together_note: Some(String::from("keep together")),
+            include_notes: false,
+            tab_policy: FNTabPolicy::default(),
+        }
+    }
+}
+
+/// A single wrapped row of a printed line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNPageRow {
+    pub line_index: usize,
+    pub text: String,
+    /// The right side of dual dialogue sits next to the left side, so its rows have no height of their own
+    pub height: f64,
+    /// The direction of the whole line, so that every row of a wrapped line is laid out the same way
+    pub direction: FNTextDirection,
+    /// The layout of the line's element, or of its side of a dual dialogue. Empty rows have the `action` layout.
+    pub layout: FNElementLayout,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNPage {
+    pub rows: Vec<FNPageRow>,
+}
+
+impl FNPage {
+    pub fn get_height(&self) -> f64 {
+        self.rows.iter().map(|row| row.height).sum()
+    }
+
+    /// Returns the index of the first line which has a row on this page.
+    pub fn get_first_line_index(&self) -> Option<usize> {
+        self.rows.iter().map(|row| row.line_index).min()
+    }
+
+    /// Returns the index of the last line which has a row on this page.
+    pub fn get_last_line_index(&self) -> Option<usize> {
+        self.rows.iter().map(|row| row.line_index).max()
+    }
+}
+
+// The rows of a single printed line (or of a whole dual dialogue), or a single empty row
+struct FNPaginationItem {
+    rows: Vec<FNPageRow>,
+    fn_type: FNLineType,
+    is_empty_line: bool,
+    can_split: bool,
+    // The height of what comes after this item, which has to fit on the same page
+    keep_with_next_height: f64,
+    is_page_break: bool,
+    // The first line of the block this item has to stay on the same page with
+    keep_together_block: Option<usize>,
+}
+
+impl FNPaginationItem {
+    fn get_height(&self) -> f64 {
+        self.rows.iter().map(|row| row.height).sum()
+    }
+}
+
+/// Returns the pages of the parsed lines.
+pub fn get_pages(
+    lines: &[FNLine],
+    options: &FNPaginationOptions,
+    measurer: &dyn FNTextMeasurer,
+) -> Vec<FNPage> {
+    let items = get_pagination_items(lines, options, measurer);
+    let mut pages: Vec<FNPage> = Vec::new();
+    let mut page = FNPage::default();
+
+    let min_rows_per_split = options.min_rows_per_split.max(1);
+
+    for item in items {
+        let remaining = options.page_height - page.get_height();
+
+        if item.is_page_break {
+            if !page.rows.is_empty() {
+                pages.push(std::mem::take(&mut page));
+            }
+            continue;
+        }
+        if item.is_empty_line {
+            // Empty lines are dropped at the top and bottom of pages
+            if !page.rows.is_empty() && item.get_height() <= remaining {
+                page.rows.extend(item.rows);
+            }
+            continue;
+        }
+        if item.get_height() + item.keep_with_next_height <= remaining {
+            page.rows.extend(item.rows);
+            continue;
+        }
+        if !item.can_split || item.get_height() <= remaining {
+            if !page.rows.is_empty() {
+                pages.push(std::mem::take(&mut page));
+            }
+            page.rows.extend(item.rows);
+            continue;
+        }
+
+        let mut rows = item.rows;
+        while !rows.is_empty() {
+            let mut fitting: usize = 0;
+            let mut height = page.get_height();
+            for row in rows.iter() {
+                if height + row.height > options.page_height {
+                    break;
+                }
+                height += row.height;
+                fitting += 1;
+            }
+            if fitting == rows.len() {
+                page.rows.append(&mut rows);
+                break;
+            }
+
+            let taken = if page.rows.is_empty() {
+                // Nothing can be moved to the next page, so split wherever the page is full
+                fitting.max(1)
+            } else if fitting >= min_rows_per_split && rows.len() - fitting >= min_rows_per_split {
+                fitting
+            } else {
+                0
+            };
+            page.rows.extend(rows.drain(..taken));
+            pages.push(std::mem::take(&mut page));
+        }
+    }
+
+    if !page.rows.is_empty() {
+        pages.push(page);
+    }
+    for page in pages.iter_mut() {
+        while page
+            .rows
+            .last()
+            .is_some_and(|row| lines[row.line_index].fn_type == FNLineType::Empty)
+        {
+            page.rows.pop();
+        }
+    }
+    pages
+}
+
+fn get_pagination_items(
+    lines: &[FNLine],
+    options: &FNPaginationOptions,
+    measurer: &dyn FNTextMeasurer,
+) -> Vec<FNPaginationItem> {
+    let printable: Vec<String> = match options.include_notes {
+        true => get_printable_strings_for_lines_with_notes(lines),
+        false => get_printable_strings_for_lines(lines),
+    }
+    .iter()
+    .map(|text| get_text_with_expanded_tabs(text, &options.tab_policy))
+    .collect();
+    // An empty line inside a boneyard (or a multiline note) isn't an empty line of the script
+    let mut is_hidden_empty_line = vec![false; lines.len()];
+    for (idx, invisible) in get_invisible_byte_ranges_for_lines(lines)
+        .iter()
+        .enumerate()
+    {
+        if let Some(next) = is_hidden_empty_line.get_mut(idx + 1) {
+            *next = invisible.is_open_at_end;
+        }
+    }
+    let directions: Vec<FNTextDirection> = printable
+        .iter()
+        .map(|text| get_text_direction(text))
+        .collect();
+    let dual_blocks = get_dual_dialogue_blocks(lines);
+    let keep_together_blocks = get_keep_together_blocks(lines, options);
+    let mut items: Vec<FNPaginationItem> = Vec::new();
+    let mut idx: usize = 0;
+
+    // `is_right_side` is `None` outside of dual dialogue
+    let get_rows = |line_index: usize,
+                    widths: &FNElementWidths,
+                    is_right_side: Option<bool>|
+     -> Vec<FNPageRow> {
+        let fn_type = &lines[line_index].fn_type;
+        let layout = match is_right_side {
+            Some(is_right_side) => options
+                .layouts
+                .get_dual_dialogue_layout_for_line_type(fn_type, is_right_side),
+            None => options.layouts.get_layout_for_line_type(fn_type),
+        }
+        .unwrap_or_default();
+        let measure = |text: &str| measurer.get_text_width(text, fn_type);
+        wrap_text_with_measure(&printable[line_index], fn_type, widths, &measure)
+            .into_iter()
+            .map(|text| FNPageRow {
+                line_index,
+                text,
+                height: measurer.get_line_height(fn_type),
+                direction: directions[line_index],
+                layout: layout.clone(),
+            })
+            .collect()
+    };
+
+    let widths = options.layouts.get_element_widths();
+    while idx < lines.len() {
+        if let Some(block) = dual_blocks.iter().find(|block| block.left_start == idx) {
+            // Each side gets half of the space, like the dual dialogue layout
+            let half = |width: usize| width / 2;
+            let dual_widths = FNElementWidths {
+                action: half(widths.action),
+                character: half(widths.character),
+                dialogue: half(widths.dialogue),
+                parenthetical: half(widths.parenthetical),
+                transition: half(widths.transition),
+            };
+            let left: Vec<FNPageRow> = (block.left_start..block.left_end)
+                .flat_map(|i| get_rows(i, &dual_widths, Some(false)))
+                .collect();
+            let right: Vec<FNPageRow> = (block.right_start..block.right_end)
+                .flat_map(|i| get_rows(i, &dual_widths, Some(true)))
+                .collect();
+            let left_height: f64 = left.iter().map(|row| row.height).sum();
+            let right_height: f64 = right.iter().map(|row| row.height).sum();
+            let (tall, short) = if left_height >= right_height {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            let mut rows = tall;
+            rows.extend(
+                short
+                    .into_iter()
+                    .map(|row| FNPageRow { height: 0.0, ..row }),
+            );
+            rows.sort_by_key(|row| row.line_index);
+            items.push(FNPaginationItem {
+                rows,
+                fn_type: FNLineType::DualDialogueCharacter,
+                is_empty_line: false,
+                can_split: false,
+                keep_with_next_height: 0.0,
+                is_page_break: false,
+                keep_together_block: keep_together_blocks[block.left_start],
+            });
+            idx = block.right_end;
+            continue;
+        }
+
+        let ln = &lines[idx];
+        // A hidden empty line has no printable text, so it gets no rows below
+        if ln.fn_type == FNLineType::Empty && !is_hidden_empty_line[idx] {
+            items.push(FNPaginationItem {
+                rows: vec![FNPageRow {
+                    line_index: idx,
+                    text: String::new(),
+                    height: measurer.get_line_height(&ln.fn_type),
+                    direction: FNTextDirection::Neutral,
+                    layout: options.layouts.action.clone(),
+                }],
+                fn_type: FNLineType::Empty,
+                is_empty_line: true,
+                can_split: false,
+                keep_with_next_height: 0.0,
+                is_page_break: false,
+                keep_together_block: None,
+            });
+        } else if ln.fn_type == FNLineType::PageBreak {
+            items.push(FNPaginationItem {
+                rows: Vec::new(),
+                fn_type: FNLineType::PageBreak,
+                is_empty_line: false,
+                can_split: false,
+                keep_with_next_height: 0.0,
+                is_page_break: true,
+                keep_together_block: None,
+            });
+        } else {
+            let rows = get_rows(idx, &widths, None);
+            if !rows.is_empty() {
+                items.push(FNPaginationItem {
+                    rows,
+                    fn_type: ln.fn_type.clone(),
+                    is_empty_line: false,
+                    can_split: !matches!(ln.fn_type, FNLineType::Heading | FNLineType::Character),
+                    keep_with_next_height: 0.0,
+                    is_page_break: false,
+                    keep_together_block: keep_together_blocks[idx],
+                });
+            }
+        }
+        idx += 1;
+    }
+
+    // Lines which aren't printed can leave empty lines next to each other; keep only one
+    items.dedup_by(|next, previous| next.is_empty_line && previous.is_empty_line);
+
+    // A block which is taller than a page has to be split anyway
+    let mut block_heights: HashMap<usize, f64> = HashMap::new();
+    for item in items.iter() {
+        if let Some(block) = item.keep_together_block {
+            *block_heights.entry(block).or_default() += item.get_height();
+        }
+    }
+    for item in items.iter_mut() {
+        if item
+            .keep_together_block
+            .is_some_and(|block| block_heights[&block] > options.page_height)
+        {
+            item.keep_together_block = None;
+        }
+    }
+
+    // Backwards, so that a heading can also keep whatever its next item keeps
+    for idx in (0..items.len()).rev() {
+        items[idx].keep_with_next_height = match items[idx].fn_type {
+            FNLineType::Heading if options.keep_heading_with_next => {
+                get_heading_keep_height(&items[idx + 1..])
+            }
+            FNLineType::Character => {
+                get_cue_keep_height(&items[idx + 1..], options.min_dialogue_rows_after_cue)
+            }
+            _ => 0.0,
+        };
+
+        let next = items.get(idx + 1);
+        if let Some(next) = next.filter(|next| {
+            next.keep_together_block.is_some()
+                && next.keep_together_block == items[idx].keep_together_block
+        }) {
+            let next_height = next.get_height() + next.keep_with_next_height;
+            let item = &mut items[idx];
+            item.can_split = false;
+            item.keep_with_next_height = item.keep_with_next_height.max(next_height);
+        }
+    }
+
+    items
+}
+
+// For every line, the first line of its block if the block has a keep together note
+fn get_keep_together_blocks(lines: &[FNLine], options: &FNPaginationOptions) -> Vec<Option<usize>> {
+    let mut blocks: Vec<Option<usize>> = vec![None; lines.len()];
+    let Some(hint) = &options.keep_together_note else {
+        return blocks;
+    };
+    let hint = hint.trim().to_lowercase();
+    let hinted_lines: HashSet<usize> = get_notes(lines)
+        .into_iter()
+        .filter(|note| note.text.trim().to_lowercase() == hint)
+        .map(|note| note.start_index)
+        .collect();
+
+    let mut start: usize = 0;
+    while start < lines.len() {
+        let end = start
+            + lines[start..]
+                .iter()
+                .take_while(|ln| ln.fn_type != FNLineType::Empty)
+                .count();
+        let has_hint = (start..end).any(|idx| hinted_lines.contains(&idx));
+        if has_hint {
+            blocks[start..end].fill(Some(start));
+        }
+        start = end + 1;
+    }
+    blocks
+}
+
+// Any empty lines after the heading, then the first row of the next element, or all of it if it can't be split
+fn get_heading_keep_height(next_items: &[FNPaginationItem]) -> f64 {
+    let mut height = 0.0;
+    for item in next_items {
+        if item.is_empty_line {
+            height += item.get_height();
+            continue;
+        }
+        if item.can_split {
+            return height + item.rows.first().map_or(0.0, |row| row.height);
+        }
+        return height + item.get_height() + item.keep_with_next_height;
+    }
+    0.0
+}
+
+fn get_cue_keep_height(next_items: &[FNPaginationItem], min_rows: usize) -> f64 {
+    next_items
+        .iter()
+        .take_while(|item| {
+            matches!(
+                item.fn_type,
+                FNLineType::Parenthetical | FNLineType::Dialogue | FNLineType::More
+            )
+        })
+        .flat_map(|item| item.rows.iter())
+        .take(min_rows)
+        .map(|row| row.height)
+        .sum()
+}
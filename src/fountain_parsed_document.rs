@@ -0,0 +1,136 @@
+//! The `FNParsedDocument` is the result of parsing a whole document: the source text, the parsed lines,
+//! and everything derived from them (title page, outline, scenes, and diagnostics), in one place.
+//!
+//! ```text
+//! let document = static_fountain_parser::parse_document(&text);
+//! for scene in &document.scenes {
+//!     let lines = document.get_scene_lines(scene);
+//! }
+//! ```
+//!
+//! The free functions of the other modules all work on `&[FNLine]`, so they can be used with `document.full.lines`.
+
+use std::fmt;
+
+use crate::fountain_characters::{get_cue_counts_for_characters, FNCharacterAliasMap};
+use crate::fountain_full_parse::{parse_document_full, FNFullParse, FNFullParseConfig};
+use crate::fountain_line::{FNLine, LINE_TYPE_COLUMN_WIDTH};
+use crate::fountain_metrics::{get_metrics, FNMetricsOptions};
+use crate::fountain_scene::{
+    get_scene_index_for_line_index, get_scenes_from_parsed_lines, FNScene,
+};
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNParsedDocument {
+    /// The text the document was parsed from
+    pub source: String,
+    /// The lines and everything `parse_document_full` derives from them
+    pub full: FNFullParse,
+    pub scenes: Vec<FNScene>,
+}
+
+impl FNParsedDocument {
+    /// Parses the text with the default `FNFullParseConfig`.
+    pub fn new(text: &str) -> Self {
+        Self::with_config(text, &FNFullParseConfig::default())
+    }
+
+    pub fn with_config(text: &str, config: &FNFullParseConfig) -> Self {
+        Self::from_full_parse(text, parse_document_full(text, config))
+    }
+
+    /// Builds a document from an existing `FNFullParse` of `source`.
+    pub fn from_full_parse(source: &str, full: FNFullParse) -> Self {
+        FNParsedDocument {
+            source: source.to_string(),
+            scenes: get_scenes_from_parsed_lines(&full.lines),
+            full,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.full.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.full.lines.is_empty()
+    }
+
+    pub fn get_line(&self, global_index: usize) -> Option<&FNLine> {
+        self.full.lines.get(global_index)
+    }
+
+    /// Returns the scene which contains the line, if any.
+    pub fn get_scene_for_line(&self, global_index: usize) -> Option<&FNScene> {
+        get_scene_index_for_line_index(&self.scenes, global_index).map(|idx| &self.scenes[idx])
+    }
+
+    pub fn get_scene_lines(&self, scene: &FNScene) -> &[FNLine] {
+        scene.get_lines(&self.full.lines)
+    }
+
+    /// Returns the values of the first title page entry with the given key (matched without case).
+    pub fn get_title_page_values(&self, key: &str) -> Option<&[String]> {
+        let key = key.trim().to_lowercase();
+        self.full
+            .title_page
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| entry.values.as_slice())
+    }
+
+    /// Returns the (canonical) name of every character with a cue, from most to fewest cues.
+    pub fn get_character_names(&self, aliases: &FNCharacterAliasMap) -> Vec<String> {
+        get_cue_counts_for_characters(&self.full.lines, aliases)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Returns `true` if there are any unclosed notes, boneyards, or parentheticals.
+    pub fn has_diagnostics(&self) -> bool {
+        !self.full.diagnostics.is_empty()
+            || !self.full.unclosed_parentheticals.is_empty()
+            || !self.full.limit_diagnostics.is_empty()
+    }
+
+    /// Returns the stats, lint findings, and page estimates of the document as JSON,
+    /// with the default `FNMetricsOptions`. See `fountain_metrics` for the fields.
+    pub fn metrics_json(&self) -> String {
+        get_metrics(self, &FNMetricsOptions::default()).to_json()
+    }
+}
+
+/// Prints a compact table of the lines, one per row: the line index, the line type, and the text as it would be
+/// printed (see `get_printable_strings_for_lines`).
+///
+/// ```text
+///     0  TitlePageTitle             Brick
+///     1  Empty
+///     2  Heading                    INT. BAR - NIGHT
+/// ```
+impl fmt::Display for FNParsedDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let index_width = self
+            .full
+            .lines
+            .len()
+            .saturating_sub(1)
+            .to_string()
+            .len()
+            .max(5);
+        let printable = get_printable_strings_for_lines(&self.full.lines);
+        for (idx, (ln, text)) in self.full.lines.iter().zip(printable).enumerate() {
+            let row = format!(
+                "{:>index_width$}  {:<type_width$}  {}",
+                idx,
+                ln.fn_type.to_string(),
+                text,
+                type_width = LINE_TYPE_COLUMN_WIDTH
+            );
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,25 @@
-use std::{collections::HashMap, default};
+//! The ranges, markers, and diagnostics of multiline notes and boneyards.
+//!
+//! `FNLine`, `FNFullParseConfig`, and `FNFullParse` carry these types, so callers can name them from here.
+
+use std::ops::Range;
 
 use uuid::Uuid;
 
+use crate::fountain_enums::FNRangedElementType;
 use crate::fountain_line::FNLine;
 
+/// The byte indices in a line's `raw_string` where each open and close pattern of one `FNRangedElementType` starts,
+/// matched or not.
+///
+/// Set on the lines of a partial map (as `note_markers` or `boneyard_markers`), so that building the ranges
+/// afterwards doesn't search the string again.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FNRangedElementMarkers {
+    pub opens: Vec<usize>,
+    pub closes: Vec<usize>,
+}
+
 /// ONLY Contains ranges within a `SelfContained` partial line at the `global_index`
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct FNPartialLineRange {
@@ -18,6 +34,8 @@ pub struct FNPartialLineRange {
 }
 /// This range struct must start with an `OrphanedOpen` line and end with an `OrphanedClose` line.
 /// May also start or end with an `OrphanedOpenAndClose` line
+///
+/// The visible text of the lines it spans is one logical line; see `get_merged_partial_lines`.
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct FNPartialMultilineRange {
     pub id: Option<Uuid>,
@@ -28,3 +46,80 @@ pub struct FNPartialMultilineRange {
     pub global_end: Option<usize>,
     pub local_end: Option<usize>,
 }
+
+/// A piece of visible text of a merged line, and where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNMergedLineSegment {
+    /// Byte range of the merged line's `string`
+    pub merged_range: Range<usize>,
+    pub global_index: usize,
+    /// Byte range of the `raw_string` of the line at `global_index`
+    pub raw_range: Range<usize>,
+}
+
+/// The visible text of the lines spanned by one or more multiline ranges, as the single line it would be
+/// without the invisibles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNMergedPartialLine {
+    /// Global indices of the raw lines which were merged. The end is exclusive.
+    pub global_range: Range<usize>,
+    /// The merged line, with `string` and `raw_string` set to the visible text, the `position` of the first raw
+    /// line, and the `fn_type` the merged text is parsed as, between the lines around it
+    pub visible_fnline: FNLine,
+    /// Every piece of visible text, in order. Empty pieces are left out.
+    pub segments: Vec<FNMergedLineSegment>,
+}
+
+impl FNMergedPartialLine {
+    /// Returns the global index of the raw line, and the byte offset in its `raw_string`, for a byte offset
+    /// of the merged `string`. An offset between two segments belongs to the later one.
+    pub fn get_raw_location(&self, merged_offset: usize) -> Option<(usize, usize)> {
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| segment.merged_range.contains(&merged_offset))
+            .or_else(|| {
+                self.segments
+                    .last()
+                    .filter(|segment| segment.merged_range.end == merged_offset)
+            })?;
+        Some((
+            segment.global_index,
+            segment.raw_range.start + merged_offset - segment.merged_range.start,
+        ))
+    }
+}
+
+/// How orphaned opens are paired with orphaned closes across lines. Fountain doesn't define this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FNRangedElementResolutionStrategy {
+    /// An orphaned open pairs with the next orphaned close.
+    #[default]
+    NextClose,
+    /// An orphaned open pairs with the last orphaned close it can reach, and everything in between is invisible.
+    /// For Notes, a close can't be reached past an empty line. This is closer to how Beat behaves.
+    LastClose,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNRangedElementDiagnosticKind {
+    /// An open pattern which never finds its close.
+    /// For Notes, this includes any open whose close only comes after an empty line.
+    UnclosedOpen,
+}
+
+/// A problem found while resolving the ranges of a ranged element, such as an unclosed `[[`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNRangedElementDiagnostic {
+    pub kind: FNRangedElementDiagnosticKind,
+    pub ranged_element_type: FNRangedElementType,
+    pub global_index: usize,
+    pub local_index: usize,
+}
+
+/// Every partial line and multiline range for a single `FNRangedElementType`.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FNRangedElementRanges {
+    pub partial_lines: Vec<FNPartialLineRange>,
+    pub multiline_ranges: Vec<FNPartialMultilineRange>,
+}
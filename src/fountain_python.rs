@@ -0,0 +1,112 @@
+//! Python bindings, enabled with the `python` feature.
+//!
+//! Build the Python module with `maturin build`, which uses the settings in `pyproject.toml`.
+//! The module is named `fountain_parser_rs`:
+//!
+//! ```python
+//! import fountain_parser_rs
+//!
+//! lines = fountain_parser_rs.parse(text)
+//! title_page = fountain_parser_rs.get_title_page(text)
+//! stats = fountain_parser_rs.get_stats(text, aliases={"JOEY": "JOE"})
+//! ```
+//!
+//! Everything is returned as plain lists and dicts, so results can go straight into a data frame.
+
+use std::collections::BTreeMap;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::fountain_characters::{get_cue_counts_for_characters, FNCharacterAliasMap};
+use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+
+/// Parses a document, and returns a dict for every line.
+#[pyfunction]
+fn parse<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyList>> {
+    let full = parse_document_full(text, &FNFullParseConfig::default());
+    let lines = PyList::empty(py);
+    for ln in &full.lines {
+        let line = PyDict::new(py);
+        line.set_item("type", ln.fn_type.to_string())?;
+        line.set_item("string", &ln.string)?;
+        line.set_item("raw_string", &ln.raw_string)?;
+        line.set_item("position", ln.position)?;
+        line.set_item("is_forced", ln.is_forced)?;
+        line.set_item("section_depth", ln.section_depth)?;
+        line.set_item("character", ln.get_character_name())?;
+        lines.append(line)?;
+    }
+    Ok(lines)
+}
+
+/// Returns the title page entries of a document, as dicts with a `key` and a list of `values`.
+#[pyfunction]
+fn get_title_page<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyList>> {
+    let full = parse_document_full(text, &FNFullParseConfig::default());
+    let entries = PyList::empty(py);
+    for entry in &full.title_page {
+        let dict = PyDict::new(py);
+        dict.set_item("key", &entry.key)?;
+        dict.set_item("values", &entry.values)?;
+        entries.append(dict)?;
+    }
+    Ok(entries)
+}
+
+/// Returns counts for a document: lines by type, scenes, pages, printed words (without the title page), and cues by character.
+///
+/// `aliases` maps an alias to its canonical name, such as `{"JOEY": "JOE"}`, so both are counted as one character.
+#[pyfunction]
+#[pyo3(signature = (text, aliases=None))]
+fn get_stats<'py>(
+    py: Python<'py>,
+    text: &str,
+    aliases: Option<BTreeMap<String, String>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let mut alias_map = FNCharacterAliasMap::new();
+    for (alias, canonical) in aliases.iter().flatten() {
+        alias_map.add_alias(alias, canonical);
+    }
+
+    let full = parse_document_full(text, &FNFullParseConfig::default());
+    let lines = &full.lines;
+
+    let mut line_types: BTreeMap<String, usize> = BTreeMap::new();
+    for ln in lines {
+        *line_types.entry(ln.fn_type.to_string()).or_insert(0) += 1;
+    }
+    let word_count: usize = get_printable_strings_for_lines(lines)
+        .iter()
+        .zip(lines)
+        .filter(|(_, ln)| !ln.fn_type.is_title_page_kind())
+        .map(|(printable, _)| printable.split_whitespace().count())
+        .sum();
+    let cues: BTreeMap<String, usize> = get_cue_counts_for_characters(lines, &alias_map)
+        .into_iter()
+        .collect();
+
+    let stats = PyDict::new(py);
+    stats.set_item("line_count", lines.len())?;
+    stats.set_item("line_types", line_types)?;
+    stats.set_item("scene_count", get_scenes_from_parsed_lines(lines).len())?;
+    stats.set_item(
+        "page_count",
+        get_pages(lines, &FNPaginationOptions::default(), &FNMonospaceMeasurer).len(),
+    )?;
+    stats.set_item("word_count", word_count)?;
+    stats.set_item("cues_by_character", cues)?;
+    Ok(stats)
+}
+
+#[pymodule]
+#[pyo3(name = "fountain_parser_rs")]
+fn fountain_python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(get_title_page, m)?)?;
+    m.add_function(wrap_pyfunction!(get_stats, m)?)?;
+    Ok(())
+}
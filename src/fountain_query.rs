@@ -0,0 +1,177 @@
+//! Finds lines by type, speaking character, location, and text, without hand-rolled traversal.
+//!
+//! An `FNQuery` can be built with its `with_*` methods, or parsed from a short pattern:
+//!
+//! ```text
+//! Dialogue character="JANE" location="BAR"
+//! Dialogue|Parenthetical character=JANE text="gun"
+//! ```
+//!
+//! Bare words are line type names (as printed by `Display`), and `|` separates alternatives.
+//! `key=value` pairs filter by `character`, `location`, or `text`. Values may be quoted to include spaces.
+//! Every filter must match; a query without filters matches every line.
+
+use std::str::FromStr;
+
+use crate::fountain_characters::{get_speaking_character_for_line_index, FNCharacterAliasMap};
+use crate::fountain_enums::FNLineType;
+use crate::fountain_heading::get_scene_heading_from_line;
+use crate::fountain_line::FNLine;
+use crate::fountain_locations::get_normalized_location;
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNQuery {
+    /// If not empty, only lines of these types match
+    pub line_types: Vec<FNLineType>,
+    /// Only lines spoken by this character (cues included) match. Compared by canonical name, ignoring case.
+    pub character: Option<String>,
+    /// Only lines in scenes whose normalized location contains these words match, such as `BAR` for `INT. JOE'S BAR`
+    pub location: Option<String>,
+    /// Only lines whose `string` contains this text match. Case is ignored.
+    pub text: Option<String>,
+}
+
+impl FNQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_line_type(mut self, fn_type: FNLineType) -> Self {
+        self.line_types.push(fn_type);
+        self
+    }
+
+    pub fn with_character(mut self, character: &str) -> Self {
+        self.character = Some(character.to_string());
+        self
+    }
+
+    pub fn with_location(mut self, location: &str) -> Self {
+        self.location = Some(location.to_string());
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+}
+
+/// Parses a query pattern, such as `Dialogue character="JANE" location="BAR"`.
+///
+/// Returns `None` for unknown line types, unknown keys, or unclosed quotes.
+pub fn get_query_from_pattern(pattern: &str) -> Option<FNQuery> {
+    let mut query = FNQuery::new();
+    for term in get_pattern_terms(pattern)? {
+        match term.split_once('=') {
+            Some((key, value)) => {
+                let value = Some(value.to_string());
+                match key.trim().to_lowercase().as_str() {
+                    "character" => query.character = value,
+                    "location" => query.location = value,
+                    "text" => query.text = value,
+                    _ => return None,
+                }
+            }
+            None => {
+                for name in term.split('|') {
+                    query.line_types.push(FNLineType::from_str(name).ok()?);
+                }
+            }
+        }
+    }
+    Some(query)
+}
+
+/// Splits a pattern on whitespace outside of double quotes, and removes the quotes.
+fn get_pattern_terms(pattern: &str) -> Option<Vec<String>> {
+    let mut terms: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut is_quoted = false;
+    for c in pattern.chars() {
+        match c {
+            '"' => is_quoted = !is_quoted,
+            c if c.is_whitespace() && !is_quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if is_quoted {
+        return None;
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    Some(terms)
+}
+
+/// Returns the global index of every line which matches the query, in document order.
+///
+/// Character names are merged with the `aliases`, so a query for a canonical name also matches its aliases.
+pub fn get_query_matches(
+    lines: &[FNLine],
+    query: &FNQuery,
+    aliases: &FNCharacterAliasMap,
+) -> Vec<usize> {
+    // Canonical names are compared case-folded, so `@Jane` and `JANE` are the same character
+    let character = query
+        .character
+        .as_ref()
+        .map(|name| aliases.get_canonical_name(name).to_lowercase());
+    let location = query
+        .location
+        .as_ref()
+        .map(|location| format!(" {} ", get_normalized_location(location)));
+    let text = query.text.as_ref().map(|text| text.to_lowercase());
+
+    // The location of every line's scene, only computed when the query needs it
+    let mut line_locations: Vec<Option<String>> = Vec::new();
+    if location.is_some() {
+        line_locations = vec![None; lines.len()];
+        for scene in get_scenes_from_parsed_lines(lines) {
+            let scene_location = scene
+                .get_heading_line(lines)
+                .and_then(get_scene_heading_from_line)
+                .map(|heading| format!(" {} ", get_normalized_location(&heading.location)));
+            for slot in line_locations.iter_mut().take(scene.end).skip(scene.start) {
+                slot.clone_from(&scene_location);
+            }
+        }
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, ln)| query.line_types.is_empty() || query.line_types.contains(&ln.fn_type))
+        .filter(|(_, ln)| match &text {
+            Some(text) => ln.string.to_lowercase().contains(text),
+            None => true,
+        })
+        .filter(|(idx, _)| match &location {
+            Some(location) => line_locations[*idx]
+                .as_ref()
+                .is_some_and(|scene_location| scene_location.contains(location.as_str())),
+            None => true,
+        })
+        .filter(|(idx, _)| match &character {
+            Some(character) => get_speaking_character_for_line_index(lines, *idx)
+                .is_some_and(|name| aliases.get_canonical_name(&name).to_lowercase() == *character),
+            None => true,
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Parses `pattern` and returns the global index of every matching line, or `None` if the pattern is invalid.
+pub fn get_pattern_matches(lines: &[FNLine], pattern: &str) -> Option<Vec<usize>> {
+    let query = get_query_from_pattern(pattern)?;
+    Some(get_query_matches(
+        lines,
+        &query,
+        &FNCharacterAliasMap::default(),
+    ))
+}
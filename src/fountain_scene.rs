@@ -0,0 +1,134 @@
+//! Groups parsed `FNLine` objects into scenes.
+//!
+//! A scene starts at a `Heading` line and runs until the next `Heading` (or the end of the document).
+//! Any non-title-page text before the first heading is treated as its own scene, without a heading.
+//!
+//! Scenes whose heading has a time of day like `CONTINUOUS`, `SAME`, or `MOMENTS LATER` are linked
+//! to the scene before them, so that continuous sequences can be followed.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_heading::{get_scene_heading_from_line, FNTimeModifier};
+use crate::fountain_line::FNLine;
+
+/// A range of lines which make up a single scene.
+///
+/// `start` is the global index of the first line in the scene, and `end` is exclusive.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNScene {
+    pub heading_index: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+    pub time_modifier: Option<FNTimeModifier>,
+    /// Index of the scene this one directly follows, if the heading has a `time_modifier`
+    pub continues_from: Option<usize>,
+}
+
+impl FNScene {
+    pub fn contains_line_index(&self, global_index: usize) -> bool {
+        global_index >= self.start && global_index < self.end
+    }
+
+    /// Returns the heading `FNLine` for this scene, if it has one.
+    pub fn get_heading_line<'a>(&self, lines: &'a [FNLine]) -> Option<&'a FNLine> {
+        self.heading_index.and_then(|idx| lines.get(idx))
+    }
+
+    pub fn get_lines<'a>(&self, lines: &'a [FNLine]) -> &'a [FNLine] {
+        let end = self.end.min(lines.len());
+        let start = self.start.min(end);
+        &lines[start..end]
+    }
+}
+
+/// Returns a `Vector` of `FNScene` objects for a list of already-parsed lines.
+pub fn get_scenes_from_parsed_lines(lines: &[FNLine]) -> Vec<FNScene> {
+    let mut scenes: Vec<FNScene> = Vec::new();
+
+    let first_content_index = lines
+        .iter()
+        .position(|ln| !ln.is_title_page() && ln.fn_type != FNLineType::TitlePageUnknown);
+
+    let Some(first_content_index) = first_content_index else {
+        return scenes;
+    };
+
+    let mut current_scene: Option<FNScene> = None;
+
+    for (idx, ln) in lines.iter().enumerate().skip(first_content_index) {
+        if ln.fn_type == FNLineType::Heading {
+            if let Some(mut scene) = current_scene.take() {
+                scene.end = idx;
+                scenes.push(scene);
+            }
+            let time_modifier =
+                get_scene_heading_from_line(ln).and_then(|heading| heading.get_time_modifier());
+            let continues_from = match (&time_modifier, scenes.len()) {
+                (Some(_), len) if len > 0 => Some(len - 1),
+                _ => None,
+            };
+            current_scene = Some(FNScene {
+                heading_index: Some(idx),
+                start: idx,
+                end: idx + 1,
+                time_modifier,
+                continues_from,
+            });
+            continue;
+        }
+
+        if current_scene.is_none() && ln.fn_type != FNLineType::Empty {
+            current_scene = Some(FNScene {
+                heading_index: None,
+                start: first_content_index,
+                end: idx + 1,
+                ..Default::default()
+            });
+        }
+    }
+
+    if let Some(mut scene) = current_scene {
+        scene.end = lines.len();
+        scenes.push(scene);
+    }
+
+    scenes
+}
+
+/// Returns the index of the scene which contains the given global line index, if any.
+pub fn get_scene_index_for_line_index(scenes: &[FNScene], global_index: usize) -> Option<usize> {
+    scenes
+        .iter()
+        .position(|scene| scene.contains_line_index(global_index))
+}
+
+/// Returns every continuous sequence of scenes, as lists of scene indices.
+///
+/// Only sequences with at least two scenes are returned. The first scene of each sequence
+/// is the one that the following `CONTINUOUS` / `SAME` / `LATER` scenes continue from.
+pub fn get_continuous_scene_sequences(scenes: &[FNScene]) -> Vec<Vec<usize>> {
+    let mut sequences: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+
+    for (idx, scene) in scenes.iter().enumerate() {
+        match scene.continues_from {
+            Some(previous) if current.last() == Some(&previous) => current.push(idx),
+            Some(previous) => {
+                if current.len() > 1 {
+                    sequences.push(current);
+                }
+                current = vec![previous, idx];
+            }
+            None => {
+                if current.len() > 1 {
+                    sequences.push(current);
+                }
+                current = vec![idx];
+            }
+        }
+    }
+    if current.len() > 1 {
+        sequences.push(current);
+    }
+
+    sequences
+}
@@ -0,0 +1,80 @@
+//! Scrubs a draft for production: every note, boneyard, synopsis, and section is taken out of the Fountain text,
+//! so that a clean draft can go to people who shouldn't see the writers' internal notes:
+//!
+//! ```text
+//! # Act One                              INT. BAR - NIGHT
+//! = Joe waits for Jane.
+//!                                    ->  Joe waits.
+//! INT. BAR - NIGHT
+//!
+//! Joe waits. [[too slow?]]
+//! /* Jane never shows. */
+//! ```
+//!
+//! Lines are stripped the way `parse_stripped` strips them, so a note or boneyard which spans several lines joins
+//! the text around it, and lines which only held notes or boneyards are dropped along with the empty line they
+//! leave behind.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_stripped::parse_stripped;
+use crate::static_fountain_preparser::get_text_from_lines;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNScrubOptions {
+    /// If `true`, sections (`# Act One`) are kept, such as for a draft which is still split into acts
+    pub keep_sections: bool,
+}
+
+/// Returns the Fountain text of the lines without notes, boneyards, synopses, and (unless `keep_sections`) sections.
+/// Text which was next to a removed note or boneyard loses its trailing whitespace. Each line keeps the line break
+/// of the last raw line it was made from.
+pub fn scrub_for_production(lines: &[FNLine], options: &FNScrubOptions) -> String {
+    let (stripped_lines, map) = parse_stripped(lines);
+    let mut scrubbed: Vec<FNLine> = Vec::with_capacity(stripped_lines.len());
+    // Whether a line was removed since the last line with text
+    let mut removed_line = false;
+    let mut next_raw_index: usize = 0;
+
+    for (ln, source) in stripped_lines.iter().zip(&map.lines) {
+        if source.raw_lines.start > next_raw_index {
+            removed_line = true;
+        }
+        next_raw_index = source.raw_lines.end;
+
+        let is_removed = match ln.fn_type {
+            FNLineType::Synopse => true,
+            FNLineType::Section => !options.keep_sections,
+            _ => false,
+        };
+        if is_removed {
+            removed_line = true;
+            continue;
+        }
+        let text = if ln.string == ln.raw_string {
+            ln.string.as_str()
+        } else {
+            ln.string.trim_end()
+        };
+        if text.is_empty() {
+            // A removed line leaves its empty lines behind, which would split or pad the elements around it
+            if removed_line
+                && scrubbed
+                    .last()
+                    .is_none_or(|last| last.raw_string.is_empty())
+            {
+                continue;
+            }
+        } else {
+            removed_line = false;
+        }
+        scrubbed.push(FNLine {
+            string: text.to_string(),
+            raw_string: text.to_string(),
+            terminator_length: lines[source.raw_lines.end - 1].terminator_length,
+            ..Default::default()
+        });
+    }
+
+    get_text_from_lines(&scrubbed)
+}
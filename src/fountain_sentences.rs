@@ -0,0 +1,161 @@
+//! Splits action and dialogue blocks into sentences, with ranges pointing back into the raw lines.
+//!
+//! Sentences may run across several lines of the same block, so each `FNSentence` records both where it starts
+//! and where it ends. Notes, boneyards, and emphasis markers are not part of the sentence text.
+//! This is useful for style checkers and text-to-speech chunking.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::{get_prose_chunks_for_spellcheck, FNTextChunk};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNSentenceBlockKind {
+    Action,
+    Dialogue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNSentence {
+    pub text: String,
+    pub kind: FNSentenceBlockKind,
+    pub start_line_index: usize,
+    /// Byte index into the `raw_string` of the start line
+    pub start_local_index: usize,
+    pub end_line_index: usize,
+    /// Byte index into the `raw_string` of the end line (exclusive)
+    pub end_local_index: usize,
+}
+
+// Maps a piece of the joined block text back to its chunk
+struct FNBlockSegment {
+    block_start: usize,
+    chunk: FNTextChunk,
+}
+
+/// Returns every sentence in the action and dialogue blocks of the parsed lines, in document order.
+pub fn get_sentences_from_lines(lines: &[FNLine]) -> Vec<FNSentence> {
+    let chunks = get_prose_chunks_for_spellcheck(lines);
+    let mut sentences: Vec<FNSentence> = Vec::new();
+
+    let mut block_kind: Option<FNSentenceBlockKind> = None;
+    let mut block_chunks: Vec<FNTextChunk> = Vec::new();
+
+    for chunk in chunks {
+        let kind = match get_block_kind(&lines[chunk.line_index].fn_type) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let continues_block = block_chunks.last().is_some_and(|last: &FNTextChunk| {
+            block_kind.as_ref() == Some(&kind)
+                && (last.line_index == chunk.line_index
+                    || is_same_block(lines, last.line_index, chunk.line_index))
+        });
+        if !continues_block && !block_chunks.is_empty() {
+            if let Some(previous_kind) = block_kind.take() {
+                sentences.extend(get_sentences_from_block(
+                    lines,
+                    &block_chunks,
+                    previous_kind,
+                ));
+            }
+            block_chunks.clear();
+        }
+        block_kind = Some(kind);
+        block_chunks.push(chunk);
+    }
+    if let Some(kind) = block_kind {
+        sentences.extend(get_sentences_from_block(lines, &block_chunks, kind));
+    }
+
+    sentences
+}
+
+fn get_block_kind(fn_type: &FNLineType) -> Option<FNSentenceBlockKind> {
+    match fn_type {
+        FNLineType::Action => Some(FNSentenceBlockKind::Action),
+        FNLineType::Dialogue | FNLineType::DualDialogue => Some(FNSentenceBlockKind::Dialogue),
+        _ => None,
+    }
+}
+
+// Two lines are in the same block if every line from `first` to `second` has the same line type
+fn is_same_block(lines: &[FNLine], first: usize, second: usize) -> bool {
+    let first_type = &lines[first].fn_type;
+    lines[first..=second]
+        .iter()
+        .all(|ln| ln.fn_type == *first_type)
+}
+
+fn get_sentences_from_block(
+    lines: &[FNLine],
+    block_chunks: &[FNTextChunk],
+    kind: FNSentenceBlockKind,
+) -> Vec<FNSentence> {
+    let mut block_text = String::new();
+    let mut segments: Vec<FNBlockSegment> = Vec::new();
+
+    for (idx, chunk) in block_chunks.iter().enumerate() {
+        if idx > 0 {
+            let previous = &block_chunks[idx - 1];
+            let needs_space = previous.line_index != chunk.line_index
+                || lines[chunk.line_index].raw_string[previous.local_end..chunk.local_start]
+                    .chars()
+                    .any(char::is_whitespace);
+            if needs_space {
+                block_text.push(' ');
+            }
+        }
+        segments.push(FNBlockSegment {
+            block_start: block_text.len(),
+            chunk: chunk.clone(),
+        });
+        block_text.push_str(&chunk.text);
+    }
+
+    let mut sentences: Vec<FNSentence> = Vec::new();
+    for (start, sentence) in block_text.split_sentence_bound_indices() {
+        let trimmed = sentence.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let start = start + (sentence.len() - sentence.trim_start().len());
+        let end = start + trimmed.len();
+        let (start_line_index, start_local_index) = get_local_position(&segments, start, false);
+        let (end_line_index, end_local_index) = get_local_position(&segments, end, true);
+        sentences.push(FNSentence {
+            text: trimmed.to_string(),
+            kind: kind.clone(),
+            start_line_index,
+            start_local_index,
+            end_line_index,
+            end_local_index,
+        });
+    }
+    sentences
+}
+
+fn get_local_position(
+    segments: &[FNBlockSegment],
+    block_offset: usize,
+    is_end: bool,
+) -> (usize, usize) {
+    let segment = segments
+        .iter()
+        .rev()
+        .find(|seg| {
+            if is_end {
+                seg.block_start < block_offset
+            } else {
+                seg.block_start <= block_offset
+            }
+        })
+        .unwrap_or(&segments[0]);
+    let offset_in_chunk = (block_offset - segment.block_start.min(block_offset))
+        .min(segment.chunk.local_end - segment.chunk.local_start);
+    (
+        segment.chunk.line_index,
+        segment.chunk.local_start + offset_in_chunk,
+    )
+}
@@ -0,0 +1,157 @@
+//! Extracts shot metadata (such as `CLOSE ON` or `POV`) from `Shot` lines, for shot-list generation.
+//!
+//! Shots may be forced with `!!`, but many writers just type them as ALL CAPS action lines.
+//! Detecting shots in ALL CAPS action lines is optional, see `FNShotParsingOptions`.
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNShotKind {
+    CloseUp,
+    ExtremeCloseUp,
+    Medium,
+    Wide,
+    Angle,
+    PointOfView,
+    Insert,
+    OverTheShoulder,
+    TwoShot,
+    Tracking,
+    Aerial,
+    BackTo,
+    /// A forced `Shot` line which doesn't start with a known prefix
+    Other,
+}
+
+// Longer prefixes first, so that `EXTREME CLOSE UP` isn't matched as `CLOSE UP`
+const SHOT_PREFIXES: [(&str, FNShotKind); 22] = [
+    ("EXTREME CLOSE UP", FNShotKind::ExtremeCloseUp),
+    ("EXTREME CLOSEUP", FNShotKind::ExtremeCloseUp),
+    ("EXTREME CLOSE ON", FNShotKind::ExtremeCloseUp),
+    ("ECU", FNShotKind::ExtremeCloseUp),
+    ("CLOSE UP", FNShotKind::CloseUp),
+    ("CLOSEUP", FNShotKind::CloseUp),
+    ("CLOSE ON", FNShotKind::CloseUp),
+    ("CU", FNShotKind::CloseUp),
+    ("MEDIUM SHOT", FNShotKind::Medium),
+    ("MEDIUM ON", FNShotKind::Medium),
+    ("WIDE SHOT", FNShotKind::Wide),
+    ("WIDE ON", FNShotKind::Wide),
+    ("ANGLE ON", FNShotKind::Angle),
+    ("POV", FNShotKind::PointOfView),
+    ("P.O.V.", FNShotKind::PointOfView),
+    ("INSERT", FNShotKind::Insert),
+    ("OVER THE SHOULDER", FNShotKind::OverTheShoulder),
+    ("OTS", FNShotKind::OverTheShoulder),
+    ("TWO SHOT", FNShotKind::TwoShot),
+    ("TRACKING", FNShotKind::Tracking),
+    ("AERIAL", FNShotKind::Aerial),
+    ("BACK TO", FNShotKind::BackTo),
+];
+
+const POV_SUFFIXES: [&str; 2] = ["'S POV", " POV"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNShotParsingOptions {
+    /// If `true`, ALL CAPS `Action` lines which start with a known shot prefix are also treated as shots.
+    pub detect_uppercase_action: bool,
+}
+
+impl Default for FNShotParsingOptions {
+    fn default() -> Self {
+        FNShotParsingOptions {
+            detect_uppercase_action: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNShot {
+    pub line_index: usize,
+    pub kind: FNShotKind,
+    /// The text after the shot prefix, such as `THE KNIFE` in `CLOSE ON: THE KNIFE`
+    pub subject: String,
+    pub is_forced: bool,
+}
+
+/// Returns every shot in the document, in order.
+pub fn get_shots_from_lines(lines: &[FNLine], options: &FNShotParsingOptions) -> Vec<FNShot> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, ln)| get_shot_from_line(ln, idx, options))
+        .collect()
+}
+
+/// Returns an `FNShot` for a single parsed line, or `None` if the line isn't a shot.
+pub fn get_shot_from_line(
+    line: &FNLine,
+    line_index: usize,
+    options: &FNShotParsingOptions,
+) -> Option<FNShot> {
+    match line.fn_type {
+        FNLineType::Shot => {
+            let text = line.string.trim();
+            let text = text.strip_prefix("!!").unwrap_or(text).trim();
+            let (kind, subject) =
+                get_shot_kind_and_subject(text).unwrap_or((FNShotKind::Other, text.to_string()));
+            Some(FNShot {
+                line_index,
+                kind,
+                subject,
+                is_forced: true,
+            })
+        }
+        FNLineType::Action if options.detect_uppercase_action => {
+            let text = line.string.trim();
+            if text.is_empty() || text != text.to_uppercase() || line.is_forced {
+                return None;
+            }
+            let (kind, subject) = get_shot_kind_and_subject(text)?;
+            Some(FNShot {
+                line_index,
+                kind,
+                subject,
+                is_forced: false,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn get_shot_kind_and_subject(text: &str) -> Option<(FNShotKind, String)> {
+    // Only ASCII is uppercased, so that every index into `upper` is also an index into `text`
+    let upper = text.to_ascii_uppercase();
+
+    for (prefix, kind) in SHOT_PREFIXES.iter() {
+        if !upper.starts_with(prefix) {
+            continue;
+        }
+        let rest = &text[prefix.len()..];
+        // The prefix has to be a whole word: `CUT` isn't `CU`
+        if rest
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '\'')
+        {
+            continue;
+        }
+        let subject = rest
+            .trim_start_matches(|c: char| c == ':' || c == '-' || c == '.' || c.is_whitespace())
+            .trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+        return Some((kind.clone(), subject.to_string()));
+    }
+
+    // Handle `JOHN'S POV` and `JOHN POV`
+    for suffix in POV_SUFFIXES {
+        if let Some(subject) = upper.strip_suffix(suffix) {
+            return Some((
+                FNShotKind::PointOfView,
+                text[..subject.len()].trim().to_string(),
+            ));
+        }
+    }
+
+    None
+}
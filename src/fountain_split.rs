@@ -0,0 +1,112 @@
+//! Splits a document into one Fountain snippet per scene, for workflows that store every scene in its own file.
+//!
+//! Each snippet holds the raw text of a single scene, without the title page. Filenames are made of the
+//! scene number (the heading's `#12#` number, or the scene's position in the document) and a slug of the heading:
+//!
+//! ```text
+//! INT. JOE'S BAR - NIGHT #12#   ->   12-int-joes-bar-night.fountain
+//! EXT. STREET - DAY             ->   002-ext-street-day.fountain
+//! ```
+
+use std::collections::HashSet;
+
+use crate::fountain_heading::get_scene_heading_from_line;
+use crate::fountain_line::FNLine;
+use crate::fountain_scene::get_scenes_from_parsed_lines;
+
+/// The text of a single scene, and a filename to store it under.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNSceneSnippet {
+    pub scene_index: usize,
+    pub heading_index: Option<usize>,
+    /// The raw lines of the scene, without leading or trailing empty lines, and ending with a newline
+    pub text: String,
+    /// A filename with the `.fountain` extension. Filenames are unique within a single split.
+    pub filename: String,
+}
+
+/// Returns a snippet for every scene in the document, in order.
+///
+/// Text before the first heading (other than the title page) gets its own snippet, with the slug `untitled`.
+pub fn split_by_scene(lines: &[FNLine]) -> Vec<FNSceneSnippet> {
+    let mut used_filenames: HashSet<String> = HashSet::new();
+
+    get_scenes_from_parsed_lines(lines)
+        .iter()
+        .enumerate()
+        .map(|(scene_index, scene)| {
+            let scene_lines = scene.get_lines(lines);
+            let first = scene_lines
+                .iter()
+                .position(|ln| !ln.raw_string.trim().is_empty())
+                .unwrap_or(scene_lines.len());
+            let last = scene_lines
+                .iter()
+                .rposition(|ln| !ln.raw_string.trim().is_empty())
+                .map_or(first, |idx| idx + 1);
+            let text: String = scene_lines[first..last]
+                .iter()
+                .map(|ln| format!("{}\n", ln.raw_string))
+                .collect();
+
+            let heading = scene
+                .get_heading_line(lines)
+                .and_then(get_scene_heading_from_line);
+            let number = heading
+                .as_ref()
+                .and_then(|heading| heading.scene_number.clone())
+                .unwrap_or_else(|| format!("{:03}", scene_index + 1));
+            let slug = heading
+                .map(|heading| {
+                    get_slug(&format!(
+                        "{} {} {}",
+                        heading.prefix,
+                        heading.location,
+                        heading.time_of_day.unwrap_or_default()
+                    ))
+                })
+                .filter(|slug| !slug.is_empty())
+                .unwrap_or_else(|| String::from("untitled"));
+            let filename = get_unique_filename(
+                &format!("{}-{}", get_slug(&number), slug),
+                &mut used_filenames,
+            );
+
+            FNSceneSnippet {
+                scene_index,
+                heading_index: scene.heading_index,
+                text,
+                filename,
+            }
+        })
+        .collect()
+}
+
+/// Returns a lowercase slug of the text: letters and digits, separated by single `-`. Apostrophes are removed.
+fn get_slug(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\'' | '\u{2019}' => {}
+            c if c.is_alphanumeric() => slug.extend(c.to_lowercase()),
+            _ => {
+                if !slug.is_empty() && !slug.ends_with('-') {
+                    slug.push('-');
+                }
+            }
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+// Repeated scene numbers and headings get a `-2`, `-3`, ... suffix
+fn get_unique_filename(stem: &str, used_filenames: &mut HashSet<String>) -> String {
+    let mut filename = format!("{}.fountain", stem);
+    let mut suffix = 2;
+    while used_filenames.contains(&filename) {
+        filename = format!("{}-{}.fountain", stem, suffix);
+        suffix += 1;
+    }
+    used_filenames.insert(filename.clone());
+    filename
+}
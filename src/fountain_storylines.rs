@@ -0,0 +1,94 @@
+//! Storylines (plotlines), tagged with notes, as in Beat:
+//!
+//! ```text
+//! INT. BAR - NIGHT [[storyline: A-plot]]
+//!
+//! Joe and Jane argue. [[storyline: B-plot, Romance]]
+//! ```
+//!
+//! Both `storyline:` and Beat's `beat:` keys are recognized, and a single note can hold several storylines,
+//! separated by commas. Names are matched without case, and keep the spelling of their first tag.
+//! A tag anywhere in a scene puts the whole scene in the storyline.
+
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::fountain_line::FNLine;
+use crate::fountain_notes::get_notes;
+use crate::fountain_scene::{get_scene_index_for_line_index, get_scenes_from_parsed_lines};
+
+// Matched against the text of a note, between its markers
+const STORYLINE_NOTE_PATTERN: &str = r"(?is)^\s*(?:storyline|beat)\s*:(.*)$";
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNStoryline {
+    pub name: String,
+    /// Global indices of every line with a tag for this storyline, in document order
+    pub line_indices: Vec<usize>,
+    /// Indices of every scene with a tag for this storyline, in document order
+    pub scene_indices: Vec<usize>,
+}
+
+impl FNStoryline {
+    /// Returns runs of consecutive scenes in this storyline, as ranges of scene indices.
+    pub fn get_scene_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for idx in &self.scene_indices {
+            match ranges.last_mut() {
+                Some(range) if range.end == *idx => range.end = idx + 1,
+                _ => ranges.push(*idx..idx + 1),
+            }
+        }
+        ranges
+    }
+}
+
+/// Returns every storyline tagged in the document, in order of their first tag.
+pub fn get_storylines(lines: &[FNLine]) -> Vec<FNStoryline> {
+    let re = Regex::new(STORYLINE_NOTE_PATTERN).unwrap();
+    let scenes = get_scenes_from_parsed_lines(lines);
+    let mut storylines: Vec<FNStoryline> = Vec::new();
+
+    for note in get_notes(lines) {
+        let line_index = note.start_index;
+        if let Some(caps) = re.captures(&note.text) {
+            for name in caps[1].split(',').map(|name| name.trim()) {
+                if name.is_empty() {
+                    continue;
+                }
+                let idx = match storylines
+                    .iter()
+                    .position(|storyline| storyline.name.to_lowercase() == name.to_lowercase())
+                {
+                    Some(idx) => idx,
+                    None => {
+                        storylines.push(FNStoryline {
+                            name: name.to_string(),
+                            ..Default::default()
+                        });
+                        storylines.len() - 1
+                    }
+                };
+                let storyline = &mut storylines[idx];
+                if storyline.line_indices.last() != Some(&line_index) {
+                    storyline.line_indices.push(line_index);
+                }
+                if let Some(scene_index) = get_scene_index_for_line_index(&scenes, line_index) {
+                    if storyline.scene_indices.last() != Some(&scene_index) {
+                        storyline.scene_indices.push(scene_index);
+                    }
+                }
+            }
+        }
+    }
+
+    storylines
+}
+
+/// Returns the storyline with the given name (matched without case), if it's tagged anywhere in the document.
+pub fn get_storyline(lines: &[FNLine], name: &str) -> Option<FNStoryline> {
+    get_storylines(lines)
+        .into_iter()
+        .find(|storyline| storyline.name.to_lowercase() == name.trim().to_lowercase())
+}
@@ -0,0 +1,128 @@
+//! Parses a document as if its notes and boneyards had been deleted, while keeping track of where the text came from.
+//!
+//! Notes and boneyards can change the meaning of the lines around them: `BOB [[note]]` should still be a
+//! character cue, and a boneyard which spans several lines joins the text before and after it into one line.
+//! `parse_stripped` classifies the stripped text, and returns an `FNStrippedMap` to map every stripped line
+//! and byte offset back to the raw lines.
+
+use std::ops::Range;
+
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_invisible_byte_ranges_for_lines;
+use crate::static_fountain_parser::get_parsed_lines_from_line_vec;
+
+/// A visible piece of a raw line, and where it was placed in the stripped line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNStrippedSegment {
+    pub raw_line_index: usize,
+    /// Byte range of the raw line's `raw_string`
+    pub raw_range: Range<usize>,
+    /// Byte offset of the segment in the stripped line's `string`
+    pub stripped_start: usize,
+}
+
+/// The raw lines which make up one stripped line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNStrippedLineSource {
+    /// More than one line only when a note or boneyard joins lines together
+    pub raw_lines: Range<usize>,
+    pub segments: Vec<FNStrippedSegment>,
+}
+
+/// Maps stripped lines back to raw lines. Raw lines which only hold notes or boneyards have no stripped line.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNStrippedMap {
+    pub lines: Vec<FNStrippedLineSource>,
+}
+
+impl FNStrippedMap {
+    pub fn get_raw_line_range(&self, stripped_index: usize) -> Option<Range<usize>> {
+        self.lines
+            .get(stripped_index)
+            .map(|source| source.raw_lines.clone())
+    }
+
+    /// Returns the stripped line which holds the text of a raw line, if any.
+    pub fn get_stripped_index_for_raw_line(&self, raw_index: usize) -> Option<usize> {
+        let idx = self
+            .lines
+            .partition_point(|source| source.raw_lines.end <= raw_index);
+        self.lines
+            .get(idx)
+            .filter(|source| source.raw_lines.start <= raw_index)
+            .map(|_| idx)
+    }
+
+    /// Returns the raw line index and the byte offset in its `raw_string` for a byte offset of a stripped line.
+    ///
+    /// The offset just past the end of the stripped line maps to the end of its last segment.
+    pub fn get_raw_position(
+        &self,
+        stripped_index: usize,
+        stripped_offset: usize,
+    ) -> Option<(usize, usize)> {
+        let source = self.lines.get(stripped_index)?;
+        let idx = source
+            .segments
+            .partition_point(|segment| segment.stripped_start <= stripped_offset);
+        let Some(segment) = idx.checked_sub(1).map(|idx| &source.segments[idx]) else {
+            // An empty line has no segments
+            return (stripped_offset == 0).then_some((source.raw_lines.start, 0));
+        };
+        let raw_offset = segment.raw_range.start + stripped_offset - segment.stripped_start;
+        (raw_offset <= segment.raw_range.end).then_some((segment.raw_line_index, raw_offset))
+    }
+}
+
+/// Parses unparsed lines with their notes and boneyards removed.
+///
+/// Every returned line's `string` is the stripped text, while `raw_string` holds the raw lines it was made from
+/// (joined by `\n`) and `position` is the position of its first raw line.
+pub fn parse_stripped(lines: &[FNLine]) -> (Vec<FNLine>, FNStrippedMap) {
+    let invisible = get_invisible_byte_ranges_for_lines(lines);
+    let mut stripped_lines: Vec<FNLine> = Vec::new();
+    let mut map = FNStrippedMap::default();
+
+    let mut idx: usize = 0;
+    while idx < lines.len() {
+        let start = idx;
+        let mut string = String::new();
+        let mut segments: Vec<FNStrippedSegment> = Vec::new();
+        loop {
+            let hidden = invisible[idx].notes.union(&invisible[idx].boneyards);
+            for range in hidden.get_complement(0..lines[idx].raw_string.len()).iter() {
+                segments.push(FNStrippedSegment {
+                    raw_line_index: idx,
+                    raw_range: range.clone(),
+                    stripped_start: string.len(),
+                });
+                string.push_str(&lines[idx].raw_string[range.clone()]);
+            }
+            idx += 1;
+            if !invisible[idx - 1].is_open_at_end || idx >= lines.len() {
+                break;
+            }
+        }
+
+        let raw_strings: Vec<&str> = lines[start..idx]
+            .iter()
+            .map(|ln| ln.raw_string.as_str())
+            .collect();
+        // Lines which only held invisible text are left out, so they don't split up the elements around them
+        if string.trim().is_empty() && raw_strings.iter().any(|raw| !raw.trim().is_empty()) {
+            continue;
+        }
+
+        stripped_lines.push(FNLine {
+            string,
+            raw_string: raw_strings.join("\n"),
+            ..lines[start].clone()
+        });
+        map.lines.push(FNStrippedLineSource {
+            raw_lines: start..idx,
+            segments,
+        });
+    }
+
+    (get_parsed_lines_from_line_vec(stripped_lines), map)
+}
@@ -0,0 +1,136 @@
+//! Interned character names and locations, so that they can be compared by id instead of by string.
+//!
+//! Character names and locations repeat hundreds of times in a script. An `FNSymbolTable` stores each
+//! distinct name once and hands out a small `FNSymbol` for it. The table is scoped to a single document:
+//! symbols from different tables can't be compared.
+//!
+//! `get_document_symbols` interns the canonical character name of every dialogue line and the normalized
+//! location of every heading, so that reports and autocomplete can work with symbols directly.
+
+use std::collections::HashMap;
+
+use crate::fountain_characters::{get_speaking_character_for_line_index, FNCharacterAliasMap};
+use crate::fountain_heading::get_scene_heading_from_line;
+use crate::fountain_line::FNLine;
+use crate::fountain_locations::get_normalized_location;
+use crate::fountain_scene::FNScene;
+
+/// The id of an interned string in an `FNSymbolTable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FNSymbol(u32);
+
+impl FNSymbol {
+    pub fn get_index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNSymbolTable {
+    names: Vec<String>,
+    symbols: HashMap<String, FNSymbol>,
+}
+
+impl FNSymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the symbol for `name`, adding it to the table if it's new.
+    pub fn intern(&mut self, name: &str) -> FNSymbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = FNSymbol(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.symbols.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    /// Returns the symbol for `name`, if it has been interned.
+    pub fn get_symbol(&self, name: &str) -> Option<FNSymbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn get_name(&self, symbol: FNSymbol) -> Option<&str> {
+        self.names.get(symbol.get_index()).map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// The symbols of a document's characters and locations, by line.
+///
+/// Characters and locations share one table, so a character and a location with the same name get the same symbol.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNDocumentSymbols {
+    pub table: FNSymbolTable,
+    /// The canonical name of the speaking character of every cue, parenthetical, and dialogue line
+    pub line_characters: Vec<Option<FNSymbol>>,
+    /// The normalized location of every heading line
+    pub line_locations: Vec<Option<FNSymbol>>,
+}
+
+impl FNDocumentSymbols {
+    pub fn get_character_symbol_for_line(&self, global_index: usize) -> Option<FNSymbol> {
+        self.line_characters.get(global_index).copied().flatten()
+    }
+
+    pub fn get_location_symbol_for_line(&self, global_index: usize) -> Option<FNSymbol> {
+        self.line_locations.get(global_index).copied().flatten()
+    }
+
+    /// Returns the symbol of every character who speaks in the scene, in order of their first line.
+    pub fn get_character_symbols_for_scene(&self, scene: &FNScene) -> Vec<FNSymbol> {
+        let mut symbols: Vec<FNSymbol> = Vec::new();
+        for idx in scene.start..scene.end {
+            if let Some(symbol) = self.get_character_symbol_for_line(idx) {
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+        }
+        symbols
+    }
+
+    pub fn get_location_symbol_for_scene(&self, scene: &FNScene) -> Option<FNSymbol> {
+        self.get_location_symbol_for_line(scene.heading_index?)
+    }
+
+    /// Returns the global index of every line spoken by a character, cues included.
+    pub fn get_line_indices_for_character(&self, symbol: FNSymbol) -> Vec<usize> {
+        self.line_characters
+            .iter()
+            .enumerate()
+            .filter(|(_, character)| **character == Some(symbol))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+/// Interns the characters and locations of a document. Character names are merged with the `aliases`.
+pub fn get_document_symbols(lines: &[FNLine], aliases: &FNCharacterAliasMap) -> FNDocumentSymbols {
+    let mut symbols = FNDocumentSymbols {
+        line_characters: Vec::with_capacity(lines.len()),
+        line_locations: Vec::with_capacity(lines.len()),
+        ..Default::default()
+    };
+    for (idx, ln) in lines.iter().enumerate() {
+        let character = get_speaking_character_for_line_index(lines, idx)
+            .map(|name| symbols.table.intern(&aliases.get_canonical_name(&name)));
+        symbols.line_characters.push(character);
+
+        let location = get_scene_heading_from_line(ln)
+            .map(|heading| get_normalized_location(&heading.location))
+            .filter(|location| !location.is_empty())
+            .map(|location| symbols.table.intern(&location));
+        symbols.line_locations.push(location);
+    }
+    symbols
+}
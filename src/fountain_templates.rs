@@ -0,0 +1,104 @@
+//! Starter documents for new scripts, with a title page and a skeleton of sections.
+//!
+//! These are meant for "New file" flows: the returned text is valid Fountain, ready to be written to disk.
+
+use crate::fountain_builder::FNScriptBuilder;
+
+/// The title page of a new script. Empty optional fields are left out of the title page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNTemplateTitlePage {
+    pub title: String,
+    pub author: String,
+    pub credit: String,
+    pub source: Option<String>,
+    pub draft_date: Option<String>,
+    /// May have several lines, such as an address
+    pub contact: Option<String>,
+}
+
+impl FNTemplateTitlePage {
+    pub fn new(title: &str, author: &str) -> Self {
+        FNTemplateTitlePage {
+            title: title.to_string(),
+            author: author.to_string(),
+            credit: String::from("Written by"),
+            source: None,
+            draft_date: None,
+            contact: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNTVEpisodeOptions {
+    pub has_teaser: bool,
+    pub act_count: usize,
+    pub has_tag: bool,
+}
+
+impl Default for FNTVEpisodeOptions {
+    fn default() -> Self {
+        FNTVEpisodeOptions {
+            has_teaser: true,
+            act_count: 4,
+            has_tag: false,
+        }
+    }
+}
+
+/// Returns a feature-length screenplay with a title page and three acts.
+pub fn new_screenplay(title_page: &FNTemplateTitlePage) -> String {
+    let mut builder = get_builder_with_title_page(title_page);
+    for act in ["Act One", "Act Two", "Act Three"] {
+        push_act(&mut builder, act, act == "Act One");
+    }
+    builder.get_text()
+}
+
+/// Returns a TV episode with a title page, and a section for the teaser, each act, and the tag.
+pub fn new_tv_episode(title_page: &FNTemplateTitlePage, options: &FNTVEpisodeOptions) -> String {
+    let mut builder = get_builder_with_title_page(title_page);
+    let mut sections: Vec<String> = Vec::new();
+    if options.has_teaser {
+        sections.push(String::from("Teaser"));
+    }
+    sections.extend((1..=options.act_count).map(|act| format!("Act {}", act)));
+    if options.has_tag {
+        sections.push(String::from("Tag"));
+    }
+
+    for (idx, section) in sections.iter().enumerate() {
+        push_act(&mut builder, section, idx == 0);
+    }
+    builder.get_text()
+}
+
+fn get_builder_with_title_page(title_page: &FNTemplateTitlePage) -> FNScriptBuilder {
+    let mut entries: Vec<(&str, &str)> = vec![
+        ("Title", title_page.title.as_str()),
+        ("Credit", title_page.credit.as_str()),
+        ("Author", title_page.author.as_str()),
+    ];
+    let optional_entries = [
+        ("Source", &title_page.source),
+        ("Draft date", &title_page.draft_date),
+        ("Contact", &title_page.contact),
+    ];
+    for (key, value) in optional_entries {
+        if let Some(value) = value {
+            entries.push((key, value.as_str()));
+        }
+    }
+
+    let mut builder = FNScriptBuilder::new();
+    builder.title_page(&entries);
+    builder
+}
+
+fn push_act(builder: &mut FNScriptBuilder, title: &str, is_first: bool) {
+    builder.section(title, 1);
+    if is_first {
+        builder.action("FADE IN:");
+    }
+    builder.scene("INT. LOCATION - DAY");
+}
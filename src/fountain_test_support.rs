@@ -0,0 +1,60 @@
+//! The fixture documents which this crate is tested against, enabled with the `test-support` feature.
+//!
+//! Downstream crates can run their own integration tests against the same documents.
+//! The fixtures are compiled into the crate, so they don't depend on the working directory.
+//!
+//! Every fixture has the expected `FNLineType` of each of its lines, as parsed by `parse_document_full`
+//! with the default config. Lines inside notes and boneyards keep the type of their text;
+//! they're hidden by the `note_ranges` and `omitted_ranges` of the line instead.
+
+use crate::fountain_enums::{FNLineType, FNUnknownLineTypeError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FNFixture {
+    pub name: &'static str,
+    pub text: &'static str,
+    // One line type name per line, as printed by `FNLineType`'s `Display`
+    expected_line_types: &'static str,
+}
+
+impl FNFixture {
+    /// Returns the expected type of every line of the fixture, or the first name in its expectations
+    /// which isn't a line type.
+    pub fn get_expected_line_types(&self) -> Result<Vec<FNLineType>, FNUnknownLineTypeError> {
+        self.expected_line_types
+            .lines()
+            .map(|name| name.parse())
+            .collect()
+    }
+}
+
+macro_rules! fixture {
+    ($name:literal) => {
+        FNFixture {
+            name: $name,
+            text: include_str!(concat!("../fountain_test_files/", $name, ".txt")),
+            expected_line_types: include_str!(concat!(
+                "../fountain_test_files/expected/",
+                $name,
+                ".line_types.txt"
+            )),
+        }
+    };
+}
+
+const FIXTURES: [FNFixture; 6] = [
+    fixture!("general_without_ranged_elements"),
+    fixture!("ranged_items_partial_line_test"),
+    fixture!("dual_dialogue"),
+    fixture!("emphasis"),
+    fixture!("title_page"),
+    fixture!("boneyard_spanning_scenes"),
+];
+
+pub fn get_fixtures() -> &'static [FNFixture] {
+    &FIXTURES
+}
+
+pub fn get_fixture(name: &str) -> Option<&'static FNFixture> {
+    FIXTURES.iter().find(|fixture| fixture.name == name)
+}
@@ -0,0 +1,517 @@
+//! Extracts the prose of a document (action, dialogue, synopses, etc.) as chunks of text with their original ranges.
+//!
+//! This is meant for running a spellchecker in a host app: character cues, headings, notes, boneyards,
+//! forcing characters, and emphasis markers are all skipped, and every chunk records exactly where it
+//! came from, so that results can be mapped back to the document.
+
+use crate::fountain_emphasis::{get_emphasis_spans_from_string, FNEmphasisKind, FNEmphasisOptions};
+use crate::fountain_enums::{FNLineType, FNRangedElementType};
+use crate::fountain_line::FNLine;
+use crate::helper_funcs::is_empty_line_for_ranged_elements;
+use crate::intervals::RangeSet;
+
+const BONEYARD_OPEN: &str = "/*";
+const BONEYARD_CLOSE: &str = "*/";
+
+/// A contiguous piece of visible text from a single line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNTextChunk {
+    pub text: String,
+    pub line_index: usize,
+    /// Byte index into the line's `raw_string` where this chunk starts
+    pub local_start: usize,
+    /// Byte index into the line's `raw_string` where this chunk ends (exclusive)
+    pub local_end: usize,
+}
+
+/// Returns `true` for the line types whose text is prose, and should be spellchecked.
+pub fn is_prose_line_type(fn_type: &FNLineType) -> bool {
+    matches!(
+        fn_type,
+        FNLineType::Action
+            | FNLineType::Dialogue
+            | FNLineType::DualDialogue
+            | FNLineType::Parenthetical
+            | FNLineType::DualDialogueParenthetical
+            | FNLineType::Synopse
+            | FNLineType::Lyrics
+            | FNLineType::Centered
+    )
+}
+
+/// Returns the prose chunks of every parsed line, in document order.
+pub fn get_prose_chunks_for_spellcheck(lines: &[FNLine]) -> Vec<FNTextChunk> {
+    let visible_ranges = get_visible_byte_ranges_for_lines(lines);
+    let mut chunks: Vec<FNTextChunk> = Vec::new();
+
+    for (line_index, ln) in lines.iter().enumerate() {
+        if !is_prose_line_type(&ln.fn_type) {
+            continue;
+        }
+        let markers_end = get_forcing_marker_len(ln);
+        let centered_end = match ln.fn_type {
+            FNLineType::Centered => ln.raw_string.trim_end().strip_suffix('<').map(|s| s.len()),
+            _ => None,
+        };
+
+        for (start, end) in &visible_ranges[line_index] {
+            let start = (*start).max(markers_end);
+            let end = centered_end.map_or(*end, |c| (*end).min(c));
+            if start >= end {
+                continue;
+            }
+            push_chunks_without_emphasis(&mut chunks, &ln.raw_string, line_index, start, end);
+        }
+    }
+
+    chunks
+}
+
+/// Returns the text of each line as it would be printed: notes and boneyards, forcing characters,
+/// the `^` of dual dialogue cues, and emphasis markers are all removed, and `\*` escapes become `*`.
+pub fn get_printable_strings_for_lines(lines: &[FNLine]) -> Vec<String> {
+    get_printable_strings_for_visible_ranges(lines, get_visible_byte_ranges_for_lines(lines))
+}
+
+/// Same as `get_printable_strings_for_lines`, but notes are kept, markers included, as they're printed
+/// in an annotated draft. Boneyards are still removed.
+pub fn get_printable_strings_for_lines_with_notes(lines: &[FNLine]) -> Vec<String> {
+    let visible_ranges: Vec<Vec<(usize, usize)>> = get_invisible_byte_ranges_for_lines(lines)
+        .iter()
+        .zip(lines)
+        .map(|(invisible, ln)| {
+            invisible
+                .boneyards
+                .get_complement(0..ln.raw_string.len())
+                .iter()
+                .map(|range| (range.start, range.end))
+                .collect()
+        })
+        .collect();
+    get_printable_strings_for_visible_ranges(lines, visible_ranges)
+}
+
+fn get_printable_strings_for_visible_ranges(
+    lines: &[FNLine],
+    visible_ranges: Vec<Vec<(usize, usize)>>,
+) -> Vec<String> {
+    lines
+        .iter()
+        .zip(visible_ranges)
+        .map(|(ln, ranges)| {
+            let visible: String = ranges
+                .iter()
+                .map(|(start, end)| &ln.raw_string[*start..*end])
+                .collect();
+            let mut text: &str = visible.trim();
+            if ln.fn_type == FNLineType::Shot {
+                text = text.strip_prefix("!!").unwrap_or(text);
+            } else if let Some(marker) = ln.forced_by.get_marker() {
+                text = text.strip_prefix(marker).unwrap_or(text);
+            }
+            match ln.fn_type {
+                FNLineType::Centered => text = text.strip_suffix('<').unwrap_or(text),
+                FNLineType::DualDialogueCharacter => text = text.strip_suffix('^').unwrap_or(text),
+                _ => {}
+            }
+            get_string_without_emphasis_markers(text.trim())
+        })
+        .collect()
+}
+
+/// A run of visible text with a single style.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNRichTextRun {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Byte index of the run in the whole visible text of the line
+    pub start: usize,
+}
+
+/// Returns the visible text of a line, split into runs with the same emphasis.
+///
+/// Notes and boneyards (from the line's `note_ranges` and `omitted_ranges`), forcing characters, emphasis markers,
+/// and the backslashes of escapes are removed, and each run's `start` is its position in what's left.
+/// The `note_ranges` and `omitted_ranges` have to be filled in first, such as by `parse_document_full`.
+pub fn get_visible_rich_text_for_line(
+    line: &FNLine,
+    options: &FNEmphasisOptions,
+) -> Vec<FNRichTextRun> {
+    let raw = line.raw_string.as_str();
+    // `string` is `raw_string` without any leading markers the parser already removed
+    let offset = if raw.ends_with(line.string.as_str()) {
+        raw.len() - line.string.len()
+    } else {
+        0
+    };
+
+    let mut hidden = line.note_ranges.union(&line.omitted_ranges);
+    hidden.insert(0..offset);
+    hidden = hidden.union(&get_decoration_byte_ranges(line, &hidden));
+
+    let mut bold = RangeSet::new();
+    let mut italic = RangeSet::new();
+    let mut underline = RangeSet::new();
+    for span in get_emphasis_spans_from_string(&line.string, options) {
+        hidden.insert(offset + span.open_start..offset + span.content_start);
+        hidden.insert(offset + span.content_end..offset + span.close_end);
+        let content = offset + span.content_start..offset + span.content_end;
+        match span.kind {
+            FNEmphasisKind::Bold => bold.insert(content),
+            FNEmphasisKind::Italic => italic.insert(content),
+            FNEmphasisKind::BoldItalic => {
+                bold.insert(content.clone());
+                italic.insert(content);
+            }
+            FNEmphasisKind::Underline => underline.insert(content),
+        }
+    }
+    let mut chars = raw.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c == '\\' && chars.peek().is_some_and(|(_, n)| *n == '*' || *n == '_') {
+            hidden.insert(idx..idx + 1);
+            chars.next();
+        }
+    }
+
+    let mut runs: Vec<FNRichTextRun> = Vec::new();
+    let mut visible_len: usize = 0;
+    for range in hidden.get_complement(0..raw.len()).iter() {
+        for (local_idx, c) in raw[range.clone()].char_indices() {
+            let idx = range.start + local_idx;
+            let style = (
+                bold.contains(idx),
+                italic.contains(idx),
+                underline.contains(idx),
+            );
+            match runs.last_mut() {
+                Some(run) if (run.bold, run.italic, run.underline) == style => run.text.push(c),
+                _ => runs.push(FNRichTextRun {
+                    text: c.to_string(),
+                    bold: style.0,
+                    italic: style.1,
+                    underline: style.2,
+                    start: visible_len,
+                }),
+            }
+            visible_len += c.len_utf8();
+        }
+    }
+
+    // Leading and trailing whitespace isn't printed
+    if let Some(first) = runs.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = runs.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+    runs.retain(|run| !run.text.is_empty());
+    let mut start: usize = 0;
+    for run in runs.iter_mut() {
+        run.start = start;
+        start += run.text.len();
+    }
+    runs
+}
+
+// Forcing characters, the `<` of centered text, and the `^` of dual dialogue cues, as byte ranges of `raw_string`
+fn get_decoration_byte_ranges(line: &FNLine, hidden: &RangeSet) -> RangeSet {
+    let raw = line.raw_string.as_str();
+    let mut decorations = RangeSet::new();
+    let visible: Vec<(usize, char)> = raw
+        .char_indices()
+        .filter(|(idx, c)| !hidden.contains(*idx) && !c.is_whitespace())
+        .collect();
+
+    if let Some((first_idx, first_char)) = visible.first().copied() {
+        if line.fn_type == FNLineType::Shot && raw[first_idx..].starts_with("!!") {
+            decorations.insert(first_idx..first_idx + 2);
+        } else if line.forced_by.get_marker() == Some(first_char) {
+            decorations.insert(first_idx..first_idx + first_char.len_utf8());
+        }
+    }
+    let last_marker = match line.fn_type {
+        FNLineType::Centered => Some('<'),
+        FNLineType::DualDialogueCharacter => Some('^'),
+        _ => None,
+    };
+    if let (Some((last_idx, last_char)), Some(marker)) = (visible.last().copied(), last_marker) {
+        if last_char == marker {
+            decorations.insert(last_idx..last_idx + last_char.len_utf8());
+        }
+    }
+    decorations
+}
+
+fn get_string_without_emphasis_markers(text: &str) -> String {
+    let mut markers = RangeSet::new();
+    for span in get_emphasis_spans_from_string(text, &FNEmphasisOptions::default()) {
+        markers.insert(span.open_start..span.content_start);
+        markers.insert(span.content_end..span.close_end);
+    }
+
+    let mut printable = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if markers.contains(idx) {
+            continue;
+        }
+        if c == '\\' && chars.peek().is_some_and(|(_, n)| *n == '*' || *n == '_') {
+            continue;
+        }
+        printable.push(c);
+    }
+    printable
+}
+
+/// The byte ranges of a line's `raw_string` which are inside a note or a boneyard, including the `[[ ]]` or `/* */` markers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNInvisibleByteRanges {
+    pub notes: RangeSet,
+    pub boneyards: RangeSet,
+    /// `true` if a note or boneyard is still open at the end of the line, and continues on the next line
+    pub is_open_at_end: bool,
+}
+
+/// Returns the byte ranges of each line's `raw_string` which are NOT inside a note or boneyard.
+///
+/// Boneyards can span any number of lines. Notes can span lines too, but a note
+/// can't contain an empty line; an unclosed note is treated as visible text.
+///
+/// When notes and boneyards are mixed up, this is the policy:
+/// - Boneyards nest: `/* a /* b */ c */` hides everything, so that already commented-out text can be commented out again.
+/// - Inside a boneyard, `[[` and `]]` mean nothing.
+/// - Inside a note, `/*` and `*/` mean nothing, and notes don't nest; the first `]]` closes the note.
+/// - A close with no open (a stray `*/` or `]]`) is visible text.
+pub fn get_visible_byte_ranges_for_lines(lines: &[FNLine]) -> Vec<Vec<(usize, usize)>> {
+    get_invisible_byte_ranges_for_lines(lines)
+        .iter()
+        .zip(lines)
+        .map(|(invisible, ln)| {
+            invisible
+                .notes
+                .union(&invisible.boneyards)
+                .get_complement(0..ln.raw_string.len())
+                .iter()
+                .map(|range| (range.start, range.end))
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the byte ranges of each line's `raw_string` which are inside a note or boneyard,
+/// with the same policy as `get_visible_byte_ranges_for_lines`.
+///
+/// Text in the line's `additional_note_ranges` is a note too, and text in its `additional_omitted_ranges` is a boneyard,
+/// so what a full parse resolved (notes of its `additional_note_types`, and ranges paired by its
+/// `ranged_element_strategy`) stays hidden. See `get_invisible_byte_ranges_for_lines_with_note_types`.
+pub fn get_invisible_byte_ranges_for_lines(lines: &[FNLine]) -> Vec<FNInvisibleByteRanges> {
+    get_invisible_byte_ranges_for_lines_with_note_types(lines, &[FNRangedElementType::note()])
+}
+
+/// Same as `get_invisible_byte_ranges_for_lines`, but any of the `note_types` opens a note, such as
+/// `<!-- -->` along with `[[ ]]`. Where two opens match at the same place, the earlier note type wins,
+/// and a note is only closed by the close of its own type.
+pub fn get_invisible_byte_ranges_for_lines_with_note_types(
+    lines: &[FNLine],
+    note_types: &[FNRangedElementType],
+) -> Vec<FNInvisibleByteRanges> {
+    let note_patterns: Vec<(String, String)> = note_types
+        .iter()
+        .map(|note_type| note_type.get_open_and_close_patterns())
+        .filter(|(open, close)| !open.is_empty() && !close.is_empty())
+        .collect();
+    let note_closes_on_later_lines: Vec<Vec<bool>> = note_patterns
+        .iter()
+        .map(|(_, close)| get_note_closes_on_later_lines(lines, close))
+        .collect();
+    let mut all_ranges: Vec<FNInvisibleByteRanges> = Vec::with_capacity(lines.len());
+    let mut boneyard_depth: usize = 0;
+    // The index in `note_patterns` of the note which is open
+    let mut open_note: Option<usize> = None;
+
+    for (line_index, ln) in lines.iter().enumerate() {
+        let raw = ln.raw_string.as_str();
+        let last_note_closes: Vec<Option<usize>> = note_patterns
+            .iter()
+            .map(|(_, close)| raw.rfind(close.as_str()))
+            .collect();
+        if is_empty_line_for_ranged_elements(raw) {
+            open_note = None;
+        }
+
+        let mut ranges = FNInvisibleByteRanges::default();
+        // Where the current note or boneyard started on this line
+        let mut hidden_start: Option<usize> = if boneyard_depth > 0 || open_note.is_some() {
+            Some(0)
+        } else {
+            None
+        };
+        let mut idx: usize = 0;
+
+        while idx < raw.len() {
+            let rest = &raw[idx..];
+            if boneyard_depth > 0 {
+                if rest.starts_with(BONEYARD_OPEN) {
+                    boneyard_depth += 1;
+                    idx += BONEYARD_OPEN.len();
+                    continue;
+                }
+                if rest.starts_with(BONEYARD_CLOSE) {
+                    boneyard_depth -= 1;
+                    idx += BONEYARD_CLOSE.len();
+                    if boneyard_depth == 0 {
+                        if let Some(start) = hidden_start.take() {
+                            ranges.boneyards.insert(start..idx);
+                        }
+                    }
+                    continue;
+                }
+            } else if let Some(pattern_idx) = open_note {
+                let close = note_patterns[pattern_idx].1.as_str();
+                if rest.starts_with(close) {
+                    open_note = None;
+                    idx += close.len();
+                    if let Some(start) = hidden_start.take() {
+                        ranges.notes.insert(start..idx);
+                    }
+                    continue;
+                }
+            } else if let Some(boneyard) = ln.additional_omitted_ranges.get_range_containing(idx) {
+                // Already resolved by the full parse, so the markers inside it don't open anything
+                let end = boneyard.end.min(raw.len());
+                ranges.boneyards.insert(idx..end);
+                idx = end;
+                continue;
+            } else if let Some(note) = ln.additional_note_ranges.get_range_containing(idx) {
+                // A note of a syntax this call doesn't know about, or one the full parse paired differently
+                let end = note.end.min(raw.len());
+                ranges.notes.insert(idx..end);
+                idx = end;
+                continue;
+            } else if rest.starts_with(BONEYARD_OPEN) {
+                hidden_start = Some(idx);
+                boneyard_depth = 1;
+                idx += BONEYARD_OPEN.len();
+                continue;
+            } else if let Some(pattern_idx) =
+                note_patterns
+                    .iter()
+                    .enumerate()
+                    .position(|(pattern_idx, (open, _))| {
+                        rest.starts_with(open.as_str())
+                            && (last_note_closes[pattern_idx]
+                                .is_some_and(|close| close >= idx + open.len())
+                                || note_closes_on_later_lines[pattern_idx][line_index])
+                    })
+            {
+                hidden_start = Some(idx);
+                open_note = Some(pattern_idx);
+                idx += note_patterns[pattern_idx].0.len();
+                continue;
+            }
+            idx += rest.chars().next().map_or(1, |c| c.len_utf8());
+        }
+
+        if let Some(start) = hidden_start {
+            if boneyard_depth > 0 {
+                ranges.boneyards.insert(start..raw.len());
+            } else if open_note.is_some() {
+                ranges.notes.insert(start..raw.len());
+            }
+        }
+        ranges.is_open_at_end = boneyard_depth > 0
+            || open_note.is_some()
+            || ln.additional_note_ranges.contains(raw.len())
+            || ln.additional_omitted_ranges.contains(raw.len());
+        all_ranges.push(ranges);
+    }
+
+    all_ranges
+}
+
+// For each line, whether a note close comes on a later line, before the next empty line.
+// Computed once, so that checking every note open doesn't scan ahead again.
+fn get_note_closes_on_later_lines(lines: &[FNLine], note_close: &str) -> Vec<bool> {
+    let mut closes_later = vec![false; lines.len()];
+    let mut close_ahead = false;
+    for (idx, ln) in lines.iter().enumerate().rev() {
+        closes_later[idx] = close_ahead;
+        let raw = ln.raw_string.as_str();
+        if is_empty_line_for_ranged_elements(raw) {
+            close_ahead = false;
+        } else if raw.contains(note_close) {
+            close_ahead = true;
+        }
+    }
+    closes_later
+}
+
+fn get_forcing_marker_len(line: &FNLine) -> usize {
+    let raw = line.raw_string.as_str();
+    let marker: &[char] = match line.fn_type {
+        FNLineType::Action => &['!'],
+        FNLineType::Synopse => &['='],
+        FNLineType::Lyrics => &['~'],
+        FNLineType::Centered => &['>'],
+        _ => &[],
+    };
+    // Centered text may have whitespace before its `>`
+    let trimmed = raw.trim_start();
+    match trimmed.chars().next() {
+        Some(c) if marker.contains(&c) => raw.len() - trimmed.len() + c.len_utf8(),
+        _ => 0,
+    }
+}
+
+fn push_chunks_without_emphasis(
+    chunks: &mut Vec<FNTextChunk>,
+    raw: &str,
+    line_index: usize,
+    start: usize,
+    end: usize,
+) {
+    let mut chunk_start = start;
+    let mut iter = raw[start..end].char_indices().peekable();
+
+    while let Some((offset, c)) = iter.next() {
+        let idx = start + offset;
+        let is_marker = c == '*' || c == '_';
+        let is_escape = c == '\\' && iter.peek().is_some_and(|(_, n)| *n == '*' || *n == '_');
+        if is_marker || is_escape {
+            push_trimmed_chunk(chunks, raw, line_index, chunk_start, idx);
+            chunk_start = idx + c.len_utf8();
+            if is_escape {
+                // The escaped character itself is visible text
+                iter.next();
+            }
+        }
+    }
+    push_trimmed_chunk(chunks, raw, line_index, chunk_start, end);
+}
+
+fn push_trimmed_chunk(
+    chunks: &mut Vec<FNTextChunk>,
+    raw: &str,
+    line_index: usize,
+    start: usize,
+    end: usize,
+) {
+    if start >= end {
+        return;
+    }
+    let text = &raw[start..end];
+    let trimmed_start = start + (text.len() - text.trim_start().len());
+    let trimmed_end = end - (text.len() - text.trim_end().len());
+    if trimmed_start >= trimmed_end {
+        return;
+    }
+    chunks.push(FNTextChunk {
+        text: raw[trimmed_start..trimmed_end].to_string(),
+        line_index,
+        local_start: trimmed_start,
+        local_end: trimmed_end,
+    });
+}
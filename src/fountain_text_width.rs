@@ -0,0 +1,31 @@
+//! The display width of text, in the columns of a monospaced font.
+//!
+//! Counting chars or even graphemes isn't enough for layout: an emoji or a CJK character takes two columns,
+//! and a ZWJ sequence like a family emoji is several chars but a single, double-width grapheme:
+//!
+//! ```text
+//! "Joe"    3 graphemes, 3 columns
+//! "café"   4 graphemes (with a combining accent), 4 columns
+//! "👍🏽"     1 grapheme, 2 columns
+//! "東京"    2 graphemes, 4 columns
+//! ```
+//!
+//! Widths come from `unicode-width`, which follows Unicode's East Asian Width and emoji presentation.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Returns the width of a single grapheme: `0` for control and zero-width characters, `2` for wide characters
+/// and emoji, and `1` for everything else. Combining marks don't add to the width of their base character.
+pub fn get_grapheme_display_width(grapheme: &str) -> usize {
+    if grapheme.chars().all(char::is_control) {
+        return 0;
+    }
+    // A grapheme is a single character cell, however many chars it's made of
+    grapheme.width().min(2)
+}
+
+/// Returns the number of columns the text takes up in a monospaced font.
+pub fn get_display_width(text: &str) -> usize {
+    text.graphemes(true).map(get_grapheme_display_width).sum()
+}
@@ -0,0 +1,72 @@
+//! Collects the title page of a parsed document into key/value entries.
+//!
+//! ```text
+//! Title: Big Fish
+//! Credit: written by
+//! Author: John August
+//! Contact:
+//!     Some Agency
+//!     555-1234
+//! ```
+//!
+//! Values may be on the same line as the key, or on the indented lines which follow it.
+//!
+//! `FNDocument` has setters (`set_title`, `set_title_page_value`, etc.) which write entries in this format.
+
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNTitlePageEntry {
+    /// The key in lowercase, as written: `title`, `draft date`, `contact info`, etc.
+    pub key: String,
+    /// Each line of the value, trimmed. Empty lines are left out.
+    pub values: Vec<String>,
+    /// The indices of every line which belongs to this entry, including the line with the key
+    pub line_indices: Vec<usize>,
+}
+
+/// Returns the title page entries of the parsed lines, in document order.
+///
+/// Only the lines at the start of the document which were parsed as title page lines are looked at.
+pub fn get_title_page_from_lines(lines: &[FNLine]) -> Vec<FNTitlePageEntry> {
+    let mut entries: Vec<FNTitlePageEntry> = Vec::new();
+
+    for (line_index, ln) in lines.iter().enumerate() {
+        if !ln.fn_type.is_title_page_kind() {
+            break;
+        }
+        let key = ln.get_title_page_key();
+        let value = match ln.string.find(':') {
+            Some(colon) if !key.is_empty() => ln.string[colon + 1..].trim(),
+            _ => ln.string.trim(),
+        };
+
+        if !key.is_empty() || entries.is_empty() {
+            entries.push(FNTitlePageEntry {
+                key,
+                values: Vec::new(),
+                line_indices: Vec::new(),
+            });
+        }
+        if let Some(entry) = entries.last_mut() {
+            if !value.is_empty() {
+                entry.values.push(value.to_string());
+            }
+            entry.line_indices.push(line_index);
+        }
+    }
+
+    entries
+}
+
+/// Returns the raw lines of a title page entry. A value with several lines is written on indented lines
+/// after the key, and every line of the value is trimmed.
+pub fn get_title_page_entry_lines(key: &str, value: &str) -> Vec<String> {
+    let value_lines: Vec<&str> = value.lines().map(|ln| ln.trim()).collect();
+    if value_lines.len() > 1 {
+        let mut lines = vec![format!("{}:", key)];
+        lines.extend(value_lines.iter().map(|ln| format!("    {}", ln)));
+        return lines;
+    }
+    vec![format!("{}: {}", key, value.trim())]
+}
@@ -0,0 +1,134 @@
+//! Recognizes standalone transitions like `FADE IN:`, `FADE OUT.`, and `THE END`.
+//!
+//! These have conventional handling which is different from normal transitions:
+//! `FADE IN:` is usually left-aligned at the top of the script, while `FADE OUT.` and `THE END`
+//! close it out. Only `FADE IN:` ends with a colon, so the `static_fountain_parser` would usually
+//! see the others as `Action` (or `Character`) lines.
+//!
+//! `fountain_full_parse::parse_document_full` re-types them with the `standalone_transitions` of its config.
+
+use crate::fountain_enums::{FNForcedBy, FNLineType};
+use crate::fountain_line::FNLine;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNStandaloneTransitionKind {
+    FadeIn,
+    FadeOut,
+    FadeToBlack,
+    TheEnd,
+    /// A caller-provided phrase, see `FNStandaloneTransitionOptions::extra_phrases`
+    Custom(String),
+}
+
+impl FNStandaloneTransitionKind {
+    /// Returns `true` if, by convention, this transition is aligned to the left margin instead of the right.
+    pub fn is_left_aligned(&self) -> bool {
+        matches!(
+            self,
+            FNStandaloneTransitionKind::FadeIn | FNStandaloneTransitionKind::TheEnd
+        )
+    }
+
+    /// Returns `true` if this transition is expected to end the script.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            FNStandaloneTransitionKind::FadeOut
+                | FNStandaloneTransitionKind::FadeToBlack
+                | FNStandaloneTransitionKind::TheEnd
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNStandaloneTransitionOptions {
+    /// If `true`, recognized lines are re-typed as `FNLineType::TransitionLine`.
+    pub retype_as_transition: bool,
+    /// Extra phrases to recognize, compared case-insensitively, ignoring trailing `.` and `:`.
+    pub extra_phrases: Vec<String>,
+}
+
+impl Default for FNStandaloneTransitionOptions {
+    fn default() -> Self {
+        FNStandaloneTransitionOptions {
+            retype_as_transition: true,
+            extra_phrases: Vec::new(),
+        }
+    }
+}
+
+/// Returns the `FNStandaloneTransitionKind` for a parsed line, if it is a standalone transition.
+///
+/// The line must be alone in its paragraph; lines of dialogue that happen to say "The end." are ignored.
+pub fn get_standalone_transition_kind(
+    line: &FNLine,
+    options: &FNStandaloneTransitionOptions,
+) -> Option<FNStandaloneTransitionKind> {
+    match line.fn_type {
+        FNLineType::Action | FNLineType::Character | FNLineType::TransitionLine => {}
+        _ => return None,
+    }
+
+    // A line forced to be something else, such as `!FADE IN:` forced to be action, is left as it is
+    if !matches!(line.forced_by, FNForcedBy::None | FNForcedBy::GreaterThan) {
+        return None;
+    }
+
+    let text = line.string.trim();
+    let text = text.strip_prefix('>').unwrap_or(text).trim();
+    let phrase = get_normalized_phrase(text);
+
+    match phrase.as_str() {
+        "FADE IN" => Some(FNStandaloneTransitionKind::FadeIn),
+        "FADE OUT" => Some(FNStandaloneTransitionKind::FadeOut),
+        "FADE TO BLACK" => Some(FNStandaloneTransitionKind::FadeToBlack),
+        "THE END" | "END" => Some(FNStandaloneTransitionKind::TheEnd),
+        _ => options
+            .extra_phrases
+            .iter()
+            .find(|extra| get_normalized_phrase(extra) == phrase)
+            .map(|extra| FNStandaloneTransitionKind::Custom(extra.clone())),
+    }
+}
+
+/// Returns a copy of the parsed lines, where each standalone transition is re-typed as a `TransitionLine`
+/// (if `retype_as_transition` is set), along with the global indices and kinds of every standalone transition.
+pub fn get_lines_with_standalone_transitions(
+    lines: &[FNLine],
+    options: &FNStandaloneTransitionOptions,
+) -> (Vec<FNLine>, Vec<(usize, FNStandaloneTransitionKind)>) {
+    let mut new_lines: Vec<FNLine> = lines.to_vec();
+    let mut transitions: Vec<(usize, FNStandaloneTransitionKind)> = Vec::new();
+
+    for (idx, ln) in lines.iter().enumerate() {
+        if !is_alone_in_paragraph(lines, idx) {
+            continue;
+        }
+        if let Some(kind) = get_standalone_transition_kind(ln, options) {
+            if options.retype_as_transition {
+                new_lines[idx].fn_type = FNLineType::TransitionLine;
+            }
+            transitions.push((idx, kind));
+        }
+    }
+
+    (new_lines, transitions)
+}
+
+fn is_alone_in_paragraph(lines: &[FNLine], idx: usize) -> bool {
+    let is_empty_at = |i: usize| {
+        lines
+            .get(i)
+            .is_none_or(|ln| ln.fn_type == FNLineType::Empty)
+    };
+    (idx == 0 || is_empty_at(idx - 1)) && is_empty_at(idx + 1)
+}
+
+fn get_normalized_phrase(text: &str) -> String {
+    text.trim()
+        .trim_end_matches(['.', ':'])
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_uppercase()
+}
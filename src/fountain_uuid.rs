@@ -0,0 +1,54 @@
+//! Generates the `uuid`s which an `FNDocument` gives its lines (and the annotations extracted from it).
+//!
+//! Uuids are random by default. A seeded generator gives the same sequence of uuids for the same seed,
+//! so two documents made from the same text with the same seed have the same line ids:
+//!
+//! ```text
+//! FNDocument::with_uuid_generator(text, FNUuidGenerator::seeded(7))   ->  line 0: 3f0a…, line 1: 91c4…
+//! FNDocument::with_uuid_generator(text, FNUuidGenerator::seeded(7))   ->  line 0: 3f0a…, line 1: 91c4…
+//! ```
+//!
+//! This makes exports and tests reproducible. Seeded uuids are valid version 4 uuids, but they're predictable,
+//! so they shouldn't be used as ids which mustn't be guessed.
+
+use uuid::{Builder, Uuid};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNUuidGenerator {
+    // `None` for random uuids
+    state: Option<u64>,
+}
+
+impl FNUuidGenerator {
+    pub fn random() -> Self {
+        FNUuidGenerator { state: None }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        FNUuidGenerator { state: Some(seed) }
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Returns the next uuid.
+    pub fn get_uuid(&mut self) -> Uuid {
+        let Some(state) = self.state.as_mut() else {
+            return Uuid::new_v4();
+        };
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&get_next_split_mix(state).to_le_bytes());
+        bytes[8..].copy_from_slice(&get_next_split_mix(state).to_le_bytes());
+        Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+// SplitMix64, which spreads consecutive states over the whole range of `u64`
+fn get_next_split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
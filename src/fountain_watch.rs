@@ -0,0 +1,98 @@
+//! Watches a Fountain file on disk, and re-parses it whenever it changes.
+//!
+//! This is only available with the `watch` feature, which depends on `notify`.
+//! It's meant for live-preview tools: each time the file is saved, the callback receives
+//! the newly parsed lines, as well as a semantic diff against the previous parse.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::fountain_diff::{get_semantic_diff, FNLineChange};
+use crate::fountain_line::FNLine;
+use crate::static_fountain_parser;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNWatchOptions {
+    /// If `true`, the callback is called once right away with the initial parse.
+    pub emit_initial_parse: bool,
+}
+
+impl Default for FNWatchOptions {
+    fn default() -> Self {
+        FNWatchOptions {
+            emit_initial_parse: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNWatchUpdate {
+    pub path: PathBuf,
+    pub lines: Vec<FNLine>,
+    /// Changes from the previous parse. For the initial parse, every line is `Inserted`.
+    pub changes: Vec<FNLineChange>,
+}
+
+/// Keeps the file watcher alive. The file stops being watched when this is dropped.
+pub struct FNWatchHandle {
+    _watcher: RecommendedWatcher,
+}
+
+/// Starts watching the file at `path`, calling `callback` with an `FNWatchUpdate` every time its contents change.
+///
+/// The callback runs on the watcher's thread. Saves which don't actually change the text are ignored.
+pub fn watch<F>(
+    path: &Path,
+    options: FNWatchOptions,
+    mut callback: F,
+) -> Result<FNWatchHandle, notify::Error>
+where
+    F: FnMut(FNWatchUpdate) + Send + 'static,
+{
+    let path_buf = path.to_path_buf();
+    let initial_text = fs::read_to_string(path)?;
+    let initial_lines =
+        static_fountain_parser::get_parsed_lines_from_raw_string(initial_text.clone());
+
+    if options.emit_initial_parse {
+        callback(FNWatchUpdate {
+            path: path_buf.clone(),
+            changes: get_semantic_diff(&[], &initial_lines),
+            lines: initial_lines.clone(),
+        });
+    }
+
+    let mut previous_text = initial_text;
+    let mut previous_lines = initial_lines;
+    let watched_path = path_buf.clone();
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        let Ok(text) = fs::read_to_string(&watched_path) else {
+            return;
+        };
+        if text == previous_text {
+            return;
+        }
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text.clone());
+        let changes = get_semantic_diff(&previous_lines, &lines);
+        previous_text = text;
+        previous_lines = lines.clone();
+        callback(FNWatchUpdate {
+            path: watched_path.clone(),
+            lines,
+            changes,
+        });
+    })?;
+
+    watcher.watch(&path_buf, RecursiveMode::NonRecursive)?;
+
+    Ok(FNWatchHandle { _watcher: watcher })
+}
@@ -0,0 +1,162 @@
+//! Wraps the printable text of a line at the column width of its element type.
+//!
+//! Widths are counted in columns, as on a typewriter, with wide characters such as emoji taking two columns
+//! (see `fountain_text_width`). Text is wrapped at word boundaries;
+//! a single word longer than the width is split. Parentheticals get a hanging indent,
+//! so that wrapped rows line up after the opening `(`.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_text_extraction::get_printable_strings_for_lines;
+use crate::fountain_text_width::get_display_width;
+
+/// The number of columns each element type may use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNElementWidths {
+    /// Also used for headings, shots, lyrics, and centered text
+    pub action: usize,
+    pub character: usize,
+    pub dialogue: usize,
+    pub parenthetical: usize,
+    pub transition: usize,
+}
+
+impl Default for FNElementWidths {
+    fn default() -> Self {
+        FNElementWidths {
+            action: 60,
+            character: 32,
+            dialogue: 35,
+            parenthetical: 22,
+            transition: 60,
+        }
+    }
+}
+
+impl FNElementWidths {
+    /// Returns the width for a line type, or `None` for line types which aren't printed.
+    pub fn get_width_for_line_type(&self, fn_type: &FNLineType) -> Option<usize> {
+        match fn_type {
+            FNLineType::Heading
+            | FNLineType::Action
+            | FNLineType::Shot
+            | FNLineType::Lyrics
+            | FNLineType::Centered => Some(self.action),
+            FNLineType::Character | FNLineType::DualDialogueCharacter => Some(self.character),
+            FNLineType::Dialogue | FNLineType::DualDialogue => Some(self.dialogue),
+            FNLineType::Parenthetical | FNLineType::DualDialogueParenthetical => {
+                Some(self.parenthetical)
+            }
+            FNLineType::TransitionLine => Some(self.transition),
+            _ => None,
+        }
+    }
+}
+
+/// Wraps the printable text of a single line.
+///
+/// Notes and boneyards are only removed if they start and end within this line;
+/// use `wrap_text` with `get_printable_strings_for_lines` to handle multiline ones.
+pub fn wrap_line(line: &FNLine, widths: &FNElementWidths) -> Vec<String> {
+    let printable = get_printable_strings_for_lines(std::slice::from_ref(line));
+    wrap_text(&printable[0], &line.fn_type, widths)
+}
+
+/// Wraps printable text at the width of the given line type. Returns no rows for line types which aren't printed.
+pub fn wrap_text(text: &str, fn_type: &FNLineType, widths: &FNElementWidths) -> Vec<String> {
+    wrap_text_with_measure(text, fn_type, widths, &get_grapheme_width)
+}
+
+/// Same as `wrap_text`, but with text widths given by `measure`, in the same unit as the `FNElementWidths`.
+/// This lets proportional fonts be wrapped with real font metrics.
+pub fn wrap_text_with_measure(
+    text: &str,
+    fn_type: &FNLineType,
+    widths: &FNElementWidths,
+    measure: &dyn Fn(&str) -> f64,
+) -> Vec<String> {
+    let Some(width) = widths.get_width_for_line_type(fn_type) else {
+        return Vec::new();
+    };
+    let width = width as f64;
+
+    let is_parenthetical = matches!(
+        fn_type,
+        FNLineType::Parenthetical | FNLineType::DualDialogueParenthetical
+    );
+    if !is_parenthetical || !text.starts_with('(') {
+        return wrap_words_with_measure(text, width, measure);
+    }
+
+    // Hanging indent: every row after the first is shifted past the `(`
+    let mut rows = wrap_words_with_measure(text, width, measure);
+    if rows.len() > 1 {
+        let first = rows.remove(0);
+        let rest = rows.join(" ");
+        let hanging_width = width - measure(" ");
+        rows = std::iter::once(first)
+            .chain(
+                wrap_words_with_measure(&rest, hanging_width, measure)
+                    .into_iter()
+                    .map(|row| format!(" {}", row)),
+            )
+            .collect();
+    }
+    rows
+}
+
+/// Greedy word wrap at `width` graphemes. Words longer than `width` are split. Whitespace between words is collapsed.
+pub fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    wrap_words_with_measure(text, width as f64, &get_grapheme_width)
+}
+
+/// Greedy word wrap, with text widths given by `measure`. Words wider than `width` are split,
+/// but every row has at least one grapheme.
+pub fn wrap_words_with_measure(
+    text: &str,
+    width: f64,
+    measure: &dyn Fn(&str) -> f64,
+) -> Vec<String> {
+    let mut rows: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        if measure(&word) > width {
+            // Split off full rows in one pass, and keep the rest as the word
+            let mut piece = String::new();
+            for grapheme in word.graphemes(true) {
+                let candidate = format!("{}{}", piece, grapheme);
+                if !piece.is_empty() && measure(&candidate) > width {
+                    if !current.is_empty() {
+                        rows.push(std::mem::take(&mut current));
+                    }
+                    rows.push(std::mem::replace(&mut piece, grapheme.to_string()));
+                    continue;
+                }
+                piece = candidate;
+            }
+            word = piece;
+        }
+        if current.is_empty() {
+            current = word;
+            continue;
+        }
+        let candidate = format!("{} {}", current, word);
+        if measure(&candidate) > width {
+            rows.push(std::mem::replace(&mut current, word));
+        } else {
+            current = candidate;
+        }
+    }
+    if !current.is_empty() {
+        rows.push(current);
+    }
+    rows
+}
+
+fn get_grapheme_width(text: &str) -> f64 {
+    get_display_width(text) as f64
+}
@@ -1,12 +1,96 @@
-pub fn only_uppercase_until_parenthesis(text: &String) -> bool {
+use std::collections::BTreeMap;
+
+pub fn only_uppercase_until_parenthesis(text: &str) -> bool {
     let until_parenthesis: Option<&str> = text.split("(").next();
     match until_parenthesis {
         Some(text) => {
-            if text == text.to_uppercase() && text.len() > 0 {
+            if text == text.to_uppercase() && !text.is_empty() {
                 return true;
             }
-            return false;
+            false
         }
-        None => return false,
+        None => false,
     }
 }
+
+/// Returns `true` if `word` appears in `text` without any alphanumeric characters directly before or after it.
+pub fn contains_whole_word(text: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+    text.match_indices(word).any(|(idx, _)| {
+        let before = text[..idx].chars().next_back();
+        let after = text[idx + word.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+/// Returns `true` if a line counts as empty for notes and boneyards: no text, or only whitespace with less than two spaces.
+pub fn is_empty_line_for_ranged_elements(raw: &str) -> bool {
+    raw.trim().is_empty() && !raw.starts_with("  ")
+}
+
+/// Returns `text` as a quoted JSON string.
+pub fn get_json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Returns the map as a JSON object of strings, with its keys in order.
+pub fn get_json_string_map(map: &BTreeMap<String, String>) -> String {
+    let entries: Vec<String> = map
+        .iter()
+        .map(|(key, value)| format!("{}:{}", get_json_string(key), get_json_string(value)))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// Returns a copy of `text` for loose comparisons: uppercase, without punctuation, and with single spaces.
+/// Apostrophes are removed entirely, so `JOE'S` and `JOES` are the same.
+pub fn get_normalized_text_for_matching(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\'' | '\u{2019}' => {}
+            c if c.is_alphanumeric() => normalized.extend(c.to_uppercase()),
+            _ => normalized.push(' '),
+        }
+    }
+    normalized
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Levenshtein distance, counted in chars
+pub fn get_edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut current_row: Vec<usize> = vec![0; b_chars.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current_row[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+            current_row[j + 1] = (previous_row[j] + substitution_cost)
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+
+    previous_row[b_chars.len()]
+}
@@ -0,0 +1,187 @@
+//! Sets of text ranges, such as the emphasized or hidden parts of a line.
+//!
+//! Emphasis, notes, boneyards, and escapes all mark ranges of a line's text, and they often overlap.
+//! `RangeSet` does the merging, subtracting, and intersecting of these ranges, so that every pass and exporter
+//! does it the same way.
+
+use std::ops::Range;
+
+/// A set of indices, stored as sorted ranges which don't overlap or touch.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RangeSet {
+    ranges: Vec<Range<usize>>,
+}
+
+impl RangeSet {
+    pub fn new() -> Self {
+        RangeSet::default()
+    }
+
+    /// Returns the ranges in order.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Range<usize>> {
+        self.ranges.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// Returns the number of indices in the set.
+    pub fn get_index_count(&self) -> usize {
+        self.ranges.iter().map(|range| range.len()).sum()
+    }
+
+    /// Adds a range, merging it with any range it overlaps or touches. Empty ranges are ignored.
+    pub fn insert(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let first = self
+            .ranges
+            .partition_point(|existing| existing.end < range.start);
+        let last = self
+            .ranges
+            .partition_point(|existing| existing.start <= range.end);
+        if first == last {
+            self.ranges.insert(first, range);
+            return;
+        }
+        let start = range.start.min(self.ranges[first].start);
+        let end = range.end.max(self.ranges[last - 1].end);
+        self.ranges.splice(first..last, std::iter::once(start..end));
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.get_range_containing(index).is_some()
+    }
+
+    /// Returns the range of the set which holds `index`, if any.
+    pub fn get_range_containing(&self, index: usize) -> Option<&Range<usize>> {
+        let idx = self.ranges.partition_point(|range| range.end <= index);
+        self.ranges.get(idx).filter(|range| range.start <= index)
+    }
+
+    /// Returns `true` if any index of `range` is in the set.
+    pub fn intersects(&self, range: Range<usize>) -> bool {
+        let idx = self
+            .ranges
+            .partition_point(|existing| existing.end <= range.start);
+        self.ranges
+            .get(idx)
+            .is_some_and(|existing| existing.start < range.end && !range.is_empty())
+    }
+
+    /// Returns `true` if every index of `range` is in the set.
+    pub fn contains_range(&self, range: Range<usize>) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+        let idx = self
+            .ranges
+            .partition_point(|existing| existing.end <= range.start);
+        self.ranges
+            .get(idx)
+            .is_some_and(|existing| existing.start <= range.start && range.end <= existing.end)
+    }
+
+    /// Returns the indices which are in both sets.
+    pub fn intersection(&self, other: &RangeSet) -> RangeSet {
+        let mut result = RangeSet::new();
+        let (mut a, mut b) = (0, 0);
+        while a < self.ranges.len() && b < other.ranges.len() {
+            let (left, right) = (&self.ranges[a], &other.ranges[b]);
+            let start = left.start.max(right.start);
+            let end = left.end.min(right.end);
+            if start < end {
+                result.ranges.push(start..end);
+            }
+            if left.end < right.end {
+                a += 1;
+            } else {
+                b += 1;
+            }
+        }
+        result
+    }
+
+    /// Removes a range from the set, splitting any range which contains it.
+    pub fn remove(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let first = self
+            .ranges
+            .partition_point(|existing| existing.end <= range.start);
+        let last = self
+            .ranges
+            .partition_point(|existing| existing.start < range.end);
+        if first >= last {
+            return;
+        }
+        let mut remaining: Vec<Range<usize>> = Vec::with_capacity(2);
+        if self.ranges[first].start < range.start {
+            remaining.push(self.ranges[first].start..range.start);
+        }
+        if range.end < self.ranges[last - 1].end {
+            remaining.push(range.end..self.ranges[last - 1].end);
+        }
+        self.ranges.splice(first..last, remaining);
+    }
+
+    /// Returns the indices which are in either set.
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        let mut result = self.clone();
+        for range in other.iter() {
+            result.insert(range.clone());
+        }
+        result
+    }
+
+    /// Returns the indices which are in this set, but not in `other`.
+    pub fn difference(&self, other: &RangeSet) -> RangeSet {
+        let mut result = self.clone();
+        for range in other.iter() {
+            result.remove(range.clone());
+        }
+        result
+    }
+
+    /// Returns the indices within `bounds`, moved so that `bounds.start` becomes `new_start`,
+    /// such as the ranges of the part of a line which is moved onto a line of its own.
+    pub fn get_slice(&self, bounds: Range<usize>, new_start: usize) -> RangeSet {
+        let mut result = RangeSet::new();
+        for range in self.ranges.iter() {
+            let start = range.start.max(bounds.start);
+            let end = range.end.min(bounds.end);
+            if start < end {
+                result.insert(start - bounds.start + new_start..end - bounds.start + new_start);
+            }
+        }
+        result
+    }
+
+    /// Returns the indices within `bounds` which are not in the set.
+    pub fn get_complement(&self, bounds: Range<usize>) -> RangeSet {
+        let mut result = RangeSet::new();
+        result.insert(bounds);
+        result.difference(self)
+    }
+}
+
+impl FromIterator<Range<usize>> for RangeSet {
+    fn from_iter<I: IntoIterator<Item = Range<usize>>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
@@ -15,19 +15,84 @@
 
 // use fountain_enums::FNRangedElementType;
 
+pub mod fountain_anchor;
+pub mod fountain_annotations;
+pub mod fountain_av;
+pub mod fountain_bidi;
+pub mod fountain_builder;
+pub mod fountain_cards;
+pub mod fountain_characters;
+pub mod fountain_colors;
+pub mod fountain_compact;
+pub mod fountain_completion;
+pub mod fountain_convert;
+pub mod fountain_cue_sheet;
+pub mod fountain_dialogue_balance;
+pub mod fountain_diff;
+pub mod fountain_document;
+pub mod fountain_draft_report;
+pub mod fountain_dual_dialogue;
+pub mod fountain_duplicate_dialogue;
+pub mod fountain_editing;
+pub mod fountain_emphasis;
 pub mod fountain_enums;
+pub mod fountain_export_hooks;
+pub mod fountain_fingerprint;
+pub mod fountain_fixed_width;
+pub mod fountain_full_parse;
+#[cfg(feature = "fuzz")]
+pub mod fountain_fuzz;
+pub mod fountain_heading;
+pub mod fountain_layout;
 pub mod fountain_line;
+pub mod fountain_locations;
+pub mod fountain_locked_pages;
+#[cfg(feature = "lsp")]
+pub mod fountain_lsp;
+pub mod fountain_metrics;
+#[cfg(feature = "node")]
+pub mod fountain_node;
+pub mod fountain_notes;
+pub mod fountain_omitted;
+pub mod fountain_outline;
+pub mod fountain_pagination;
+pub mod fountain_parsed_document;
+pub mod fountain_partial_line_range;
+#[cfg(feature = "python")]
+mod fountain_python;
+pub mod fountain_query;
+pub mod fountain_scene;
+pub mod fountain_scrub;
+pub mod fountain_sentences;
+pub mod fountain_shot;
+pub mod fountain_split;
+pub mod fountain_storylines;
+pub mod fountain_stripped;
+pub mod fountain_symbols;
+pub mod fountain_templates;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fountain_test_support;
+pub mod fountain_text_extraction;
+pub mod fountain_text_width;
+pub mod fountain_title_page;
+pub mod fountain_transitions;
+pub mod fountain_uuid;
+#[cfg(feature = "watch")]
+pub mod fountain_watch;
+pub mod fountain_wrap;
+pub mod intervals;
 pub mod location_and_length;
+#[cfg(feature = "macros")]
+pub mod macros;
+pub mod partial_line_resolver;
 pub mod static_fountain_parser;
+pub mod static_fountain_preparser;
 
-mod fountain_partial_line_range;
 mod helper_funcs;
-mod partial_line_resolver;
-mod static_fountain_preparser;
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, fs};
+    use std::{collections::BTreeMap, fs};
     use unicode_segmentation::*;
 
     use crate::{
@@ -41,8 +106,7 @@ mod tests {
         let file_result: Result<String, std::io::Error> = fs::read_to_string(file_path);
         match file_result {
             Ok(text) => {
-                let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
-                print_all_lines_with_line_type(lines);
+                println!("{}", static_fountain_parser::parse_document(&text));
             }
             Err(_err) => {
                 panic!("File or file path was invalid.")
@@ -61,7 +125,7 @@ mod tests {
                 ));
 
             let indices: Option<
-                std::collections::HashMap<String, std::collections::HashMap<usize, Vec<usize>>>,
+                std::collections::BTreeMap<String, std::collections::BTreeMap<usize, Vec<usize>>>,
             > = partial_line_resolver::get_global_and_local_indices_of_ranged_element(
                 &unparsed_lines,
                 &FNRangedElementType::note(),
@@ -81,7 +145,7 @@ mod tests {
             ));
 
             let indices: Option<
-                std::collections::HashMap<String, std::collections::HashMap<usize, Vec<usize>>>,
+                std::collections::BTreeMap<String, std::collections::BTreeMap<usize, Vec<usize>>>,
             > = partial_line_resolver::get_global_and_local_indices_of_ranged_element(
                 &test_lines,
                 &FNRangedElementType::note(),
@@ -131,7 +195,7 @@ mod tests {
                 Some(document_string),
             );
 
-            let mut new_lines_map: HashMap<usize, FNLine> = HashMap::new();
+            let mut new_lines_map: BTreeMap<usize, FNLine> = BTreeMap::new();
 
             for (idx, ln) in unparsed_lines.iter().enumerate() {
                 let (local_opens, local_closes) =
@@ -142,7 +206,7 @@ mod tests {
                 let partial_fnline_result =
                     partial_line_resolver::get_local_partial_type_for_single_line(
                         ln,
-                        &ranged_element_type,
+                        ranged_element_type,
                         Some(&local_opens),
                         Some(&local_closes),
                     );
@@ -160,11 +224,7 @@ mod tests {
                 }
             }
 
-            let mut sorted_keys: Vec<usize> = new_lines_map.keys().copied().collect();
-            sorted_keys.sort();
-
-            for global_idx in sorted_keys {
-                let line = new_lines_map.get(&global_idx).unwrap();
+            for (global_idx, line) in new_lines_map.iter() {
                 println!(
                     "Line #{}\tPartial type from new line:{:?}\tRaw String from new line:{}",
                     global_idx, line.note_type, line.raw_string
@@ -193,6 +253,9 @@ mod tests {
                 );
             match partial_fnline_result {
                 Some(fnlinemap) => {
+                    // Lines come back in document order
+                    let indices: Vec<usize> = fnlinemap.keys().copied().collect();
+                    assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
                     for (idx, fnline) in fnlinemap.iter() {
                         println!(
                             "Line Index #: {} \tNote type: {:?}\tRaw String:{}",
@@ -207,67 +270,4425 @@ mod tests {
         }
     }
 
-    pub fn print_all_lines_with_line_type(lines: Vec<FNLine>) {
-        for ln in &lines {
-            println!("{:?}\t\t\t{}", ln.fn_type, ln.string);
+    #[cfg(feature = "macros")]
+    #[test]
+    pub fn test_macro_expansion() {
+        use crate::macros;
+
+        let text = String::from("{{x = Bob}}\nSCENE {{serial scene}}\n{{x}} walks in on {{date}}.\nSCENE {{serial scene}}");
+        let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(text));
+        let context = macros::FNMacroContext {
+            date: Some((2024, 3, 9)),
+            ..Default::default()
+        };
+        assert_eq!(macros::get_macros_from_lines(&lines).len(), 5);
+
+        let (expanded, source_map) = macros::get_expanded_lines_and_source_map(&lines, &context);
+        assert_eq!(expanded[0].string, "");
+        assert_eq!(expanded[1].string, "SCENE 1");
+        assert_eq!(expanded[2].string, "Bob walks in on 2024-03-09.");
+        assert_eq!(expanded[3].string, "SCENE 2");
+
+        let date_entry = &source_map[3];
+        assert_eq!(
+            &expanded[2].string[date_entry.expanded_start..date_entry.expanded_end],
+            "2024-03-09"
+        );
+        assert_eq!(
+            &lines[2].raw_string[date_entry.raw_start..date_entry.raw_end],
+            "{{date}}"
+        );
+
+        // Only the macros are replaced in the parsed `string`, so a forced cue stays without its `@`
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "{{x = Bob}}\n\n@{{x}}\nHi.",
+        ));
+        let (expanded, source_map) = macros::get_expanded_lines_and_source_map(&lines, &context);
+        assert_eq!(expanded[2].string, "Bob");
+        assert_eq!(expanded[2].raw_string, "@{{x}}");
+        assert_eq!((source_map[1].raw_start, source_map[1].raw_end), (1, 6));
+        assert_eq!(
+            (source_map[1].expanded_start, source_map[1].expanded_end),
+            (0, 3)
+        );
+    }
+
+    #[test]
+    pub fn test_character_introductions() {
+        use crate::fountain_characters;
+
+        let text = String::from(
+            "INT. KITCHEN - DAY\n\nMARY enters, holding a pan.\n\nMARY\nWhere is everyone?\n\nBOB (V.O.)\nUpstairs!\n\nEXT. GARDEN - DAY\n\nBob waves.\n\nBOB\nHi.",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+
+        let introductions = fountain_characters::get_character_introductions(&lines);
+        assert_eq!(introductions.len(), 2);
+        assert_eq!(introductions[0].name, "MARY");
+        assert_eq!(introductions[0].introduction_index, Some(2));
+        assert_eq!(introductions[0].first_cue_scene_index, Some(0));
+        assert_eq!(introductions[1].name, "BOB");
+        assert!(!introductions[1].is_introduced_before_speaking());
+
+        let unintroduced = fountain_characters::get_characters_speaking_before_introduction(
+            &lines,
+            &fountain_characters::FNCharacterAliasMap::default(),
+        );
+        assert_eq!(unintroduced.len(), 1);
+        assert_eq!(unintroduced[0].name, "BOB");
+    }
+
+    #[test]
+    pub fn test_character_alias_map() {
+        use crate::fountain_characters::{self, FNCharacterAliasMap};
+
+        let text = String::from(
+            "INT. PRECINCT - NIGHT\n\nDETECTIVE JANE SMITH reads a file.\n\nDET. SMITH\nNothing.\n\nSMITH\nStill nothing.\n\nJANE SMITH (O.S.)\nWait.",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("DET. SMITH", "JANE SMITH");
+        aliases.add_alias("smith", "Jane Smith");
+
+        let counts = fountain_characters::get_cue_counts_for_characters(&lines, &aliases);
+        assert_eq!(counts, vec![("JANE SMITH".to_string(), 3)]);
+
+        let introductions =
+            fountain_characters::get_character_introductions_with_aliases(&lines, &aliases);
+        assert_eq!(introductions.len(), 1);
+        assert_eq!(introductions[0].introduction_index, Some(2));
+        assert!(
+            fountain_characters::get_characters_speaking_before_introduction(&lines, &aliases)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    pub fn test_character_alias_map_order() {
+        use crate::fountain_characters::FNCharacterAliasMap;
+
+        let pairs = [
+            ("SMITH", "JANE SMITH"),
+            ("DET. SMITH", "SMITH"),
+            ("detective smith", "Det. Smith"),
+        ];
+        let orders = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        for order in orders {
+            let mut aliases = FNCharacterAliasMap::new();
+            for idx in order {
+                aliases.add_alias(pairs[idx].0, pairs[idx].1);
+            }
+            for name in ["SMITH", "DET. SMITH", "DETECTIVE SMITH", "JANE SMITH"] {
+                assert_eq!(
+                    aliases.get_canonical_name(name),
+                    "JANE SMITH",
+                    "{:?}",
+                    order
+                );
+            }
+            assert_eq!(
+                aliases.get_all_names_for("smith"),
+                vec!["DET. SMITH", "DETECTIVE SMITH", "JANE SMITH", "SMITH"]
+            );
         }
+
+        // An alias of itself doesn't undo an earlier merge
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("SMITH", "JANE SMITH");
+        aliases.add_alias("JANE SMITH", "SMITH");
+        assert_eq!(aliases.get_canonical_name("SMITH"), "JANE SMITH");
+        assert_eq!(aliases.get_canonical_name("JANE SMITH"), "JANE SMITH");
     }
+
     #[test]
-    pub fn get_partial_multiline_ranges() {
-        let file_result: Result<String, std::io::Error> = ranged_items_test_file_path_result();
+    pub fn test_location_clusters() {
+        use crate::fountain_locations::{self, FNLocationNormalizationOptions};
+
+        let text = String::from(
+            "INT. JOE'S BAR - NIGHT\n\nAction.\n\nINT. JOES BAR - DAY\n\nAction.\n\nEXT. JOE'S BAR\n\nAction.\n\nINT. JOE'S BARR - NIGHT\n\nAction.",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+
+        let exact = fountain_locations::get_location_clusters(
+            &lines,
+            &FNLocationNormalizationOptions::default(),
+        );
+        assert_eq!(exact.len(), 2);
+        assert_eq!(exact[0].canonical_name, "JOE'S BAR");
+        assert_eq!(exact[0].normalized_name, "JOES BAR");
+        assert_eq!(exact[0].heading_indices, vec![0, 4, 8]);
+
+        let fuzzy = fountain_locations::get_location_clusters(
+            &lines,
+            &FNLocationNormalizationOptions {
+                fuzzy_max_distance: Some(1),
+            },
+        );
+        assert_eq!(fuzzy.len(), 1);
+        assert_eq!(fuzzy[0].variants.len(), 3);
+    }
+
+    #[test]
+    pub fn test_continuous_scene_linking() {
+        use crate::fountain_heading::FNTimeModifier;
+        use crate::fountain_scene;
+
+        let text = String::from(
+            "INT. HALLWAY - NIGHT\n\nShe runs.\n\nINT. STAIRWELL - CONTINUOUS\n\nShe runs down.\n\nEXT. STREET - MOMENTS LATER\n\nShe's out.\n\nINT. HOME - DAY\n\nShe sleeps.",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+        let scenes = fountain_scene::get_scenes_from_parsed_lines(&lines);
+
+        assert_eq!(scenes.len(), 4);
+        assert_eq!(scenes[0].continues_from, None);
+        assert_eq!(scenes[1].time_modifier, Some(FNTimeModifier::Continuous));
+        assert_eq!(scenes[1].continues_from, Some(0));
+        assert_eq!(scenes[2].time_modifier, Some(FNTimeModifier::MomentsLater));
+        assert_eq!(scenes[2].continues_from, Some(1));
+        assert_eq!(scenes[3].continues_from, None);
+        assert_eq!(
+            fountain_scene::get_continuous_scene_sequences(&scenes),
+            vec![vec![0, 1, 2]]
+        );
+    }
+
+    #[test]
+    pub fn test_shot_parsing() {
+        use crate::fountain_shot::{self, FNShotKind, FNShotParsingOptions};
+
+        let text = String::from(
+            "INT. KITCHEN - DAY\n\n!!CLOSE ON: THE KNIFE\n\nANGLE ON THE DOOR\n\nMARY'S POV\n\nCUT IS NOT A SHOT\n\n!!SLOW PUSH IN",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+
+        let shots = fountain_shot::get_shots_from_lines(&lines, &FNShotParsingOptions::default());
+        assert_eq!(shots.len(), 4);
+        assert_eq!(shots[0].kind, FNShotKind::CloseUp);
+        assert_eq!(shots[0].subject, "THE KNIFE");
+        assert!(shots[0].is_forced);
+        assert_eq!(shots[1].kind, FNShotKind::Angle);
+        assert_eq!(shots[1].subject, "THE DOOR");
+        assert!(!shots[1].is_forced);
+        assert_eq!(shots[2].kind, FNShotKind::PointOfView);
+        assert_eq!(shots[2].subject, "MARY");
+        assert_eq!(shots[3].kind, FNShotKind::Other);
+
+        let forced_only = fountain_shot::get_shots_from_lines(
+            &lines,
+            &FNShotParsingOptions {
+                detect_uppercase_action: false,
+            },
+        );
+        assert_eq!(forced_only.len(), 2);
+
+        // Uppercasing these changes their length in bytes
+        for text in ["\n!!OTſ THE DOOR\n", "\n!!ıé POV\n"] {
+            let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text.to_string());
+            let shots =
+                fountain_shot::get_shots_from_lines(&lines, &FNShotParsingOptions::default());
+            assert_eq!(shots.len(), 1);
+        }
+    }
+
+    #[test]
+    pub fn test_standalone_transitions() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_transitions::{self, FNStandaloneTransitionKind};
+
+        let text = String::from(
+            "FADE IN:\n\nINT. KITCHEN - DAY\n\nMARY\nThe end.\n\nFADE OUT.\n\nTHE END",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+
+        let (new_lines, transitions) = fountain_transitions::get_lines_with_standalone_transitions(
+            &lines,
+            &Default::default(),
+        );
+        assert_eq!(
+            transitions,
+            vec![
+                (0, FNStandaloneTransitionKind::FadeIn),
+                (7, FNStandaloneTransitionKind::FadeOut),
+                (9, FNStandaloneTransitionKind::TheEnd),
+            ]
+        );
+        assert!(transitions[0].1.is_left_aligned());
+        assert_eq!(new_lines[7].fn_type, FNLineType::TransitionLine);
+        assert_eq!(new_lines[5].fn_type, FNLineType::Dialogue);
+
+        // Lines forced to be action stay action, at the start of the document too
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "!FADE IN:\n\nINT. KITCHEN - DAY\n\n!THE END",
+        ));
+        assert_eq!(lines[0].fn_type, FNLineType::Action);
+        let (new_lines, transitions) = fountain_transitions::get_lines_with_standalone_transitions(
+            &lines,
+            &Default::default(),
+        );
+        assert!(transitions.is_empty());
+        assert_eq!(new_lines[0].fn_type, FNLineType::Action);
+
+        // The full parse re-types them too, and `FADE IN:` doesn't start a title page
+        let full = parse_document_full(
+            "FADE IN:\n\nINT. KITCHEN - DAY\n\nMary waits.\n\nTHE END",
+            &FNFullParseConfig::default(),
+        );
+        assert!(full.title_page.is_empty());
+        assert_eq!(
+            full.standalone_transitions,
+            vec![
+                (0, FNStandaloneTransitionKind::FadeIn),
+                (6, FNStandaloneTransitionKind::TheEnd),
+            ]
+        );
+        assert_eq!(full.lines[0].fn_type, FNLineType::TransitionLine);
+        assert_eq!(full.lines[6].fn_type, FNLineType::TransitionLine);
+        let full = parse_document_full(
+            "Mary waits.\n\nThe end.",
+            &FNFullParseConfig {
+                standalone_transitions: None,
+                ..Default::default()
+            },
+        );
+        assert!(full.standalone_transitions.is_empty());
+        assert_eq!(full.lines[2].fn_type, FNLineType::Action);
+    }
+
+    #[test]
+    pub fn test_document_snapshots() {
+        use crate::fountain_document::FNDocument;
+
+        let mut document = FNDocument::new(String::from("INT. HOUSE - DAY\n\nA dog barks."));
+        let first = document.snapshot();
+        assert_eq!(first.get_version(), 0);
+
+        let mut changed = document.get_line(2).unwrap().clone();
+        changed.string = String::from("A cat meows.");
+        assert_eq!(document.replace_line(2, changed), Some(1));
+        assert_eq!(document.replace_line(99, FNLine::default()), None);
+
+        let second = document.snapshot();
+        assert_eq!(second.get_version(), 1);
+        assert_eq!(first.get_line(2).unwrap().string, "A dog barks.");
+        assert_eq!(second.get_line(2).unwrap().string, "A cat meows.");
+        assert!(first.shares_line_with(&second, 0));
+        assert!(!first.shares_line_with(&second, 2));
+    }
+
+    #[test]
+    pub fn test_anchors_survive_edits() {
+        use crate::fountain_document::FNDocument;
+
+        let text = String::from("INT. HOUSE - DAY\n\nA dog barks.\n\nA cat meows.");
+        let mut document = FNDocument::new(text);
+        let anchor = document.create_anchor(4, 2).unwrap();
+
+        document.insert_line(0, FNLine::default());
+        assert_eq!(document.resolve_anchor(&anchor).unwrap().line_index, 5);
+
+        let reparsed = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. HOUSE - NIGHT\n\nA dog barks.\n\nA cat meows.",
+        ));
+        document.replace_all_lines(reparsed);
+        let position = document.resolve_anchor(&anchor).unwrap();
+        assert_eq!(position.line_index, 4);
+        assert_eq!(position.local_offset, 2);
+
+        document.remove_line(4);
+        assert_eq!(document.resolve_anchor(&anchor), None);
+
+        // Unchanged lines between two edits keep their identity too
+        let mut document = FNDocument::new(String::from(
+            "INT. HOUSE - DAY\n\nA dog barks.\n\nA cat meows.\n\nTHE END",
+        ));
+        let anchor = document.create_anchor(4, 2).unwrap();
+        document.replace_all_lines(static_fountain_parser::get_parsed_lines_from_raw_string(
+            String::from(
+                "INT. HOUSE - NIGHT\n\nA dog barks.\n\nA bird sings.\n\nA cat meows.\n\nFIN",
+            ),
+        ));
+        assert_eq!(document.resolve_anchor(&anchor).unwrap().line_index, 6);
+    }
+
+    #[test]
+    pub fn test_annotation_notes_round_trip() {
+        use crate::fountain_annotations::{self, FNAnnotation, FNAnnotationLayer};
+        use crate::fountain_document::FNDocument;
+
+        let document = FNDocument::new(String::from(
+            "INT. HOUSE - DAY\n\nThe door creaks open.[[Regular note]]",
+        ));
+        let mut layer = FNAnnotationLayer::new();
+        let mut annotation =
+            FNAnnotation::new(document.create_anchor(2, 21).unwrap(), "Jane", "Too quiet?");
+        annotation.timestamp = 1712345678;
+        layer.add_annotation(annotation);
+
+        let text =
+            fountain_annotations::get_document_text_with_annotations_as_notes(&document, &layer);
+        assert_eq!(
+            text,
+            "INT. HOUSE - DAY\n\nThe door creaks open.[[@Jane (1712345678): Too quiet?]][[Regular note]]"
+        );
+
+        let mut reloaded = FNDocument::new(text);
+        let extracted = fountain_annotations::extract_annotations_from_document(&mut reloaded);
+        assert_eq!(
+            reloaded.get_line(2).unwrap().raw_string,
+            "The door creaks open.[[Regular note]]"
+        );
+        let resolved = extracted.get_resolved_annotations(&reloaded);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0.author, "Jane");
+        assert_eq!(resolved[0].0.timestamp, 1712345678);
+        assert_eq!(resolved[0].0.text, "Too quiet?");
+        assert_eq!(resolved[0].1.local_offset, 21);
+    }
+
+    #[test]
+    pub fn test_prose_chunks_for_spellcheck() {
+        use crate::fountain_text_extraction;
+
+        let text = String::from(
+            "INT. HOUSE - DAY\n\nA *big* dog[[a note]] barks.\n/* hidden\nstill hidden */Seen.\n\nMARY\nHello [[unclosed\n\n= A synopsis",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+        let chunks = fountain_text_extraction::get_prose_chunks_for_spellcheck(&lines);
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                "A",
+                "big",
+                "dog",
+                "barks.",
+                "Seen.",
+                "Hello [[unclosed",
+                "A synopsis"
+            ]
+        );
+        let seen = &chunks[4];
+        assert_eq!(seen.line_index, 4);
+        assert_eq!(
+            &lines[4].raw_string[seen.local_start..seen.local_end],
+            "Seen."
+        );
+    }
+
+    #[test]
+    pub fn test_sentence_segmentation() {
+        use crate::fountain_sentences::{self, FNSentenceBlockKind};
+
+        let text = String::from(
+            "INT. HOUSE - DAY\n\nA dog barks. The cat\nignores it.\n\nMARY\nQuiet! Please, *both* of you.",
+        );
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text);
+        let sentences = fountain_sentences::get_sentences_from_lines(&lines);
+        let texts: Vec<&str> = sentences.iter().map(|s| s.text.as_str()).collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                "A dog barks.",
+                "The cat ignores it.",
+                "Quiet!",
+                "Please, both of you."
+            ]
+        );
+        assert_eq!(sentences[1].kind, FNSentenceBlockKind::Action);
+        assert_eq!(sentences[1].start_line_index, 2);
+        assert_eq!(sentences[1].start_local_index, 13);
+        assert_eq!(sentences[1].end_line_index, 3);
+        assert_eq!(sentences[1].end_local_index, 11);
+        assert_eq!(sentences[3].kind, FNSentenceBlockKind::Dialogue);
+        assert_eq!(sentences[3].end_local_index, lines[6].raw_string.len());
+    }
+
+    #[test]
+    pub fn test_semantic_diff() {
+        use crate::fountain_diff::{self, FNLineChange};
+        use crate::fountain_enums::FNLineType;
+
+        let old = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. HOUSE - DAY\n\nMARY\nHello.\n\nThe door shuts.",
+        ));
+        let new = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. HOUSE - NIGHT\n\nMARY\n\nThe door shuts.\nFin.",
+        ));
+
+        assert_eq!(
+            fountain_diff::get_semantic_diff(&old, &new),
+            vec![
+                FNLineChange::TextChanged {
+                    old_index: 0,
+                    new_index: 0
+                },
+                FNLineChange::Retyped {
+                    old_index: 2,
+                    new_index: 2,
+                    old_type: FNLineType::Character,
+                    new_type: FNLineType::Action,
+                },
+                FNLineChange::Removed { old_index: 3 },
+                FNLineChange::Inserted { new_index: 5 },
+            ]
+        );
+
+        // Edits far apart in a long document, which a quadratic table couldn't hold
+        let old: Vec<FNLine> = (0..50000)
+            .map(|i| FNLine {
+                raw_string: format!("Line {}.", i),
+                ..Default::default()
+            })
+            .collect();
+        let mut new = old.clone();
+        new[100].raw_string = String::from("Changed.");
+        new.insert(25000, FNLine::default());
+        new.remove(40001);
+        assert_eq!(
+            fountain_diff::get_semantic_diff(&old, &new),
+            vec![
+                FNLineChange::TextChanged {
+                    old_index: 100,
+                    new_index: 100
+                },
+                FNLineChange::Inserted { new_index: 25000 },
+                FNLineChange::Removed { old_index: 40000 },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_emphasis_protects_urls_and_identifiers() {
+        use crate::fountain_emphasis::{
+            self, FNEmphasisInterpretation, FNEmphasisKind, FNEmphasisOptions,
+        };
+
+        let text =
+            "See https://example.com/some_page_name and my_var_name, *really* _now_ **bold**.";
+        let strict =
+            fountain_emphasis::get_emphasis_spans_from_string(text, &FNEmphasisOptions::default());
+        let kinds: Vec<FNEmphasisKind> = strict.iter().map(|s| s.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                FNEmphasisKind::Italic,
+                FNEmphasisKind::Underline,
+                FNEmphasisKind::Bold
+            ]
+        );
+        assert_eq!(
+            &text[strict[0].content_start..strict[0].content_end],
+            "really"
+        );
+
+        let permissive = fountain_emphasis::get_emphasis_spans_from_string(
+            text,
+            &FNEmphasisOptions {
+                interpretation: FNEmphasisInterpretation::Permissive,
+            },
+        );
+        assert_eq!(permissive[0].kind, FNEmphasisKind::Underline);
+        assert_eq!(
+            &text[permissive[0].content_start..permissive[0].content_end],
+            "page"
+        );
+
+        let lines =
+            static_fountain_parser::get_parsed_lines_from_raw_string(String::from("A *big* dog."));
+        let emphasized =
+            fountain_emphasis::get_lines_with_emphasis(&lines, &FNEmphasisOptions::default());
+        assert_eq!(emphasized[0].italic_ranges.get_index_count(), 3);
+        assert!(emphasized[0].italic_ranges.contains(3));
+
+        // A closer pairs with the innermost opener, and unpaired openers don't slow the search down
+        let text = "*a *b* c*";
+        let nested =
+            fountain_emphasis::get_emphasis_spans_from_string(text, &FNEmphasisOptions::default());
+        let contents: Vec<&str> = nested
+            .iter()
+            .map(|span| &text[span.content_start..span.content_end])
+            .collect();
+        assert_eq!(contents, vec!["a *b* c", "b"]);
+        let text = format!("{}*end*", "*open ".repeat(50_000));
+        let spans =
+            fountain_emphasis::get_emphasis_spans_from_string(&text, &FNEmphasisOptions::default());
+        assert_eq!(spans.len(), 1);
+    }
+
+    #[test]
+    pub fn test_forced_character_cues() {
+        use crate::fountain_enums::FNLineType;
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Intro.\n\n@McCONAUGHEY\nAlright, alright.\n\n@lucy (V.O.) ^\nHey.\n",
+        ));
+        assert_eq!(lines[2].fn_type, FNLineType::Character);
+        assert!(lines[2].forced_character_cue);
+        assert_eq!(lines[2].string, "McCONAUGHEY");
+        assert_eq!(lines[2].raw_string, "@McCONAUGHEY");
+        assert_eq!(lines[3].fn_type, FNLineType::Dialogue);
+
+        assert_eq!(lines[5].fn_type, FNLineType::DualDialogueCharacter);
+        assert!(lines[5].forced_character_cue);
+        assert_eq!(lines[5].get_character_name(), Some(String::from("lucy")));
+        assert_eq!(lines[6].fn_type, FNLineType::DualDialogue);
+
+        // Parsing the lines again keeps the cues forced
+        let reparsed = static_fountain_parser::get_parsed_lines_from_line_vec(lines.clone());
+        assert_eq!(reparsed, lines);
+    }
+
+    #[test]
+    pub fn test_forced_by_markers() {
+        use crate::fountain_enums::FNForcedBy;
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "# Act One\n\n= The setup\n\n.SNIPER NEST\n\n!ALL CAPS ACTION\n\n@McCLANE\n~Yippee ki-yay\n\n> THE END <\n\nINT. HOUSE - DAY\n",
+        ));
+        let forced: Vec<FNForcedBy> = lines.iter().map(|ln| ln.forced_by.clone()).collect();
+        assert_eq!(forced[0], FNForcedBy::Hash);
+        assert_eq!(forced[2], FNForcedBy::Equals);
+        assert_eq!(forced[4], FNForcedBy::Dot);
+        assert_eq!(forced[6], FNForcedBy::Bang);
+        assert_eq!(forced[8], FNForcedBy::At);
+        assert_eq!(forced[9], FNForcedBy::Tilde);
+        assert_eq!(forced[11], FNForcedBy::GreaterThan);
+        assert_eq!(forced[13], FNForcedBy::None);
+        assert_eq!(
+            lines[4]
+                .raw_string
+                .strip_prefix(forced[4].get_marker().unwrap()),
+            Some("SNIPER NEST")
+        );
+    }
+
+    #[test]
+    pub fn test_outline_section_titles() {
+        use crate::fountain_outline::{self, FNOutlineElementKind};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "# Act One\n\n### Sequence 3 ###\n\n.ROOFTOP #4#\n\nAction.\n",
+        ));
+        assert_eq!(lines[2].section_depth, 3);
+        assert_eq!(
+            lines[2].get_section_title(),
+            Some(String::from("Sequence 3"))
+        );
+
+        let outline = fountain_outline::get_outline_from_lines(&lines);
+        assert_eq!(outline.len(), 3);
+        assert_eq!(outline[0].title, "Act One");
+        assert_eq!(outline[0].depth, 1);
+        assert_eq!(outline[1].title, "Sequence 3");
+        assert_eq!(outline[2].kind, FNOutlineElementKind::Heading);
+        assert_eq!(outline[2].title, "ROOFTOP");
+        assert_eq!(outline[2].depth, 3);
+        assert_eq!(outline[2].scene_number, Some(String::from("4")));
+    }
+
+    #[test]
+    pub fn test_multiline_notes_terminate_at_empty_lines() {
+        use crate::fountain_partial_line_range::FNRangedElementDiagnosticKind;
+
         let ranged_element_type = FNRangedElementType::note();
-        if let Ok(document_string) = file_result {
-            println!("I am here");
-            let unparsed_lines: Vec<FNLine> =
-                static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
-                    document_string,
-                ));
-            let partial_map_opt =
-                partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
-                    &unparsed_lines,
-                    &ranged_element_type,
-                );
-            if let Some(partial_map) = partial_map_opt {
-                let multiline_ranges =
-                    partial_line_resolver::get_partial_multiline_ranges_from_partial_map(
-                        &partial_map,
-                        &unparsed_lines,
-                        &ranged_element_type,
-                    );
-                for range in multiline_ranges {
-                    let start = range.global_start.unwrap();
-                    let end = range.global_end.unwrap();
-                    println!("---------------------------");
-                    for i in (start..=end) {
-                        if let Some(ln) = unparsed_lines.get(i) {
-                            let gphs: String = ln.raw_string.graphemes(true).take(50).collect();
-                            println!("Line #{},\t Raw String:{}", i, ln.raw_string);
-                        }
-                    }
-                    println!("Range: {:#?}", range);
+        let unparsed_lines: Vec<FNLine> =
+            static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(String::from(
+                "Text [[open note\n\nStill text]] here.\n\nOther [[a\n  \nb]] end.\n",
+            )));
+        let partial_map = partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+            &unparsed_lines,
+            &ranged_element_type,
+        )
+        .unwrap();
+        let (ranges, diagnostics) =
+            partial_line_resolver::get_partial_multiline_ranges_and_diagnostics_from_partial_map(
+                &partial_map,
+                &unparsed_lines,
+                &ranged_element_type,
+            );
 
-                    println!("---------------------------");
-                }
+        // The two-space line doesn't end the second note
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].global_start, Some(4));
+        assert_eq!(ranges[0].global_end, Some(6));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].kind,
+            FNRangedElementDiagnosticKind::UnclosedOpen
+        );
+        assert_eq!(diagnostics[0].global_index, 0);
+        assert_eq!(diagnostics[0].local_index, 5);
+    }
+
+    #[test]
+    pub fn test_invisibles_nesting_and_overlap_policy() {
+        use crate::fountain_text_extraction::get_visible_byte_ranges_for_lines;
+
+        let visible_text = |text: &str| -> Vec<String> {
+            let lines =
+                static_fountain_parser::get_parsed_lines_from_raw_string(String::from(text));
+            get_visible_byte_ranges_for_lines(&lines)
+                .iter()
+                .zip(lines.iter())
+                .map(|(ranges, ln)| {
+                    ranges
+                        .iter()
+                        .map(|(start, end)| &ln.raw_string[*start..*end])
+                        .collect::<String>()
+                })
+                .collect()
+        };
+
+        // Boneyards nest
+        assert_eq!(visible_text("A /* b /* c */ d */ e."), vec!["A  e."]);
+        assert_eq!(
+            visible_text("A /* b\n/* c */\nd */ e."),
+            vec!["A ", "", " e."]
+        );
+        // Notes mean nothing inside a boneyard
+        assert_eq!(visible_text("A /* [[ b */ c ]] d."), vec!["A  c ]] d."]);
+        // Boneyards mean nothing inside a note
+        assert_eq!(visible_text("A [[ /* b ]] c */ d."), vec!["A  c */ d."]);
+    }
+
+    #[test]
+    pub fn test_visible_text_on_partial_lines() {
+        let unparsed_lines: Vec<FNLine> =
+            static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(String::from(
+                "Text [[note]] more.\n\nA /* gone\nstill gone */ back.\n",
+            )));
+
+        let note_map = partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+            &unparsed_lines,
+            &FNRangedElementType::note(),
+        )
+        .unwrap();
+        let note_ranges = partial_line_resolver::create_single_line_partial_line_ranges(
+            &note_map,
+            &unparsed_lines,
+        );
+        assert_eq!(note_ranges.len(), 1);
+        assert_eq!(note_ranges[0].global_index, Some(0));
+        assert_eq!(
+            (note_ranges[0].local_start, note_ranges[0].local_end),
+            (Some(5), Some(13))
+        );
+        assert_eq!(
+            note_ranges[0].visible_fnline.as_ref().unwrap().string,
+            "Text  more."
+        );
+
+        let boneyard_map = partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+            &unparsed_lines,
+            &FNRangedElementType::boneyard(),
+        )
+        .unwrap();
+        let boneyard_ranges = partial_line_resolver::create_single_line_partial_line_ranges(
+            &boneyard_map,
+            &unparsed_lines,
+        );
+        let visible: Vec<String> = boneyard_ranges
+            .iter()
+            .map(|r| r.visible_fnline.as_ref().unwrap().string.clone())
+            .collect();
+        assert_eq!(visible, vec!["A ", " back."]);
+    }
+
+    #[test]
+    pub fn test_parse_document_full() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+
+        let text = "Title: Big Fish\nContact:\n    Some Agency\n    555-1234\n\n# Act One\n\nINT. HOUSE - DAY\n\nA *dog* [[good boy]] barks.\n/* cut\nthis */ Then [[never closed\n\nDone.\n";
+        let full = parse_document_full(text, &FNFullParseConfig::default());
+
+        assert_eq!(full.title_page.len(), 2);
+        assert_eq!(full.title_page[0].values, vec!["Big Fish"]);
+        assert_eq!(full.title_page[1].key, "contact");
+        assert_eq!(full.title_page[1].values, vec!["Some Agency", "555-1234"]);
+
+        assert_eq!(full.outline.len(), 2);
+        assert_eq!(full.outline[1].title, "INT. HOUSE - DAY");
+
+        assert!(!full.lines[9].italic_ranges.is_empty());
+        assert_eq!(full.notes.partial_lines[0].global_index, Some(9));
+        assert_eq!(full.boneyards.multiline_ranges.len(), 1);
+        assert_eq!(full.diagnostics.len(), 1);
+        assert_eq!(full.diagnostics[0].global_index, 11);
+    }
+
+    #[test]
+    pub fn test_preparser_newline_policy_and_positions() {
+        use crate::static_fountain_preparser::{self, FNNewlinePolicy, FNPreparseOptions};
+
+        let text = "Line one\r\nLíne two\rLine three\n";
+        let default_lines = static_fountain_preparser::get_unparsed_lines_from_raw_string(
+            text,
+            &FNPreparseOptions::default(),
+        );
+        assert_eq!(default_lines.len(), 2);
+        assert_eq!(default_lines[1].raw_string, "Líne two\rLine three");
+
+        let lines = static_fountain_preparser::get_unparsed_lines_from_raw_string(
+            text,
+            &FNPreparseOptions {
+                newline_policy: FNNewlinePolicy::NormalizeCrLfAndCr,
+                ..Default::default()
+            },
+        );
+        let positions: Vec<i32> = lines.iter().map(|ln| ln.position).collect();
+        assert_eq!(positions, vec![0, 9, 18]);
+        let terminator_lengths: Vec<i32> = lines.iter().map(|ln| ln.terminator_length).collect();
+        assert_eq!(terminator_lengths, vec![1, 1, 1]);
+
+        let preserved = static_fountain_preparser::get_unparsed_lines_from_raw_string(
+            text,
+            &FNPreparseOptions {
+                newline_policy: FNNewlinePolicy::Preserve,
+                ..Default::default()
+            },
+        );
+        let raw_strings: Vec<&str> = preserved.iter().map(|ln| ln.raw_string.as_str()).collect();
+        assert_eq!(raw_strings, vec!["Line one", "Líne two\rLine three"]);
+        let terminator_lengths: Vec<i32> =
+            preserved.iter().map(|ln| ln.terminator_length).collect();
+        assert_eq!(terminator_lengths, vec![2, 1]);
+        let offsets = static_fountain_preparser::get_byte_offsets_for_lines(&preserved);
+        assert_eq!(offsets, vec![0, 10]);
+        assert!(text[offsets[1]..].starts_with("Líne two"));
+        // `\r\n` is a single grapheme, so positions don't depend on the policy
+        assert_eq!(preserved[1].position, default_lines[1].position);
+    }
+
+    #[test]
+    pub fn test_line_type_conversions() {
+        use crate::fountain_enums::FNLineType;
+
+        for fn_type in FNLineType::vec_of_line_types() {
+            let number = u8::from(&fn_type);
+            assert_eq!(FNLineType::try_from(number), Ok(fn_type.clone()));
+            assert_eq!(fn_type.to_string().parse::<FNLineType>(), Ok(fn_type));
+        }
+        assert_eq!(u8::from(FNLineType::Heading), 10);
+        assert_eq!(u8::from(FNLineType::PartialLine), 100);
+        assert!(FNLineType::DualDialogueMore.is_dialogue_kind());
+        assert!(FNLineType::TitlePageUnknown.is_title_page_kind());
+        assert!(!FNLineType::Action.is_character_kind());
+        assert_eq!(
+            "dualdialogue".parse::<FNLineType>(),
+            Ok(FNLineType::DualDialogue)
+        );
+        assert!(FNLineType::try_from(42).is_err());
+        assert!("Scene".parse::<FNLineType>().is_err());
+    }
+
+    #[test]
+    pub fn test_line_and_document_builders() {
+        use crate::fountain_builder::FNDocumentBuilder;
+        use crate::fountain_enums::{FNForcedBy, FNLineType};
+
+        let line = FNLine::new("INT. HOUSE - DAY", FNLineType::Heading)
+            .with_scene_number("1")
+            .with_forced_by(FNForcedBy::Dot);
+        assert_eq!(line.length, 16);
+        assert!(line.is_forced);
+        assert_eq!(line.scene_number, "1");
+
+        let mut builder = FNDocumentBuilder::new();
+        builder
+            .push_line(line)
+            .push_empty()
+            .push("Café.", FNLineType::Action);
+        assert_eq!(builder.get_text(), "INT. HOUSE - DAY\n\nCafé.\n");
+
+        let positions: Vec<i32> = builder.get_lines().iter().map(|ln| ln.position).collect();
+        let parsed_positions: Vec<i32> =
+            static_fountain_parser::get_parsed_lines_from_raw_string(builder.get_text())
+                .iter()
+                .map(|ln| ln.position)
+                .collect();
+        assert_eq!(positions, vec![0, 17, 18]);
+        assert_eq!(positions, parsed_positions);
+
+        let document = builder.build_document();
+        assert_eq!(document.len(), 3);
+        assert!(document.get_line(2).unwrap().uuid.is_some());
+    }
+
+    #[test]
+    pub fn test_script_builder() {
+        use crate::fountain_builder::FNScriptBuilder;
+        use crate::fountain_enums::FNLineType;
+
+        let mut builder = FNScriptBuilder::new();
+        builder
+            .section("Act One", 1)
+            .scene("INT. TAVERN - NIGHT")
+            .action("The bard tunes a lute.\nNOBODY CARES")
+            .dialogue("Bard", "(to the room)\nA song!")
+            .scene("the road")
+            .transition("FADE OUT.");
+        assert_eq!(
+            builder.get_text(),
+            "# Act One\n\nINT. TAVERN - NIGHT\n\nThe bard tunes a lute.\nNOBODY CARES\n\n@Bard\n(to the room)\nA song!\n\n.the road\n\nFADE OUT.\n"
+        );
+
+        let types: Vec<FNLineType> = builder
+            .build()
+            .into_iter()
+            .map(|ln| ln.fn_type)
+            .filter(|t| *t != FNLineType::Empty)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                FNLineType::Section,
+                FNLineType::Heading,
+                FNLineType::Action,
+                FNLineType::Action,
+                FNLineType::Character,
+                FNLineType::Parenthetical,
+                FNLineType::Dialogue,
+                FNLineType::Heading,
+                FNLineType::TransitionLine,
+            ]
+        );
+
+        // A block is parsed after the element before it, so a dual dialogue cue still follows its dialogue
+        let mut builder = FNScriptBuilder::new();
+        builder
+            .title_page(&[("Title", "Duet")])
+            .scene("INT. STAGE - NIGHT")
+            .dialogue("BOB", "Hi.")
+            .dialogue("ALICE ^", "Hey.");
+        let types: Vec<FNLineType> = builder.build().into_iter().map(|ln| ln.fn_type).collect();
+        assert_eq!(types[7], FNLineType::DualDialogueCharacter);
+        assert_eq!(types[8], FNLineType::DualDialogue);
+    }
+
+    #[test]
+    pub fn test_templates() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_outline::FNOutlineElementKind;
+        use crate::fountain_templates::{self, FNTVEpisodeOptions, FNTemplateTitlePage};
+
+        let mut title_page = FNTemplateTitlePage::new("Big Fish", "John August");
+        title_page.contact = Some(String::from("Some Agency\n555-1234"));
+
+        let screenplay = fountain_templates::new_screenplay(&title_page);
+        let full = parse_document_full(&screenplay, &FNFullParseConfig::default());
+        let keys: Vec<&str> = full.title_page.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["title", "credit", "author", "contact"]);
+        assert_eq!(full.title_page[3].values, vec!["Some Agency", "555-1234"]);
+        let sections: Vec<&str> = full
+            .outline
+            .iter()
+            .filter(|e| e.kind == FNOutlineElementKind::Section)
+            .map(|e| e.title.as_str())
+            .collect();
+        assert_eq!(sections, vec!["Act One", "Act Two", "Act Three"]);
+
+        let episode = fountain_templates::new_tv_episode(
+            &title_page,
+            &FNTVEpisodeOptions {
+                has_tag: true,
+                ..Default::default()
+            },
+        );
+        let full = parse_document_full(&episode, &FNFullParseConfig::default());
+        let sections: Vec<&str> = full
+            .outline
+            .iter()
+            .filter(|e| e.kind == FNOutlineElementKind::Section)
+            .map(|e| e.title.as_str())
+            .collect();
+        assert_eq!(
+            sections,
+            vec!["Teaser", "Act 1", "Act 2", "Act 3", "Act 4", "Tag"]
+        );
+    }
+
+    #[test]
+    pub fn test_dual_dialogue_layout() {
+        use crate::fountain_dual_dialogue::{self, FNDualDialogueLayoutOptions};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Intro.\n\nBRICK\nScrew retirement, and screw you.\n\nSTEEL ^\n(shouting)\nNo!\n\nThey fight.\n",
+        ));
+        let blocks = fountain_dual_dialogue::get_dual_dialogue_blocks(&lines);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].left_start, blocks[0].left_end), (2, 4));
+        assert_eq!((blocks[0].right_start, blocks[0].right_end), (5, 8));
+
+        let layout = fountain_dual_dialogue::get_dual_dialogue_layout(
+            &lines,
+            &blocks[0],
+            &FNDualDialogueLayoutOptions {
+                total_width: 34,
+                gutter: 2,
+            },
+        );
+        assert_eq!(layout.column_width, 16);
+        assert_eq!(
+            layout.get_fixed_width_lines(),
+            vec![
+                "BRICK             STEEL",
+                "Screw             (shouting)",
+                "retirement, and   No!",
+                "screw you.",
+            ]
+        );
+
+        // Notes, boneyards, and emphasis markers aren't printed
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "BRICK\n*Go* [[louder?]] on.\n\nSTEEL ^\nNo! /* cut\nthis */\n",
+        ));
+        let blocks = fountain_dual_dialogue::get_dual_dialogue_blocks(&lines);
+        let layout = fountain_dual_dialogue::get_dual_dialogue_layout(
+            &lines,
+            &blocks[0],
+            &FNDualDialogueLayoutOptions::default(),
+        );
+        assert_eq!(
+            layout.get_fixed_width_lines(),
+            vec![
+                "BRICK                           STEEL",
+                "Go on.                          No!"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_fixed_width_renderer() {
+        use crate::fountain_fixed_width::{self, FNFixedWidthElementLayout, FNFixedWidthOptions};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Test\n\n# Act One\n\nINT. HOUSE - DAY\n\nA *dog* [[good boy]] barks.\n\nBOB\n(quietly)\nHello there, old friend.\n\nCUT TO:\n",
+        ));
+        let options = FNFixedWidthOptions {
+            action: FNFixedWidthElementLayout::new(0, 30),
+            character: FNFixedWidthElementLayout::new(12, 18),
+            dialogue: FNFixedWidthElementLayout::new(5, 15),
+            parenthetical: FNFixedWidthElementLayout::new(9, 12),
+            transition: FNFixedWidthElementLayout::new(0, 30),
+        };
+        assert_eq!(
+            fountain_fixed_width::get_fixed_width_text(&lines, &options),
+            "INT. HOUSE - DAY\n\nA dog barks.\n\n            BOB\n         (quietly)\n     Hello there,\n     old friend.\n\n                       CUT TO:\n"
+        );
+    }
+
+    #[test]
+    pub fn test_wrap_line_per_element_type() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_wrap::{self, FNElementWidths};
+
+        let widths = FNElementWidths {
+            parenthetical: 12,
+            dialogue: 10,
+            ..Default::default()
+        };
+        let parenthetical = FNLine::new("(under his breath, sadly)", FNLineType::Parenthetical);
+        assert_eq!(
+            fountain_wrap::wrap_line(&parenthetical, &widths),
+            vec!["(under his", " breath,", " sadly)"]
+        );
+
+        let dialogue = FNLine::new(
+            "I *never* [[check]] said supercalifragilistic.",
+            FNLineType::Dialogue,
+        );
+        assert_eq!(
+            fountain_wrap::wrap_line(&dialogue, &widths),
+            vec!["I never", "said", "supercalif", "ragilistic", "."]
+        );
+        assert!(
+            fountain_wrap::wrap_line(&FNLine::new("# Act", FNLineType::Section), &widths)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    pub fn test_pagination_with_text_measurer() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_layout::{FNAlignment, FNElementLayout, FNElementLayouts};
+        use crate::fountain_pagination::{
+            self, FNMonospaceMeasurer, FNPaginationOptions, FNTextMeasurer,
+        };
+
+        // Every grapheme is twice as wide as in Courier
+        struct FNWideMeasurer;
+        impl FNTextMeasurer for FNWideMeasurer {
+            fn get_text_width(&self, text: &str, _fn_type: &FNLineType) -> f64 {
+                2.0 * text.graphemes(true).count() as f64
             }
         }
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "One two three.\n\nFour five six.\n\nSeven eight.\n",
+        ));
+        let options = FNPaginationOptions {
+            page_height: 3.0,
+            layouts: FNElementLayouts {
+                action: FNElementLayout::new(15, 14, FNAlignment::Left),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].get_height(), 3.0);
+        assert_eq!(pages[1].rows[0].text, "Seven eight.");
+
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNWideMeasurer);
+        let texts: Vec<Vec<&str>> = pages
+            .iter()
+            .map(|page| page.rows.iter().map(|row| row.text.as_str()).collect())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                vec!["One two", "three."],
+                vec!["Four", "five", "six."],
+                vec!["Seven", "eight."]
+            ]
+        );
     }
 
     #[test]
-    pub fn get_stripped_string_from_parsed_and_partial_lines() {
-        let file_result: Result<String, std::io::Error> = ranged_items_test_file_path_result();
-        let ranged_element_type = FNRangedElementType::note();
-        if let Ok(document_string) = file_result {
-            let unparsed_lines: Vec<FNLine> =
-                static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
-                    document_string,
-                ));
-            let partial_map_opt =
-                partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
-                    &unparsed_lines,
-                    &ranged_element_type,
-                );
-            if let Some(partial_map) = partial_map_opt {
-                let multiline_ranges =
+    pub fn test_pagination_widow_and_orphan_control() {
+        use crate::fountain_layout::{FNAlignment, FNElementLayout, FNElementLayouts};
+        use crate::fountain_pagination::{self, FNMonospaceMeasurer, FNPage, FNPaginationOptions};
+
+        let page_texts = |pages: &[FNPage]| -> Vec<Vec<String>> {
+            pages
+                .iter()
+                .map(|page| page.rows.iter().map(|row| row.text.clone()).collect())
+                .collect()
+        };
+        let options = FNPaginationOptions {
+            page_height: 5.0,
+            layouts: FNElementLayouts {
+                action: FNElementLayout::new(15, 12, FNAlignment::Left),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // The heading would be the last row of the first page
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "A.\n\nB.\n\nINT. X - DAY\n\nC.\n",
+        ));
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(
+            page_texts(&pages),
+            vec![vec!["A.", "", "B."], vec!["INT. X - DAY", "", "C."]]
+        );
+
+        // Only one row of dialogue would fit after the cue
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "A.\n\nB.\n\nBOB\nLine one.\nLine two.\n",
+        ));
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(pages[1].rows[0].text, "BOB");
+
+        // Only one row of the paragraph would stay on the first page
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "A.\n\nB.\n\nOne two three four five six.\n",
+        ));
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(
+            page_texts(&pages)[1],
+            vec!["One two", "three four", "five six."]
+        );
+
+        let split_options = FNPaginationOptions {
+            min_rows_per_split: 1,
+            ..options
+        };
+        let pages = fountain_pagination::get_pages(&lines, &split_options, &FNMonospaceMeasurer);
+        assert_eq!(page_texts(&pages)[0], vec!["A.", "", "B.", "", "One two"]);
+    }
+
+    #[test]
+    pub fn test_pagination_page_breaks_and_keep_together() {
+        use crate::fountain_pagination::{self, FNMonospaceMeasurer, FNPage, FNPaginationOptions};
+
+        let page_texts = |pages: &[FNPage]| -> Vec<Vec<String>> {
+            pages
+                .iter()
+                .map(|page| page.rows.iter().map(|row| row.text.clone()).collect())
+                .collect()
+        };
+        let options = FNPaginationOptions {
+            page_height: 7.0,
+            ..Default::default()
+        };
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "A.\n\n===\n\nB.\n",
+        ));
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(page_texts(&pages), vec![vec!["A."], vec!["B."]]);
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "A.\n\nB.\n\nBOB\nOne. [[Keep Together]]\nTwo.\nThree.\n",
+        ));
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(page_texts(&pages)[1], vec!["BOB", "One.", "Two.", "Three."]);
+
+        let no_hint_options = FNPaginationOptions {
+            keep_together_note: None,
+            ..options.clone()
+        };
+        let pages = fountain_pagination::get_pages(&lines, &no_hint_options, &FNMonospaceMeasurer);
+        assert_eq!(page_texts(&pages)[1], vec!["Three."]);
+
+        // A note inside a boneyard isn't a note
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "A.\n\nB.\n\nBOB\nOne. /* [[Keep Together]] */\nTwo.\nThree.\n",
+        ));
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        assert_eq!(page_texts(&pages)[1], vec!["Three."]);
+    }
+
+    #[test]
+    pub fn test_pagination_of_notes_and_boneyards() {
+        use crate::fountain_pagination::{self, FNMonospaceMeasurer, FNPage, FNPaginationOptions};
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        let page_texts = |pages: &[FNPage]| -> Vec<Vec<String>> {
+            pages
+                .iter()
+                .map(|page| page.rows.iter().map(|row| row.text.clone()).collect())
+                .collect()
+        };
+        let options = FNPaginationOptions::default();
+        let get_texts = |text: &str, options: &FNPaginationOptions| {
+            let document = FNParsedDocument::new(text);
+            page_texts(&fountain_pagination::get_pages(
+                &document.full.lines,
+                options,
+                &FNMonospaceMeasurer,
+            ))
+        };
+
+        // The empty lines inside the boneyard aren't empty lines of the script
+        assert_eq!(
+            get_texts("A.\n/*\n\n\n*/\nB.\n", &options),
+            vec![vec!["A.", "B."]]
+        );
+        assert_eq!(
+            get_texts("A. [[a note]]\n[[b\nc]]\nB.\n", &options),
+            vec![vec!["A.", "B."]]
+        );
+
+        let with_notes = FNPaginationOptions {
+            include_notes: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_texts("A. [[a note]]\n[[b\nc]]\nB. /* cut */\n", &with_notes),
+            vec![vec!["A. [[a note]]", "[[b", "c]]", "B."]]
+        );
+    }
+
+    #[test]
+    pub fn test_locked_page_numbers() {
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_locked_pages;
+        use crate::fountain_pagination::{self, FNMonospaceMeasurer, FNPaginationOptions};
+
+        let options = FNPaginationOptions {
+            page_height: 1.0,
+            ..Default::default()
+        };
+        let get_numbers = |document: &FNDocument, locked| -> Vec<String> {
+            let lines = document.get_lines();
+            let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+            fountain_locked_pages::get_page_numbers_with_locked_pagination(&lines, &pages, locked)
+                .iter()
+                .map(|number| number.to_string())
+                .collect()
+        };
+
+        let mut document = FNDocument::new(String::from("A.\n\nB.\n\nC.\n"));
+        let lines = document.get_lines();
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        let numbers = fountain_locked_pages::get_page_numbers(&pages);
+        let locked = fountain_locked_pages::get_locked_pagination(&lines, &pages, &numbers);
+        assert_eq!(get_numbers(&document, &locked), vec!["1", "2", "3"]);
+
+        document.replace_all_lines(static_fountain_parser::get_parsed_lines_from_raw_string(
+            String::from("A.\n\nX.\n\nY.\n\nB.\n\nC.\n"),
+        ));
+        assert_eq!(
+            get_numbers(&document, &locked),
+            vec!["1", "1A", "1B", "2", "3"]
+        );
+
+        document.replace_all_lines(static_fountain_parser::get_parsed_lines_from_raw_string(
+            String::from("Z.\n\nA.\n\nX.\n\nY.\n\nB.\n\nC.\n"),
+        ));
+        assert_eq!(
+            get_numbers(&document, &locked),
+            vec!["A1", "1", "1A", "1B", "2", "3"]
+        );
+
+        // Once lettered pages are locked too, a page after `1B` is `1C`, and one between `1A` and `1B` is `1AA`
+        let lines = document.get_lines();
+        let pages = fountain_pagination::get_pages(&lines, &options, &FNMonospaceMeasurer);
+        let numbers =
+            fountain_locked_pages::get_page_numbers_with_locked_pagination(&lines, &pages, &locked);
+        let locked = fountain_locked_pages::get_locked_pagination(&lines, &pages, &numbers);
+        document.replace_all_lines(static_fountain_parser::get_parsed_lines_from_raw_string(
+            String::from("Z.\n\nA.\n\nX.\n\nW.\n\nY.\n\nV.\n\nB.\n\nC.\n"),
+        ));
+        assert_eq!(
+            get_numbers(&document, &locked),
+            vec!["A1", "1", "1A", "1AA", "1B", "1C", "2", "3"]
+        );
+    }
+
+    #[test]
+    pub fn test_draft_report() {
+        use crate::fountain_draft_report::{self, FNDraftReportOptions};
+
+        let old = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. A - DAY\n\nBOB\nHi.\n\nINT. B - DAY\n\nSomething happens.\n",
+        ));
+        let new = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. A - DAY\n\nBOB\nHello.\n\nALICE\n(quietly)\nBye.\n\nINT. C - NIGHT\n\nSomething happens.\n",
+        ));
+        let report =
+            fountain_draft_report::get_draft_report(&old, &new, &FNDraftReportOptions::default());
+
+        assert_eq!(report.scenes_added, vec!["INT. C - NIGHT"]);
+        assert_eq!(report.scenes_cut, vec!["INT. B - DAY"]);
+        assert_eq!(report.scenes_changed, vec!["INT. A - DAY"]);
+        let dialogue: Vec<(&str, usize, usize, usize)> = report
+            .dialogue_changes
+            .iter()
+            .map(|c| {
+                (
+                    c.character.as_str(),
+                    c.lines_added,
+                    c.lines_removed,
+                    c.lines_changed,
+                )
+            })
+            .collect();
+        assert_eq!(dialogue, vec![("ALICE", 3, 0, 0), ("BOB", 0, 0, 1)]);
+        assert_eq!(report.get_page_count_delta(), 0);
+
+        assert!(report
+            .to_json()
+            .contains("\"scenes_cut\":[\"INT. B - DAY\"]"));
+        assert!(report.to_markdown().contains("| ALICE | 3 | 0 | 0 |"));
+        assert_eq!(report.old_draft_date, None);
+
+        let old = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Test\nDraft date: First Draft\n\nINT. A - DAY\n\nBOB\nHi.\n",
+        ));
+        let new = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Test\nDraft date: Blue Revision\n\nINT. A - DAY\n\nROBERT\nHello.\n",
+        ));
+        let mut options = FNDraftReportOptions::default();
+        options.aliases.add_alias("ROBERT", "BOB");
+        let report = fountain_draft_report::get_draft_report(&old, &new, &options);
+        assert_eq!(report.old_draft_date.as_deref(), Some("First Draft"));
+        assert_eq!(report.new_draft_date.as_deref(), Some("Blue Revision"));
+        assert_eq!(report.dialogue_changes.len(), 1);
+        assert_eq!(report.dialogue_changes[0].character, "BOB");
+        assert_eq!(report.dialogue_changes[0].lines_changed, 2);
+        assert!(report
+            .to_markdown()
+            .contains("Drafts: First Draft -> Blue Revision"));
+    }
+
+    #[test]
+    pub fn test_scene_fingerprints() {
+        use crate::fountain_fingerprint;
+
+        let get_fingerprints = |text: &str| {
+            let lines =
+                static_fountain_parser::get_parsed_lines_from_raw_string(String::from(text));
+            fountain_fingerprint::get_scene_fingerprints(&lines)
+        };
+
+        let original =
+            get_fingerprints("INT. A - DAY\n\nBob waits.\n\nINT. B - DAY\n\nAlice leaves.\n");
+        let reformatted = get_fingerprints(
+            "INT. A - DAY\n\n\nBob   waits. [[fix this]]\n\nINT. B - DAY\n\nAlice leaves.\n/* cut */\n",
+        );
+        let edited =
+            get_fingerprints("INT. A - DAY\n\nBob waits.\n\nINT. B - DAY\n\nAlice stays.\n");
+
+        assert_eq!(original.len(), 2);
+        assert_eq!(original, reformatted);
+        assert_eq!(original[0], edited[0]);
+        assert_ne!(original[1], edited[1]);
+    }
+
+    #[test]
+    pub fn test_partial_ranges_for_dirty_range() {
+        use crate::fountain_partial_line_range::FNRangedElementResolutionStrategy;
+
+        let ranged_element_type = FNRangedElementType::boneyard();
+        let get_lines = |text: &str| -> Vec<FNLine> {
+            static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(String::from(
+                text,
+            )))
+        };
+        let get_full = |lines: &[FNLine]| {
+            let map = partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+                lines,
+                &ranged_element_type,
+            )
+            .unwrap();
+            let (ranges, diagnostics) =
+                partial_line_resolver::get_partial_multiline_ranges_and_diagnostics_from_partial_map(
+                    &map,
+                    lines,
+                    &ranged_element_type,
+                );
+            (map, ranges, diagnostics)
+        };
+
+        let old_lines = get_lines("A /*\nB\nC */ D\nE\nF /*\nG\nH */\n");
+        let (old_map, old_ranges, old_diagnostics) = get_full(&old_lines);
+
+        // "B" becomes three lines, one of which closes the first boneyard early and opens another one
+        let new_lines = get_lines("A /*\nB */\nX /*\nY\nC */ D\nE\nF /*\nG\nH */\n");
+        let (full_map, full_ranges, full_diagnostics) = get_full(&new_lines);
+
+        let map = partial_line_resolver::get_updated_partial_fnline_map_for_dirty_range(
+            &old_map,
+            &new_lines,
+            1..4,
+            2,
+            &ranged_element_type,
+        );
+        assert_eq!(map, full_map);
+
+        let (ranges, diagnostics) =
+            partial_line_resolver::get_updated_partial_multiline_ranges_for_dirty_range(
+                &old_ranges,
+                &old_diagnostics,
+                &map,
+                &new_lines,
+                1..4,
+                &ranged_element_type,
+                FNRangedElementResolutionStrategy::NextClose,
+            );
+        assert_eq!(ranges, full_ranges);
+        assert_eq!(diagnostics, full_diagnostics);
+        assert_eq!(ranges.len(), 3);
+    }
+
+    #[test]
+    pub fn test_partial_ranges_pair_with_last_close() {
+        use crate::fountain_partial_line_range::FNRangedElementResolutionStrategy;
+
+        let get_ranges = |text: &str, ranged_element_type: FNRangedElementType, strategy| {
+            let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+                String::from(text),
+            ));
+            let map = partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+                &lines,
+                &ranged_element_type,
+            )
+            .unwrap();
+            let (ranges, diagnostics) =
+                partial_line_resolver::get_partial_multiline_ranges_and_diagnostics_with_strategy(
+                    &map,
+                    &lines,
+                    &ranged_element_type,
+                    strategy,
+                );
+            let spans: Vec<(usize, usize)> = ranges
+                .iter()
+                .map(|range| (range.global_start.unwrap(), range.global_end.unwrap()))
+                .collect();
+            (spans, diagnostics.len())
+        };
+
+        let text = "A /*\nB */ C\nD */\n";
+        assert_eq!(
+            get_ranges(
+                text,
+                FNRangedElementType::boneyard(),
+                FNRangedElementResolutionStrategy::NextClose
+            ),
+            (vec![(0, 1)], 0)
+        );
+        assert_eq!(
+            get_ranges(
+                text,
+                FNRangedElementType::boneyard(),
+                FNRangedElementResolutionStrategy::LastClose
+            ),
+            (vec![(0, 2)], 0)
+        );
+
+        // A note can't reach a close after an empty line
+        let text = "A [[\nB ]]\nC ]]\n\nD ]]\n";
+        assert_eq!(
+            get_ranges(
+                text,
+                FNRangedElementType::note(),
+                FNRangedElementResolutionStrategy::LastClose
+            ),
+            (vec![(0, 2)], 0)
+        );
+    }
+
+    #[test]
+    fn test_last_close_ranges_are_invisible() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_partial_line_range::FNRangedElementResolutionStrategy;
+        use crate::fountain_text_extraction::get_printable_strings_for_lines;
+        use crate::intervals::RangeSet;
+
+        let last_close = FNFullParseConfig {
+            ranged_element_strategy: FNRangedElementResolutionStrategy::LastClose,
+            ..Default::default()
+        };
+        for (text, is_boneyard, stray_close) in [
+            ("A /*\nB */ C\nD */ E\n", true, "D */ E"),
+            ("A [[\nB ]] C\nD ]] E\n", false, "D ]] E"),
+        ] {
+            let next = parse_document_full(text, &FNFullParseConfig::default());
+            assert_eq!(
+                get_printable_strings_for_lines(&next.lines)[..3],
+                ["A", "C", stray_close]
+            );
+
+            let last = parse_document_full(text, &last_close);
+            let hidden: Vec<&RangeSet> = last
+                .lines
+                .iter()
+                .map(|ln| {
+                    if is_boneyard {
+                        &ln.omitted_ranges
+                    } else {
+                        &ln.note_ranges
+                    }
+                })
+                .collect();
+            assert!(hidden[1].contains_range(0..6));
+            assert!(hidden[2].contains_range(0..4));
+            assert!(!hidden[2].contains(4));
+            assert_eq!(
+                get_printable_strings_for_lines(&last.lines)[..3],
+                ["A", "", "E"]
+            );
+            let merged = last.get_merged_partial_lines();
+            assert_eq!(merged[0].global_range, 0..3);
+            assert_eq!(merged[0].visible_fnline.string, "A  E");
+        }
+    }
+
+    #[test]
+    pub fn test_lines_inside_multiline_ranges_are_invisible_only() {
+        use crate::fountain_enums::FNPartialLineType;
+        use crate::fountain_full_parse::{self, FNFullParseConfig};
+
+        let parse = fountain_full_parse::parse_document_full(
+            "A /*\nB /* x */ C\nD\nE */ F\n",
+            &FNFullParseConfig::default(),
+        );
+        let boneyard_types: Vec<Option<FNPartialLineType>> = parse
+            .lines
+            .iter()
+            .map(|ln| ln.boneyard_type.clone())
+            .collect();
+        assert_eq!(
+            boneyard_types[..4],
+            [
+                Some(FNPartialLineType::OrphanedOpen),
+                Some(FNPartialLineType::InvisibleOnly),
+                Some(FNPartialLineType::InvisibleOnly),
+                Some(FNPartialLineType::OrphanedClose),
+            ]
+        );
+        let partial_indices: Vec<Option<usize>> = parse
+            .boneyards
+            .partial_lines
+            .iter()
+            .map(|range| range.global_index)
+            .collect();
+        assert_eq!(partial_indices, vec![Some(0), Some(3)]);
+    }
+
+    #[test]
+    pub fn test_invisible_ranges_on_lines() {
+        use crate::fountain_full_parse::{self, FNFullParseConfig};
+        use crate::intervals::RangeSet;
+
+        let parse = fountain_full_parse::parse_document_full(
+            "Hi [[note]] there /* cut\nstill cut */ done.\n",
+            &FNFullParseConfig::default(),
+        );
+        let get_spans = |set: &RangeSet| -> Vec<(usize, usize)> {
+            set.iter().map(|range| (range.start, range.end)).collect()
+        };
+        assert_eq!(get_spans(&parse.lines[0].note_ranges), vec![(3, 11)]);
+        assert_eq!(get_spans(&parse.lines[0].omitted_ranges), vec![(18, 24)]);
+        assert!(parse.lines[1].note_ranges.is_empty());
+        assert_eq!(get_spans(&parse.lines[1].omitted_ranges), vec![(0, 12)]);
+    }
+
+    #[test]
+    pub fn test_range_set_intersection_and_containment() {
+        use crate::intervals::RangeSet;
+
+        let bold: RangeSet = [2..5, 4..8, 10..12].into_iter().collect();
+        assert_eq!(bold.ranges(), [2..8, 10..12]);
+        assert!(bold.contains(7) && !bold.contains(8));
+        assert!(bold.contains_range(3..6) && !bold.contains_range(7..11));
+        assert!(bold.intersects(7..11) && !bold.intersects(8..10));
+
+        let italic: RangeSet = [0..3, 6..11].into_iter().collect();
+        assert_eq!(bold.intersection(&italic).ranges(), [2..3, 6..8, 10..11]);
+    }
+
+    #[test]
+    pub fn test_range_set_union_and_difference() {
+        use crate::intervals::RangeSet;
+
+        let get_spans = |set: &RangeSet| -> Vec<(usize, usize)> {
+            set.iter().map(|range| (range.start, range.end)).collect()
+        };
+        let notes: RangeSet = [3..8, 20..25].into_iter().collect();
+        let mut boneyards = RangeSet::new();
+        boneyards.insert(6..12);
+
+        let hidden = notes.union(&boneyards);
+        assert_eq!(get_spans(&hidden), vec![(3, 12), (20, 25)]);
+        assert_eq!(
+            get_spans(&hidden.get_complement(0..30)),
+            vec![(0, 3), (12, 20), (25, 30)]
+        );
+        assert_eq!(
+            get_spans(&notes.difference(&boneyards)),
+            vec![(3, 6), (20, 25)]
+        );
+
+        let mut set = hidden.clone();
+        set.remove(5..7);
+        set.remove(0..2);
+        assert_eq!(get_spans(&set), vec![(3, 5), (7, 12), (20, 25)]);
+        assert_eq!(set.get_index_count(), 12);
+    }
+
+    #[test]
+    pub fn test_visible_rich_text() {
+        use crate::fountain_emphasis::FNEmphasisOptions;
+        use crate::fountain_full_parse::{self, FNFullParseConfig};
+        use crate::fountain_text_extraction;
+
+        let parse = fountain_full_parse::parse_document_full(
+            "Hi [[note]] *there* and **bold _under_** \\*x\n\n!Loud. /* cut */\n",
+            &FNFullParseConfig::default(),
+        );
+        let runs = fountain_text_extraction::get_visible_rich_text_for_line(
+            &parse.lines[0],
+            &FNEmphasisOptions::default(),
+        );
+        let styled: Vec<(&str, bool, bool, bool, usize)> = runs
+            .iter()
+            .map(|run| {
+                (
+                    run.text.as_str(),
+                    run.bold,
+                    run.italic,
+                    run.underline,
+                    run.start,
+                )
+            })
+            .collect();
+        assert_eq!(
+            styled,
+            vec![
+                ("Hi  ", false, false, false, 0),
+                ("there", false, true, false, 4),
+                (" and ", false, false, false, 9),
+                ("bold ", true, false, false, 14),
+                ("under", true, false, true, 19),
+                (" *x", false, false, false, 24),
+            ]
+        );
+
+        let runs = fountain_text_extraction::get_visible_rich_text_for_line(
+            &parse.lines[2],
+            &FNEmphasisOptions::default(),
+        );
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].text, "Loud.");
+    }
+
+    #[test]
+    fn test_parse_stripped() {
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_stripped::parse_stripped;
+
+        let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+            String::from(
+                "INT. HOUSE - DAY\n\nBOB [[rename?]]\nHello /* there\nold text\n*/ friend.\n[[a note]]\nBye.",
+            ),
+        ));
+        let (stripped, map) = parse_stripped(&lines);
+
+        let strings: Vec<&str> = stripped.iter().map(|ln| ln.string.as_str()).collect();
+        assert_eq!(
+            strings,
+            vec!["INT. HOUSE - DAY", "", "BOB ", "Hello  friend.", "Bye."]
+        );
+        assert_eq!(stripped[2].fn_type, FNLineType::Character);
+        assert_eq!(stripped[3].fn_type, FNLineType::Dialogue);
+        assert_eq!(stripped[4].fn_type, FNLineType::Dialogue);
+        assert_eq!(
+            stripped[3].raw_string,
+            "Hello /* there\nold text\n*/ friend."
+        );
+        assert_eq!(stripped[3].position, lines[3].position);
+
+        assert_eq!(map.get_raw_line_range(3), Some(3..6));
+        assert_eq!(map.get_stripped_index_for_raw_line(4), Some(3));
+        assert_eq!(map.get_stripped_index_for_raw_line(6), None);
+        // The "f" of "friend" is on the third raw line of the joined line
+        assert_eq!(map.get_raw_position(3, 7), Some((5, 3)));
+        assert_eq!(map.get_raw_position(3, 0), Some((3, 0)));
+
+        let document = FNDocument::new(String::from(
+            "INT. HOUSE - DAY\n\nBOB [[rename?]]\nHello /* there\nold text\n*/ friend.\n[[a note]]\nBye.",
+        ));
+        assert_eq!(
+            document.get_raw_index_from_stripped_index(3, 7),
+            Some((5, 3))
+        );
+        assert_eq!(document.get_raw_index_from_stripped_index(9, 0), None);
+
+        assert_eq!(
+            partial_line_resolver::delete_ranged_text_with_recursion(String::from(
+                "Hello /* there\r\nold */ friend. [[a note]]\r\nBye /* unclosed\n"
+            )),
+            "Hello  friend. \r\nBye "
+        );
+    }
+
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn test_parse_arbitrary_with_pathological_input() {
+        use crate::fountain_fuzz::parse_arbitrary;
+
+        parse_arbitrary(b"");
+        parse_arbitrary(&[0xff, 0xfe, b'\n', 0x80]);
+        parse_arbitrary("/*".repeat(10000).as_bytes());
+        parse_arbitrary("[[\n".repeat(10000).as_bytes());
+        parse_arbitrary("*/]]\n\n".repeat(10000).as_bytes());
+        parse_arbitrary("BOB\n(\n*_\\".repeat(10000).as_bytes());
+    }
+
+    #[test]
+    fn test_partial_line_resolver_with_malformed_input() {
+        use crate::fountain_enums::FNPartialLineType;
+
+        let note = FNRangedElementType::note();
+        let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+            String::from("]]\n]] text [[\n[[\n]]\n*/ /* */"),
+        ));
+
+        // Without the local indices, they are calculated from the line
+        assert_eq!(
+            partial_line_resolver::get_local_partial_type_for_single_line(
+                &lines[1], &note, None, None
+            ),
+            Some(FNPartialLineType::OrphanedOpenAndClose)
+        );
+
+        let partials_map =
+            partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(&lines, &note)
+                .unwrap_or_default();
+        assert_eq!(
+            partials_map.get(&0).and_then(|ln| ln.note_type.clone()),
+            Some(FNPartialLineType::OrphanedClose)
+        );
+        let ranges = partial_line_resolver::get_partial_multiline_ranges_from_partial_map(
+            &partials_map,
+            &lines,
+            &note,
+        );
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(
+            (ranges[0].global_start, ranges[0].global_end),
+            (Some(1), Some(3))
+        );
+
+        // A partial type which doesn't match the text is skipped instead of panicking
+        let mut mismatched_map = partials_map.clone();
+        mismatched_map.insert(
+            4,
+            FNLine {
+                note_type: Some(FNPartialLineType::OrphanedOpen),
+                ..lines[4].clone()
+            },
+        );
+        let (_, diagnostics) =
+            partial_line_resolver::get_partial_multiline_ranges_and_diagnostics_from_partial_map(
+                &mismatched_map,
+                &lines,
+                &note,
+            );
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.global_index != 4));
+    }
+
+    #[test]
+    fn test_fixtures_match_expected_line_types() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_test_support::{get_fixture, get_fixtures};
+
+        for fixture in get_fixtures() {
+            let full = parse_document_full(fixture.text, &FNFullParseConfig::default());
+            let line_types: Vec<FNLineType> =
+                full.lines.iter().map(|ln| ln.fn_type.clone()).collect();
+            assert_eq!(
+                line_types,
+                fixture.get_expected_line_types().unwrap(),
+                "fixture: {}",
+                fixture.name
+            );
+        }
+        assert!(get_fixture("dual_dialogue").is_some());
+        assert!(get_fixture("missing").is_none());
+    }
+
+    #[test]
+    fn test_compact_document() {
+        use crate::fountain_compact::{get_memory_size_of_lines, FNCompactDocument};
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+
+        // About 200 pages
+        let scene = "INT. HOUSE - DAY\n\nThe *lady* SHRIEKS. [[Too loud?]]\n\n@McCLANE\n(quietly)\nGet out of my house!\n\n";
+        let text = scene.repeat(1400);
+        let lines = parse_document_full(&text, &FNFullParseConfig::default()).lines;
+        let compact = FNCompactDocument::from_lines(&lines);
+
+        assert_eq!(compact.len(), lines.len());
+        assert_eq!(compact.get_string(4), Some("McCLANE"));
+        assert_eq!(compact.get_raw_string(4), Some("@McCLANE"));
+        assert_eq!(compact.get_lines(), lines);
+
+        let compact_size = compact.get_memory_size();
+        let lines_size = get_memory_size_of_lines(&lines);
+        assert!(compact_size * 3 < lines_size);
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        use crate::fountain_characters::FNCharacterAliasMap;
+        use crate::fountain_scene::get_scenes_from_parsed_lines;
+        use crate::fountain_symbols::get_document_symbols;
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. JOE'S BAR - NIGHT\n\nDET. SMITH\nWhere were you?\n\nJOE\n(shrugs)\nHere.\n\nINT. JOES BAR - LATER\n\nSMITH\nStill here?",
+        ));
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("DET. SMITH", "SMITH");
+        let symbols = get_document_symbols(&lines, &aliases);
+
+        let smith = symbols.table.get_symbol("SMITH").unwrap();
+        assert_eq!(symbols.table.get_name(smith), Some("SMITH"));
+        assert_eq!(symbols.get_character_symbol_for_line(3), Some(smith));
+        assert_eq!(symbols.get_character_symbol_for_line(12), Some(smith));
+        assert_eq!(
+            symbols.get_line_indices_for_character(smith),
+            vec![2, 3, 11, 12]
+        );
+
+        let scenes = get_scenes_from_parsed_lines(&lines);
+        let joe = symbols.table.get_symbol("JOE").unwrap();
+        assert_eq!(
+            symbols.get_character_symbols_for_scene(&scenes[0]),
+            vec![smith, joe]
+        );
+        assert_eq!(
+            symbols.get_location_symbol_for_scene(&scenes[0]),
+            symbols.get_location_symbol_for_scene(&scenes[1])
+        );
+        assert_eq!(symbols.table.len(), 3);
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lsp_features() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_lsp::*;
+        use lsp_types::Position;
+
+        let full = parse_document_full(
+            "# Act One\n\nINT. JOE'S BAR - NIGHT\n\nJOE\nHi. [[fix]]\n\nEXT. STREET - DAY\n\n[[unclosed\n\nJ",
+            &FNFullParseConfig::default(),
+        );
+        let lines = &full.lines;
+
+        let diagnostics = get_lsp_diagnostics(&full);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(9, 0));
+
+        let symbols = get_lsp_document_symbols(lines);
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "Act One");
+        let scenes = symbols[0].children.as_ref().unwrap();
+        assert_eq!(scenes.len(), 2);
+        assert_eq!(
+            (scenes[0].range.start.line, scenes[0].range.end.line),
+            (2, 6)
+        );
+        let characters = scenes[0].children.as_ref().unwrap();
+        assert_eq!(characters.len(), 1);
+        assert_eq!(characters[0].name, "JOE");
+        assert_eq!(characters[0].range.start, Position::new(4, 0));
+
+        let folds: Vec<(u32, u32)> = get_lsp_folding_ranges(lines)
+            .iter()
+            .map(|fold| (fold.start_line, fold.end_line))
+            .collect();
+        assert_eq!(folds, vec![(0, 11), (2, 6), (7, 11)]);
+
+        // "Hi. " is dialogue, and the note is a comment
+        let tokens = get_lsp_semantic_tokens(lines);
+        let dialogue = tokens
+            .iter()
+            .position(|token| token.token_type == 2)
+            .unwrap();
+        assert_eq!(
+            (tokens[dialogue].delta_line, tokens[dialogue].length),
+            (1, 4)
+        );
+        assert_eq!(
+            (
+                tokens[dialogue + 1].delta_start,
+                tokens[dialogue + 1].length
+            ),
+            (4, 7)
+        );
+
+        let characters = get_lsp_completions(lines, Position::new(11, 1));
+        assert_eq!(characters[0].label, "JOE");
+        let locations = get_lsp_completions(lines, Position::new(7, 4));
+        assert_eq!(locations.len(), 2);
+        let times = get_lsp_completions(lines, Position::new(7, 14));
+        assert_eq!(times[0].label, "DAY");
+        assert_eq!(times[1].label, "NIGHT");
+    }
+
+    #[test]
+    fn test_query_matches() {
+        use crate::fountain_characters::FNCharacterAliasMap;
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_query::{
+            get_pattern_matches, get_query_from_pattern, get_query_matches, FNQuery,
+        };
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. JOE'S BAR - NIGHT\n\nJANE\nA drink.\n\nJOE\nSure.\n\nEXT. STREET - DAY\n\nJANE\n(calling)\nTaxi!",
+        ));
+        assert_eq!(
+            get_pattern_matches(&lines, "Dialogue character=\"JANE\" location=\"BAR\""),
+            Some(vec![3])
+        );
+        assert_eq!(
+            get_pattern_matches(&lines, "Dialogue|Parenthetical character=jane"),
+            Some(vec![3, 11, 12])
+        );
+        assert_eq!(get_pattern_matches(&lines, "text=taxi"), Some(vec![12]));
+        assert_eq!(
+            get_pattern_matches(&lines, "location=\"JOE'S BAR\" Character"),
+            Some(vec![2, 5])
+        );
+        assert_eq!(get_pattern_matches(&lines, "location=AR"), Some(vec![]));
+        assert_eq!(get_query_from_pattern("Dialog"), None);
+        assert_eq!(get_query_from_pattern("speaker=JANE"), None);
+        assert_eq!(get_query_from_pattern("text=\"unclosed"), None);
+
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("JANE", "JANE DOE");
+        let query = FNQuery::new()
+            .with_line_type(FNLineType::Dialogue)
+            .with_character("Jane Doe")
+            .with_location("street");
+        assert_eq!(get_query_matches(&lines, &query, &aliases), vec![12]);
+
+        // A forced cue in mixed case is the same character as its uppercase cue
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "@Jane\nHi.\n\nJANE\nBye.",
+        ));
+        let query = FNQuery::new()
+            .with_line_type(FNLineType::Dialogue)
+            .with_character("jane");
+        assert_eq!(
+            get_query_matches(&lines, &query, &FNCharacterAliasMap::new()),
+            vec![1, 4]
+        );
+    }
+
+    #[test]
+    fn test_split_by_scene() {
+        use crate::fountain_split::split_by_scene;
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Test\nAuthor: Me\n\nA cold open.\n\nINT. JOE'S BAR - NIGHT #12#\n\nJOE\nHi.\n\nEXT. STREET - DAY\n\nRain.\n\nEXT. STREET - DAY\n\nMore rain.\n",
+        ));
+        let snippets = split_by_scene(&lines);
+        let filenames: Vec<&str> = snippets.iter().map(|s| s.filename.as_str()).collect();
+        assert_eq!(
+            filenames,
+            vec![
+                "001-untitled.fountain",
+                "12-int-joes-bar-night.fountain",
+                "003-ext-street-day.fountain",
+                "004-ext-street-day.fountain",
+            ]
+        );
+        assert_eq!(snippets[0].text, "A cold open.\n");
+        assert_eq!(
+            snippets[1].text,
+            "INT. JOE'S BAR - NIGHT #12#\n\nJOE\nHi.\n"
+        );
+        assert_eq!(snippets[3].text, "EXT. STREET - DAY\n\nMore rain.\n");
+        assert_eq!(snippets[1].heading_index, Some(5));
+    }
+
+    #[test]
+    fn test_cards() {
+        use crate::fountain_cards::{get_cards, get_cards_json, FNCardOptions};
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+
+        let mut text = String::from(
+            "INT. BAR - NIGHT\n\n= Joe meets *Jane*.\n\n[[cut?]]\n\nJoe waits. [[slow]]\n\n",
+        );
+        text.push_str(&"More.\n\n".repeat(13));
+        text.push_str("EXT. STREET - DAY\n\nJANE\nTaxi!\n");
+        let mut lines = parse_document_full(&text, &FNFullParseConfig::default()).lines;
+        lines[0].color = String::from("red");
+
+        let cards = get_cards(&lines, &FNCardOptions::default());
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].heading, "INT. BAR - NIGHT");
+        assert_eq!(cards[0].synopsis.as_deref(), Some("Joe meets Jane."));
+        assert_eq!(cards[0].first_action.as_deref(), Some("Joe waits."));
+        assert_eq!(cards[0].color.as_deref(), Some("red"));
+        assert_eq!(cards[0].page_eighths, 4);
+        assert_eq!(cards[1].first_action, None);
+        assert_eq!(cards[1].page_eighths, 1);
+        assert_eq!(
+            get_cards_json(&cards[1..]),
+            "[{\"scene_index\":1,\"heading_index\":34,\"heading\":\"EXT. STREET - DAY\",\"synopsis\":null,\"color\":null,\"page_eighths\":1,\"first_action\":null}]"
+        );
+    }
+
+    #[test]
+    fn test_storylines() {
+        use crate::fountain_storylines::{get_storyline, get_storylines};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Test\n\nINT. BAR - NIGHT [[storyline: A-plot]]\n\nJoe waits.\n\nINT. BAR - LATER\n\nJane arrives. [[Beat: a-plot, Romance]]\n\nEXT. STREET - DAY\n\nRain.\n\nEXT. PARK - DAY\n\nKiss. [[storyline: romance]]",
+        ));
+        let storylines = get_storylines(&lines);
+        let names: Vec<&str> = storylines.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["A-plot", "Romance"]);
+        assert_eq!(storylines[0].line_indices, vec![2, 8]);
+        assert_eq!(storylines[0].get_scene_ranges(), vec![0..2]);
+        assert_eq!(storylines[1].scene_indices, vec![1, 3]);
+        assert_eq!(storylines[1].get_scene_ranges(), vec![1..2, 3..4]);
+        assert_eq!(
+            get_storyline(&lines, "ROMANCE").map(|s| s.line_indices),
+            Some(vec![8, 16])
+        );
+        assert_eq!(get_storyline(&lines, "C-plot"), None);
+
+        // A tag in a boneyard doesn't count, and a tag can span lines
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT /* [[storyline: C-plot]] */\n\nJoe waits. [[storyline:\nD-plot]]\n",
+        ));
+        let storylines = get_storylines(&lines);
+        let names: Vec<&str> = storylines.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["D-plot"]);
+        assert_eq!(storylines[0].line_indices, vec![2]);
+    }
+
+    #[test]
+    fn test_export_hooks() {
+        use crate::fountain_export_hooks::{
+            get_personalized_pages, get_text_with_substitutions, FNCopyPersonalization,
+            FNExportHook,
+        };
+        use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\nA note for {{recipient}}, {{unknown}}. [[{{recipient}}]]\n",
+        ));
+        let options = FNPaginationOptions::default();
+        let copy = FNCopyPersonalization {
+            recipient: String::from("Jane Doe"),
+            watermark: String::from("CONFIDENTIAL"),
+            draft_id: String::from("D-0042"),
+            ..Default::default()
+        };
+        let personalized = get_personalized_pages(&lines, &options, &FNMonospaceMeasurer, &copy);
+        assert_eq!(personalized.len(), 1);
+        assert_eq!(personalized[0].header, None);
+        assert_eq!(personalized[0].footer.as_deref(), Some("Jane Doe - D-0042"));
+        assert_eq!(personalized[0].watermark.as_deref(), Some("CONFIDENTIAL"));
+        assert!(personalized[0]
+            .rows
+            .iter()
+            .any(|row| row.text == "A note for Jane Doe, {{unknown}}."));
+
+        struct PageNumbers;
+        impl FNExportHook for PageNumbers {
+            fn get_page_header(&self, page_index: usize, page_count: usize) -> Option<String> {
+                Some(format!("{}/{}", page_index + 1, page_count))
+            }
+        }
+        assert_eq!(
+            get_personalized_pages(&lines, &options, &FNMonospaceMeasurer, &PageNumbers)[0]
+                .header
+                .as_deref(),
+            Some("1/1")
+        );
+
+        // Values are filled in before pagination, so a long one wraps like the rest of the text
+        let long_copy = FNCopyPersonalization {
+            recipient: "Jane ".repeat(20),
+            ..Default::default()
+        };
+        let personalized =
+            get_personalized_pages(&lines, &options, &FNMonospaceMeasurer, &long_copy);
+        assert!(
+            personalized[0].rows.len()
+                > get_pages(&lines, &options, &FNMonospaceMeasurer)[0]
+                    .rows
+                    .len()
+        );
+        assert!(personalized[0].rows.iter().all(|row| row.text.len() <= 60));
+        assert_eq!(
+            get_text_with_substitutions("{{ draft_id }} {{", &copy),
+            "D-0042 {{"
+        );
+    }
+
+    #[test]
+    fn test_omit_scene() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_omitted::{is_omitted_placeholder, omit_scene};
+        use crate::static_fountain_preparser::{FNNewlinePolicy, FNPreparseOptions};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT #1#\n\nJoe waits.\n\nEXT. STREET - DAY #2#\n\nRain.\n\nINT. CAR - DAY #3#\n\nDriving.",
+        ));
+        let omitted = omit_scene(&lines, "2").unwrap();
+        assert_eq!(
+            omitted,
+            "INT. BAR - NIGHT #1#\n\nJoe waits.\n\n.OMITTED #2#\n\n/*\nEXT. STREET - DAY #2#\n\nRain.\n*/\n\nINT. CAR - DAY #3#\n\nDriving."
+        );
+        assert_eq!(omit_scene(&lines, "4"), None);
+
+        let omitted_lines = static_fountain_parser::get_parsed_lines_from_raw_string(omitted);
+        assert!(is_omitted_placeholder(&omitted_lines[4]));
+        assert!(!is_omitted_placeholder(&omitted_lines[0]));
+        assert_eq!(
+            omit_scene(&omitted_lines, "3").map(|t| t.ends_with("Driving.\n*/")),
+            Some(true)
+        );
+
+        // The document's line breaks are kept, including the one at the end
+        let full = parse_document_full(
+            "INT. BAR - NIGHT #1#\r\n\r\nJoe waits.\r\n\r\nEXT. STREET - DAY #2#\r\n\r\nRain.\r\n",
+            &FNFullParseConfig {
+                preparse: FNPreparseOptions {
+                    newline_policy: FNNewlinePolicy::Preserve,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            omit_scene(&full.lines, "2").as_deref(),
+            Some("INT. BAR - NIGHT #1#\r\n\r\nJoe waits.\r\n\r\n.OMITTED #2#\r\n\r\n/*\r\nEXT. STREET - DAY #2#\r\n\r\nRain.\r\n*/\r\n")
+        );
+    }
+
+    #[test]
+    fn test_restore_omitted_scene() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_omitted::{get_omitted_scenes, omit_scene, restore_omitted_scene};
+        use crate::static_fountain_preparser::{FNNewlinePolicy, FNPreparseOptions};
+
+        let text =
+            "INT. BAR - NIGHT #1#\n\nJoe waits.\n\nEXT. STREET - DAY #2#\n\nRain.\n\nINT. CAR - DAY #3#";
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(text));
+        let omitted_lines = static_fountain_parser::get_parsed_lines_from_raw_string(
+            omit_scene(&lines, "2").unwrap(),
+        );
+        let omitted = get_omitted_scenes(&omitted_lines);
+        assert_eq!(omitted.len(), 1);
+        assert_eq!(omitted[0].scene_number.as_deref(), Some("2"));
+        assert_eq!(
+            (
+                omitted[0].boneyard_open_index,
+                omitted[0].boneyard_close_index
+            ),
+            (6, 10)
+        );
+        assert_eq!(
+            restore_omitted_scene(&omitted_lines, "2").as_deref(),
+            Some(text)
+        );
+        assert_eq!(restore_omitted_scene(&omitted_lines, "1"), None);
+
+        // The restored heading takes the number of its placeholder
+        let renumbered = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            ".OMITTED #2A#\n\n/*\nEXT. STREET - DAY\n\nRain.\n*/",
+        ));
+        assert_eq!(
+            restore_omitted_scene(&renumbered, "2A").as_deref(),
+            Some("EXT. STREET - DAY #2A#\n\nRain.")
+        );
+
+        // The document's line breaks are kept, including the one at the end
+        let full = parse_document_full(
+            ".OMITTED #2#\r\n\r\n/*\r\nEXT. STREET - DAY\r\n\r\nRain.\r\n*/\r\n",
+            &FNFullParseConfig {
+                preparse: FNPreparseOptions {
+                    newline_policy: FNNewlinePolicy::Preserve,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            restore_omitted_scene(&full.lines, "2").as_deref(),
+            Some("EXT. STREET - DAY #2#\r\n\r\nRain.\r\n")
+        );
+    }
+
+    #[test]
+    fn test_cue_options() {
+        use crate::fountain_enums::FNLineType;
+        use crate::static_fountain_parser::{
+            get_parsed_lines_from_line_vec_with_options, FNParseOptions,
+        };
+
+        let text =
+            "BANG!\nThe door flies open.\n\nFBI\nAgents swarm in.\n\nWHO?\nNobody.\n\n@WHO?\nMe.\n\nJO\nHi.";
+        let get_types = |options: &FNParseOptions| -> Vec<FNLineType> {
+            get_parsed_lines_from_line_vec_with_options(
+                static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+                    text.to_string(),
+                )),
+                options,
+            )
+            .iter()
+            .map(|ln| ln.fn_type.clone())
+            .collect()
+        };
+        let cue_indices = |types: Vec<FNLineType>| -> Vec<usize> {
+            types
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| **t == FNLineType::Character)
+                .map(|(idx, _)| idx)
+                .collect()
+        };
+
+        // The default options parse like the parser always has
+        let types = get_types(&FNParseOptions::default());
+        assert_eq!(cue_indices(types.clone()), vec![0, 3, 6, 9, 12]);
+        assert_eq!(types[1], FNLineType::Dialogue);
+        assert_eq!(types[7], FNLineType::Dialogue);
+        let parsed: Vec<FNLineType> =
+            static_fountain_parser::get_parsed_lines_from_raw_string(text.to_string())
+                .iter()
+                .map(|ln| ln.fn_type.clone())
+                .collect();
+        assert_eq!(types, parsed);
+
+        let no_punctuation = FNParseOptions {
+            allow_cue_trailing_punctuation: false,
+            ..Default::default()
+        };
+        assert_eq!(cue_indices(get_types(&no_punctuation)), vec![3, 9, 12]);
+
+        let strict = FNParseOptions {
+            min_cue_length: 3,
+            allow_cue_trailing_punctuation: false,
+            cue_exemptions: vec![String::from("fbi")],
+            ..Default::default()
+        };
+        let types = get_types(&strict);
+        assert_eq!(types[3], FNLineType::Action);
+        assert_eq!(cue_indices(types), vec![9]);
+    }
+
+    #[test]
+    fn test_numbered_and_punctuated_cues() {
+        use crate::fountain_enums::{FNForcedBy, FNLineType};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "911 OPERATOR\nWhat's your emergency?\n\n#2 GUARD\nHalt!\n\n#2 GUARD\n\n1984\nA year.\n\n.44 MAGNUM\nBig gun.\n\nJOE\n.44 caliber.",
+        ));
+        let types: Vec<FNLineType> = lines.iter().map(|ln| ln.fn_type.clone()).collect();
+        assert_eq!(types[0], FNLineType::Character);
+        assert_eq!(
+            lines[0].get_character_name().as_deref(),
+            Some("911 OPERATOR")
+        );
+        assert_eq!(types[3], FNLineType::Character);
+        assert_eq!(types[4], FNLineType::Dialogue);
+        // Without dialogue, a numbered line is still a section
+        assert_eq!(types[6], FNLineType::Section);
+        assert_eq!(lines[6].forced_by, FNForcedBy::Hash);
+        assert_eq!(lines[6].section_depth, 1);
+        assert_eq!(types[8], FNLineType::Action);
+        assert_eq!(types[11], FNLineType::Character);
+        assert_eq!(types[15], FNLineType::Dialogue);
+    }
+
+    #[test]
+    fn test_multiline_parentheticals() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+
+        let full = parse_document_full(
+            "JOE\n(quietly, as the\ndoor closes)\nBye.\n\nJANE\n(never\nclosed\n\nBOB ^\n(also\nthis)\nOk.",
+            &FNFullParseConfig::default(),
+        );
+        let types: Vec<FNLineType> = full.lines.iter().map(|ln| ln.fn_type.clone()).collect();
+        assert_eq!(
+            types[1..4],
+            [
+                FNLineType::Parenthetical,
+                FNLineType::Parenthetical,
+                FNLineType::Dialogue
+            ]
+        );
+        assert_eq!(
+            types[6..8],
+            [FNLineType::Parenthetical, FNLineType::Parenthetical]
+        );
+        assert_eq!(
+            types[10..13],
+            [
+                FNLineType::DualDialogueParenthetical,
+                FNLineType::DualDialogueParenthetical,
+                FNLineType::DualDialogue
+            ]
+        );
+        assert_eq!(full.unclosed_parentheticals, vec![6]);
+    }
+
+    #[test]
+    fn test_heading_detection() {
+        use crate::fountain_enums::FNLineType;
+
+        let cases: [(&str, bool); 18] = [
+            ("INT. BAR - NIGHT", true),
+            ("EXT. STREET - DAY", true),
+            ("int. bar - night", true),
+            ("INT BAR - NIGHT", true),
+            ("EXT STREET", true),
+            ("EST. CITY - DAY", true),
+            ("INT./EXT. CAR - DAY", true),
+            ("INT/EXT CAR - DAY", true),
+            ("EXT/INT. CAR - DAY", true),
+            ("I/E. CAR - DAY", true),
+            ("I/E CAR - DAY", true),
+            ("INT.BAR", true),
+            ("ESTATE SALE SIGNS EVERYWHERE.", false),
+            ("International calls are expensive.", false),
+            ("Exterior walls crumble.", false),
+            ("INTERIOR DESIGN MAGAZINE.", false),
+            ("INT.", false),
+            ("EXT ", false),
+        ];
+        for (text, is_heading) in cases {
+            let lines = static_fountain_parser::get_parsed_lines_from_raw_string(format!(
+                "\n{}\n\nAction.",
+                text
+            ));
+            assert_eq!(
+                lines[1].fn_type == FNLineType::Heading,
+                is_heading,
+                "heading: {:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_heading_prefix_with_multibyte_uppercase() {
+        use crate::fountain_heading;
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        // `ﬅ` uppercases to `ST`, which is longer in bytes
+        let document = FNParsedDocument::new("\n.Eﬅ HOUSE - DAY\n\nAction.\n");
+        assert_eq!(document.scenes.len(), 1);
+        assert_eq!(document.scenes[0].heading_index, Some(1));
+        let heading = fountain_heading::get_scene_heading_from_string("Eﬅ HOUSE - DAY");
+        assert_eq!(heading.prefix, "");
+        assert_eq!(heading.location, "Eﬅ HOUSE");
+    }
+
+    #[test]
+    fn test_transition_needs_empty_line_after() {
+        use crate::fountain_enums::FNLineType;
+        use crate::static_fountain_parser::{
+            get_parsed_lines_from_line_vec_with_options, FNParseOptions,
+        };
+
+        let text =
+            "Joe leaves.\n\nCUT TO:\n\nINT. BAR - DAY\n\nNARRATOR:\nIt was late.\n\nFADE OUT:";
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(text.to_string());
+        assert_eq!(lines[2].fn_type, FNLineType::TransitionLine);
+        assert_eq!(lines[6].fn_type, FNLineType::Character);
+        assert_eq!(lines[7].fn_type, FNLineType::Dialogue);
+        assert_eq!(lines[9].fn_type, FNLineType::TransitionLine);
+
+        let lenient = FNParseOptions {
+            require_empty_line_after_transition: false,
+            ..Default::default()
+        };
+        let lines = get_parsed_lines_from_line_vec_with_options(
+            static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(text.to_string())),
+            &lenient,
+        );
+        assert_eq!(lines[6].fn_type, FNLineType::TransitionLine);
+    }
+
+    #[test]
+    fn test_centered_with_whitespace_and_emphasis() {
+        use crate::fountain_enums::{FNForcedBy, FNLineType};
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_text_extraction::{
+            get_printable_strings_for_lines, get_prose_chunks_for_spellcheck,
+        };
+
+        let full = parse_document_full(
+            "> THE END <  \n\n  >**THE** _END_<\n\n> CUT TO:",
+            &FNFullParseConfig::default(),
+        );
+        let lines = &full.lines;
+        assert_eq!(lines[0].fn_type, FNLineType::Centered);
+        assert_eq!(lines[2].fn_type, FNLineType::Centered);
+        assert_eq!(lines[2].forced_by, FNForcedBy::GreaterThan);
+        assert_eq!(lines[4].fn_type, FNLineType::TransitionLine);
+        assert!(lines[2].bold_ranges.contains_range(5..8));
+        assert_eq!(
+            get_printable_strings_for_lines(lines)[..3],
+            [
+                String::from("THE END"),
+                String::new(),
+                String::from("THE END")
+            ]
+        );
+        let chunks: Vec<String> = get_prose_chunks_for_spellcheck(lines)
+            .into_iter()
+            .map(|chunk| chunk.text)
+            .collect();
+        assert!(chunks
+            .iter()
+            .all(|chunk| !chunk.contains('>') && !chunk.contains('<')));
+    }
+
+    #[test]
+    fn test_title_page_region() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::static_fountain_parser::get_title_page_region_end;
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Brick\nNotes: line one\n    line two\n!Forced: value\n\nTime: Noon\nJoe waits.\n\nJOE\nRe: the money.",
+        ));
+        assert_eq!(get_title_page_region_end(&lines), 4);
+        let types: Vec<FNLineType> = lines.iter().map(|ln| ln.fn_type.clone()).collect();
+        assert_eq!(
+            types[..4],
+            [
+                FNLineType::TitlePageTitle,
+                FNLineType::TitlePageUnknown,
+                FNLineType::TitlePageUnknown,
+                FNLineType::TitlePageUnknown
+            ]
+        );
+        assert_eq!(types[5], FNLineType::Action);
+        assert_eq!(types[9], FNLineType::Dialogue);
+
+        let no_title_page = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\nTitle: not a title page",
+        ));
+        assert_eq!(get_title_page_region_end(&no_title_page), 0);
+        assert_eq!(no_title_page[2].fn_type, FNLineType::Action);
+
+        // An unknown key without a value is a transition, not a title page
+        let fade_in = "FADE IN:\n\nINT. HOUSE - DAY\n\nJoe waits.";
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(fade_in));
+        assert_eq!(get_title_page_region_end(&lines), 0);
+        assert_eq!(lines[0].fn_type, FNLineType::TransitionLine);
+        assert_eq!(lines[2].fn_type, FNLineType::Heading);
+        let full = parse_document_full(fade_in, &FNFullParseConfig::default());
+        assert!(full.title_page.is_empty());
+
+        // It still starts one with a value, or with an indented line continuing it
+        for text in [
+            "Revision: 2\n\nINT. HOUSE - DAY",
+            "Notes:\n    Second draft\n\nINT. HOUSE - DAY",
+        ] {
+            let lines =
+                static_fountain_parser::get_parsed_lines_from_raw_string(String::from(text));
+            assert_eq!(
+                get_title_page_region_end(&lines),
+                text.lines().position(str::is_empty).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parsed_document() {
+        use crate::fountain_characters::FNCharacterAliasMap;
+        use crate::fountain_enums::FNLineType;
+
+        let document = static_fountain_parser::parse_document(
+            "Title: Brick\nAuthor: Stu\n\nINT. BAR - NIGHT\n\nJOE\n(quietly\n\nEXT. STREET - DAY\n\nJANE\nHi.\n\nJOE\nBye. [[unclosed",
+        );
+        assert_eq!(document.len(), 15);
+        assert_eq!(document.source.lines().count(), 15);
+        assert_eq!(document.scenes.len(), 2);
+        assert_eq!(
+            document.get_title_page_values("AUTHOR"),
+            Some(&[String::from("Stu")][..])
+        );
+        assert_eq!(document.get_title_page_values("contact"), None);
+        assert_eq!(document.full.outline.len(), 2);
+        assert_eq!(
+            document.get_scene_for_line(11).map(|scene| scene.start),
+            Some(8)
+        );
+        assert_eq!(document.get_scene_lines(&document.scenes[0]).len(), 5);
+        assert_eq!(
+            document.get_line(5).map(|ln| ln.fn_type.clone()),
+            Some(FNLineType::Character)
+        );
+        assert_eq!(
+            document.get_character_names(&FNCharacterAliasMap::default()),
+            vec!["JOE", "JANE"]
+        );
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("JANE", "JOE");
+        assert_eq!(document.get_character_names(&aliases), vec!["JOE"]);
+        assert_eq!(document.full.unclosed_parentheticals, vec![6]);
+        assert!(document.has_diagnostics());
+    }
+
+    #[test]
+    fn test_parsed_document_display() {
+        use crate::fountain_enums::FNLineType;
+
+        let document =
+            static_fountain_parser::parse_document("INT. BAR - NIGHT\n\nJoe waits. [[late]]");
+        assert_eq!(
+            document.to_string(),
+            "    0  Heading                    INT. BAR - NIGHT\n    1  Empty\n    2  Action                     Joe waits.\n"
+        );
+        assert_eq!(
+            FNLine::new("JOE", FNLineType::Character).to_string(),
+            "Character                  JOE"
+        );
+    }
+
+    #[test]
+    fn test_tab_policy() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_layout::{FNAlignment, FNElementLayout, FNElementLayouts};
+        use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+        use crate::static_fountain_preparser::{
+            self, get_text_with_expanded_tabs, FNPreparseOptions, FNTabPolicy,
+        };
+
+        assert_eq!(
+            get_text_with_expanded_tabs("a\tbc\td", &FNTabPolicy::Expand(4)),
+            "a   bc  d"
+        );
+        assert_eq!(
+            get_text_with_expanded_tabs("a\tb", &FNTabPolicy::Preserve),
+            "a\tb"
+        );
+
+        // A tab-indented title page line continues the previous value, even with a colon in it
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "Title: Brick\nContact:\n\tJane Doe\n\tPhone: 555-0100\n\nINT. BAR - NIGHT",
+        ));
+        assert_eq!(lines[3].fn_type, FNLineType::TitlePageContact);
+
+        let options = FNPreparseOptions {
+            tab_policy: FNTabPolicy::Expand(4),
+            ..Default::default()
+        };
+        let lines =
+            static_fountain_preparser::get_unparsed_lines_from_raw_string("\tJane\tDoe", &options);
+        assert_eq!(lines[0].raw_string, "    Jane    Doe");
+        assert_eq!(lines[0].string, lines[0].raw_string);
+        // Wide characters take two columns before the tab stop
+        assert_eq!(
+            get_text_with_expanded_tabs("\u{6771}\tx", &FNTabPolicy::Expand(4)),
+            "\u{6771}  x"
+        );
+
+        // Pagination expands the tabs left in the text the same way, so it lays out lines split without expanding
+        // their tabs just like lines whose tabs were expanded before parsing
+        let text = "Joe\twaits\there.\n\n\u{6771}\u{4EAC}\tmeets\tJane.\n";
+        let get_rows = |preparse_tabs: FNTabPolicy, pagination_tabs: FNTabPolicy| -> Vec<String> {
+            let config = FNFullParseConfig {
+                preparse: FNPreparseOptions {
+                    tab_policy: preparse_tabs,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let options = FNPaginationOptions {
+                layouts: FNElementLayouts {
+                    action: FNElementLayout::new(15, 12, FNAlignment::Left),
+                    ..Default::default()
+                },
+                tab_policy: pagination_tabs,
+                ..Default::default()
+            };
+            let lines = parse_document_full(text, &config).lines;
+            get_pages(&lines, &options, &FNMonospaceMeasurer)[0]
+                .rows
+                .iter()
+                .map(|row| row.text.clone())
+                .collect()
+        };
+        assert_eq!(
+            get_rows(FNTabPolicy::Preserve, FNTabPolicy::Expand(4)),
+            get_rows(FNTabPolicy::Expand(4), FNTabPolicy::Preserve)
+        );
+    }
+
+    #[test]
+    fn test_preparser_round_trip() {
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        for text in [
+            "",
+            "\n",
+            "a",
+            "a\n",
+            "a\n\n",
+            "a\r\n\r\nb\n\n\n",
+            "\r\na\rb",
+        ] {
+            let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+                text.to_string(),
+            ));
+            let total: usize = lines
+                .iter()
+                .map(|ln| ln.raw_string.len() + ln.terminator_length as usize)
+                .sum();
+            assert_eq!(total, text.len(), "{:?}", text);
+            assert_eq!(get_text_from_lines(&lines), text);
+        }
+
+        let lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+            String::from("INT. BAR - NIGHT\n\n"),
+        ));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].raw_string, "");
+
+        // Lines added without a terminator are still separated from the next line
+        let lines = vec![
+            FNLine::new("Joe waits.", crate::fountain_enums::FNLineType::Action),
+            FNLine::new("Jane leaves.", crate::fountain_enums::FNLineType::Action),
+        ];
+        assert_eq!(get_text_from_lines(&lines), "Joe waits.\nJane leaves.");
+    }
+
+    #[test]
+    fn test_character_presence() {
+        use crate::fountain_characters::{get_character_presence, FNCharacterAliasMap};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. KITCHEN - DAY\n\nMARY enters. BANG!\n\nMARY\nWhere is everyone?\n\nEXT. GARDEN - DAY\n\nMARY watches DET. SMITH dig.\n\nSMITH\nNothing.\n\nINT. CAR - NIGHT\n\nEmpty.",
+        ));
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("DET. SMITH", "SMITH");
+        let presence = get_character_presence(&lines, &aliases);
+
+        assert_eq!(presence.characters, vec!["MARY", "SMITH"]);
+        assert_eq!(presence.speaking.len(), 3);
+        assert!(presence.speaking[1].contains("SMITH"));
+        assert!(!presence.speaking[1].contains("MARY"));
+        assert!(presence.mentioned[1].contains("MARY"));
+        assert!(presence.is_present(1, "mary"));
+        assert_eq!(presence.get_scenes_for_character("MARY"), vec![0, 1]);
+        assert_eq!(presence.get_scenes_for_character("SMITH"), vec![1]);
+        assert!(presence.get_characters_in_scene(2).is_empty());
+        assert!(!presence.get_characters_in_scene(0).contains("BANG"));
+    }
+
+    #[test]
+    fn test_non_speaking_characters() {
+        use crate::fountain_characters::{get_non_speaking_characters, FNCharacterAliasMap};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BANK - DAY\n\nMARY waits. A GUARD #2 yawns. BANG!\n\nMARY\nHello?\n\nEXT. STREET - DAY\n\nThe GUARD #2's radio crackles. DET. SMITH arrives.\n\nINT. CAR - NIGHT\n\nOFFICER JONES, bored, eats.",
+        ));
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("OFFICER JONES", "JONES");
+        let characters = get_non_speaking_characters(&lines, &aliases);
+        let names: Vec<&str> = characters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["GUARD #2", "DET. SMITH", "JONES"]);
+        assert_eq!(characters[0].introduction_index, 2);
+        assert_eq!(characters[0].mention_indices, vec![2, 9]);
+        assert_eq!(characters[0].scene_indices, vec![0, 1]);
+        assert_eq!(characters[2].mention_indices, vec![13]);
+    }
+
+    #[test]
+    fn test_character_metadata() {
+        use crate::fountain_characters::{
+            get_character_list, get_character_list_json, FNCharacterAliasMap, FNCharacterMetadata,
+        };
+        use crate::fountain_draft_report::{self, FNDraftReportOptions};
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\nA WAITER passes.\n\nDET. SMITH\nWell?\n\nJANE\nNo.\n\nSMITH\nFine.",
+        ));
+        let mut aliases = FNCharacterAliasMap::new();
+        aliases.add_alias("DET. SMITH", "SMITH");
+        let mut metadata = FNCharacterMetadata::new();
+        metadata.set("Smith", "actor", "Sam \"Doc\" Reyes");
+        metadata.set("jane", "gender", "female");
+        metadata.set("waiter", "color", "gray");
+        assert_eq!(metadata.get("SMITH", "actor"), Some("Sam \"Doc\" Reyes"));
+        assert_eq!(metadata.remove("waiter", "missing"), None);
+
+        let list = get_character_list(&lines, &aliases, &metadata);
+        let names: Vec<(&str, usize)> = list
+            .iter()
+            .map(|e| (e.name.as_str(), e.cue_count))
+            .collect();
+        assert_eq!(names, vec![("SMITH", 2), ("JANE", 1), ("WAITER", 0)]);
+        assert!(!list[2].is_speaking());
+        assert_eq!(
+            list[2].metadata.get("color").map(String::as_str),
+            Some("gray")
+        );
+        assert!(get_character_list_json(&list).contains(
+            "{\"name\":\"SMITH\",\"cue_count\":2,\"scene_indices\":[0],\"metadata\":{\"actor\":\"Sam \\\"Doc\\\" Reyes\"}}"
+        ));
+
+        let options = FNDraftReportOptions {
+            character_metadata: metadata,
+            aliases,
+            ..Default::default()
+        };
+        let report = fountain_draft_report::get_draft_report(&[], &lines, &options);
+        let smith = report
+            .dialogue_changes
+            .iter()
+            .find(|c| c.character == "SMITH")
+            .unwrap();
+        assert_eq!(smith.lines_added, 4);
+        assert_eq!(
+            smith.metadata.get("actor").map(String::as_str),
+            Some("Sam \"Doc\" Reyes")
+        );
+        let jane = report
+            .dialogue_changes
+            .iter()
+            .find(|c| c.character == "JANE")
+            .unwrap();
+        assert_eq!(
+            jane.metadata.get("gender").map(String::as_str),
+            Some("female")
+        );
+        assert!(report
+            .to_json()
+            .contains("\"metadata\":{\"gender\":\"female\"}"));
+    }
+
+    #[test]
+    fn test_dialogue_balance() {
+        use crate::fountain_characters::FNCharacterAliasMap;
+        use crate::fountain_dialogue_balance::{
+            get_dialogue_balance, FNDialogueBalanceBucketing, FNDialogueBalanceOptions,
+        };
+        use crate::fountain_pagination::FNPaginationOptions;
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\nJANE\nOne two three.\n\nJOE\n(sighs)\nFour.\n\nEXT. STREET - DAY\n\nJOE\nFive six.\n\nJANE ^\nSeven six.",
+        ));
+        let balance = get_dialogue_balance(
+            &lines,
+            &FNCharacterAliasMap::default(),
+            &FNDialogueBalanceOptions {
+                bucketing: FNDialogueBalanceBucketing::Scene,
+                ..Default::default()
+            },
+        );
+        assert_eq!(balance.characters, vec!["JANE", "JOE"]);
+        assert_eq!(balance.buckets.len(), 2);
+        assert_eq!(balance.buckets[0].get_total_words(), 4);
+        assert_eq!(balance.get_shares_for_character("JANE"), vec![0.75, 0.5]);
+        assert_eq!(balance.get_shares_for_character("NOBODY"), vec![0.0, 0.0]);
+        assert_eq!(
+            balance.to_json(),
+            "{\"characters\":[\"JANE\",\"JOE\"],\"shares\":{\"JANE\":[0.7500,0.5000],\"JOE\":[0.2500,0.5000]}}"
+        );
+
+        let paged = get_dialogue_balance(
+            &lines,
+            &FNCharacterAliasMap::default(),
+            &FNDialogueBalanceOptions {
+                pagination: FNPaginationOptions {
+                    page_height: 8.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert!(paged.buckets.len() > 1);
+        let total: usize = paged.buckets.iter().map(|b| b.get_total_words()).sum();
+        assert_eq!(total, 8);
+    }
+
+    #[test]
+    fn test_duplicate_dialogue() {
+        use crate::fountain_duplicate_dialogue::{
+            get_duplicate_dialogue, FNDuplicateDialogueOptions,
+        };
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. CAR - NIGHT\n\nJOE\nI told you, we're not going back there.\n\nJANE\nYes.\n\nJOE\nYes.\n\nEXT. ROAD - NIGHT\n\nJANE\nI told you we're not going back there!\n\nJOE\nI told you we're not going back in there.",
+        ));
+        let duplicates = get_duplicate_dialogue(&lines, &FNDuplicateDialogueOptions::default());
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(
+            duplicates[0].text,
+            "I told you, we're not going back there."
+        );
+        assert_eq!(duplicates[0].line_indices, vec![3, 14, 17]);
+        assert_eq!(duplicates[0].characters, vec!["JOE", "JANE", "JOE"]);
+        assert!(!duplicates[0].is_exact());
+
+        let exact = get_duplicate_dialogue(
+            &lines,
+            &FNDuplicateDialogueOptions {
+                min_similarity: 1.0,
+                min_words: 1,
+                ..Default::default()
+            },
+        );
+        assert_eq!(exact.len(), 2);
+        assert_eq!(exact[0].line_indices, vec![3, 14]);
+        assert_eq!(exact[1].line_indices, vec![6, 9]);
+        assert!(exact[1].is_exact());
+
+        let same_character = get_duplicate_dialogue(
+            &lines,
+            &FNDuplicateDialogueOptions {
+                same_character_only: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(same_character.len(), 1);
+        assert_eq!(same_character[0].line_indices, vec![3, 17]);
+    }
+
+    #[test]
+    fn test_note_tasks() {
+        use crate::fountain_notes::{
+            get_note_tasks, get_note_tasks_json, get_notes, FNNoteTaskOptions,
+        };
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "[[todo fix the cold open]]\n\nINT. BAR - NIGHT\n\nJoe waits.[[TODO: cut?]][[a plain note]]\n/* [[FIXME: hidden]] */\n\nEXT. ROAD - DAY\n\nJane runs. [[Note to self: longer\nchase here]] [[TODOS are not tasks]]",
+        ));
+        let notes = get_notes(&lines);
+        let texts: Vec<&str> = notes.iter().map(|note| note.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "todo fix the cold open",
+                "TODO: cut?",
+                "a plain note",
+                "Note to self: longer\nchase here",
+                "TODOS are not tasks",
+            ]
+        );
+        assert_eq!((notes[3].start_index, notes[3].end_index), (9, 10));
+        assert_eq!((notes[1].local_start, notes[2].local_start), (10, 24));
+
+        let tasks = get_note_tasks(&lines, &FNNoteTaskOptions::default());
+        let summary: Vec<(&str, &str, Option<usize>)> = tasks
+            .iter()
+            .map(|task| (task.prefix.as_str(), task.text.as_str(), task.scene_index))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("TODO", "fix the cold open", Some(0)),
+                ("TODO", "cut?", Some(1)),
+                ("NOTE TO SELF", "longer\nchase here", Some(2)),
+            ]
+        );
+        assert_eq!(tasks[0].scene_heading, None);
+        assert_eq!(tasks[1].scene_heading.as_deref(), Some("INT. BAR - NIGHT"));
+        assert!(get_note_tasks_json(&tasks).starts_with(
+            "[{\"prefix\":\"TODO\",\"text\":\"fix the cold open\",\"start_index\":0,\"end_index\":0,\"scene_index\":0,\"scene_heading\":null}"
+        ));
+    }
+
+    #[test]
+    fn test_display_width() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_fixed_width::{get_fixed_width_text, FNFixedWidthOptions};
+        use crate::fountain_text_width::{get_display_width, get_grapheme_display_width};
+        use crate::fountain_wrap::wrap_words;
+
+        let cases = [
+            ("Joe", 3),
+            ("cafe\u{301}", 4),
+            ("\u{1F44D}", 2),
+            ("\u{1F44D}\u{1F3FD}", 2),
+            // Man, ZWJ, woman, ZWJ, girl: one grapheme
+            ("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", 2),
+            ("\u{2764}\u{FE0F}", 2),
+            ("\u{1F1EF}\u{1F1F5}", 2),
+            ("\u{6771}\u{4EAC}", 4),
+            ("\u{645}\u{631}\u{62D}\u{628}\u{627}", 5),
+            ("\u{628}\u{64E}\u{627}\u{628}", 3),
+            ("a\u{200B}b", 2),
+            ("", 0),
+        ];
+        for (text, width) in cases {
+            assert_eq!(get_display_width(text), width, "{:?}", text);
+        }
+        assert_eq!(get_grapheme_display_width("\u{200D}"), 0);
+
+        // Wide graphemes are never split, and rows stay within the width
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let rows = wrap_words(&format!("{} {} {}", family, family, family), 5);
+        assert_eq!(
+            rows,
+            vec![format!("{} {}", family, family), family.to_string()]
+        );
+        assert_eq!(wrap_words("\u{1F44D}\u{1F44D}\u{1F44D}", 4).len(), 2);
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\n> \u{6771}\u{4EAC} <",
+        ));
+        assert_eq!(lines[2].fn_type, FNLineType::Centered);
+        let text = get_fixed_width_text(&lines, &FNFixedWidthOptions::default());
+        let centered = text.lines().last().unwrap();
+        let plain = get_fixed_width_text(
+            &static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+                "INT. BAR - NIGHT\n\n> ABCD <",
+            )),
+            &FNFixedWidthOptions::default(),
+        );
+        // Both centered lines are 4 columns wide, so they start at the same column
+        assert_eq!(
+            centered.find('\u{6771}'),
+            plain.lines().last().unwrap().find('A')
+        );
+    }
+
+    #[test]
+    fn test_text_direction() {
+        use crate::fountain_bidi::{
+            get_text_direction, get_text_directions_for_lines, FNTextDirection,
+        };
+        use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+
+        // Hebrew with an English name in it
+        assert_eq!(
+            get_text_direction("\u{5E9}\u{5DC}\u{5D5}\u{5DD}, JOE, \u{5DE}\u{5D4} \u{5E9}\u{5DC}\u{5D5}\u{5DE}\u{5DA}?"),
+            FNTextDirection::RightToLeft
+        );
+        // Arabic with harakat, which don't count on their own
+        assert_eq!(
+            get_text_direction("\u{628}\u{64E}\u{627}\u{628}"),
+            FNTextDirection::RightToLeft
+        );
+        assert_eq!(
+            get_text_direction("Joe says \u{5E9}\u{5DC}\u{5D5}\u{5DD}"),
+            FNTextDirection::LeftToRight
+        );
+        // The first strong character decides, whatever most of the letters are
+        assert_eq!(
+            get_text_direction("\u{5D0} says hello"),
+            FNTextDirection::RightToLeft
+        );
+        // Text inside an isolate is skipped
+        assert_eq!(
+            get_text_direction("\u{2067}\u{5D0}\u{5D1}\u{2069} ab"),
+            FNTextDirection::LeftToRight
+        );
+        assert_eq!(get_text_direction("... 123 !"), FNTextDirection::Neutral);
+        assert_eq!(FNTextDirection::RightToLeft.get_html_dir(), "rtl");
+        assert_eq!(FNTextDirection::Neutral.get_html_dir(), "auto");
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\nJOE\n[[an English note, which isn't printed]] \u{5E9}\u{5DC}\u{5D5}\u{5DD}\n",
+        ));
+        assert_eq!(lines[3].get_text_direction(), FNTextDirection::LeftToRight);
+        let directions = get_text_directions_for_lines(&lines);
+        assert_eq!(directions[0], FNTextDirection::LeftToRight);
+        assert_eq!(directions[1], FNTextDirection::Neutral);
+        assert_eq!(directions[3], FNTextDirection::RightToLeft);
+
+        let pages = get_pages(
+            &lines,
+            &FNPaginationOptions::default(),
+            &FNMonospaceMeasurer,
+        );
+        let row = pages[0]
+            .rows
+            .iter()
+            .find(|row| row.line_index == 3)
+            .unwrap();
+        assert_eq!(row.direction, FNTextDirection::RightToLeft);
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        use crate::fountain_full_parse::{
+            parse_document_full, FNFullParseConfig, FNParseLimitDiagnosticKind, FNParseLimits,
+        };
+
+        let config = FNFullParseConfig {
+            limits: FNParseLimits {
+                max_document_bytes: Some(200),
+                max_line_bytes: Some(40),
+                max_ranged_element_markers_per_line: Some(4),
+            },
+            ..Default::default()
+        };
+
+        let long_line = "Joe waits by the door. ".repeat(4);
+        let markers = "Joe [[a]] [[b]] [[c]] waits.";
+        let text = format!("INT. BAR - NIGHT\n\n{}\n\n{}\n", long_line, markers);
+        let full = parse_document_full(&text, &config);
+        assert_eq!(full.limit_diagnostics.len(), 2);
+        assert_eq!(
+            full.limit_diagnostics[0].kind,
+            FNParseLimitDiagnosticKind::LineTooLong
+        );
+        assert_eq!(full.limit_diagnostics[0].global_index, 2);
+        assert_eq!(full.limit_diagnostics[0].original_bytes, long_line.len());
+        assert_eq!(full.lines[2].raw_string.len(), 40);
+        // The fifth marker is the `[[` of the third note, so the first two notes are kept
+        assert_eq!(
+            full.limit_diagnostics[1].kind,
+            FNParseLimitDiagnosticKind::TooManyRangedElementMarkers
+        );
+        assert_eq!(full.lines[4].raw_string, "Joe [[a]] [[b]] ");
+        assert_eq!(crate::fountain_notes::get_notes(&full.lines).len(), 2);
+
+        // Cuts never split a character
+        let full = parse_document_full(&"\u{5E9}".repeat(150), &config);
+        assert_eq!(
+            full.limit_diagnostics[0].kind,
+            FNParseLimitDiagnosticKind::DocumentTooLarge
+        );
+        assert_eq!(full.limit_diagnostics[0].kept_bytes, 200);
+        assert_eq!(full.lines[0].raw_string.len(), 40);
+
+        // A million opens stay bounded with the default limits
+        let full = parse_document_full(&"[[".repeat(1_000_000), &FNFullParseConfig::default());
+        assert_eq!(full.lines.len(), 1);
+        assert_eq!(full.lines[0].raw_string.len(), 512);
+
+        let full = parse_document_full(&text, &FNFullParseConfig::default());
+        assert!(full.limit_diagnostics.is_empty());
+
+        // `parse_document` cuts to the default limits, and reports it
+        let opens = "[[".repeat(300);
+        let document = static_fountain_parser::parse_document(&opens);
+        assert_eq!(document.full.lines[0].raw_string.len(), 512);
+        assert!(document.has_diagnostics());
+        assert_eq!(
+            document.full.limit_diagnostics[0].kind,
+            FNParseLimitDiagnosticKind::TooManyRangedElementMarkers
+        );
+
+        // The entry points without diagnostics never drop any of the text
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(opens.clone());
+        assert_eq!(lines[0].raw_string, opens);
+        let lines =
+            static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(opens.clone()));
+        assert_eq!(lines[0].raw_string, opens);
+        let document = crate::fountain_document::FNDocument::new(opens.clone());
+        assert_eq!(document.get_line(0).unwrap().raw_string, opens);
+    }
+
+    #[test]
+    fn test_heading_completions() {
+        use crate::fountain_completion::{
+            get_heading_completions, FNHeadingCompletionKind, FNHeadingCompletionOptions,
+            FNHeadingCompletionSource,
+        };
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. KITCHEN - NIGHT\n\nEXT. KIDS' PARK - DUSK\n\nINT. KITCHEN - LATE NIGHT\n\nINT. KITCHEN - NIGHT\n\nEXT. STREET - NOON\n",
+        ));
+        let options = FNHeadingCompletionOptions::default();
+
+        let locations = get_heading_completions(&lines, "INT. KI", &options);
+        let labels: Vec<&str> = locations.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["KITCHEN", "KIDS' PARK"]);
+        assert_eq!(locations[0].kind, FNHeadingCompletionKind::Location);
+        assert_eq!(locations[0].text, "INT. KITCHEN");
+        assert_eq!(locations[0].count, 3);
+        assert_eq!(locations[1].match_ranges, vec![0..2]);
+        assert_eq!(get_heading_completions(&lines, "INT. ", &options).len(), 3);
+
+        // Times already used in the kitchen come first, then the rest of the document, then common ones
+        let times = get_heading_completions(&lines, "INT. KITCHEN - ", &options);
+        let labels: Vec<&str> = times.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(
+            &labels[..5],
+            &["NIGHT", "LATE NIGHT", "DUSK", "NOON", "DAY"]
+        );
+        assert_eq!(times[0].text, "INT. KITCHEN - NIGHT");
+        assert_eq!(times[0].count, 2);
+        assert_eq!(times[2].source, FNHeadingCompletionSource::Document);
+        assert_eq!(times[4].source, FNHeadingCompletionSource::Common);
+        assert_eq!(labels.iter().filter(|label| **label == "NIGHT").count(), 1);
+
+        // A word in the middle of a time matches too, after the times which start with it
+        let times = get_heading_completions(&lines, "int. kitchen - n", &options);
+        let labels: Vec<&str> = times.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["NIGHT", "LATE NIGHT", "NOON"]);
+        assert_eq!(times[1].match_ranges, vec![5..6]);
+        assert_eq!(times[0].text, "int. kitchen - NIGHT");
+
+        let times = get_heading_completions(&lines, "EXT. STREET - MOM LA", &options);
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].label, "MOMENTS LATER");
+        assert_eq!(times[0].match_ranges, vec![0..3, 8..10]);
+
+        assert!(get_heading_completions(&lines, "KITCHEN", &options).is_empty());
+    }
+
+    #[test]
+    fn test_dialogue_continuation() {
+        use crate::fountain_editing::{
+            get_dialogue_block_for_line_index, get_dialogue_continuation,
+            FNDialogueContinuationOptions,
+        };
+
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. BAR - NIGHT\n\nJOE (V.O.)\n(quietly)\nWait here.\n\nHe leaves.\nThe door shuts.\n\nJANE\nFine.\n\nEXT. STREET - DAY\n\nCars pass.",
+        ));
+        let options = FNDialogueContinuationOptions::default();
+        assert_eq!(get_dialogue_block_for_line_index(&lines, 3), Some(2..5));
+        assert_eq!(get_dialogue_block_for_line_index(&lines, 6), None);
+
+        // Inside the block, and on an empty line right after it, a new line of dialogue
+        for line_index in [2, 3, 5] {
+            let continuation = get_dialogue_continuation(&lines, line_index, &options).unwrap();
+            assert_eq!(continuation.character, "JOE");
+            assert_eq!(continuation.insert_after_index, 4);
+            assert_eq!(continuation.text, "\n");
+            assert!(!continuation.is_interrupted);
+        }
+
+        // After the action, a new cue at the end of the paragraph
+        let continuation = get_dialogue_continuation(&lines, 6, &options).unwrap();
+        assert!(continuation.is_interrupted);
+        assert_eq!(continuation.block, 2..5);
+        assert_eq!(continuation.insert_after_index, 7);
+        assert_eq!(continuation.text, "\n\nJOE (V.O.) (CONT'D)\n");
+        let continuation = get_dialogue_continuation(&lines, 8, &options).unwrap();
+        assert_eq!(continuation.insert_after_index, 8);
+        assert_eq!(continuation.text, "\nJOE (V.O.) (CONT'D)\n");
+
+        // Nothing to continue across a heading
+        assert_eq!(get_dialogue_continuation(&lines, 14, &options), None);
+        assert_eq!(get_dialogue_continuation(&lines, 0, &options), None);
+
+        // A cue which was already continued isn't continued twice, and `^` isn't repeated
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "JOE\nHi.\n\nJANE (cont'd) ^\nHello.\n\nShe waves.\n",
+        ));
+        let continuation = get_dialogue_continuation(&lines, 6, &options).unwrap();
+        assert_eq!(continuation.text, "\n\nJANE (CONT'D)\n");
+        let continuation = get_dialogue_continuation(
+            &lines,
+            6,
+            &FNDialogueContinuationOptions {
+                continued_extension: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(continuation.text, "\n\nJANE (cont'd)\n");
+    }
+
+    #[test]
+    fn test_split_line_at() {
+        use crate::fountain_diff::FNLineChange;
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_enums::FNLineType;
+        use crate::intervals::RangeSet;
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        let mut document = FNDocument::new(String::from(
+            "INT. HOUSE - DAY\n\nA dog barks. A cat meows.\n\n~La la la la\n\n> THE END <\n\nAfter.",
+        ));
+        let uuid = document.get_line(2).unwrap().uuid;
+        assert_eq!(
+            document.split_line_at(2, 12),
+            Some(vec![
+                FNLineChange::TextChanged {
+                    old_index: 2,
+                    new_index: 2
+                },
+                FNLineChange::Inserted { new_index: 3 },
+            ])
+        );
+        assert_eq!(document.get_version(), 1);
+        assert_eq!(document.get_line(2).unwrap().raw_string, "A dog barks.");
+        assert_eq!(document.get_line(3).unwrap().raw_string, " A cat meows.");
+        assert_eq!(document.get_line(2).unwrap().uuid, uuid);
+        assert_ne!(document.get_line(3).unwrap().uuid, uuid);
+
+        document.split_line_at(5, 6).unwrap();
+        assert_eq!(document.get_line(6).unwrap().raw_string, "~ la la");
+        document.split_line_at(8, 5).unwrap();
+        assert_eq!(document.get_line(8).unwrap().raw_string, "> THE <");
+        assert_eq!(document.get_line(9).unwrap().raw_string, "> END <");
+
+        // Splitting at the very start leaves an empty line
+        document.split_line_at(11, 0).unwrap();
+        assert_eq!(document.get_line(11).unwrap().fn_type, FNLineType::Empty);
+        assert_eq!(document.get_line(12).unwrap().raw_string, "After.");
+
+        // Headings, bad offsets, and lines past the end can't be split
+        assert_eq!(document.split_line_at(0, 4), None);
+        assert_eq!(document.split_line_at(2, 99), None);
+        assert_eq!(document.split_line_at(99, 0), None);
+
+        // The types and positions are the same as parsing the new text from scratch
+        let reparsed = FNDocument::new(get_text_from_lines(&document.get_lines()));
+        assert_eq!(reparsed.len(), document.len());
+        for idx in 0..document.len() {
+            let (split, parsed) = (
+                document.get_line(idx).unwrap(),
+                reparsed.get_line(idx).unwrap(),
+            );
+            assert_eq!(split.raw_string, parsed.raw_string);
+            assert_eq!(split.fn_type, parsed.fn_type, "line {}", idx);
+            assert_eq!(split.position, parsed.position, "line {}", idx);
+        }
+
+        let mut ranges = RangeSet::new();
+        ranges.insert(2..8);
+        assert_eq!(ranges.get_slice(5..10, 1).ranges()[0], 1..4);
+
+        // An ALL CAPS action line split after the name becomes a cue and its dialogue
+        let mut document = FNDocument::new(String::from(
+            "Intro.\n\nJOE *slowly* waits [[here]].\n\nEnd.",
+        ));
+        document.split_line_at(2, 3).unwrap();
+        assert_eq!(document.get_line(2).unwrap().fn_type, FNLineType::Character);
+        assert_eq!(document.get_line(3).unwrap().fn_type, FNLineType::Dialogue);
+        let reparsed = FNDocument::new(get_text_from_lines(&document.get_lines()));
+        for idx in 0..document.len() {
+            let (split, parsed) = (
+                document.get_line(idx).unwrap(),
+                reparsed.get_line(idx).unwrap(),
+            );
+            assert_eq!(split.italic_ranges, parsed.italic_ranges, "line {}", idx);
+            assert_eq!(split.note_ranges, parsed.note_ranges, "line {}", idx);
+        }
+        assert!(!document.get_line(3).unwrap().note_ranges.is_empty());
+    }
+
+    #[test]
+    fn test_join_with_previous() {
+        use crate::fountain_diff::FNLineChange;
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_enums::FNLineType;
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        let text = "INT. HOUSE - DAY\n\nA dog barks.\n\nJOE\nHi.\nHow are you?\n\n> THE <\n> END <\n\nAfter.";
+        let mut document = FNDocument::new(String::from(text));
+
+        // Joining a split paragraph gives it back
+        document.split_line_at(2, 5).unwrap();
+        let uuid = document.get_line(2).unwrap().uuid;
+        assert_eq!(
+            document.join_with_previous(3),
+            Some(vec![
+                FNLineChange::TextChanged {
+                    old_index: 2,
+                    new_index: 2
+                },
+                FNLineChange::Removed { old_index: 3 },
+            ])
+        );
+        assert_eq!(get_text_from_lines(&document.get_lines()), text);
+        assert_eq!(document.get_line(2).unwrap().uuid, uuid);
+
+        // Dialogue lines are joined with a space
+        document.join_with_previous(6).unwrap();
+        assert_eq!(document.get_line(5).unwrap().raw_string, "Hi. How are you?");
+        assert_eq!(document.get_line(5).unwrap().fn_type, FNLineType::Dialogue);
+
+        // Centered lines keep a single pair of markers
+        document.join_with_previous(8).unwrap();
+        assert_eq!(document.get_line(7).unwrap().raw_string, "> THE END <");
+        assert_eq!(document.get_line(7).unwrap().fn_type, FNLineType::Centered);
+
+        // Joining the dialogue into its cue leaves the cue without dialogue, so it's retyped as action
+        let changes = document.join_with_previous(5).unwrap();
+        assert_eq!(
+            document.get_line(4).unwrap().raw_string,
+            "JOE Hi. How are you?"
+        );
+        assert_eq!(document.get_line(4).unwrap().fn_type, FNLineType::Action);
+        assert_eq!(changes.len(), 2);
+
+        // Removing an empty line merges two paragraphs, and the types match a fresh parse
+        document.join_with_previous(3).unwrap();
+        let reparsed = FNDocument::new(get_text_from_lines(&document.get_lines()));
+        assert_eq!(reparsed.len(), document.len());
+        for idx in 0..document.len() {
+            let (joined, parsed) = (
+                document.get_line(idx).unwrap(),
+                reparsed.get_line(idx).unwrap(),
+            );
+            assert_eq!(joined.raw_string, parsed.raw_string);
+            assert_eq!(joined.fn_type, parsed.fn_type, "line {}", idx);
+            assert_eq!(joined.position, parsed.position, "line {}", idx);
+        }
+
+        assert_eq!(document.join_with_previous(0), None);
+        assert_eq!(document.join_with_previous(99), None);
+
+        // The lines around the join keep their ranges, and the joined line gets its own, as in a fresh parse
+        let text = "*Joe* waits [[here]].\nHe /* really */\n_leaves_ [[now]].\nThe **end**.";
+        let mut document = FNDocument::new(String::from(text));
+        document.join_with_previous(2).unwrap();
+        let reparsed = FNDocument::new(get_text_from_lines(&document.get_lines()));
+        for idx in 0..document.len() {
+            let (joined, parsed) = (
+                document.get_line(idx).unwrap(),
+                reparsed.get_line(idx).unwrap(),
+            );
+            assert_eq!(joined.bold_ranges, parsed.bold_ranges, "line {}", idx);
+            assert_eq!(joined.italic_ranges, parsed.italic_ranges, "line {}", idx);
+            assert_eq!(
+                joined.underlined_ranges, parsed.underlined_ranges,
+                "line {}",
+                idx
+            );
+            assert_eq!(joined.note_ranges, parsed.note_ranges, "line {}", idx);
+            assert_eq!(joined.omitted_ranges, parsed.omitted_ranges, "line {}", idx);
+        }
+        assert!(!document.get_line(0).unwrap().note_ranges.is_empty());
+        assert!(!document.get_line(1).unwrap().omitted_ranges.is_empty());
+        assert!(!document.get_line(2).unwrap().bold_ranges.is_empty());
+    }
+
+    #[test]
+    fn test_force_line_type() {
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_enums::FNLineType;
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        let text = "INT. HOUSE - DAY\n\nJOE\nHi.\n\nCUT TO:\n\nThe end.";
+        let mut document = FNDocument::new(String::from(text));
+        let raw_at =
+            |document: &FNDocument, idx: usize| document.get_line(idx).unwrap().raw_string.clone();
+
+        // Forcing the cue to action retypes its dialogue too
+        let changes = document.force_line_type(2, FNLineType::Action).unwrap();
+        assert_eq!(raw_at(&document, 2), "!JOE");
+        assert_eq!(document.get_line(3).unwrap().fn_type, FNLineType::Action);
+        assert_eq!(changes.len(), 2);
+
+        // The cue doesn't need a marker once the `!` is gone
+        document.force_line_type(2, FNLineType::Character).unwrap();
+        assert_eq!(raw_at(&document, 2), "JOE");
+        assert_eq!(document.get_line(3).unwrap().fn_type, FNLineType::Dialogue);
+
+        document.force_line_type(7, FNLineType::Heading).unwrap();
+        assert_eq!(raw_at(&document, 7), ".The end.");
+        document.force_line_type(0, FNLineType::Centered).unwrap();
+        assert_eq!(raw_at(&document, 0), "> INT. HOUSE - DAY <");
+        document
+            .force_line_type(0, FNLineType::TransitionLine)
+            .unwrap();
+        assert_eq!(raw_at(&document, 0), "> INT. HOUSE - DAY");
+        document.force_line_type(0, FNLineType::Section).unwrap();
+        assert_eq!(raw_at(&document, 0), "# INT. HOUSE - DAY");
+        document.force_line_type(0, FNLineType::Heading).unwrap();
+        assert_eq!(raw_at(&document, 0), "INT. HOUSE - DAY");
+
+        // The positions of later lines follow the changed text
+        let reparsed = FNDocument::new(get_text_from_lines(&document.get_lines()));
+        for idx in 0..document.len() {
+            let (forced, parsed) = (
+                document.get_line(idx).unwrap(),
+                reparsed.get_line(idx).unwrap(),
+            );
+            assert_eq!(forced.fn_type, parsed.fn_type, "line {}", idx);
+            assert_eq!(forced.position, parsed.position, "line {}", idx);
+        }
+
+        let version = document.get_version();
+        assert_eq!(
+            document.force_line_type(5, FNLineType::TransitionLine),
+            Some(Vec::new())
+        );
+        assert_eq!(document.force_line_type(3, FNLineType::Parenthetical), None);
+        assert_eq!(document.force_line_type(99, FNLineType::Action), None);
+        assert_eq!(document.get_version(), version);
+
+        // Forcing a line keeps the ranges of the lines around it, and finds its own again
+        let mut document =
+            FNDocument::new(String::from("Intro.\n\n*Joe* [[cue?]]\nHi /* there */."));
+        document.force_line_type(2, FNLineType::Character).unwrap();
+        assert_eq!(document.get_line(2).unwrap().fn_type, FNLineType::Character);
+        assert_eq!(document.get_line(2).unwrap().note_ranges.ranges()[0], 7..15);
+        assert!(!document.get_line(2).unwrap().italic_ranges.is_empty());
+        assert!(!document.get_line(3).unwrap().omitted_ranges.is_empty());
+    }
+
+    #[test]
+    fn test_title_page_setters() {
+        use crate::fountain_diff::FNLineChange;
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_title_page::get_title_page_from_lines;
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        let text = "INT. HOUSE - DAY\n\nA dog barks.";
+        let mut document = FNDocument::new(String::from(text));
+        let text_of = |document: &FNDocument| get_text_from_lines(&document.get_lines());
+
+        // A document without a title page gets one, with an empty line after it
+        assert_eq!(
+            document.set_title("Big Fish"),
+            vec![
+                FNLineChange::Inserted { new_index: 0 },
+                FNLineChange::Inserted { new_index: 1 },
+            ]
+        );
+        assert_eq!(
+            text_of(&document),
+            "Title: Big Fish\n\nINT. HOUSE - DAY\n\nA dog barks."
+        );
+        assert_eq!(
+            document.get_line(0).unwrap().fn_type,
+            FNLineType::TitlePageTitle
+        );
+        assert_eq!(document.get_line(2).unwrap().fn_type, FNLineType::Heading);
+
+        // New entries go at the end of the title page, and existing ones are replaced
+        document.set_author("John August");
+        let uuid = document.get_line(0).unwrap().uuid;
+        assert_eq!(
+            document.set_title("Big Fish 2"),
+            vec![FNLineChange::TextChanged {
+                old_index: 0,
+                new_index: 0
+            }]
+        );
+        assert_eq!(document.get_line(0).unwrap().uuid, uuid);
+        document.set_title_page_value("Contact", "Some Agency\n555-1234");
+        document.set_draft_date("October 2026");
+        assert_eq!(
+            text_of(&document),
+            "Title: Big Fish 2\nAuthor: John August\nContact:\n    Some Agency\n    555-1234\nDraft date: October 2026\n\nINT. HOUSE - DAY\n\nA dog barks."
+        );
+
+        // A multi-line value can become a single line, and the types and positions match a fresh parse
+        document.set_title_page_value("CONTACT", "555-1234");
+        let reparsed = FNDocument::new(text_of(&document));
+        assert_eq!(reparsed.len(), document.len());
+        for idx in 0..document.len() {
+            let (set, parsed) = (
+                document.get_line(idx).unwrap(),
+                reparsed.get_line(idx).unwrap(),
+            );
+            assert_eq!(set.fn_type, parsed.fn_type, "line {}", idx);
+            assert_eq!(set.position, parsed.position, "line {}", idx);
+        }
+        let entries = get_title_page_from_lines(&document.get_lines());
+        assert_eq!(entries[2].key, "contact");
+        assert_eq!(entries[2].values, vec!["555-1234"]);
+
+        // Removing every entry gives the original text back
+        for key in ["title", "author", "contact", "draft date"] {
+            assert!(document.remove_title_page_value(key).is_some());
+        }
+        assert_eq!(text_of(&document), text);
+        assert_eq!(document.remove_title_page_value("title"), None);
+
+        // Keys are kept as written, and `Authors` is the author
+        let mut document = FNDocument::new(String::from("TITLE: A\nAUTHORS: B"));
+        document.set_author("C");
+        document.set_title_page_value("Source", "D");
+        assert_eq!(text_of(&document), "TITLE: A\nAUTHORS: C\nSource: D");
+
+        let mut document = FNDocument::new(String::new());
+        document.set_title("Big Fish");
+        assert_eq!(text_of(&document), "Title: Big Fish");
+
+        // A script starting with `FADE IN:` has no title page, so one is added above it
+        let mut document = FNDocument::new(String::from("FADE IN:\n\nINT. HOUSE - DAY"));
+        document.set_title("My Script");
+        assert_eq!(
+            text_of(&document),
+            "Title: My Script\n\nFADE IN:\n\nINT. HOUSE - DAY"
+        );
+        assert_eq!(
+            document.get_line(2).unwrap().fn_type,
+            FNLineType::TransitionLine
+        );
+
+        // Only indented lines continue a value, so the other lines after it are kept
+        let mut document = FNDocument::new(String::from(
+            "Title: Old\n    Part two\nJoe waits.\n\nINT. HOUSE - DAY",
+        ));
+        document.set_title("New");
+        assert_eq!(
+            text_of(&document),
+            "Title: New\nJoe waits.\n\nINT. HOUSE - DAY"
+        );
+        document.remove_title_page_value("title");
+        assert_eq!(text_of(&document), "Joe waits.\n\nINT. HOUSE - DAY");
+
+        // A boneyard opened in the title hides the lines up to its close, past the reparsed title page,
+        // and they're visible again once it's removed, as in a fresh parse
+        let mut document = FNDocument::new(String::from(
+            "Title: A\n\nINT. BAR\n\nJoe waits.\n\nBob */ leaves.",
+        ));
+        for title in ["A /* draft", "A"] {
+            document.set_title(title);
+            let reparsed = FNDocument::new(text_of(&document));
+            for idx in 0..document.len() {
+                let (set, parsed) = (
+                    document.get_line(idx).unwrap(),
+                    reparsed.get_line(idx).unwrap(),
+                );
+                assert_eq!(set.omitted_ranges, parsed.omitted_ranges, "line {}", idx);
+                assert_eq!(
+                    set.additional_omitted_ranges, parsed.additional_omitted_ranges,
+                    "line {}",
+                    idx
+                );
+                assert_eq!(set.boneyard_type, parsed.boneyard_type, "line {}", idx);
+            }
+            assert_eq!(
+                document.get_line(4).unwrap().omitted_ranges.is_empty(),
+                title == "A"
+            );
+        }
+    }
+
+    #[test]
+    fn test_document_edits_match_fresh_parse() {
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_enums::FNLineType;
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        type FNEdit = fn(&mut FNDocument);
+        let cases: [(&str, FNEdit); 8] = [
+            // An empty line added before a heading, or before a cue at the end of the document
+            ("He sits down.\nINT. BAR - NIGHT", |document| {
+                document.split_line_at(0, 13);
+            }),
+            ("He waits.\nJOE", |document| {
+                document.split_line_at(0, 9);
+            }),
+            // A line break at the end of the document
+            ("He waits.", |document| {
+                document.split_line_at(0, 9);
+            }),
+            // An empty line removed, so that the heading isn't one anymore
+            ("He waits.\n\nINT. BAR - NIGHT\n\nJOE", |document| {
+                document.join_with_previous(2);
+                document.join_with_previous(1);
+            }),
+            ("He waits.\n\njoe\nHi.\n\nTHE END", |document| {
+                document.force_line_type(2, FNLineType::Character);
+                document.force_line_type(5, FNLineType::Action);
+            }),
+            // A title page added above a script starting with `FADE IN:`
+            ("FADE IN:\n\nINT. HOUSE - DAY", |document| {
+                document.set_title("My Script");
+            }),
+            // Removing the title page makes the next paragraph the first one
+            ("Title: A\n\nNotes: x\n\nINT. HOUSE - DAY", |document| {
+                document.remove_title_page_value("title");
+            }),
+            ("Title: A\n\nJOE\nHi.", |document| {
+                document.set_author("B");
+                document.set_draft_date("Today");
+                document.remove_title_page_value("title");
+            }),
+        ];
+        for (text, edit) in cases {
+            let mut document = FNDocument::new(String::from(text));
+            edit(&mut document);
+            let edited_text = get_text_from_lines(&document.get_lines());
+            let parsed =
+                static_fountain_parser::get_parsed_lines_from_raw_string(edited_text.clone());
+            assert_eq!(document.len(), parsed.len(), "{:?}", edited_text);
+            for (edited, parsed) in document.get_lines().iter().zip(&parsed) {
+                assert_eq!(edited.raw_string, parsed.raw_string, "{:?}", edited_text);
+                assert_eq!(edited.fn_type, parsed.fn_type, "{:?}", edited_text);
+                assert_eq!(edited.position, parsed.position, "{:?}", edited_text);
+                assert_eq!(
+                    edited.terminator_length, parsed.terminator_length,
+                    "{:?}",
+                    edited_text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_edit_ranges_match_fresh_parse() {
+        use crate::fountain_document::FNDocument;
+        use crate::static_fountain_preparser::get_text_from_lines;
+
+        type FNEdit = fn(&mut FNDocument);
+        let cases: [(&str, FNEdit); 6] = [
+            // A boneyard opened in the title, which reaches the end of the document
+            (">C<\n*/*/\n*/*/\n  \n]]\nFADE IN:", |document| {
+                document.set_title("A /* d");
+            }),
+            // Closing the boneyard which covered the last line
+            ("Title: A /* d\n\n.", |document| {
+                document.set_title("X");
+            }),
+            // `*/*/` leaves a boneyard open, without a range showing it
+            ("*/*/\n\n!!<@", |document| {
+                document.set_title("A /* d");
+            }),
+            ("/*\n*/*/\n\n!!<@", |document| {
+                document.join_with_previous(1);
+            }),
+            // A nested boneyard, split and joined away from its opener
+            ("A /* b\n/* c */\nd */\n.", |document| {
+                document.split_line_at(2, 2);
+            }),
+            ("A /* b\n/* c */\nd */\n.", |document| {
+                document.join_with_previous(2);
+            }),
+        ];
+        for (text, edit) in cases {
+            let mut document = FNDocument::new(String::from(text));
+            edit(&mut document);
+            let edited_text = get_text_from_lines(&document.get_lines());
+            let parsed = FNDocument::new(edited_text.clone());
+            assert_eq!(document.len(), parsed.len(), "{:?}", edited_text);
+            for (edited, parsed) in document.get_lines().iter().zip(parsed.get_lines().iter()) {
+                assert_eq!(edited.raw_string, parsed.raw_string, "{:?}", edited_text);
+                assert_eq!(edited.fn_type, parsed.fn_type, "{:?}", edited_text);
+                assert_eq!(edited.note_ranges, parsed.note_ranges, "{:?}", edited_text);
+                assert_eq!(
+                    edited.omitted_ranges, parsed.omitted_ranges,
+                    "{:?}",
+                    edited_text
+                );
+                assert_eq!(edited.note_type, parsed.note_type, "{:?}", edited_text);
+                assert_eq!(
+                    edited.boneyard_type, parsed.boneyard_type,
+                    "{:?}",
+                    edited_text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_element_layouts() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_fixed_width::{
+            get_fixed_width_rows, get_fixed_width_rows_for_layouts, FNFixedWidthOptions,
+        };
+        use crate::fountain_layout::{FNAlignment, FNElementLayout, FNElementLayouts};
+        use crate::fountain_pagination::{get_pages, FNMonospaceMeasurer, FNPaginationOptions};
+
+        let layouts = FNElementLayouts::default();
+        let table = layouts.get_layout_table();
+        let layout_of = |fn_type: FNLineType| {
+            table
+                .iter()
+                .find(|(t, _)| *t == fn_type)
+                .map(|(_, l)| l.clone())
+        };
+        assert_eq!(
+            layout_of(FNLineType::Heading),
+            Some(FNElementLayout::new(15, 60, FNAlignment::Left))
+        );
+        assert_eq!(
+            layout_of(FNLineType::Centered).unwrap().alignment,
+            FNAlignment::Center
+        );
+        assert_eq!(
+            layout_of(FNLineType::TransitionLine).unwrap().alignment,
+            FNAlignment::Right
+        );
+        assert_eq!(layout_of(FNLineType::Section), None);
+        assert_eq!(FNAlignment::Right.get_css_text_align(), "right");
+
+        let transition = &layouts.transition;
+        assert_eq!(transition.get_row_start(7), 68);
+        assert_eq!(
+            layout_of(FNLineType::Centered).unwrap().get_row_start(10),
+            40
+        );
+
+        // Each side of a dual dialogue gets half of the action columns
+        let left = layouts
+            .get_dual_dialogue_layout_for_line_type(&FNLineType::Character, false)
+            .unwrap();
+        let right = layouts
+            .get_dual_dialogue_layout_for_line_type(&FNLineType::DualDialogueCharacter, true)
+            .unwrap();
+        assert_eq!((left.left_margin, left.width), (29, 16));
+        assert_eq!((right.left_margin, right.width), (59, 16));
+
+        // The fixed-width renderer follows the layouts, including their alignments
+        let lines = static_fountain_parser::get_parsed_lines_from_raw_string(String::from(
+            "INT. HOUSE - DAY\n\nBOB\nHello.\n\nCUT TO:\n\nSTEVE\nHi.\n\nJANE ^\nHey.",
+        ));
+        assert_eq!(
+            get_fixed_width_rows_for_layouts(&lines, &layouts),
+            get_fixed_width_rows(&lines, &FNFixedWidthOptions::default())
+        );
+        let left_transitions = FNElementLayouts {
+            transition: FNElementLayout::new(15, 60, FNAlignment::Left),
+            ..Default::default()
+        };
+        let rows = get_fixed_width_rows_for_layouts(&lines, &left_transitions);
+        assert!(rows.contains(&format!("{}CUT TO:", " ".repeat(15))));
+
+        // Every row of a page has the layout of its element
+        let pages = get_pages(
+            &lines,
+            &FNPaginationOptions::default(),
+            &FNMonospaceMeasurer,
+        );
+        let row_layout = |line_index: usize| {
+            pages[0]
+                .rows
+                .iter()
+                .find(|row| row.line_index == line_index)
+                .unwrap()
+                .layout
+                .clone()
+        };
+        assert_eq!(row_layout(2), layouts.character);
+        assert_eq!(row_layout(3), layouts.dialogue);
+        assert_eq!(row_layout(1), layouts.action);
+        assert_eq!(row_layout(5).alignment, FNAlignment::Right);
+        assert_eq!(row_layout(7).left_margin, 29);
+        assert_eq!(row_layout(10).left_margin, 59);
+    }
+
+    #[test]
+    fn test_convert_dir() {
+        use std::fs;
+
+        use crate::fountain_convert::{self, FNConvertOptions, FNOutputFormat};
+        use crate::fountain_layout::FNElementLayouts;
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        let text = "Title: Fish & Chips\n\nINT. HOUSE - DAY\n\nBOB\nHello <there>.\n\nSTEVE\nHi.\n\nJANE ^\nHey.\n\n===\n\nCUT TO:";
+        let document = FNParsedDocument::new(text);
+
+        let json = fountain_convert::get_json_text(&document);
+        assert!(json.starts_with(
+            "{\"title_page\":[{\"key\":\"title\",\"values\":[\"Fish & Chips\"]}],\"lines\":["
+        ));
+        assert!(json.contains("{\"type\":\"Heading\",\"text\":\"INT. HOUSE - DAY\",\"raw\":\"INT. HOUSE - DAY\",\"color\":null}"));
+
+        let fdx = fountain_convert::get_fdx_text(&document);
+        assert!(
+            fdx.contains("<Paragraph Type=\"Scene Heading\">\n      <Text>INT. HOUSE - DAY</Text>")
+        );
+        assert!(fdx.contains("<Text>Hello &lt;there&gt;.</Text>"));
+        assert!(fdx.contains(
+            "<DualDialogue>\n        <Paragraph Type=\"Character\">\n          <Text>STEVE</Text>"
+        ));
+        assert!(fdx.contains("<Paragraph Type=\"Transition\" StartsNewPage=\"Yes\">"));
+        assert!(fdx.contains("<Paragraph Type=\"General\" Alignment=\"Center\">\n        <Text>Fish &amp; Chips</Text>"));
+
+        let html = fountain_convert::get_html_text(&document, &FNElementLayouts::default());
+        assert!(html.contains("<title>Fish &amp; Chips</title>"));
+        assert!(html
+            .contains(".transition-line { margin-left: 1.5in; width: 6in; text-align: right; }"));
+        assert!(html.contains(
+            "<p class=\"character\">BOB</p>\n<p class=\"dialogue\">Hello &lt;there&gt;.</p>\n<br>"
+        ));
+        assert!(html.contains("<hr class=\"page-break\">"));
+
+        // A directory with a nested script, a file which isn't Fountain, and one which isn't UTF-8
+        let root = std::env::temp_dir().join(format!("fountain-convert-{}", uuid::Uuid::new_v4()));
+        let (input_dir, output_dir) = (root.join("scripts"), root.join("converted"));
+        fs::create_dir_all(input_dir.join("s01")).unwrap();
+        fs::write(input_dir.join("pilot.fountain"), text).unwrap();
+        fs::write(
+            input_dir.join("s01").join("e02.FOUNTAIN"),
+            "EXT. PARK - NIGHT",
+        )
+        .unwrap();
+        fs::write(input_dir.join("notes.txt"), "Not a script").unwrap();
+        fs::write(input_dir.join("broken.fountain"), [0xff, 0xfe, 0x00]).unwrap();
+        // A symlink back to the input directory isn't followed
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&input_dir, input_dir.join("s01").join("loop")).unwrap();
+
+        let options = FNConvertOptions {
+            output_dir: Some(output_dir.clone()),
+            ..Default::default()
+        };
+        let report =
+            fountain_convert::convert_dir(&input_dir, FNOutputFormat::Fdx, &options).unwrap();
+        assert_eq!(report.converted.len(), 2);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].path, input_dir.join("broken.fountain"));
+        assert_eq!(
+            fs::read_to_string(output_dir.join("pilot.fdx")).unwrap(),
+            fdx
+        );
+        assert!(output_dir.join("s01").join("e02.fdx").exists());
+        assert!(!output_dir.join("notes.fdx").exists());
+
+        // Existing outputs are only replaced with `overwrite`
+        let report =
+            fountain_convert::convert_dir(&input_dir, FNOutputFormat::Fdx, &options).unwrap();
+        assert!(!report.is_success());
+        assert!(report
+            .to_string()
+            .starts_with("Converted 0 files, 3 failed\n  "));
+        let overwrite = FNConvertOptions {
+            overwrite: true,
+            ..options.clone()
+        };
+        let report =
+            fountain_convert::convert_dir(&input_dir, FNOutputFormat::Fdx, &overwrite).unwrap();
+        assert_eq!(report.converted.len(), 2);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(fountain_convert::convert_dir(&input_dir, FNOutputFormat::Json, &options).is_err());
+    }
+
+    #[test]
+    fn test_seeded_uuids() {
+        use crate::fountain_annotations::extract_annotations_from_document;
+        use crate::fountain_document::FNDocument;
+        use crate::fountain_uuid::FNUuidGenerator;
+
+        let text = String::from(
+            "INT. HOUSE - DAY\n\nA dog barks.[[@Jane (1712345678): Too loud?]]\n\nJOE\nHi.",
+        );
+        let get_uuids = |document: &FNDocument| -> Vec<uuid::Uuid> {
+            document
+                .get_lines()
+                .iter()
+                .map(|ln| ln.uuid.unwrap())
+                .collect()
+        };
+
+        // The same seed gives the same ids, for the lines of the parse and for every line added later
+        let mut first = FNDocument::with_uuid_generator(text.clone(), FNUuidGenerator::seeded(7));
+        let mut second = FNDocument::with_uuid_generator(text.clone(), FNUuidGenerator::seeded(7));
+        assert_eq!(get_uuids(&first), get_uuids(&second));
+        first.split_line_at(2, 6).unwrap();
+        second.split_line_at(2, 6).unwrap();
+        assert_eq!(get_uuids(&first), get_uuids(&second));
+        assert_eq!(
+            extract_annotations_from_document(&mut first).get_annotations()[0].id,
+            extract_annotations_from_document(&mut second).get_annotations()[0].id
+        );
+
+        // Seeded ids are distinct version 4 uuids, and differ between seeds
+        let uuids = get_uuids(&first);
+        assert!(uuids.iter().all(|uuid| uuid.get_version_num() == 4));
+        let mut sorted = uuids.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), uuids.len());
+        let other = FNDocument::with_uuid_generator(text.clone(), FNUuidGenerator::seeded(8));
+        assert_ne!(
+            get_uuids(&other)[0],
+            get_uuids(&FNDocument::with_uuid_generator(
+                text.clone(),
+                FNUuidGenerator::seeded(7)
+            ))[0]
+        );
+
+        // Random ids by default
+        assert!(!FNUuidGenerator::default().is_seeded());
+        assert_ne!(
+            get_uuids(&FNDocument::new(text.clone())),
+            get_uuids(&FNDocument::new(text))
+        );
+    }
+
+    #[test]
+    fn test_merged_partial_lines() {
+        use crate::fountain_enums::FNLineType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+
+        let text = "INT. HOUSE [[a note\nthat goes on]] - DAY\n\nBOB /* hidden\nstill hidden */\nHello.\n\nA /* b [[c\nd]] */ e.";
+        let full = parse_document_full(text, &FNFullParseConfig::default());
+        let merged = full.get_merged_partial_lines();
+        assert_eq!(merged.len(), 3);
+
+        // The heading is split by a note
+        let heading = &merged[0];
+        assert_eq!(heading.global_range, 0..2);
+        assert_eq!(heading.visible_fnline.string, "INT. HOUSE  - DAY");
+        assert_eq!(heading.visible_fnline.fn_type, FNLineType::Heading);
+        assert_eq!(heading.visible_fnline.position, full.lines[0].position);
+        let dash = heading.visible_fnline.string.find('-').unwrap();
+        assert_eq!(
+            heading.get_raw_location(dash),
+            Some((1, full.lines[1].raw_string.find('-').unwrap()))
+        );
+        assert_eq!(heading.get_raw_location(0), Some((0, 0)));
+        assert_eq!(
+            heading.get_raw_location(heading.visible_fnline.string.len()),
+            Some((1, full.lines[1].raw_string.len()))
+        );
+        assert_eq!(heading.get_raw_location(99), None);
+
+        // The cue keeps its dialogue after the boneyard
+        let cue = &merged[1];
+        assert_eq!(cue.global_range, 3..5);
+        assert_eq!(cue.visible_fnline.string, "BOB ");
+        assert_eq!(cue.visible_fnline.fn_type, FNLineType::Character);
+        assert_eq!(cue.segments.len(), 1);
+
+        // A note inside a boneyard is one line with it
+        assert_eq!(merged[2].global_range, 7..9);
+        assert_eq!(merged[2].visible_fnline.string, "A  e.");
+        assert_eq!(merged[2].visible_fnline.fn_type, FNLineType::Action);
+    }
+
+    #[test]
+    pub fn test_ranged_element_markers_on_partial_lines() {
+        use crate::fountain_full_parse::{self, FNFullParseConfig};
+        use crate::fountain_partial_line_range::FNRangedElementMarkers;
+
+        let unparsed_lines = static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+            String::from("A [[x]] [[\nB ]] C\nD\n"),
+        ));
+        let note = FNRangedElementType::note();
+        let mut partials_map =
+            partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+                &unparsed_lines,
+                &note,
+            )
+            .unwrap();
+        assert_eq!(
+            partials_map[&0].note_markers,
+            Some(FNRangedElementMarkers {
+                opens: vec![2, 8],
+                closes: vec![5],
+            })
+        );
+        assert_eq!(
+            partials_map[&1].note_markers,
+            Some(FNRangedElementMarkers {
+                opens: vec![],
+                closes: vec![2],
+            })
+        );
+        assert!(partials_map[&1].boneyard_markers.is_none());
+        assert!(!partials_map.contains_key(&2));
+
+        let ranges = partial_line_resolver::get_partial_multiline_ranges_from_partial_map(
+            &partials_map,
+            &unparsed_lines,
+            &note,
+        );
+        assert_eq!(
+            (ranges[0].local_start, ranges[0].local_end),
+            (Some(8), Some(2))
+        );
+
+        // The ranges are built from the stored markers, without searching the lines again
+        partials_map.get_mut(&1).unwrap().note_markers = Some(FNRangedElementMarkers {
+            opens: vec![],
+            closes: vec![3],
+        });
+        let ranges = partial_line_resolver::get_partial_multiline_ranges_from_partial_map(
+            &partials_map,
+            &unparsed_lines,
+            &note,
+        );
+        assert_eq!(ranges[0].local_end, Some(3));
+
+        let parse = fountain_full_parse::parse_document_full(
+            "A /* x\nB */ C\n",
+            &FNFullParseConfig::default(),
+        );
+        assert_eq!(
+            parse.lines[1].boneyard_markers,
+            Some(FNRangedElementMarkers {
+                opens: vec![],
+                closes: vec![2],
+            })
+        );
+    }
+
+    #[test]
+    fn test_review_html() {
+        use crate::fountain_convert::{self, FNOutputFormat};
+        use crate::fountain_layout::FNElementLayouts;
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        let document = FNParsedDocument::new(
+            "Joe waits. [[Too slow?]] He leaves.\n\n[[Cut the\nnext scene?]]\n\nJOE\nBye & see you. [[<louder>]]\n",
+        );
+        let html = fountain_convert::get_converted_text(
+            &document,
+            FNOutputFormat::ReviewHtml,
+            &FNElementLayouts::default(),
+        );
+        assert!(html.contains(
+            "<aside class=\"margin-note\" id=\"note-1\"><a href=\"#note-ref-1\">1</a> Too slow?</aside>\n<p class=\"action\">Joe waits. <a class=\"note-ref\" id=\"note-ref-1\" href=\"#note-1\">1</a> He leaves.</p>"
+        ));
+        assert!(html.contains("<a href=\"#note-ref-2\">2</a> Cut the\nnext scene?</aside>"));
+        assert!(html.contains(
+            "<p class=\"dialogue\">Bye &amp; see you. <a class=\"note-ref\" id=\"note-ref-3\" href=\"#note-3\">3</a></p>"
+        ));
+        assert!(html.contains("<a href=\"#note-ref-3\">3</a> &lt;louder&gt;</aside>"));
+
+        // Without notes, the page is the same as plain HTML
+        let plain = FNParsedDocument::new("Joe waits.\n");
+        assert_eq!(
+            fountain_convert::get_review_html_text(&plain, &FNElementLayouts::default()),
+            fountain_convert::get_html_text(&plain, &FNElementLayouts::default())
+        );
+        assert!(
+            !fountain_convert::get_html_text(&document, &FNElementLayouts::default())
+                .contains("Too slow?")
+        );
+    }
+
+    #[test]
+    fn test_scene_colors_and_markers() {
+        use crate::fountain_colors::{get_color_from_note, get_markers, FNMarker};
+        use crate::fountain_convert;
+        use crate::fountain_layout::FNElementLayouts;
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        assert_eq!(
+            get_color_from_note(" COLOR Red "),
+            Some(String::from("red"))
+        );
+        assert_eq!(
+            get_color_from_note("#FF8800"),
+            Some(String::from("#ff8800"))
+        );
+        assert_eq!(get_color_from_note("redo this"), None);
+
+        let document = FNParsedDocument::new(
+            "# Act One [[blue]]\n\nINT. BAR - NIGHT [[COLOR RED]]\n\nJoe leaves. [[marker green: cut?]] [[markers are notes]]\n\nEXT. ROAD - DAY [[marker]]\n",
+        );
+        assert_eq!(document.full.lines[0].color, "blue");
+        assert_eq!(document.full.lines[2].color, "red");
+        assert_eq!(document.full.lines[6].color, "");
+        let colors: Vec<Option<&str>> = document
+            .full
+            .outline
+            .iter()
+            .map(|element| element.color.as_deref())
+            .collect();
+        assert_eq!(colors, vec![Some("blue"), Some("red"), None]);
+        assert_eq!(
+            get_markers(&document.full.lines),
+            vec![
+                FNMarker {
+                    line_index: 4,
+                    color: Some(String::from("green")),
+                    description: String::from("cut?"),
+                },
+                FNMarker {
+                    line_index: 6,
+                    color: None,
+                    description: String::new(),
+                },
+            ]
+        );
+
+        let json = fountain_convert::get_json_text(&document);
+        assert!(json.contains("\"raw\":\"INT. BAR - NIGHT [[COLOR RED]]\",\"color\":\"red\"}"));
+        assert!(json.ends_with(
+            "\"markers\":[{\"line_index\":4,\"color\":\"green\",\"description\":\"cut?\"},{\"line_index\":6,\"color\":null,\"description\":\"\"}]}"
+        ));
+        let html = fountain_convert::get_html_text(&document, &FNElementLayouts::default());
+        assert!(html.contains("<p class=\"heading\" style=\"color: #e0404a\">INT. BAR - NIGHT</p>"));
+        assert!(html.contains("<p class=\"heading\">EXT. ROAD - DAY</p>"));
+        let fdx = fountain_convert::get_fdx_text(&document);
+        assert!(fdx.contains(
+            "<Paragraph Type=\"Scene Heading\">\n      <SceneProperties Color=\"#E0E040404A4A\"/>\n      <Text>INT. BAR - NIGHT</Text>"
+        ));
+    }
+
+    #[test]
+    fn test_av_and_radio_scripts() {
+        use crate::fountain_av::{self, FNAvOptions, FNAvRowKind};
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_parsed_document::FNParsedDocument;
+        use crate::static_fountain_preparser::{FNNewlinePolicy, FNPreparseOptions};
+
+        let document = FNParsedDocument::new(
+            "INT. STUDIO - NIGHT\n\nA door slams.\nFootsteps. [[louder]]\n\nJOE\n(quietly)\nAre you still there?\n\nJANE (V.O.)\nYes.\n",
+        );
+        let rows = fountain_av::get_av_rows(&document.full.lines);
+        let summary: Vec<(FNAvRowKind, Option<&str>, &str)> = rows
+            .iter()
+            .map(|row| (row.kind, row.speaker.as_deref(), row.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (FNAvRowKind::Scene, None, "INT. STUDIO - NIGHT"),
+                (FNAvRowKind::Direction, None, "A door slams. Footsteps."),
+                (
+                    FNAvRowKind::Speech,
+                    Some("JOE"),
+                    "(quietly) Are you still there?"
+                ),
+                (FNAvRowKind::Speech, Some("JANE (V.O.)"), "Yes."),
+            ]
+        );
+        assert_eq!(rows[2].line_indices, 5..8);
+
+        let options = FNAvOptions {
+            speaker_width: 8,
+            line_width: 20,
+        };
+        assert_eq!(
+            fountain_av::get_two_column_text(&document.full.lines, &options),
+            "INT. STUDIO - NIGHT\n\n        A door slams.\n        Footsteps.\n\nJOE     (quietly) Are you\n        still there?\n\nJANE    Yes.\n(V.O.)\n"
+        );
+        assert_eq!(
+            fountain_av::get_radio_script_text(&document.full.lines, &options),
+            "SCENE 1: INT. STUDIO -\nNIGHT\n\n    [A door slams.\n    Footsteps.]\n\n1.  JOE: (quietly) Are\n    you still there?\n\n2.  JANE (V.O.): Yes.\n"
+        );
+
+        // Columns are padded by display width, and rows keep the document's line breaks
+        let full = parse_document_full(
+            "\u{6771}\u{4EAC}\r\nHi.\r\n",
+            &FNFullParseConfig {
+                preparse: FNPreparseOptions {
+                    newline_policy: FNNewlinePolicy::Preserve,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            fountain_av::get_two_column_text(&full.lines, &options),
+            "\u{6771}\u{4EAC}    Hi.\r\n"
+        );
+
+        // The number column grows with the number of speeches
+        let document = FNParsedDocument::new(&"JOE\nHi.\n\n".repeat(1000));
+        let radio = fountain_av::get_radio_script_text(&document.full.lines, &options);
+        assert!(radio.contains("\n999.  JOE: Hi.\n"));
+        assert!(radio.contains("\n1000. JOE: Hi.\n"));
+    }
+
+    #[test]
+    fn test_cue_sheet() {
+        use crate::fountain_cue_sheet::{self, FNCueSheetOptions};
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        let document = FNParsedDocument::new(
+            "INT. STUDIO - NIGHT\n\nJOE KNOCKS twice. A dog BARKS. The door SLAMS. [[ADD RAIN]]\n\nJOE\n(quietly)\nAre you still there?\n\nJANE (V.O.)\nYes.\n\nJOE\nGood.\n\nEXT. STREET - DAY\n\nA car HONKS. BOOM!\n",
+        );
+        let sheet =
+            fountain_cue_sheet::get_cue_sheet(&document.full.lines, &FNCueSheetOptions::default());
+        assert_eq!(sheet.len(), 2);
+        assert_eq!(sheet[0].heading, "INT. STUDIO - NIGHT");
+        let characters: Vec<(&str, usize, usize)> = sheet[0]
+            .characters
+            .iter()
+            .map(|character| (character.name.as_str(), character.speeches, character.words))
+            .collect();
+        assert_eq!(characters, vec![("JOE", 2, 5), ("JANE", 1, 1)]);
+        let cues: Vec<(usize, &str)> = sheet[0]
+            .sound_cues
+            .iter()
+            .map(|cue| (cue.line_index, cue.word.as_str()))
+            .collect();
+        assert_eq!(cues, vec![(2, "KNOCKS"), (2, "BARKS"), (2, "SLAMS")]);
+        assert!(sheet[1].characters.is_empty());
+        assert_eq!(sheet[1].sound_cues.len(), 2);
+
+        // Without keywords, any capitalized word which isn't a speaking character is a cue
+        let options = FNCueSheetOptions {
+            sound_keywords: Vec::new(),
+            ..Default::default()
+        };
+        let sheet = fountain_cue_sheet::get_cue_sheet(&document.full.lines, &options);
+        let words: Vec<&str> = sheet[0]
+            .sound_cues
+            .iter()
+            .map(|cue| cue.word.as_str())
+            .collect();
+        assert_eq!(words, vec!["KNOCKS", "BARKS", "SLAMS"]);
+
+        assert_eq!(
+            fountain_cue_sheet::get_cue_sheet_json(&sheet[1..]),
+            "[{\"scene_index\":1,\"heading\":\"EXT. STREET - DAY\",\"characters\":[],\"sound_cues\":[{\"line_index\":16,\"word\":\"HONKS\"},{\"line_index\":16,\"word\":\"BOOM\"}]}]"
+        );
+        assert_eq!(
+            fountain_cue_sheet::get_cue_sheet_markdown(&sheet),
+            "# Cue sheet\n\n## 1. INT. STUDIO - NIGHT\n\n| Character | Speeches | Words |\n| --- | --- | --- |\n| JOE | 2 | 5 |\n| JANE | 1 | 1 |\n\nSound cues: KNOCKS, BARKS, SLAMS\n\n## 2. EXT. STREET - DAY\n\nSound cues: HONKS, BOOM\n"
+        );
+    }
+
+    #[test]
+    fn test_metrics_json() {
+        use crate::fountain_metrics::{self, FNLintSeverity, FNMetricsOptions};
+        use crate::fountain_parsed_document::FNParsedDocument;
+
+        let document = FNParsedDocument::new(
+            "Title: Brick\n\n# Act One\n\nINT. STUDIO - NIGHT\n\nJOE waits. [[fix this\n\nJOE\nWe're not going back there.\n\nJANE\nWe're not going back there.\n\nJOE\n(softly\n",
+        );
+        let metrics = fountain_metrics::get_metrics(&document, &FNMetricsOptions::default());
+        assert_eq!(metrics.scene_count, 1);
+        assert_eq!(
+            metrics.character_cues,
+            vec![(String::from("JOE"), 2), (String::from("JANE"), 1)]
+        );
+        // The unclosed note is printed
+        assert_eq!(metrics.word_count, 22);
+        assert_eq!(metrics.dialogue_word_count, 10);
+        assert_eq!(metrics.action_word_count, 4);
+        assert_eq!(metrics.page_count, 1);
+        assert_eq!(metrics.page_length, 0.25);
+        assert_eq!(metrics.estimated_runtime_minutes, 0);
+        let findings: Vec<(&str, usize)> = metrics
+            .findings
+            .iter()
+            .map(|finding| (finding.rule, finding.line_index))
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                ("unclosed-note", 6),
+                ("speaks-before-introduction", 11),
+                ("duplicate-dialogue", 12),
+                ("unclosed-parenthetical", 15),
+            ]
+        );
+        assert!(metrics
+            .findings
+            .iter()
+            .all(|finding| finding.severity == FNLintSeverity::Warning));
+
+        let json = document.metrics_json();
+        assert!(json.starts_with(
+            "{\"schema_version\":1,\"stats\":{\"scene_count\":1,\"character_count\":2,\"word_count\":22,"
+        ));
+        assert!(json.contains(
+            "\"pages\":{\"page_count\":1,\"page_length\":0.25,\"estimated_runtime_minutes\":0}"
+        ));
+        assert!(json.contains("\"lint\":{\"error_count\":0,\"warning_count\":4,\"findings\":[{\"rule\":\"unclosed-note\",\"severity\":\"warning\",\"line_index\":6,\"message\":\"Unclosed note\"}"));
+    }
+
+    #[test]
+    fn test_additional_note_types() {
+        use crate::fountain_enums::FNRangedElementType;
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig, FNParseLimits};
+        use crate::fountain_text_extraction::get_printable_strings_for_lines;
+
+        let text = "Joe waits. <!-- private -->\nJane [cut?] leaves. [[a [regular] note]]\n\n<!-- a comment\nover two lines -->\n\n/* <!-- not a note */ Gone.\n\nAn <!-- unclosed comment\n";
+        let config = FNFullParseConfig {
+            additional_note_types: vec![
+                FNRangedElementType::html_comment_note(),
+                FNRangedElementType::single_bracket_note(),
+            ],
+            ..Default::default()
+        };
+        let full = parse_document_full(text, &config);
+        let printable = get_printable_strings_for_lines(&full.lines);
+        assert_eq!(printable[0], "Joe waits.");
+        assert_eq!(printable[1], "Jane  leaves.");
+        assert_eq!(printable[3], "");
+        assert_eq!(printable[4], "");
+        assert_eq!(printable[6], "Gone.");
+        assert_eq!(printable[8], "An <!-- unclosed comment");
+        // Only the notes of the additional syntaxes
+        assert!(full.lines[1].additional_note_ranges.contains_range(5..11));
+        assert_eq!(full.lines[1].additional_note_ranges.get_index_count(), 6);
+        assert!(full.lines[3].additional_note_ranges.contains_range(0..14));
+        assert_eq!(full.notes.multiline_ranges.len(), 1);
+        assert_eq!(full.notes.multiline_ranges[0].global_start, Some(3));
+
+        // Only the unclosed comment is a diagnostic, with the open of its own syntax
+        assert_eq!(full.diagnostics.len(), 1);
+        assert_eq!(full.diagnostics[0].global_index, 8);
+        assert_eq!(full.diagnostics[0].local_index, 3);
+        assert_eq!(
+            full.diagnostics[0].ranged_element_type,
+            FNRangedElementType::html_comment_note()
+        );
+
+        // Without the additional syntaxes, the text stays
+        let full = parse_document_full(text, &FNFullParseConfig::default());
+        let printable = get_printable_strings_for_lines(&full.lines);
+        assert_eq!(printable[0], "Joe waits. <!-- private -->");
+        assert_eq!(printable[1], "Jane [cut?] leaves.");
+
+        // The `[` of each `[[` isn't counted against the limit on markers
+        let config = FNFullParseConfig {
+            limits: FNParseLimits {
+                max_ranged_element_markers_per_line: Some(4),
+                ..Default::default()
+            },
+            ..config
+        };
+        let full = parse_document_full("Joe [[a]] [[b]] leaves.", &config);
+        assert!(full.limit_diagnostics.is_empty());
+        assert_eq!(
+            get_printable_strings_for_lines(&full.lines)[0],
+            "Joe   leaves."
+        );
+    }
+
+    #[test]
+    fn test_scrub_for_production() {
+        use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig};
+        use crate::fountain_parsed_document::FNParsedDocument;
+        use crate::fountain_scrub::{scrub_for_production, FNScrubOptions};
+        use crate::static_fountain_preparser::{FNNewlinePolicy, FNPreparseOptions};
+
+        let document = FNParsedDocument::new(
+            "Title: Brick\n\n# Act One\n= Joe waits for Jane.\n\nINT. BAR - NIGHT [[COLOR RED]]\n\nJoe waits. [[too slow?]]\n\n[[marker: cut?]]\n\nJOE /* (V.O.) */\nShe's not /* ever\n*/ coming.\n\n/*\nJane never shows.\n*/\n\n## The Street\n\nEXT. STREET - DAY\n",
+        );
+        assert_eq!(
+            scrub_for_production(&document.full.lines, &FNScrubOptions::default()),
+            "Title: Brick\n\nINT. BAR - NIGHT\n\nJoe waits.\n\nJOE\nShe's not  coming.\n\nEXT. STREET - DAY\n"
+        );
+        assert_eq!(
+            scrub_for_production(&document.full.lines, &FNScrubOptions { keep_sections: true }),
+            "Title: Brick\n\n# Act One\n\nINT. BAR - NIGHT\n\nJoe waits.\n\nJOE\nShe's not  coming.\n\n## The Street\n\nEXT. STREET - DAY\n"
+        );
+
+        // Line breaks are kept, and a multiline note of another syntax joins the text around it
+        let full = parse_document_full(
+            "Joe <!-- a\r\nsecret b\r\nc --> leaves.\r\n\r\nBOB\r\nHi.",
+            &FNFullParseConfig {
+                additional_note_types: vec![FNRangedElementType::html_comment_note()],
+                preparse: FNPreparseOptions {
+                    newline_policy: FNNewlinePolicy::Preserve,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            scrub_for_production(&full.lines, &FNScrubOptions::default()),
+            "Joe  leaves.\r\n\r\nBOB\r\nHi."
+        );
+    }
+
+    #[test]
+    pub fn get_partial_multiline_ranges() {
+        let file_result: Result<String, std::io::Error> = ranged_items_test_file_path_result();
+        let ranged_element_type = FNRangedElementType::note();
+        if let Ok(document_string) = file_result {
+            println!("I am here");
+            let unparsed_lines: Vec<FNLine> =
+                static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+                    document_string,
+                ));
+            let partial_map_opt =
+                partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+                    &unparsed_lines,
+                    &ranged_element_type,
+                );
+            if let Some(partial_map) = partial_map_opt {
+                let multiline_ranges =
+                    partial_line_resolver::get_partial_multiline_ranges_from_partial_map(
+                        &partial_map,
+                        &unparsed_lines,
+                        &ranged_element_type,
+                    );
+                for range in multiline_ranges {
+                    let start = range.global_start.unwrap();
+                    let end = range.global_end.unwrap();
+                    println!("---------------------------");
+                    for i in start..=end {
+                        if let Some(ln) = unparsed_lines.get(i) {
+                            let _gphs: String = ln.raw_string.graphemes(true).take(50).collect();
+                            println!("Line #{},\t Raw String:{}", i, ln.raw_string);
+                        }
+                    }
+                    println!("Range: {:#?}", range);
+
+                    println!("---------------------------");
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn get_stripped_string_from_parsed_and_partial_lines() {
+        let file_result: Result<String, std::io::Error> = ranged_items_test_file_path_result();
+        let ranged_element_type = FNRangedElementType::note();
+        if let Ok(document_string) = file_result {
+            let unparsed_lines: Vec<FNLine> =
+                static_fountain_parser::get_unparsed_line_array_from_raw_string(Some(
+                    document_string,
+                ));
+            let partial_map_opt =
+                partial_line_resolver::get_partial_fnline_map_for_ranged_element_type(
+                    &unparsed_lines,
+                    &ranged_element_type,
+                );
+            if let Some(partial_map) = partial_map_opt {
+                let _multiline_ranges =
                     partial_line_resolver::get_partial_multiline_ranges_from_partial_map(
                         &partial_map,
                         &unparsed_lines,
@@ -0,0 +1,246 @@
+//! Handles Beat-style text macros, which are written between double curly braces: `{{ ... }}`
+//!
+//! Supported macros:
+//!
+//! - `{{date}}` or `{{date FORMAT}}` - the current date. `FORMAT` may use `YYYY`, `MM`, and `DD`.
+//! - `{{serial name}}` (or `{{serie name}}`) - an auto-incrementing counter, starting at 1
+//! - `{{name = value}}` - assigns a user variable; this expands to nothing
+//! - `{{name}}` - prints a user variable
+//!
+//! Macros are detected on the `raw_string` of each `FNLine`. Expanding them gives back a copy of the lines
+//! with the macros replaced in the `string` field (which otherwise stays as it was, such as a cue without its `@`),
+//! plus a source map so that an editor can still find where each expanded piece of text came from in the raw document.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fountain_line::FNLine;
+
+const MACRO_OPEN: &str = "{{";
+const MACRO_CLOSE: &str = "}}";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FNMacroKind {
+    Date { format: Option<String> },
+    Serial { name: String },
+    Assignment { name: String, value: String },
+    Variable { name: String },
+}
+
+/// A single `{{ ... }}` span within a line.
+///
+/// `local_start` and `local_end` are byte indices into the line's `raw_string`;
+/// `local_end` is exclusive and includes the closing braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNMacro {
+    pub line_index: usize,
+    pub local_start: usize,
+    pub local_end: usize,
+    pub raw_text: String,
+    pub kind: FNMacroKind,
+}
+
+/// The values used while expanding macros.
+///
+/// If `date` is `None`, the current system date is used.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FNMacroContext {
+    pub variables: HashMap<String, String>,
+    pub date: Option<(i32, u32, u32)>,
+}
+
+/// Maps a single expanded macro back to its span in the raw line.
+///
+/// All indices are byte indices. `raw_*` refer to the line's `raw_string`,
+/// and `expanded_*` refer to the `string` of the expanded line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNMacroSourceMapEntry {
+    pub line_index: usize,
+    pub raw_start: usize,
+    pub raw_end: usize,
+    pub expanded_start: usize,
+    pub expanded_end: usize,
+}
+
+// ----- Public Functions -----
+
+/// Returns every macro found in the given lines, in document order.
+///
+/// An opening `{{` without a matching `}}` on the same line is not a macro.
+pub fn get_macros_from_lines(lines: &[FNLine]) -> Vec<FNMacro> {
+    let mut macros: Vec<FNMacro> = Vec::new();
+    for (line_index, ln) in lines.iter().enumerate() {
+        macros.extend(get_macros_from_single_line(&ln.raw_string, line_index));
+    }
+    macros
+}
+
+/// Returns a copy of the lines with every macro expanded, as well as a source map for each expanded macro.
+///
+/// Counters are incremented in document order, and assignments only affect the macros which come after them.
+/// Variables which were never assigned (and aren't in the `FNMacroContext`) are left as-is.
+pub fn get_expanded_lines_and_source_map(
+    lines: &[FNLine],
+    context: &FNMacroContext,
+) -> (Vec<FNLine>, Vec<FNMacroSourceMapEntry>) {
+    let mut variables: HashMap<String, String> = context.variables.clone();
+    let mut counters: HashMap<String, usize> = HashMap::new();
+    let date = context.date.unwrap_or_else(get_current_date);
+
+    let mut expanded_lines: Vec<FNLine> = Vec::with_capacity(lines.len());
+    let mut source_map: Vec<FNMacroSourceMapEntry> = Vec::new();
+
+    for (line_index, ln) in lines.iter().enumerate() {
+        // Expanded in the `string`, and mapped to the same macros of the `raw_string`
+        let line_macros = get_macros_from_single_line(&ln.string, line_index);
+        let mut new_line = ln.clone();
+        if line_macros.is_empty() {
+            expanded_lines.push(new_line);
+            continue;
+        }
+        let mut raw_macros = get_macros_from_single_line(&ln.raw_string, line_index).into_iter();
+
+        let mut expanded = String::new();
+        let mut last_end: usize = 0;
+
+        for mac in line_macros {
+            expanded.push_str(&ln.string[last_end..mac.local_start]);
+
+            let replacement: String = match &mac.kind {
+                FNMacroKind::Date { format } => get_formatted_date(date, format.as_deref()),
+                FNMacroKind::Serial { name } => {
+                    let counter = counters.entry(name.clone()).or_insert(0);
+                    *counter += 1;
+                    counter.to_string()
+                }
+                FNMacroKind::Assignment { name, value } => {
+                    variables.insert(name.clone(), value.clone());
+                    String::new()
+                }
+                FNMacroKind::Variable { name } => match variables.get(name) {
+                    Some(value) => value.clone(),
+                    None => mac.raw_text.clone(),
+                },
+            };
+
+            let expanded_start = expanded.len();
+            expanded.push_str(&replacement);
+            if let Some(raw_mac) = raw_macros.find(|raw_mac| raw_mac.raw_text == mac.raw_text) {
+                source_map.push(FNMacroSourceMapEntry {
+                    line_index,
+                    raw_start: raw_mac.local_start,
+                    raw_end: raw_mac.local_end,
+                    expanded_start,
+                    expanded_end: expanded.len(),
+                });
+            }
+            last_end = mac.local_end;
+        }
+        expanded.push_str(&ln.string[last_end..]);
+
+        new_line.string = expanded;
+        expanded_lines.push(new_line);
+    }
+
+    (expanded_lines, source_map)
+}
+
+// ----- Private Functions -----
+
+fn get_macros_from_single_line(raw_string: &str, line_index: usize) -> Vec<FNMacro> {
+    let mut macros: Vec<FNMacro> = Vec::new();
+    let mut search_start: usize = 0;
+
+    while let Some(open_offset) = raw_string[search_start..].find(MACRO_OPEN) {
+        let local_start = search_start + open_offset;
+        let content_start = local_start + MACRO_OPEN.len();
+        let Some(close_offset) = raw_string[content_start..].find(MACRO_CLOSE) else {
+            break;
+        };
+        let content_end = content_start + close_offset;
+        let local_end = content_end + MACRO_CLOSE.len();
+
+        if let Some(kind) = get_macro_kind_from_content(&raw_string[content_start..content_end]) {
+            macros.push(FNMacro {
+                line_index,
+                local_start,
+                local_end,
+                raw_text: raw_string[local_start..local_end].to_string(),
+                kind,
+            });
+        }
+        search_start = local_end;
+    }
+
+    macros
+}
+
+fn get_macro_kind_from_content(content: &str) -> Option<FNMacroKind> {
+    let content = content.trim();
+    if content.is_empty() {
+        return None;
+    }
+
+    if let Some((name, value)) = content.split_once('=') {
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        return Some(FNMacroKind::Assignment {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+        });
+    }
+
+    let mut words = content.split_whitespace();
+    let keyword = words.next().unwrap_or_default();
+    let rest: Vec<&str> = words.collect();
+
+    match keyword.to_lowercase().as_str() {
+        "date" => Some(FNMacroKind::Date {
+            format: if rest.is_empty() {
+                None
+            } else {
+                Some(rest.join(" "))
+            },
+        }),
+        "serial" | "serie" => Some(FNMacroKind::Serial {
+            name: rest.join(" "),
+        }),
+        _ => Some(FNMacroKind::Variable {
+            name: content.to_string(),
+        }),
+    }
+}
+
+fn get_formatted_date(date: (i32, u32, u32), format: Option<&str>) -> String {
+    let (year, month, day) = date;
+    let format = format.unwrap_or("YYYY-MM-DD");
+    format
+        .replace("YYYY", &format!("{:04}", year))
+        .replace("MM", &format!("{:02}", month))
+        .replace("DD", &format!("{:02}", day))
+}
+
+/// Converts the current system time into a (year, month, day) tuple, in UTC.
+fn get_current_date() -> (i32, u32, u32) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    get_civil_date_from_days((seconds / 86_400) as i64)
+}
+
+// Howard Hinnant's `civil_from_days` algorithm
+fn get_civil_date_from_days(days_since_epoch: i64) -> (i32, u32, u32) {
+    let z = days_since_epoch + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+    (year, month, day)
+}
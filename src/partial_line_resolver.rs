@@ -3,93 +3,159 @@
 /// A "partial" line range is any line that is interrupted by a
 /// multiline invisible (such as `Boneyard` or `Note`), which would be only a single line
 /// if the multiline invisible were not present.
-use std::collections::{HashMap, HashSet};
-use std::hash::Hash;
-
-use enum_iterator::last;
-use unicode_segmentation::UnicodeSegmentation;
-use uuid::{uuid, Uuid};
+///
+/// It's public for callers who resolve notes and boneyards step by step, rather than with `parse_document_full`.
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
 
-use crate::fountain_enums::{FNLineType, FNPartialLineType, FNRangedElementType};
+use crate::fountain_enums::{FNPartialLineType, FNRangedElementType};
 use crate::fountain_line::FNLine;
-use crate::fountain_partial_line_range::{FNPartialLineRange, FNPartialMultilineRange};
+use crate::fountain_partial_line_range::{
+    FNMergedLineSegment, FNMergedPartialLine, FNPartialLineRange, FNPartialMultilineRange,
+    FNRangedElementDiagnostic, FNRangedElementDiagnosticKind, FNRangedElementMarkers,
+    FNRangedElementResolutionStrategy,
+};
+use crate::fountain_text_extraction::{
+    get_invisible_byte_ranges_for_lines, get_invisible_byte_ranges_for_lines_with_note_types,
+    get_visible_byte_ranges_for_lines,
+};
+use crate::helper_funcs::is_empty_line_for_ranged_elements;
+use crate::intervals::RangeSet;
+use crate::static_fountain_parser;
+use crate::static_fountain_preparser::{self, FNPreparseOptions};
 
-/// Given an FNRangedElementType, Returns an optional HashMap of indices and corresponding FNLine objects with updated PartialLineType added.
+/// Given an FNRangedElementType, Returns an optional BTreeMap of indices and corresponding FNLine objects with updated PartialLineType added, in order of their index.
 /// These updated FNLines are to be used to handle extracting the printable text (if any) so that it may be handled by the `static_fountain_parser`
 ///
 /// This only gives a map for one element type, so this function must be called at least twice - once for Notes, and once for Boneyards.
+///
+/// Each line in the map also has its `note_markers` or `boneyard_markers` set, which the functions building the ranges
+/// read instead of searching the line again.
 pub fn get_partial_fnline_map_for_ranged_element_type(
-    lines: &Vec<FNLine>,
+    lines: &[FNLine],
     ranged_element_type: &FNRangedElementType,
-) -> Option<HashMap<usize, FNLine>> {
+) -> Option<BTreeMap<usize, FNLine>> {
     //TODO: Make this function receive the global and local indices as args rather than calculate them in here
     //TODO: Make this function receive the partial_types_for_global_indices map as an arg instead of calculating in here
     //TODO: What do we do with the output of this god damn fuction aaahhhhhhh
-    let mut partials_opens_map: HashMap<usize, Vec<usize>> = HashMap::new();
-    let mut partials_closes_map: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut partials_markers_map: BTreeMap<usize, FNRangedElementMarkers> = BTreeMap::new();
 
     let element_specific_global_indices =
         get_global_indices_of_ranged_element(lines, ranged_element_type);
 
-    let mut partials_types_for_global_indices_map: HashMap<usize, FNPartialLineType> =
-        HashMap::new();
+    let mut partials_types_for_global_indices_map: BTreeMap<usize, FNPartialLineType> =
+        BTreeMap::new();
 
     for global_idx in element_specific_global_indices {
         if let Some(ln) = lines.get(global_idx) {
-            let (cur_opens_local_indices, cur_closes_local_indices) =
-                get_local_indices_of_ranged_element(ln, ranged_element_type);
-            partials_opens_map.insert(global_idx.clone(), cur_opens_local_indices);
-            partials_closes_map.insert(global_idx.clone(), cur_closes_local_indices);
-
-            let opens_locals_opt = partials_opens_map.get(&global_idx);
-            let closes_locals_opt = partials_closes_map.get(&global_idx);
+            let (opens, closes) = get_local_indices_of_ranged_element(ln, ranged_element_type);
             let partials_type_opt = get_local_partial_type_for_single_line(
                 ln,
                 ranged_element_type,
-                opens_locals_opt,
-                closes_locals_opt,
+                Some(&opens),
+                Some(&closes),
             );
 
-            match partials_type_opt {
-                Some(cur_type) => {
-                    partials_types_for_global_indices_map.insert(global_idx, cur_type);
-                }
-                None => {}
+            if let Some(cur_type) = partials_type_opt {
+                partials_types_for_global_indices_map.insert(global_idx, cur_type);
+                partials_markers_map.insert(global_idx, FNRangedElementMarkers { opens, closes });
             }
         }
     }
 
-    let mut sorted_partials_map_keys: Vec<usize> = partials_types_for_global_indices_map
-        .keys()
-        .copied()
-        .collect();
-    sorted_partials_map_keys.sort();
-
-    let mut fnline_map: HashMap<usize, FNLine> = HashMap::new();
+    let mut fnline_map: BTreeMap<usize, FNLine> = BTreeMap::new();
 
-    for (_, global_idx) in sorted_partials_map_keys.iter().enumerate() {
-        if let Some(cur_type) = partials_types_for_global_indices_map.get(global_idx) {
-            if let Some(ln) = lines.get(global_idx.clone()) {
-                let mut new_line = ln.clone();
-                match ranged_element_type {
-                    FNRangedElementType::Boneyard { open, close } => {
-                        new_line.boneyard_type = Some(cur_type.clone());
-                    }
-                    FNRangedElementType::Note { open, close } => {
-                        new_line.note_type = Some(cur_type.clone());
-                    }
-                    FNRangedElementType::Other { open, close } => {
-                        continue; // Change this part if newer ranged element types are added to fountain
-                    }
+    for (global_idx, cur_type) in partials_types_for_global_indices_map.iter() {
+        if let Some(ln) = lines.get(*global_idx) {
+            let mut new_line = ln.clone();
+            let markers = partials_markers_map.remove(global_idx);
+            match ranged_element_type {
+                FNRangedElementType::Boneyard { open: _, close: _ } => {
+                    new_line.boneyard_type = Some(cur_type.clone());
+                    new_line.boneyard_markers = markers;
+                }
+                FNRangedElementType::Note { open: _, close: _ } => {
+                    new_line.note_type = Some(cur_type.clone());
+                    new_line.note_markers = markers;
+                }
+                FNRangedElementType::Other { open: _, close: _ } => {
+                    continue; // Change this part if newer ranged element types are added to fountain
                 }
-                fnline_map.insert(global_idx.clone(), new_line);
             }
+            fnline_map.insert(*global_idx, new_line);
         }
     }
 
     Some(fnline_map)
 }
 
+/// Same as `get_partial_fnline_map_for_ranged_element_type`, after an edit, reusing the `previous_map` from before the edit.
+///
+/// `dirty_range` holds the indices of the edited lines in the new `lines`, and `line_delta` is how many lines
+/// the edit added (negative if it removed lines). Only the lines in the `dirty_range` are scanned again;
+/// the entries of every other line are moved to their new index.
+pub fn get_updated_partial_fnline_map_for_dirty_range(
+    previous_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
+    dirty_range: Range<usize>,
+    line_delta: isize,
+    ranged_element_type: &FNRangedElementType,
+) -> BTreeMap<usize, FNLine> {
+    let old_dirty_end = dirty_range.end as isize - line_delta;
+    let mut fnline_map: BTreeMap<usize, FNLine> = BTreeMap::new();
+
+    for (old_idx, previous_line) in previous_map {
+        let new_idx = if *old_idx < dirty_range.start {
+            *old_idx
+        } else if *old_idx as isize >= old_dirty_end {
+            (*old_idx as isize + line_delta) as usize
+        } else {
+            continue;
+        };
+        let partial_type = match ranged_element_type {
+            FNRangedElementType::Boneyard { open: _, close: _ } => &previous_line.boneyard_type,
+            FNRangedElementType::Note { open: _, close: _ } => &previous_line.note_type,
+            FNRangedElementType::Other { open: _, close: _ } => continue,
+        };
+        let Some(ln) = lines.get(new_idx) else {
+            continue;
+        };
+        if let Some(mut new_line) =
+            get_copy_of_fnline_with_new_partial_type(ln.clone(), partial_type, ranged_element_type)
+        {
+            let markers = get_ranged_element_markers(previous_line, ranged_element_type);
+            set_ranged_element_markers(&mut new_line, ranged_element_type, markers.into_owned());
+            fnline_map.insert(new_idx, new_line);
+        }
+    }
+
+    for global_idx in dirty_range {
+        let Some(ln) = lines.get(global_idx) else {
+            continue;
+        };
+        let (opens, closes) = get_local_indices_of_ranged_element(ln, ranged_element_type);
+        let partial_type = get_local_partial_type_for_single_line(
+            ln,
+            ranged_element_type,
+            Some(&opens),
+            Some(&closes),
+        );
+        if let Some(mut new_line) =
+            get_copy_of_fnline_with_new_partial_type(ln.clone(), &partial_type, ranged_element_type)
+        {
+            set_ranged_element_markers(
+                &mut new_line,
+                ranged_element_type,
+                FNRangedElementMarkers { opens, closes },
+            );
+            fnline_map.insert(global_idx, new_line);
+        }
+    }
+
+    fnline_map
+}
+
 pub fn get_copy_of_fnline_with_new_partial_type(
     mut line: FNLine,
     partial_type_opt: &Option<FNPartialLineType>,
@@ -110,8 +176,42 @@ pub fn get_copy_of_fnline_with_new_partial_type(
     Some(line)
 }
 
+/// Returns the opens and closes of the `ranged_element_type` in the line: the ones stored on it by
+/// `get_partial_fnline_map_for_ranged_element_type`, or else the ones found in its `raw_string`.
+pub fn get_ranged_element_markers<'a>(
+    line: &'a FNLine,
+    ranged_element_type: &FNRangedElementType,
+) -> Cow<'a, FNRangedElementMarkers> {
+    let stored = match ranged_element_type {
+        FNRangedElementType::Boneyard { open: _, close: _ } => &line.boneyard_markers,
+        FNRangedElementType::Note { open: _, close: _ } => &line.note_markers,
+        FNRangedElementType::Other { open: _, close: _ } => &None,
+    };
+    match stored {
+        Some(markers) => Cow::Borrowed(markers),
+        None => {
+            let (opens, closes) = get_local_indices_of_ranged_element(line, ranged_element_type);
+            Cow::Owned(FNRangedElementMarkers { opens, closes })
+        }
+    }
+}
+
+fn set_ranged_element_markers(
+    line: &mut FNLine,
+    ranged_element_type: &FNRangedElementType,
+    markers: FNRangedElementMarkers,
+) {
+    match ranged_element_type {
+        FNRangedElementType::Boneyard { open: _, close: _ } => {
+            line.boneyard_markers = Some(markers)
+        }
+        FNRangedElementType::Note { open: _, close: _ } => line.note_markers = Some(markers),
+        FNRangedElementType::Other { open: _, close: _ } => {}
+    }
+}
+
 fn get_global_indices_of_ranged_element(
-    lines: &Vec<FNLine>,
+    lines: &[FNLine],
     ranged_element_type: &FNRangedElementType,
 ) -> Vec<usize> {
     let (opens_pattern, closes_pattern) = ranged_element_type.get_open_and_close_patterns();
@@ -119,27 +219,27 @@ fn get_global_indices_of_ranged_element(
 
     for (idx, ln) in lines.iter().enumerate() {
         if ln.raw_string.contains(&opens_pattern) || ln.raw_string.contains(&closes_pattern) {
-            global_indices_vec.push(idx.clone());
+            global_indices_vec.push(idx);
         }
     }
     global_indices_vec
 }
 
-/// Returns a HashMap of Global and Local indices across a `Vector` of `FNLine` for "Opens" and "Closes" patterns for an `FNRangedElementType`.
-///```
-/// "Opens": Hashmap<global_index, local_index_set>>
-/// "Closes": Hashmap<global_index, local_index_set>>
+/// Returns a BTreeMap of Global and Local indices across a `Vector` of `FNLine` for "Opens" and "Closes" patterns for an `FNRangedElementType`.
+///```text
+/// "Opens": BTreeMap<global_index, local_index_set>>
+/// "Closes": BTreeMap<global_index, local_index_set>>
 ///```
 /// NOTE: Opens and Closes have to be MATCHED and VALIDATED:
 /// - Some opens or closes may be orphaned because they don't have a match
 /// - Some opens or closes may not make a valid pair because there is an `empty line` between them
 /// - An empty line in the context of Notes and Boneyards means a line with zero text OR if only whitespace, less than two spaces
 pub fn get_global_and_local_indices_of_ranged_element(
-    lines: &Vec<FNLine>,
+    lines: &[FNLine],
     ranged_element_type: &FNRangedElementType,
-) -> Option<HashMap<String, HashMap<usize, Vec<usize>>>> {
-    let mut indices_opens_map: HashMap<usize, Vec<usize>> = HashMap::new();
-    let mut indices_closes_map: HashMap<usize, Vec<usize>> = HashMap::new();
+) -> Option<BTreeMap<String, BTreeMap<usize, Vec<usize>>>> {
+    let mut indices_opens_map: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut indices_closes_map: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
 
     let (opens_pattern, closes_pattern) = ranged_element_type.get_open_and_close_patterns();
     //this for loop only puts the global indexes in with blank Vecs
@@ -157,76 +257,216 @@ pub fn get_global_and_local_indices_of_ranged_element(
     for (global_idx, ln) in lines.iter().enumerate() {
         let (open_matches, close_matches) =
             get_local_indices_of_ranged_element(ln, ranged_element_type);
-        for (local_idx, s) in open_matches.iter().enumerate() {
+        for (local_idx, _s) in open_matches.iter().enumerate() {
             if let Some(opens_locals_vec) = indices_opens_map.get_mut(&global_idx) {
                 opens_locals_vec.push(local_idx);
             }
         }
-        for (local_idx, s) in close_matches.iter().enumerate() {
+        for (local_idx, _s) in close_matches.iter().enumerate() {
             if let Some(closes_locals_vec) = indices_closes_map.get_mut(&global_idx) {
                 closes_locals_vec.push(local_idx);
             }
         }
     }
 
-    let mut indicies_map: HashMap<String, HashMap<usize, Vec<usize>>> = HashMap::new();
+    let mut indicies_map: BTreeMap<String, BTreeMap<usize, Vec<usize>>> = BTreeMap::new();
     indicies_map.insert("Opens".to_string(), indices_opens_map);
     indicies_map.insert("Closes".to_string(), indices_closes_map);
 
     Some(indicies_map)
 }
 
-//TODO:
 // getting ranges of partial lines is not actually defined behavior in Fountain syntax.
-// There are two possible strategies I see:
-// 1. Easy mode -- only pair ORPHANED OPENS to ORPHANED CLOSES, and ONLY IF there are ZERO standalone
+// There are two possible strategies, selected with `FNRangedElementResolutionStrategy`:
+// 1. Easy mode (`NextClose`) -- only pair ORPHANED OPENS to ORPHANED CLOSES, and ONLY IF there are ZERO standalone
 // partials between them.
-// 2. - Tedious mode -- pair orphaned opens to the LAST VALID close. This means capturing any line in between as an
+// 2. - Tedious mode (`LastClose`) -- pair orphaned opens to the LAST VALID close. This means capturing any line in between as an
 // InvisibleOnly.
 
 /// Returns a Vector of FNPartialMultilineRange objects. These objects are used to handle
 /// the differences between the "raw" document and the visible lines at a high level.
 /// Each FNPartialMultilineRange object has global and local indices for the start and end of multiline invisibles.
 /// This implimentation ensures there are ZERO SelfContained or InvisibleOnly lines between an Orphaned Open and an Orphaned Close.
-/// After receiving these ranges, you must iterate through the lines between and mark each line as InvisibleOnly.
+/// After receiving these ranges, pass them to `get_partial_fnline_map_with_multiline_ranges` to mark each line in between as InvisibleOnly.
 /// In other words -- FNPartialMultilineRange objects can ONLY exist if there aren't any other opens or closes between the two.
 /// Otherwise, it isn't a valid FNPartialMultilineRange.
 /// This is done for simplicity and because I will throw my brain into a trash compactor if I don't.
 pub fn get_partial_multiline_ranges_from_partial_map(
-    partials_map: &HashMap<usize, FNLine>,
-    lines: &Vec<FNLine>,
+    partials_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
     ranged_element_type: &FNRangedElementType,
 ) -> Vec<FNPartialMultilineRange> {
-    let mut sorted_partials_keys: Vec<usize> = partials_map.keys().copied().collect();
-    sorted_partials_keys.sort();
+    get_partial_multiline_ranges_and_diagnostics_from_partial_map(
+        partials_map,
+        lines,
+        ranged_element_type,
+    )
+    .0
+}
+
+/// Same as `get_partial_multiline_ranges_from_partial_map`, but also returns a diagnostic for every open which is never closed.
+///
+/// Notes can't contain an empty line, so for Notes, an open followed by an empty line (less than two spaces)
+/// before its close is dropped, and reported as an `UnclosedOpen`.
+pub fn get_partial_multiline_ranges_and_diagnostics_from_partial_map(
+    partials_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
+    ranged_element_type: &FNRangedElementType,
+) -> (Vec<FNPartialMultilineRange>, Vec<FNRangedElementDiagnostic>) {
+    get_partial_multiline_ranges_and_diagnostics_with_strategy(
+        partials_map,
+        lines,
+        ranged_element_type,
+        FNRangedElementResolutionStrategy::default(),
+    )
+}
+
+/// Same as `get_partial_multiline_ranges_and_diagnostics_from_partial_map`, with the given strategy for pairing
+/// orphaned opens and closes.
+pub fn get_partial_multiline_ranges_and_diagnostics_with_strategy(
+    partials_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
+    ranged_element_type: &FNRangedElementType,
+    strategy: FNRangedElementResolutionStrategy,
+) -> (Vec<FNPartialMultilineRange>, Vec<FNRangedElementDiagnostic>) {
+    let sorted_partials_keys: Vec<usize> = partials_map.keys().copied().collect();
+
+    resolve_partial_multiline_ranges(
+        partials_map,
+        &sorted_partials_keys,
+        lines,
+        ranged_element_type,
+        strategy,
+    )
+}
+
+/// Same as `get_partial_multiline_ranges_and_diagnostics_from_partial_map`, after an edit, reusing the
+/// `previous_ranges` and `previous_diagnostics` from before the edit.
+///
+/// `dirty_range` holds the indices of the edited lines in the new `lines`. The `partials_map` must already be
+/// up to date, as returned by `get_updated_partial_fnline_map_for_dirty_range`.
+///
+/// Ranges which end before the edit are kept as they are. Only the partial lines from the start of the
+/// first range touching the edit (or of an earlier unclosed open, which the edit might close) are resolved again.
+/// With the `LastClose` strategy, a close added by the edit can extend any earlier range, so every range is resolved again.
+pub fn get_updated_partial_multiline_ranges_for_dirty_range(
+    previous_ranges: &[FNPartialMultilineRange],
+    previous_diagnostics: &[FNRangedElementDiagnostic],
+    partials_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
+    dirty_range: Range<usize>,
+    ranged_element_type: &FNRangedElementType,
+    strategy: FNRangedElementResolutionStrategy,
+) -> (Vec<FNPartialMultilineRange>, Vec<FNRangedElementDiagnostic>) {
+    let mut resume_start = dirty_range.start;
+    for range in previous_ranges {
+        if let (Some(start), Some(end)) = (range.global_start, range.global_end) {
+            if end >= dirty_range.start || strategy == FNRangedElementResolutionStrategy::LastClose
+            {
+                resume_start = resume_start.min(start);
+            }
+        }
+    }
+    for diagnostic in previous_diagnostics {
+        if diagnostic.ranged_element_type == *ranged_element_type
+            && diagnostic.kind == FNRangedElementDiagnosticKind::UnclosedOpen
+        {
+            resume_start = resume_start.min(diagnostic.global_index);
+        }
+    }
+
+    let mut ranges: Vec<FNPartialMultilineRange> = previous_ranges
+        .iter()
+        .filter(|range| range.global_end.is_some_and(|end| end <= resume_start))
+        .cloned()
+        .collect();
+    let mut diagnostics: Vec<FNRangedElementDiagnostic> = previous_diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.ranged_element_type != *ranged_element_type
+                || diagnostic.global_index < resume_start
+        })
+        .cloned()
+        .collect();
+
+    let sorted_partials_keys: Vec<usize> = partials_map
+        .range(resume_start..)
+        .map(|(idx, _)| *idx)
+        .collect();
+
+    let (new_ranges, new_diagnostics) = resolve_partial_multiline_ranges(
+        partials_map,
+        &sorted_partials_keys,
+        lines,
+        ranged_element_type,
+        strategy,
+    );
+    ranges.extend(new_ranges);
+    diagnostics.extend(new_diagnostics);
+    (ranges, diagnostics)
+}
+
+fn resolve_partial_multiline_ranges(
+    partials_map: &BTreeMap<usize, FNLine>,
+    sorted_partials_keys: &[usize],
+    lines: &[FNLine],
+    ranged_element_type: &FNRangedElementType,
+    strategy: FNRangedElementResolutionStrategy,
+) -> (Vec<FNPartialMultilineRange>, Vec<FNRangedElementDiagnostic>) {
+    if strategy == FNRangedElementResolutionStrategy::LastClose {
+        return resolve_partial_multiline_ranges_to_last_close(
+            partials_map,
+            sorted_partials_keys,
+            lines,
+            ranged_element_type,
+        );
+    }
+    let mut diagnostics: Vec<FNRangedElementDiagnostic> = Vec::new();
+    let terminates_at_empty_lines = matches!(
+        ranged_element_type,
+        FNRangedElementType::Note { open: _, close: _ }
+    );
+    let empty_line_counts = get_empty_line_counts(lines);
 
     let mut last_unresolved_open_idx: Option<usize> = None;
     let mut last_unresolved_open_local_idx: Option<usize> = None;
 
     let mut partial_line_ranges_vec: Vec<FNPartialMultilineRange> = Vec::new();
 
-    let (_, closes_pat) = ranged_element_type.get_open_and_close_patterns();
-
     for global_idx in sorted_partials_keys.iter() {
         if let Some(ln) = partials_map.get(global_idx) {
             let partial_type = match ranged_element_type {
-                FNRangedElementType::Boneyard { open, close } => &ln.boneyard_type,
-                FNRangedElementType::Note { open, close } => &ln.note_type,
-                FNRangedElementType::Other { open, close } => &None,
+                FNRangedElementType::Boneyard { open: _, close: _ } => &ln.boneyard_type,
+                FNRangedElementType::Note { open: _, close: _ } => &ln.note_type,
+                FNRangedElementType::Other { open: _, close: _ } => &None,
             };
+            if let Some(open_idx) = last_unresolved_open_idx {
+                let is_terminated = terminates_at_empty_lines
+                    && has_empty_line_between(&empty_line_counts, open_idx + 1, *global_idx);
+                if is_terminated {
+                    diagnostics.push(FNRangedElementDiagnostic {
+                        kind: FNRangedElementDiagnosticKind::UnclosedOpen,
+                        ranged_element_type: ranged_element_type.clone(),
+                        global_index: open_idx,
+                        local_index: last_unresolved_open_local_idx.unwrap_or_default(),
+                    });
+                    last_unresolved_open_idx = None;
+                    last_unresolved_open_local_idx = None;
+                }
+            }
             if let Some(_last_unresolved_open) = last_unresolved_open_idx {
                 match partial_type {
                     Some(FNPartialLineType::OrphanedClose)
                     | Some(FNPartialLineType::OrphanedOpenAndClose) => {
                         let new_multiline_partial_range = FNPartialMultilineRange {
                             id: None,
-                            global_start: last_unresolved_open_idx.clone(),
-                            local_start: last_unresolved_open_local_idx.clone(),
-                            global_end: Some(global_idx.clone()),
-                            local_end: get_first_match_in_string(
-                                closes_pat.clone(),
-                                ln.raw_string.clone(),
-                            ),
+                            global_start: last_unresolved_open_idx,
+                            local_start: last_unresolved_open_local_idx,
+                            global_end: Some(*global_idx),
+                            local_end: get_ranged_element_markers(ln, ranged_element_type)
+                                .closes
+                                .first()
+                                .copied(),
                         };
                         partial_line_ranges_vec.push(new_multiline_partial_range);
                         match partial_type {
@@ -235,11 +475,14 @@ pub fn get_partial_multiline_ranges_from_partial_map(
                                 last_unresolved_open_local_idx = None;
                             }
                             Some(FNPartialLineType::OrphanedOpenAndClose) => {
-                                last_unresolved_open_idx = Some(global_idx.clone());
-                                let (open_locals, _) =
-                                    get_local_indices_of_ranged_element(ln, ranged_element_type);
+                                // A line whose partial type doesn't match its text has no open to resolve
                                 last_unresolved_open_local_idx =
-                                    Some(open_locals.last().unwrap().clone())
+                                    get_ranged_element_markers(ln, ranged_element_type)
+                                        .opens
+                                        .last()
+                                        .copied();
+                                last_unresolved_open_idx =
+                                    last_unresolved_open_local_idx.map(|_| *global_idx);
                             }
                             _ => {}
                         }
@@ -253,34 +496,351 @@ pub fn get_partial_multiline_ranges_from_partial_map(
             match partial_type {
                 Some(FNPartialLineType::OrphanedOpen)
                 | Some(FNPartialLineType::OrphanedOpenAndClose) => {
-                    last_unresolved_open_idx = Some(global_idx.clone());
-                    //TODO store the orphaned open/close indices in the FNLine instead of recalculating them smh
-                    let (open_locals, _) =
-                        get_local_indices_of_ranged_element(ln, ranged_element_type);
-                    last_unresolved_open_local_idx = Some(open_locals.last().unwrap().clone())
+                    last_unresolved_open_local_idx =
+                        get_ranged_element_markers(ln, ranged_element_type)
+                            .opens
+                            .last()
+                            .copied();
+                    last_unresolved_open_idx = last_unresolved_open_local_idx.map(|_| *global_idx);
                 }
                 _ => {}
             }
         }
     }
 
-    partial_line_ranges_vec
+    if let Some(open_idx) = last_unresolved_open_idx {
+        diagnostics.push(FNRangedElementDiagnostic {
+            kind: FNRangedElementDiagnosticKind::UnclosedOpen,
+            ranged_element_type: ranged_element_type.clone(),
+            global_index: open_idx,
+            local_index: last_unresolved_open_local_idx.unwrap_or_default(),
+        });
+    }
+
+    (partial_line_ranges_vec, diagnostics)
+}
+
+/// Returns a copy of the `partials_map` where every line strictly between the start and end of a multiline range
+/// is `InvisibleOnly`, whatever it was before. Lines between which weren't in the map are added to it.
+pub fn get_partial_fnline_map_with_multiline_ranges(
+    partials_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
+    multiline_ranges: &[FNPartialMultilineRange],
+    ranged_element_type: &FNRangedElementType,
+) -> BTreeMap<usize, FNLine> {
+    let mut fnline_map = partials_map.clone();
+    for range in multiline_ranges {
+        let (Some(start), Some(end)) = (range.global_start, range.global_end) else {
+            continue;
+        };
+        for global_idx in start + 1..end {
+            let Some(ln) = fnline_map.get(&global_idx).or(lines.get(global_idx)) else {
+                continue;
+            };
+            if let Some(new_line) = get_copy_of_fnline_with_new_partial_type(
+                ln.clone(),
+                &Some(FNPartialLineType::InvisibleOnly),
+                ranged_element_type,
+            ) {
+                fnline_map.insert(global_idx, new_line);
+            }
+        }
+    }
+    fnline_map
+}
+
+/// Returns one merged line for each group of lines spanned by the multiline ranges, in document order.
+/// Ranges of notes and boneyards can be passed together: ranges which share a line are merged into one line.
+///
+/// The visible text of each line is found the same way as `get_visible_byte_ranges_for_lines`, so a note inside a
+/// boneyard is hidden too. The merged text is parsed with the visible text of the lines around it, up to the
+/// empty lines before and after it.
+pub fn get_merged_partial_lines(
+    lines: &[FNLine],
+    multiline_ranges: &[FNPartialMultilineRange],
+) -> Vec<FNMergedPartialLine> {
+    let mut spans: Vec<Range<usize>> = multiline_ranges
+        .iter()
+        .filter_map(|range| Some(range.global_start?..range.global_end? + 1))
+        .filter(|span| span.end <= lines.len())
+        .collect();
+    spans.sort_by_key(|span| span.start);
+    let mut merged_spans: Vec<Range<usize>> = Vec::new();
+    for span in spans {
+        match merged_spans.last_mut() {
+            Some(last) if span.start < last.end => last.end = last.end.max(span.end),
+            _ => merged_spans.push(span),
+        }
+    }
+
+    let visible_ranges = get_visible_byte_ranges_for_lines(lines);
+    let visible_texts: Vec<String> = lines
+        .iter()
+        .zip(&visible_ranges)
+        .map(|(ln, ranges)| {
+            ranges
+                .iter()
+                .map(|(start, end)| &ln.raw_string[*start..*end])
+                .collect()
+        })
+        .collect();
+
+    merged_spans
+        .into_iter()
+        .map(|global_range| {
+            let mut text = String::new();
+            let mut segments: Vec<FNMergedLineSegment> = Vec::new();
+            for global_index in global_range.clone() {
+                for (start, end) in &visible_ranges[global_index] {
+                    if start == end {
+                        continue;
+                    }
+                    segments.push(FNMergedLineSegment {
+                        merged_range: text.len()..text.len() + end - start,
+                        global_index,
+                        raw_range: *start..*end,
+                    });
+                    text.push_str(&lines[global_index].raw_string[*start..*end]);
+                }
+            }
+            FNMergedPartialLine {
+                visible_fnline: get_parsed_merged_line(
+                    lines,
+                    &visible_texts,
+                    global_range.clone(),
+                    text,
+                ),
+                global_range,
+                segments,
+            }
+        })
+        .collect()
+}
+
+/// Returns a copy of the lines with `note_ranges` and `omitted_ranges` filled in with the byte ranges of each line's
+/// `raw_string` which are inside a note or a boneyard, markers included.
+///
+/// This uses the same policy as `get_visible_byte_ranges_for_lines`, so a note inside a boneyard is only in `omitted_ranges`.
+pub fn get_lines_with_invisible_ranges(lines: &[FNLine]) -> Vec<FNLine> {
+    get_lines_with_invisible_ranges_with_note_types(lines, &[FNRangedElementType::note()])
+}
+
+/// Same as `get_lines_with_invisible_ranges`, but any of the `note_types` opens a note.
+/// See `get_invisible_byte_ranges_for_lines_with_note_types`.
+pub fn get_lines_with_invisible_ranges_with_note_types(
+    lines: &[FNLine],
+    note_types: &[FNRangedElementType],
+) -> Vec<FNLine> {
+    get_invisible_byte_ranges_for_lines_with_note_types(lines, note_types)
+        .iter()
+        .zip(lines)
+        .map(|(invisible, ln)| {
+            let mut new_line = ln.clone();
+            new_line.note_ranges = invisible.notes.clone();
+            new_line.omitted_ranges = invisible.boneyards.clone();
+            new_line
+        })
+        .collect()
+}
+
+/// Same as `get_lines_with_invisible_ranges_with_note_types`, but the text spanned by each of the `resolved_ranges`,
+/// from its open to the end of its close, is hidden too, so that the ranges follow the strategy the multiline
+/// ranges were resolved with (such as `LastClose`) rather than only the scanner's own pairing.
+///
+/// `resolved_ranges` are the multiline ranges of each ranged element type. What they hide beyond the scanner is kept in
+/// the lines' `additional_note_ranges` and `additional_omitted_ranges`, so that `get_invisible_byte_ranges_for_lines`
+/// finds the same ranges later. A note which opens inside a boneyard, or a boneyard which opens inside a note, is left out.
+pub fn get_lines_with_invisible_ranges_for_resolved_ranges(
+    lines: &[FNLine],
+    note_types: &[FNRangedElementType],
+    resolved_ranges: &[(FNRangedElementType, Vec<FNPartialMultilineRange>)],
+) -> Vec<FNLine> {
+    let scanned = get_invisible_byte_ranges_for_lines_with_note_types(lines, note_types);
+    let mut resolved_notes: Vec<RangeSet> = vec![RangeSet::default(); lines.len()];
+    let mut resolved_boneyards: Vec<RangeSet> = vec![RangeSet::default(); lines.len()];
+
+    for (ranged_element_type, multiline_ranges) in resolved_ranges {
+        let is_boneyard = matches!(ranged_element_type, FNRangedElementType::Boneyard { .. });
+        // Whether `get_invisible_byte_ranges_for_lines` finds this type without the ranges of the lines
+        let is_scanned_type = is_boneyard || *ranged_element_type == FNRangedElementType::note();
+        let close_len = ranged_element_type.get_open_and_close_patterns().1.len();
+        for range in multiline_ranges {
+            let (Some(start), Some(local_start), Some(end), Some(local_end)) = (
+                range.global_start,
+                range.local_start,
+                range.global_end,
+                range.local_end,
+            ) else {
+                continue;
+            };
+            if end >= lines.len() || start > end {
+                continue;
+            }
+            let other_kind = if is_boneyard {
+                &scanned[start].notes
+            } else {
+                &scanned[start].boneyards
+            };
+            if other_kind.contains(local_start) {
+                continue;
+            }
+            for global_index in start..=end {
+                let line_len = lines[global_index].raw_string.len();
+                let line_start = if global_index == start {
+                    local_start
+                } else {
+                    0
+                };
+                // A range which goes on to the next line reaches one byte past the end, unless the scanner
+                // already knows it does
+                let is_known_open = is_scanned_type && scanned[global_index].is_open_at_end;
+                let line_end = if global_index == end {
+                    (local_end + close_len).min(line_len)
+                } else {
+                    line_len + usize::from(!is_known_open)
+                };
+                if line_start < line_end {
+                    let hidden = if is_boneyard {
+                        &mut resolved_boneyards[global_index]
+                    } else {
+                        &mut resolved_notes[global_index]
+                    };
+                    hidden.insert(line_start..line_end);
+                }
+            }
+        }
+    }
+
+    let lines_with_resolved: Vec<FNLine> = lines
+        .iter()
+        .enumerate()
+        .map(|(idx, ln)| {
+            let mut new_line = ln.clone();
+            new_line.additional_omitted_ranges =
+                resolved_boneyards[idx].difference(&scanned[idx].boneyards);
+            new_line.additional_note_ranges = resolved_notes[idx]
+                .difference(&scanned[idx].notes)
+                .difference(&new_line.additional_omitted_ranges);
+            new_line
+        })
+        .collect();
+    // Scanned again, so that a marker inside a resolved range doesn't open anything
+    get_lines_with_invisible_ranges_with_note_types(&lines_with_resolved, note_types)
 }
 
-fn get_first_match_in_string(opens_pattern: String, line_string: String) -> Option<(usize)> {
-    let mut indices = line_string.match_indices(&opens_pattern);
-    if let Some((idx, _)) = indices.next() {
-        return Some(idx);
+// Tedious mode: every orphaned open pairs with the last orphaned close it can reach
+fn resolve_partial_multiline_ranges_to_last_close(
+    partials_map: &BTreeMap<usize, FNLine>,
+    sorted_partials_keys: &[usize],
+    lines: &[FNLine],
+    ranged_element_type: &FNRangedElementType,
+) -> (Vec<FNPartialMultilineRange>, Vec<FNRangedElementDiagnostic>) {
+    let mut diagnostics: Vec<FNRangedElementDiagnostic> = Vec::new();
+    let mut partial_line_ranges_vec: Vec<FNPartialMultilineRange> = Vec::new();
+    let terminates_at_empty_lines = matches!(
+        ranged_element_type,
+        FNRangedElementType::Note { open: _, close: _ }
+    );
+    let empty_line_counts = get_empty_line_counts(lines);
+
+    let partial_types: Vec<Option<FNPartialLineType>> = sorted_partials_keys
+        .iter()
+        .map(|global_idx| {
+            let ln = partials_map.get(global_idx)?;
+            match ranged_element_type {
+                FNRangedElementType::Boneyard { open: _, close: _ } => ln.boneyard_type.clone(),
+                FNRangedElementType::Note { open: _, close: _ } => ln.note_type.clone(),
+                FNRangedElementType::Other { open: _, close: _ } => None,
+            }
+        })
+        .collect();
+
+    let mut key_idx: usize = 0;
+    while key_idx < sorted_partials_keys.len() {
+        let is_open = matches!(
+            partial_types[key_idx],
+            Some(FNPartialLineType::OrphanedOpen) | Some(FNPartialLineType::OrphanedOpenAndClose)
+        );
+        if !is_open {
+            key_idx += 1;
+            continue;
+        }
+        let open_idx = sorted_partials_keys[key_idx];
+        let open_local_idx =
+            get_ranged_element_markers(&partials_map[&open_idx], ranged_element_type)
+                .opens
+                .last()
+                .copied()
+                .unwrap_or_default();
+
+        // The first partial line which the open can't reach
+        let reach_end = (key_idx + 1..sorted_partials_keys.len())
+            .find(|later| {
+                terminates_at_empty_lines
+                    && has_empty_line_between(
+                        &empty_line_counts,
+                        open_idx + 1,
+                        sorted_partials_keys[*later],
+                    )
+            })
+            .unwrap_or(sorted_partials_keys.len());
+        let last_close = (key_idx + 1..reach_end).rev().find(|later| {
+            matches!(
+                partial_types[*later],
+                Some(FNPartialLineType::OrphanedClose)
+                    | Some(FNPartialLineType::OrphanedOpenAndClose)
+            )
+        });
+
+        let Some(close_key_idx) = last_close else {
+            diagnostics.push(FNRangedElementDiagnostic {
+                kind: FNRangedElementDiagnosticKind::UnclosedOpen,
+                ranged_element_type: ranged_element_type.clone(),
+                global_index: open_idx,
+                local_index: open_local_idx,
+            });
+            key_idx = reach_end;
+            continue;
+        };
+        let close_idx = sorted_partials_keys[close_key_idx];
+        partial_line_ranges_vec.push(FNPartialMultilineRange {
+            id: None,
+            global_start: Some(open_idx),
+            local_start: Some(open_local_idx),
+            global_end: Some(close_idx),
+            local_end: get_ranged_element_markers(&partials_map[&close_idx], ranged_element_type)
+                .closes
+                .first()
+                .copied(),
+        });
+        // The open at the end of an `OrphanedOpenAndClose` line starts looking for its own close
+        key_idx = match partial_types[close_key_idx] {
+            Some(FNPartialLineType::OrphanedOpenAndClose) => close_key_idx,
+            _ => close_key_idx + 1,
+        };
     }
-    None
+
+    (partial_line_ranges_vec, diagnostics)
 }
 
-fn get_last_valid_close_in_string(closes_pattern: String, line_string: String) -> Option<(usize)> {
-    let indices = line_string.match_indices(&closes_pattern);
-    if let Some((idx, _)) = indices.last() {
-        return Some(idx);
+// The number of empty lines before each line index, so that checking for an empty line between two lines doesn't scan them
+fn get_empty_line_counts(lines: &[FNLine]) -> Vec<usize> {
+    let mut counts = Vec::with_capacity(lines.len() + 1);
+    let mut count: usize = 0;
+    counts.push(count);
+    for ln in lines {
+        if is_empty_line_for_ranged_elements(&ln.raw_string) {
+            count += 1;
+        }
+        counts.push(count);
     }
-    None
+    counts
+}
+
+// Whether any line in `start..end` is empty
+fn has_empty_line_between(empty_line_counts: &[usize], start: usize, end: usize) -> bool {
+    let end = end.min(empty_line_counts.len() - 1);
+    start < end && empty_line_counts[end] > empty_line_counts[start]
 }
 
 pub fn get_local_indices_of_ranged_element(
@@ -310,6 +870,8 @@ pub fn get_local_indices_of_ranged_element(
 ///
 /// If there are no opens or closes, or if there is no non-invisble text, this returns `None`.
 ///
+/// The local indices of the opens and closes may be `None`, in which case they are taken from the line's markers
+/// (see `get_ranged_element_markers`).
 pub fn get_local_partial_type_for_single_line(
     line: &FNLine,
     ranged_element_type: &FNRangedElementType,
@@ -332,8 +894,14 @@ pub fn get_local_partial_type_for_single_line(
     }
     // If program gets here, the string must contain both opens and closes
 
-    let opens_local_indices: &Vec<usize> = opens_locals_opt.unwrap();
-    let closes_local_indices: &Vec<usize> = closes_locals_opt.unwrap();
+    // The local indices are calculated here if the caller didn't pass them
+    let (opens_local_indices, closes_local_indices) = match (opens_locals_opt, closes_locals_opt) {
+        (Some(opens), Some(closes)) => (opens.clone(), closes.clone()),
+        _ => {
+            let markers = get_ranged_element_markers(line, ranged_element_type).into_owned();
+            (markers.opens, markers.closes)
+        }
+    };
 
     // Handling DANGLING / ORPHANED opens or closes
     let has_orphaned_opens = opens_local_indices.last() > closes_local_indices.last();
@@ -369,8 +937,8 @@ pub fn get_local_partial_type_for_single_line(
     // find if there is text in the middle
     // look for case where an open is after a close: ]][[
     // the distance between the open and close should be >= 1: ]] [[
-    for (_opn_meta_index, open_local_idx) in opens_local_indices.iter().enumerate() {
-        for (_cls_meta_index, cls_local_idx) in closes_local_indices.iter().enumerate() {
+    for open_local_idx in opens_local_indices.iter() {
+        for cls_local_idx in closes_local_indices.iter() {
             if open_local_idx > cls_local_idx {
                 // This is the only close local index before the current open local index
                 if open_local_idx - cls_local_idx > 0 {
@@ -383,25 +951,33 @@ pub fn get_local_partial_type_for_single_line(
     Some(FNPartialLineType::InvisibleOnly)
 }
 
-/// Returns an String with the given FNRangedElementType text removed
-/// recursive function; calls itself until there are no more opens or closes patterns
+/// Returns the string with its notes and boneyards removed, including those which span several of its lines.
+///
+/// The string is split into lines and scanned with the same policy as `get_printable_strings_for_lines`.
+/// A line break inside a removed note or boneyard is removed along with it, and every other line break is kept as written.
 pub fn delete_ranged_text_with_recursion(string: String) -> String {
-    todo!()
-    //      get first open in current string:
-    //      if there are ZERO opens patterns in this string, then return the String
-    //      if there are ZERO closes patterns in this string, then return the String
-    //      else: iterate through closes until:
-    //          if next close exists && next open exists:
-    //              if next close index > next open index:
-    //                  delete the text from current string, starting from the first until this exact close
-    //                  pass new string to new iteration of this function (do a recursion)
-    //                      return the output from the above call
-    //          if next close exists && !next open exists:
-    //                    continue;
-    //          else:
-    //              delete text from current string from open until this exact close
-    //              pass new string to new iteration of this function (do a recursion)
-    //                      return the output from the above call
+    let lines = static_fountain_preparser::get_unparsed_lines_from_raw_string(
+        &string,
+        &FNPreparseOptions::default(),
+    );
+    let invisible = get_invisible_byte_ranges_for_lines(&lines);
+    let mut text = String::with_capacity(string.len());
+
+    for (ln, invisible) in lines.iter().zip(&invisible) {
+        let hidden = invisible.notes.union(&invisible.boneyards);
+        for range in hidden.get_complement(0..ln.raw_string.len()).iter() {
+            text.push_str(&ln.raw_string[range.clone()]);
+        }
+        if invisible.is_open_at_end {
+            continue;
+        }
+        text.push_str(match ln.terminator_length {
+            2 => "\r\n",
+            1 => "\n",
+            _ => "",
+        });
+    }
+    text
 }
 
 // There are two types of ranged elements to handle:
@@ -413,10 +989,97 @@ pub fn delete_ranged_text_with_recursion(string: String) -> String {
 // But what if this is actually the middle of a multi-line note?
 
 /// Only creates FNPartialLineRange objects, NOT FNPartialMultilineRange objects.
-/// These should be handled AFTER creating the FNPartialMultilineRange objects.
-/// Example: If a SelfContained line is between the opening and closing line of a
-/// FNPartialMultilineRange, then that SelfContained should become an InvisibleOnly line, even if it would
-/// otherwise have valid printable text.
-pub fn create_single_line_partial_line_ranges() {
-    todo!()
+/// Returns one `FNPartialLineRange` for each `SelfContained` or `Orphaned*` line in the `partials_map`, sorted by `global_index`.
+///
+/// `local_start` and `local_end` span the invisible text in the line, and `visible_fnline` is a copy of the line whose `string`
+/// has every note and boneyard removed. This looks at the whole document, so if a SelfContained line is between the opening
+/// and closing line of a multiline invisible, its visible string is empty, even if it would otherwise have printable text.
+pub fn create_single_line_partial_line_ranges(
+    partials_map: &BTreeMap<usize, FNLine>,
+    lines: &[FNLine],
+) -> Vec<FNPartialLineRange> {
+    let visible_ranges = get_visible_byte_ranges_for_lines(lines);
+
+    let mut partial_line_ranges_vec: Vec<FNPartialLineRange> = Vec::new();
+
+    for global_idx in partials_map.keys().copied() {
+        let (Some(partial_line), Some(ranges)) = (
+            partials_map.get(&global_idx),
+            visible_ranges.get(global_idx),
+        ) else {
+            continue;
+        };
+        let is_partial = [&partial_line.note_type, &partial_line.boneyard_type]
+            .iter()
+            .any(|partial_type| {
+                matches!(
+                    partial_type,
+                    Some(FNPartialLineType::SelfContained)
+                        | Some(FNPartialLineType::OrphanedOpen)
+                        | Some(FNPartialLineType::OrphanedClose)
+                        | Some(FNPartialLineType::OrphanedOpenAndClose)
+                )
+            });
+        if !is_partial {
+            continue;
+        }
+
+        let raw = partial_line.raw_string.as_str();
+        let first_hidden = match ranges.first() {
+            Some((0, end)) => *end,
+            _ => 0,
+        };
+        let last_hidden_end = match ranges.last() {
+            Some((start, end)) if *end == raw.len() => *start,
+            _ => raw.len(),
+        };
+
+        let mut visible_fnline = partial_line.clone();
+        visible_fnline.string = ranges
+            .iter()
+            .map(|(start, end)| &raw[*start..*end])
+            .collect();
+
+        partial_line_ranges_vec.push(FNPartialLineRange {
+            id: None,
+            global_index: Some(global_idx),
+            local_start: Some(first_hidden),
+            local_end: Some(last_hidden_end.max(first_hidden)),
+            visible_fnline: Some(visible_fnline),
+        });
+    }
+
+    partial_line_ranges_vec
+}
+
+// The merged text parsed between the visible text of the lines around it, up to the empty lines around it
+fn get_parsed_merged_line(
+    lines: &[FNLine],
+    visible_texts: &[String],
+    global_range: Range<usize>,
+    text: String,
+) -> FNLine {
+    let is_empty = |idx: &usize| visible_texts[*idx].is_empty();
+    let window_start = (0..global_range.start).rev().find(is_empty).unwrap_or(0);
+    let window_end = (global_range.end..lines.len())
+        .find(is_empty)
+        .map_or(lines.len(), |idx| idx + 1);
+    let get_unparsed = |text: &str, ln: &FNLine| FNLine {
+        string: text.to_string(),
+        raw_string: text.to_string(),
+        position: ln.position,
+        length: text.len() as i32,
+        terminator_length: ln.terminator_length,
+        ..Default::default()
+    };
+
+    let mut unparsed: Vec<FNLine> = (window_start..global_range.start)
+        .map(|idx| get_unparsed(&visible_texts[idx], &lines[idx]))
+        .collect();
+    unparsed.push(get_unparsed(&text, &lines[global_range.start]));
+    unparsed.extend(
+        (global_range.end..window_end).map(|idx| get_unparsed(&visible_texts[idx], &lines[idx])),
+    );
+    static_fountain_parser::get_parsed_lines_from_line_vec(unparsed)
+        .swap_remove(global_range.start - window_start)
 }
@@ -15,89 +15,215 @@
 
 //! The static_fountain_parser
 
-use std::vec;
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::fountain_enums::FNLineType;
+use crate::fountain_enums::{FNForcedBy, FNLineType};
+use crate::fountain_full_parse::{parse_document_full, FNFullParseConfig, FNParseLimits};
+use crate::fountain_heading::get_heading_prefix_len;
 use crate::fountain_line::FNLine;
+use crate::fountain_parsed_document::FNParsedDocument;
+use crate::static_fountain_preparser::{self, FNPreparseOptions};
 
 // ----- Public Functions -----
 
+/// Options for the rules which decide line types.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FNParseOptions {
+    /// Character cues need at least this many graphemes before any parenthesis (such as `(V.O.)`)
+    pub min_cue_length: usize,
+    /// If `false`, all-caps lines ending in `!` or `?` (like `BANG!`) are never character cues, unless forced with `@`.
+    /// `true` by default, like the parser has always been.
+    pub allow_cue_trailing_punctuation: bool,
+    /// All-caps lines which are never character cues unless forced with `@`, such as sound effects or acronyms.
+    /// Compared without case, and ignoring whitespace around them.
+    pub cue_exemptions: Vec<String>,
+    /// If `true`, unforced transitions (all-caps lines ending in `:`) need an empty line after them, as well as before
+    pub require_empty_line_after_transition: bool,
+}
+
+impl Default for FNParseOptions {
+    fn default() -> Self {
+        FNParseOptions {
+            min_cue_length: 1,
+            allow_cue_trailing_punctuation: true,
+            cue_exemptions: Vec::new(),
+            require_empty_line_after_transition: true,
+        }
+    }
+}
+
+/// Parses a raw text document with the whole pipeline, and returns it as an `FNParsedDocument`.
+///
+/// The text is cut to the default `FNParseLimits`, and every cut is in `full.limit_diagnostics`.
+pub fn parse_document(text: &str) -> FNParsedDocument {
+    FNParsedDocument::new(text)
+}
+
+pub fn parse_document_with_config(text: &str, config: &FNFullParseConfig) -> FNParsedDocument {
+    FNParsedDocument::with_config(text, config)
+}
+
 /// Returns a `Vector` of fountain-parsed `FNLine` objects for a raw text document string.
 ///
 /// Each `FNLine` contains the `string`, the `FNLineType` for the line, and other metadata as properties.
+/// These are the `lines` of `parse_document`, so notes, boneyards, and emphasis are resolved too.
+/// The text isn't cut to any `FNParseLimits`, since there'd be nowhere to report the cuts;
+/// use `parse_document` for that.
 pub fn get_parsed_lines_from_raw_string(text: String) -> Vec<FNLine> {
-    let lines: Vec<FNLine> = get_unparsed_line_array_from_raw_string(Some(text));
-
-    get_parsed_lines_from_line_vec(lines)
+    let config = FNFullParseConfig {
+        limits: FNParseLimits::unlimited(),
+        ..Default::default()
+    };
+    parse_document_full(&text, &config).lines
 }
 
 /// Splits the document by newlines, then returns a list of Unparsed `FNLine` objects.
 ///
 /// Each `FNLine` object contains a single line of text, as well as metadata and attributes such as `FNLineType`
 /// and the line's position within the document string.
+///
+/// Trailing empty lines are kept, and each line records the length of its line break, so that
+/// `static_fountain_preparser::get_text_from_lines` gives back the original text.
+///
+/// The text isn't cut to any `FNParseLimits`. Use `fountain_full_parse::get_unparsed_lines_within_limits`
+/// to cut it, with a diagnostic for every cut.
 pub fn get_unparsed_line_array_from_raw_string(text: Option<String>) -> Vec<FNLine> {
-    let mut unparsed_lines: Vec<FNLine> = vec![];
-
-    let raw_text = match text {
-        Some(the_text) => the_text,
-        None => String::from(""),
-    };
+    let raw_text = text.unwrap_or_default();
 
-    // if (text == None): text = ""
-    let fixed_text = raw_text.replace("\r\n", "\n"); // Replace MS Word/Windows line breaks with unix newlines
-
-    let raw_lines = fixed_text.lines();
-    let mut position: i32 = 0; // To track at which position every line begins
-
-    for r in raw_lines {
-        unparsed_lines.push(FNLine {
-            fn_type: FNLineType::Unparsed,
-            string: r.to_string(),
-            raw_string: r.to_string(),
-            position: position,
-            ..Default::default()
-        });
-        let grapheme_len = r.graphemes(true).count();
-        position += (grapheme_len + 1) as i32; // +1 is to account for newline character
-    }
-
-    unparsed_lines
+    static_fountain_preparser::get_unparsed_lines_from_raw_string(
+        &raw_text,
+        &FNPreparseOptions::default(),
+    )
 }
 
 pub fn get_parsed_lines_from_line_vec(lines: Vec<FNLine>) -> Vec<FNLine> {
-    // the actual parsing
-    let mut index: usize = 0;
+    get_parsed_lines_from_line_vec_with_options(lines, &FNParseOptions::default())
+}
 
+pub fn get_parsed_lines_from_line_vec_with_options(
+    lines: Vec<FNLine>,
+    options: &FNParseOptions,
+) -> Vec<FNLine> {
+    // the actual parsing
     let mut cloned_lines_vec: Vec<FNLine> = lines.clone();
+    let title_page_end = get_title_page_region_end(&lines);
 
     for (l, cur_line) in lines.iter().enumerate() {
-        //println!("Index", index);
         let mut cur_clone = cur_line.clone();
-        (cur_clone.fn_type, cur_clone.is_forced) = parse_line_type_for(&cloned_lines_vec, index);
+        (cur_clone.fn_type, cur_clone.is_forced) = if l < title_page_end {
+            (
+                _get_title_page_element_type(&cur_clone, &cloned_lines_vec[..l]),
+                false,
+            )
+        } else {
+            parse_line_type_for(&cloned_lines_vec, l, options)
+        };
+        cur_clone.forced_by = get_forced_by_for_line(&cur_clone);
+        cur_clone.section_depth = cur_clone.get_section_depth();
+
+        // Strip the `@` from forced character cues, so that `string` only holds the displayed name
+        if cur_clone.is_forced && cur_clone.is_any_character() {
+            if let Some(stripped) = cur_clone.string.strip_prefix('@') {
+                cur_clone.string = stripped.to_string();
+                cur_clone.forced_character_cue = true;
+            }
+        }
 
         // Check if previous line is supposed to actually be just action
         // (Characters need 1 empty line before and 1 NON-empty line after)
 
-        if cur_clone.fn_type == FNLineType::Empty && l > 0 && cloned_lines_vec.len() > 0 {
-            let prev: &mut FNLine = &mut cloned_lines_vec[l - 1].clone();
-
-            if prev.fn_type == FNLineType::Character {
-                prev.fn_type = FNLineType::Action;
+        if cur_clone.fn_type == FNLineType::Empty && l > 0 && !cloned_lines_vec.is_empty() {
+            let prev: &mut FNLine = &mut cloned_lines_vec[l - 1];
+
+            if prev.fn_type == FNLineType::Character && !prev.forced_character_cue {
+                // A numbered cue like `#2 GUARD` without dialogue was a section after all
+                if prev.string.starts_with('#') {
+                    prev.fn_type = FNLineType::Section;
+                    prev.is_forced = true;
+                    prev.forced_by = FNForcedBy::Hash;
+                    prev.section_depth = prev.get_section_depth();
+                } else {
+                    prev.fn_type = FNLineType::Action;
+                }
             }
         }
 
         cloned_lines_vec[l] = cur_clone;
-        index += 1;
     }
 
     cloned_lines_vec
 }
 
+/// Returns the global index of the first line of every parenthetical whose `(` is never closed.
+///
+/// A parenthetical can span several lines, but it has to be closed before the dialogue block ends.
+pub fn get_unclosed_parenthetical_indices(lines: &[FNLine]) -> Vec<usize> {
+    let mut unclosed: Vec<usize> = Vec::new();
+    let mut open_index: Option<usize> = None;
+    for (idx, ln) in lines.iter().enumerate() {
+        if !ln.is_any_parenthetical() {
+            if let Some(open) = open_index.take() {
+                unclosed.push(open);
+            }
+            continue;
+        }
+        if open_index.is_none() && ln.string.trim_start().starts_with('(') {
+            open_index = Some(idx);
+        }
+        if !_is_parenthetical_left_open(ln) {
+            open_index = None;
+        }
+    }
+    unclosed.extend(open_index);
+    unclosed
+}
+
+/// Returns the (exclusive) end of the title page: the index of the first empty line, if the document starts
+/// with a title page key. Returns `0` for documents without a title page.
+///
+/// A known key like `Title:` always starts a title page. Any other key only does if it has a value, or an indented
+/// line continuing it, so that a script starting with `FADE IN:` has no title page.
+/// A line forced to be action, such as `!Title: value`, never starts one.
+///
+/// Every line before the end is a title page line, and no line after it is.
+pub fn get_title_page_region_end(lines: &[FNLine]) -> usize {
+    match lines.first() {
+        Some(first) if _is_title_page_start(first, lines.get(1)) => lines
+            .iter()
+            .position(|ln| ln.string.trim().is_empty())
+            .unwrap_or(lines.len()),
+        _ => 0,
+    }
+}
+
 // ----- Private Functions -----
 
+/// Returns which marker forced the line's type. Must be called before the `@` is stripped from new character cues.
+fn get_forced_by_for_line(line: &FNLine) -> FNForcedBy {
+    if !line.is_forced {
+        return FNForcedBy::None;
+    }
+    if line.forced_character_cue {
+        return FNForcedBy::At;
+    }
+    match line.string.trim_start().chars().next() {
+        Some('!') => FNForcedBy::Bang,
+        Some('.') => FNForcedBy::Dot,
+        Some('>') => FNForcedBy::GreaterThan,
+        Some('@') => FNForcedBy::At,
+        Some('~') => FNForcedBy::Tilde,
+        Some('=') => FNForcedBy::Equals,
+        Some('#') => FNForcedBy::Hash,
+        _ => FNForcedBy::None,
+    }
+}
+
 /// Parses and returns the `LineType` for a given line.
-fn parse_line_type_for(lines: &Vec<FNLine>, index: usize) -> (FNLineType, bool) {
+fn parse_line_type_for(
+    lines: &[FNLine],
+    index: usize,
+    options: &FNParseOptions,
+) -> (FNLineType, bool) {
     let mut is_forced: bool = false;
 
     let empty_line = FNLine {
@@ -112,7 +238,7 @@ fn parse_line_type_for(lines: &Vec<FNLine>, index: usize) -> (FNLineType, bool)
         line = line_ref;
     }
 
-    //let mut next_line: Result<&FNLine, &str> = Result::Err("No next line.");
+    let mut next_line: Result<&FNLine, &str> = Result::Err("No next line.");
     let mut previous_line: Result<&FNLine, &str> = Result::Err("No previous line.");
 
     if !lines.is_empty() {
@@ -120,7 +246,7 @@ fn parse_line_type_for(lines: &Vec<FNLine>, index: usize) -> (FNLineType, bool)
             previous_line = Ok(&lines[index - 1]);
         }
         if { index + 1 } < lines.len() {
-            //next_line = Ok(&lines[index + 1]);
+            next_line = Ok(&lines[index + 1]);
         }
     }
 
@@ -140,21 +266,24 @@ fn parse_line_type_for(lines: &Vec<FNLine>, index: usize) -> (FNLineType, bool)
 
     // --------- Check FORCED elements
     let forced_element_result: Option<FNLineType> =
-        _check_if_forced_element(line, &previous_line_is_empty);
+        _check_if_forced_element(line, &previous_line_is_empty, options);
 
     if let Some(line_type) = forced_element_result {
         is_forced = true;
         return (line_type, is_forced);
     }
 
-    // --------- Title page
-    let title_page_result: Option<FNLineType> = _check_if_title_page_element(line, &previous_line);
-    if let Some(line_type) = title_page_result {
-        return (line_type, is_forced);
-    }
-
     // --------- Transitions
-    let transition_result: Option<FNLineType> = _check_if_transition(line, &previous_line_is_empty);
+    // The next line hasn't been parsed yet, so only its text can be checked
+    let next_line_is_empty: bool = match next_line {
+        Ok(line) => line.string.trim().is_empty(),
+        Err(_) => true,
+    };
+    let transition_result: Option<FNLineType> = _check_if_transition(
+        line,
+        &previous_line_is_empty,
+        &(next_line_is_empty || !options.require_empty_line_after_transition),
+    );
     if let Some(line_type) = transition_result {
         return (line_type, is_forced);
     }
@@ -174,7 +303,7 @@ fn parse_line_type_for(lines: &Vec<FNLine>, index: usize) -> (FNLineType, bool)
     }
     // --------- Character
 
-    let character_result: Option<FNLineType> = _check_if_character(line, &previous_line);
+    let character_result: Option<FNLineType> = _check_if_character(line, &previous_line, options);
     if let Some(line_type) = character_result {
         return (line_type, is_forced);
     }
@@ -191,11 +320,16 @@ fn parse_line_type_for(lines: &Vec<FNLine>, index: usize) -> (FNLineType, bool)
 }
 
 // ---------- Parsing sub-functions ----------
-fn _check_if_transition(line: &FNLine, previous_line_is_empty: &bool) -> Option<FNLineType> {
+fn _check_if_transition(
+    line: &FNLine,
+    previous_line_is_empty: &bool,
+    next_line_is_empty: &bool,
+) -> Option<FNLineType> {
     if line.string.len() > 2
-        && line.string.graphemes(true).last() == Some(":")
+        && line.string.graphemes(true).next_back() == Some(":")
         && line.string == line.string.to_uppercase()
         && *previous_line_is_empty
+        && *next_line_is_empty
     {
         return Some(FNLineType::TransitionLine);
     }
@@ -207,8 +341,11 @@ fn _check_if_dialogue_or_parenthetical(
     previous_line: &Result<&FNLine, &str>,
 ) -> Option<FNLineType> {
     if let Ok(pl) = previous_line {
-        if pl.is_dialogue() && pl.string.len() > 0 {
-            if line.string.graphemes(true).nth(0) == Some("(") {
+        if pl.fn_type == FNLineType::Parenthetical && _is_parenthetical_left_open(pl) {
+            return Some(FNLineType::Parenthetical);
+        }
+        if pl.is_dialogue() && !pl.string.is_empty() {
+            if line.string.graphemes(true).next() == Some("(") {
                 return Some(FNLineType::Parenthetical);
             }
             return Some(FNLineType::Dialogue);
@@ -221,45 +358,41 @@ fn _check_if_dialogue_or_parenthetical(
     None
 }
 fn _check_if_heading(line: &FNLine, previous_line_is_empty: &bool) -> Option<FNLineType> {
-    if !(*previous_line_is_empty && line.string.len() >= 3) {
+    if !*previous_line_is_empty {
         return None;
     }
-    let first_3_graphemes = line
-        .string
-        .graphemes(true)
-        .take(3)
-        .collect::<Vec<&str>>()
-        .join("");
-
-    match first_3_graphemes.to_lowercase().as_str() {
-        "int" => {}
-        "ext" => {}
-        "est" => {}
-        "i/e" => {}
-        _ => return None,
-    }
-
-    // To avoid words like "international" from becoming headings, the extension HAS to end with either dot, space or slash
-    let next_grapheme = line.string.graphemes(true).nth(4);
-    match next_grapheme {
-        Some(".") | Some(" ") | Some("/") => {
-            return Some(FNLineType::Heading);
-        }
-        _ => None,
+    // To avoid words like "international" from becoming headings, the prefix HAS to end with either dot, space or slash,
+    // and something has to follow it
+    let prefix_len = get_heading_prefix_len(&line.string)?;
+    if line.string[prefix_len..].trim().is_empty() {
+        return None;
     }
+    Some(FNLineType::Heading)
 }
 
-fn _check_if_forced_element(line: &FNLine, previous_line_is_empty: &bool) -> Option<FNLineType> {
-    let first_grapheme_option: Option<&str> = line.string.graphemes(true).nth(0);
-    let last_grapheme_option: Option<&str> = line.string.graphemes(true).last();
+fn _check_if_forced_element(
+    line: &FNLine,
+    previous_line_is_empty: &bool,
+    options: &FNParseOptions,
+) -> Option<FNLineType> {
+    let first_grapheme_option: Option<&str> = line.string.graphemes(true).next();
+    let last_grapheme_option: Option<&str> = line.string.graphemes(true).next_back();
 
-    if first_grapheme_option == None || last_grapheme_option == None {
+    if first_grapheme_option.is_none() || last_grapheme_option.is_none() {
         return None;
     }
 
     let first_grapheme = first_grapheme_option.unwrap_or_default();
     let last_grapheme = last_grapheme_option.unwrap_or_default();
 
+    // Forced cues which were parsed before have already had their `@` stripped
+    if line.forced_character_cue {
+        if last_grapheme == "^" && *previous_line_is_empty {
+            return Some(FNLineType::DualDialogueCharacter);
+        }
+        return Some(FNLineType::Character);
+    }
+
     // TODO: Handle escaped characters outside of the static parser
     // Check for escaped characters
     // if (firstChar == '\\'):
@@ -283,6 +416,13 @@ fn _check_if_forced_element(line: &FNLine, previous_line_is_empty: &bool) -> Opt
         return Some(FNLineType::PageBreak);
     }
 
+    // --------- Centered
+    // Whitespace around centered text is allowed, for readability
+    let trimmed = line.string.trim();
+    if trimmed.len() > 1 && trimmed.starts_with('>') && trimmed.ends_with('<') {
+        return Some(FNLineType::Centered);
+    }
+
     // --------- FORCED Action or Shot
     if first_grapheme == "!" {
         // checks raw first char again, to enable escaping \\ a `!` char
@@ -304,7 +444,7 @@ fn _check_if_forced_element(line: &FNLine, previous_line_is_empty: &bool) -> Opt
             let second_grapheme_option = line.string.graphemes(true).nth(1);
 
             if let Some(sg) = second_grapheme_option {
-                if sg == "." {
+                if sg == "." || sg.chars().all(|c| c.is_ascii_digit()) {
                     return None;
                 }
             }
@@ -315,15 +455,17 @@ fn _check_if_forced_element(line: &FNLine, previous_line_is_empty: &bool) -> Opt
 
     // Rest of the FORCED FNLine Types
     match first_grapheme {
-        ">" => {
-            if last_grapheme == "<" {
-                return Some(FNLineType::Centered);
-            }
-            Some(FNLineType::TransitionLine)
-        }
+        ">" => Some(FNLineType::TransitionLine),
         "~" => Some(FNLineType::Lyrics),
         "=" => Some(FNLineType::Synopse),
-        "#" => Some(FNLineType::Section),
+        "#" => {
+            // Numbered cues like `#2 GUARD` are only sections if no dialogue follows them,
+            // which is decided once the next line is parsed
+            if *previous_line_is_empty && _is_numbered_cue(line, options) {
+                return None;
+            }
+            Some(FNLineType::Section)
+        }
         "@" => {
             if last_grapheme == "^" && *previous_line_is_empty {
                 return Some(FNLineType::DualDialogueCharacter);
@@ -334,60 +476,68 @@ fn _check_if_forced_element(line: &FNLine, previous_line_is_empty: &bool) -> Opt
             if *previous_line_is_empty {
                 return Some(FNLineType::Heading);
             }
-            return None;
+            None
         }
         _ => None,
     }
 }
 
-fn _check_if_title_page_element(
-    line: &FNLine,
-    previous_line: &Result<&FNLine, &str>,
-) -> Option<FNLineType> {
-    if let Ok(pl) = previous_line {
-        if !pl.is_title_page() {
-            return None;
-        }
+// Lines inside the title page region: `key: value` lines, and the lines continuing their values
+fn _get_title_page_element_type(line: &FNLine, previous_lines: &[FNLine]) -> FNLineType {
+    match line.get_title_page_key().as_str() {
+        "" => match previous_lines.last() {
+            Some(pl) if pl.fn_type.is_title_page_kind() => pl.fn_type.clone(),
+            _ => FNLineType::TitlePageUnknown,
+        },
+        key => _get_known_title_page_key_type(key).unwrap_or(FNLineType::TitlePageUnknown),
     }
+}
 
-    let key: String = line.get_title_page_key();
-
-    if key.len() > 0 && !key.is_empty() {
-        match key.as_str() {
-            "title" => return Some(FNLineType::TitlePageTitle),
-            "author" => return Some(FNLineType::TitlePageAuthor),
-            "authors" => return Some(FNLineType::TitlePageAuthor),
-            "credit" => return Some(FNLineType::TitlePageCredit),
-            "source" => return Some(FNLineType::TitlePageSource),
-            "contact" => return Some(FNLineType::TitlePageContact),
-            "contacts" => return Some(FNLineType::TitlePageContact),
-            "contact info" => return Some(FNLineType::TitlePageContact),
-            "draft date" => return Some(FNLineType::TitlePageDraftDate),
-            "draft" => return Some(FNLineType::TitlePageDraftDate),
-            _ => return Some(FNLineType::TitlePageUnknown),
-        }
+fn _get_known_title_page_key_type(key: &str) -> Option<FNLineType> {
+    match key {
+        "title" => Some(FNLineType::TitlePageTitle),
+        "author" | "authors" => Some(FNLineType::TitlePageAuthor),
+        "credit" => Some(FNLineType::TitlePageCredit),
+        "source" => Some(FNLineType::TitlePageSource),
+        "contact" | "contacts" | "contact info" => Some(FNLineType::TitlePageContact),
+        "draft date" | "draft" => Some(FNLineType::TitlePageDraftDate),
+        _ => None,
     }
+}
 
-    if let Ok(pl) = previous_line {
-        let prev_key = pl.get_title_page_key();
-        if prev_key.len() > 0 || line.string.starts_with("\t") || line.string.starts_with("   ") {
-            return Some(pl.fn_type.clone());
-        }
+// An unknown key like `FADE IN:` on its own is more likely a transition than a title page
+fn _is_title_page_start(first: &FNLine, next: Option<&FNLine>) -> bool {
+    let key = first.get_title_page_key();
+    if key.is_empty() || first.string.trim_start().starts_with('!') {
+        return false;
     }
-    None
+    if _get_known_title_page_key_type(&key).is_some() {
+        return true;
+    }
+    let has_value = first
+        .string
+        .split_once(':')
+        .is_some_and(|(_, value)| !value.trim().is_empty());
+    has_value
+        || next.is_some_and(|ln| ln.string.starts_with([' ', '\t']) && !ln.string.trim().is_empty())
 }
 
-fn _check_if_character(line: &FNLine, previous_line: &Result<&FNLine, &str>) -> Option<FNLineType> {
+fn _check_if_character(
+    line: &FNLine,
+    previous_line: &Result<&FNLine, &str>,
+    options: &FNParseOptions,
+) -> Option<FNLineType> {
     use crate::helper_funcs::only_uppercase_until_parenthesis;
-    if !(only_uppercase_until_parenthesis(&line.string) && line.string != "") {
+    if !only_uppercase_until_parenthesis(&line.string) || line.string.is_empty() {
         return None;
     }
-    if line.string != line.string.trim() {
-        if line.string.starts_with("  ") {
-            return None;
-        }
+    if line.string != line.string.trim() && line.string.starts_with("  ") {
+        return None;
     }
-    let last_char_opt = line.string.graphemes(true).last();
+    if !_is_allowed_as_character(line, options) {
+        return None;
+    }
+    let last_char_opt = line.string.graphemes(true).next_back();
 
     if last_char_opt == Some("^") {
         return Some(FNLineType::DualDialogueCharacter);
@@ -401,8 +551,52 @@ fn _check_if_character(line: &FNLine, previous_line: &Result<&FNLine, &str>) ->
     Some(FNLineType::Character)
 }
 
+// `#` followed by a number, then an all-caps name, such as `#2 GUARD`
+fn _is_numbered_cue(line: &FNLine, options: &FNParseOptions) -> bool {
+    use crate::helper_funcs::only_uppercase_until_parenthesis;
+    line.string[1..].starts_with(|c: char| c.is_ascii_digit())
+        && only_uppercase_until_parenthesis(&line.string)
+        && _is_allowed_as_character(line, options)
+}
+
+// Applies the cue length, punctuation, and exemption options to an all-caps line
+fn _is_allowed_as_character(line: &FNLine, options: &FNParseOptions) -> bool {
+    let text = line.string.trim();
+    let name = text.split('(').next().unwrap_or_default().trim();
+    if name.graphemes(true).count() < options.min_cue_length {
+        return false;
+    }
+    // Numbers alone (`1984`) are never cues, but names can start with them (`911 OPERATOR`)
+    if !name.chars().any(|c| c.is_alphabetic()) {
+        return false;
+    }
+    // The `^` of dual dialogue cues comes after any punctuation
+    let without_caret = text.strip_suffix('^').unwrap_or(text).trim_end();
+    if !options.allow_cue_trailing_punctuation
+        && (without_caret.ends_with('!') || without_caret.ends_with('?'))
+    {
+        return false;
+    }
+    !options
+        .cue_exemptions
+        .iter()
+        .any(|exemption| exemption.trim().to_uppercase() == without_caret.to_uppercase())
+}
+
+/// Returns `true` if a parenthetical line leaves its `(` open, so that the next line continues it.
+///
+/// Continuation lines have no `(` of their own, and stay open until a line with a `)`.
+fn _is_parenthetical_left_open(line: &FNLine) -> bool {
+    match (line.string.rfind('('), line.string.rfind(')')) {
+        (Some(open), Some(close)) => open > close,
+        (Some(_), None) => true,
+        (None, Some(_)) => false,
+        (None, None) => true,
+    }
+}
+
 fn _check_if_empty_line(line: &FNLine) -> Option<FNLineType> {
-    if line.string.len() == 0 {
+    if line.string.is_empty() {
         Some(FNLineType::Empty)
     } else {
         None
@@ -417,8 +611,11 @@ fn _check_if_dual_dialogue(
             return None;
         }
 
-        if let Some(gp) = line.string.graphemes(true).nth(0) {
-            if gp == "(" {
+        if let Some(gp) = line.string.graphemes(true).next() {
+            if gp == "("
+                || (pl.fn_type == FNLineType::DualDialogueParenthetical
+                    && _is_parenthetical_left_open(pl))
+            {
                 return Some(FNLineType::DualDialogueParenthetical);
             }
             return Some(FNLineType::DualDialogue);
@@ -1,7 +1,207 @@
-/// Uses the partial_line_resolver to label lines as PartialLine
-/// Then, gives back a pre-parsed vec of lines, plus a HashMap for the partial ranges
-/// HashMap<partial_line_number, HashSet<index(es) for that particular partial line>>
+//! The static_fountain_preparser
+//!
+//! Everything that happens to a document before any line is parsed: newline normalization, tab expansion,
+//! splitting the text into lines, and building the table of line positions.
+//!
+//! `static_fountain_parser::get_unparsed_line_array_from_raw_string` uses these with the default options;
+//! advanced consumers can call them directly to customize pre-processing.
+//! Notes and boneyards are resolved after this step, by the `partial_line_resolver`.
 
-pub fn parse_for_ranged_partials() {
-    todo!();
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::fountain_enums::FNLineType;
+use crate::fountain_line::FNLine;
+use crate::fountain_text_width::get_grapheme_display_width;
+
+/// Which line breaks are turned into `\n` before the text is split.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FNNewlinePolicy {
+    /// Windows `\r\n` line breaks become `\n`. A lone `\r` stays in the line's text.
+    NormalizeCrLf,
+    /// Both `\r\n` and old Mac-style lone `\r` line breaks become `\n`.
+    NormalizeCrLfAndCr,
+    /// Lines are split at the same line breaks as `NormalizeCrLf`, but each line's `terminator_length` is the
+    /// length of its own line break in the source, so that byte offsets match the original text.
+    #[default]
+    Preserve,
+}
+
+/// How tabs are counted. Tabs indent title page continuations, and are sometimes used to fake the indentation
+/// of dialogue, so they shouldn't just count as a single column.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FNTabPolicy {
+    /// Tabs stay in the text, and are measured like any other character.
+    #[default]
+    Preserve,
+    /// Each tab is replaced with spaces, up to the next multiple of this many columns.
+    ///
+    /// This changes the text of every line before it's parsed, so emphasis ranges, wrapping, and pagination are all
+    /// based on the expanded text. Use `Preserve` if offsets into the source matter.
+    Expand(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FNPreparseOptions {
+    pub newline_policy: FNNewlinePolicy,
+    pub tab_policy: FNTabPolicy,
+}
+
+/// Returns the text with its tabs expanded according to the `FNTabPolicy`. Columns are counted in display width
+/// (see `fountain_text_width`), the same way wrapping and pagination measure text.
+pub fn get_text_with_expanded_tabs(text: &str, tab_policy: &FNTabPolicy) -> String {
+    let FNTabPolicy::Expand(tab_width) = tab_policy else {
+        return text.to_string();
+    };
+    if !text.contains('\t') {
+        return text.to_string();
+    }
+    let tab_width = (*tab_width).max(1);
+    let mut expanded = String::with_capacity(text.len());
+    let mut column: usize = 0;
+    for grapheme in text.graphemes(true) {
+        if grapheme == "\t" {
+            let spaces = tab_width - column % tab_width;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push_str(grapheme);
+            column += get_grapheme_display_width(grapheme);
+        }
+    }
+    expanded
+}
+
+/// Returns the text with its line breaks normalized to `\n`, according to the `FNNewlinePolicy`.
+///
+/// `Preserve` is normalized like `NormalizeCrLf`; use `get_split_lines_with_terminators` to keep the line breaks.
+pub fn get_normalized_text(text: &str, newline_policy: &FNNewlinePolicy) -> String {
+    let normalized = text.replace("\r\n", "\n");
+    match newline_policy {
+        FNNewlinePolicy::NormalizeCrLf | FNNewlinePolicy::Preserve => normalized,
+        FNNewlinePolicy::NormalizeCrLfAndCr => normalized.replace('\r', "\n"),
+    }
+}
+
+/// Splits normalized text into lines. A trailing newline ends the last line, and does not create an extra
+/// empty line: `"a\n\n"` is `"a"` and an empty line, each followed by a line break.
+pub fn get_split_lines(normalized_text: &str) -> Vec<&str> {
+    normalized_text.split_terminator('\n').collect()
+}
+
+/// Splits text into lines, each with the byte length of the line break after it (`0` if there is none).
+///
+/// Lines are the same as `get_split_lines(&get_normalized_text(text, newline_policy))`. With `Preserve`, a `\r\n`
+/// has a length of `2`; with the other policies, every line break is the `\n` it was normalized to.
+pub fn get_split_lines_with_terminators<'a>(
+    text: &'a str,
+    newline_policy: &FNNewlinePolicy,
+) -> Vec<(&'a str, usize)> {
+    let is_break = |c: char| {
+        c == '\n' || (c == '\r' && *newline_policy == FNNewlinePolicy::NormalizeCrLfAndCr)
+    };
+    let mut lines: Vec<(&str, usize)> = Vec::new();
+    let mut remaining = text;
+    while !remaining.is_empty() {
+        let Some(break_start) = remaining.find(is_break) else {
+            lines.push((remaining, 0));
+            break;
+        };
+        let break_len = if remaining[break_start..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        // With `\n` as the only line break, the `\r` of a `\r\n` is right before it
+        let line_end = match remaining[..break_start].strip_suffix('\r') {
+            Some(line) if remaining[break_start..].starts_with('\n') => line.len(),
+            _ => break_start,
+        };
+        let line = &remaining[..line_end];
+        let break_end = break_start + break_len;
+        let terminator_length = match newline_policy {
+            FNNewlinePolicy::Preserve => break_end - line_end,
+            _ => 1,
+        };
+        lines.push((line, terminator_length));
+        remaining = &remaining[break_end..];
+    }
+    lines
+}
+
+/// Returns the position (starting index) of each line in the document, counted in graphemes.
+///
+/// Each line break counts as a single grapheme. A `\r\n` is a single grapheme as well, so positions are the same
+/// in the original and the normalized text, whatever the `FNNewlinePolicy`.
+pub fn get_line_positions(lines: &[&str]) -> Vec<i32> {
+    let mut positions: Vec<i32> = Vec::with_capacity(lines.len());
+    let mut position: i32 = 0; // To track at which position every line begins
+
+    for ln in lines {
+        positions.push(position);
+        let grapheme_len = ln.graphemes(true).count();
+        position += (grapheme_len + 1) as i32; // +1 is to account for newline character
+    }
+
+    positions
+}
+
+/// Splits the document into a list of Unparsed `FNLine` objects, with their `position` and `terminator_length` set.
+///
+/// No text is lost: trailing empty lines are kept, and the last line's `terminator_length` records whether the
+/// text ends with a line break. With `FNNewlinePolicy::Preserve` and `FNTabPolicy::Preserve`,
+/// the sum of every `raw_string.len() + terminator_length` is `text.len()`, and `get_text_from_lines` returns `text`.
+pub fn get_unparsed_lines_from_raw_string(text: &str, options: &FNPreparseOptions) -> Vec<FNLine> {
+    let split_lines = get_split_lines_with_terminators(text, &options.newline_policy);
+    let raw_lines: Vec<&str> = split_lines.iter().map(|(r, _)| *r).collect();
+    let positions = get_line_positions(&raw_lines);
+
+    split_lines
+        .iter()
+        .zip(positions)
+        .map(|((r, terminator_length), position)| {
+            let text = get_text_with_expanded_tabs(r, &options.tab_policy);
+            FNLine {
+                fn_type: FNLineType::Unparsed,
+                string: text.clone(),
+                raw_string: text,
+                position,
+                terminator_length: *terminator_length as i32,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Returns the byte offset of each line's `raw_string`, counting every line's `terminator_length`.
+///
+/// With `FNNewlinePolicy::Preserve`, these are offsets into the original text; otherwise, into the normalized text.
+pub fn get_byte_offsets_for_lines(lines: &[FNLine]) -> Vec<usize> {
+    let mut offsets: Vec<usize> = Vec::with_capacity(lines.len());
+    let mut offset: usize = 0;
+    for ln in lines {
+        offsets.push(offset);
+        offset += ln.raw_string.len() + ln.terminator_length.max(0) as usize;
+    }
+    offsets
+}
+
+/// Joins lines back into a document, with the line breaks recorded in their `terminator_length`.
+///
+/// Every line but the last is followed by a line break, even if it was added without a `terminator_length`:
+/// a length of `2` is written as `\r\n`, and anything else as `\n`. The last line only ends with a line break
+/// if its `terminator_length` is above `0`.
+pub fn get_text_from_lines(lines: &[FNLine]) -> String {
+    let mut text = String::with_capacity(lines.iter().map(|ln| ln.raw_string.len() + 2).sum());
+    for (idx, ln) in lines.iter().enumerate() {
+        text.push_str(&ln.raw_string);
+        if idx + 1 == lines.len() && ln.terminator_length <= 0 {
+            break;
+        }
+        text.push_str(if ln.terminator_length == 2 {
+            "\r\n"
+        } else {
+            "\n"
+        });
+    }
+    text
 }